@@ -0,0 +1,2170 @@
+//! Procedural implementation of the macros in [`comp`](https://docs.rs/comp/).
+//!
+//! It accepts the same syntax as the `macro_rules` version, but keeps the span of every clause,
+//! so that type errors point at the offending `<-` line instead of the whole invocation, and
+//! malformed syntax is reported with a friendly message.
+//!
+//! Don't depend on this crate directly, enable the `proc-macro` feature of `comp` and use the
+//! macros from `comp::spanned` instead.
+
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::{Delimiter, Span, TokenStream as TokenStream2, TokenTree};
+use quote::{quote, quote_spanned, ToTokens};
+use syn::parse::{Parse, ParseStream, Parser};
+use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
+use syn::{
+    token, Attribute, Expr, GenericParam, Generics, Ident, Lifetime, Pat, PatType, Stmt, Token,
+    Type, Visibility,
+};
+
+/// syntax extension specific for Option
+///
+/// See the documentation of `comp` for more details.
+#[proc_macro]
+pub fn option(input: TokenStream) -> TokenStream {
+    expand(Kind::Option, input)
+}
+
+/// syntax extension specific for Result
+///
+/// See the documentation of `comp` for more details.
+#[proc_macro]
+pub fn result(input: TokenStream) -> TokenStream {
+    expand(Kind::Result, input)
+}
+
+/// syntax extension specific for Iterator
+///
+/// See the documentation of `comp` for more details.
+#[proc_macro]
+pub fn iter(input: TokenStream) -> TokenStream {
+    expand(Kind::Iter, input)
+}
+
+/// syntax extension for any type implementing `comp::Bind` and `comp::Pure`
+///
+/// See the documentation of `comp` for more details.
+#[proc_macro]
+pub fn comp(input: TokenStream) -> TokenStream {
+    expand(Kind::Comp, input)
+}
+
+/// named iterator type over an Iterator comprehension
+///
+/// See the documentation of `comp` for more details.
+#[proc_macro]
+pub fn iter_struct(input: TokenStream) -> TokenStream {
+    match IterStruct::parse.parse(input) {
+        Ok(item) => item.expand().into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+/// string of the code a comprehension expands to
+///
+/// See the documentation of `comp` for more details.
+#[proc_macro]
+pub fn comp_expand(input: TokenStream) -> TokenStream {
+    let parser = |input: ParseStream| {
+        let name: Ident = input.parse()?;
+        let kind = match name.to_string().as_str() {
+            "option" => Kind::Option,
+            "result" => Kind::Result,
+            "iter" => Kind::Iter,
+            "comp" => Kind::Comp,
+            _ => {
+                let message = "expected `option!`, `result!`, `iter!` or `comp!`";
+                return Err(syn::Error::new(name.span(), message));
+            }
+        };
+        input.parse::<Token![!]>()?;
+        let body: proc_macro2::Group = input.parse()?;
+        let expanded = try_expand(kind, body.stream())?;
+        Ok(syn::LitStr::new(&expanded.to_string(), name.span()))
+    };
+    match parser.parse(input) {
+        Ok(lit) => lit.into_token_stream().into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+fn expand(kind: Kind, input: TokenStream) -> TokenStream {
+    match try_expand(kind, input.into()) {
+        Ok(expanded) => expanded.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+fn try_expand(kind: Kind, input: TokenStream2) -> syn::Result<TokenStream2> {
+    let scoped = has_try_before_bind(input.clone());
+    let mut body = Body::parse.parse2(input)?;
+    if let Some((name, _)) = &body.terminal {
+        if kind != Kind::Iter {
+            let message = format!("`{}` is only supported in `iter!`", name);
+            return Err(syn::Error::new(name.span(), message));
+        }
+    }
+    for keyword in body.from_fn.iter().chain(&body.send) {
+        if kind != Kind::Iter {
+            let message = format!("`{}` is only supported in `iter!`", keyword);
+            return Err(syn::Error::new(keyword.span(), message));
+        }
+    }
+    if kind != Kind::Result {
+        if let Some(error) = &body.error {
+            let message = "`type Error` is only supported in `result!`";
+            return Err(syn::Error::new(error.span(), message));
+        }
+        if body.send.is_some() {
+            // asserted on every source, so that the error points at the offending binding
+            for clause in &mut body.clauses {
+                if let Clause::Bind(binding) = clause {
+                    let alternatives = &mut binding.alternatives;
+                    for expr in Some(&mut binding.expr).into_iter().chain(alternatives) {
+                        *expr = syn::parse_quote_spanned! {located_at(expr.span())=>
+                            ::comp::__send(#expr)
+                        };
+                    }
+                }
+            }
+        }
+        if kind == Kind::Iter {
+            hoist_sources(&mut body.clauses)?;
+        }
+        let mut expanded = match body.from_fn {
+            Some(_) => state_machine(&body.clauses, &body.ret),
+            None => kind.expand(&body.clauses, &body.ret),
+        };
+        if let Some(keyword) = &body.send {
+            expanded = quote_spanned!(located_at(keyword.span())=> ::comp::__send(#expanded));
+        }
+        return Ok(match &body.terminal {
+            Some((name, _)) if name == "collect" => {
+                let ty = &body.collection;
+                quote_spanned! {located_at(name.span())=>
+                    ::core::iter::Iterator::collect::<#ty>(
+                        ::comp::__into_iter!(#expanded)
+                    )
+                }
+            }
+            Some((name, args)) if name == "extend" => {
+                let target = &args[0];
+                quote_spanned! {located_at(name.span())=>
+                    ::core::iter::Extend::extend(&mut #target, #expanded)
+                }
+            }
+            Some((name, args)) => {
+                let span = located_at(name.span());
+                let (method, args) = match name.to_string().as_str() {
+                    "first" => (Ident::new("next", name.span()), quote!()),
+                    "any" | "all" => {
+                        (name.clone(), quote_spanned!(span=> , ::core::convert::identity))
+                    }
+                    _ => (name.clone(), quote!(#( , #args )*)),
+                };
+                quote_spanned! {span=>
+                    ::core::iter::Iterator::#method(
+                        &mut ::comp::__into_iter!(#expanded)
+                        #args
+                    )
+                }
+            }
+            None => expanded,
+        });
+    }
+    if let Some(error) = &body.error {
+        for clause in &mut body.clauses {
+            if let Clause::Bind(binding) = clause {
+                // the expression before `else` may be an `Option`, which is lifted by `else error`
+                let count = binding.alternatives.len() + usize::from(binding.fallback.is_none());
+                let exprs = Some(&mut binding.expr).into_iter().chain(&mut binding.alternatives);
+                for expr in exprs.take(count) {
+                    *expr = syn::parse_quote_spanned! {located_at(expr.span())=>
+                        ::core::result::Result::map_err(
+                            #expr,
+                            <#error as ::core::convert::From<_>>::from,
+                        )
+                    };
+                }
+            }
+        }
+    }
+    // `?` before the first binding short-circuits the comprehension rather than the enclosing
+    // function, the bindings already run the rest in closures
+    let mut expanded = kind.expand(&body.clauses, &body.ret);
+    if scoped {
+        expanded = quote!(::comp::__scope(|| { #expanded }));
+    }
+    Ok(match &body.error {
+        Some(error) => {
+            quote!(::core::convert::identity::<::core::result::Result<_, #error>>(#expanded))
+        }
+        None => expanded,
+    })
+}
+
+/// Whether `?` appears before the first `<-` of the body, in any group but those of macro
+/// variables, the same as `@scope` of the `macro_rules` `result!` checks.
+fn has_try_before_bind(input: TokenStream2) -> bool {
+    fn flatten(input: TokenStream2, tokens: &mut Vec<TokenTree>) {
+        for token in input {
+            match token {
+                TokenTree::Group(group) if group.delimiter() != Delimiter::None => {
+                    flatten(group.stream(), tokens)
+                }
+                token => tokens.push(token),
+            }
+        }
+    }
+
+    let mut tokens = Vec::new();
+    flatten(input, &mut tokens);
+    let is_punct = |token: Option<&TokenTree>, ch: char| match token {
+        Some(TokenTree::Punct(punct)) => punct.as_char() == ch,
+        _ => false,
+    };
+    for (i, token) in tokens.iter().enumerate() {
+        if is_punct(Some(token), '?') {
+            return true;
+        }
+        if is_punct(Some(token), '<') && is_punct(tokens.get(i + 1), '-') {
+            return false;
+        }
+    }
+    false
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    Option,
+    Result,
+    Iter,
+    Comp,
+}
+
+impl Kind {
+    fn expand(self, clauses: &[Clause], ret: &Option<Expr>) -> TokenStream2 {
+        let (clause, tail) = match clauses.split_first() {
+            Some(split) => split,
+            None => return self.pure(ret),
+        };
+        let rest = self.expand(tail, ret);
+
+        match clause {
+            Clause::Bind(binding) => {
+                let Binding {
+                    pat,
+                    ty,
+                    expr,
+                    limit,
+                    skip,
+                    otherwise,
+                    fallback,
+                    ..
+                } = &**binding;
+                let param = match ty {
+                    Some(ty) => quote!(#pat: #ty),
+                    None => quote!(#pat),
+                };
+                let span = located_at(expr.span());
+                // the value matched against a pattern which may not match
+                let arg = match ty {
+                    Some(ty) => quote_spanned!(span=> v: #ty),
+                    None => quote_spanned!(span=> v),
+                };
+                let expr = match self.source(binding) {
+                    Ok(expr) => expr,
+                    Err(err) => return err,
+                };
+                if let Some(default) = fallback {
+                    return match self {
+                        Kind::Option => quote_spanned! {span=>
+                            {
+                                let #param = match #expr {
+                                    ::core::option::Option::Some(v) => v,
+                                    ::core::option::Option::None => #default,
+                                };
+                                #rest
+                            }
+                        },
+                        Kind::Result => quote_spanned! {span=>
+                            ::comp::Else::bind_else(#expr, || #default)
+                                .and_then(move |#param| { #rest })
+                        },
+                        Kind::Iter | Kind::Comp => {
+                            let span = located_at(default.span());
+                            syn::Error::new(
+                                span,
+                                "`else default` is only supported in `option!` and `result!`",
+                            )
+                            .to_compile_error()
+                        }
+                    };
+                }
+                if let Some(cond) = limit {
+                    let span = located_at(cond.span());
+                    if self != Kind::Iter {
+                        return syn::Error::new(span, "`while` is only supported in `iter!`")
+                            .to_compile_error();
+                    }
+                    return quote_spanned! {span=>
+                        {
+                            let iter = ::core::iter::Iterator::map(
+                                ::comp::__into_iter!(#expr),
+                                move |#param| if #cond {
+                                    ::core::option::Option::Some({ #rest })
+                                } else {
+                                    ::core::option::Option::None
+                                },
+                            );
+                            let is_some = ::core::option::Option::is_some;
+                            let iter = ::core::iter::Iterator::take_while(iter, is_some);
+                            ::core::iter::Iterator::flatten(::core::iter::Iterator::flatten(iter))
+                        }
+                    };
+                }
+                if let Some(cond) = skip {
+                    let span = located_at(cond.span());
+                    if self != Kind::Iter {
+                        return syn::Error::new(span, "`skip_while` is only supported in `iter!`")
+                            .to_compile_error();
+                    }
+                    return quote_spanned! {span=>
+                        {
+                            let mut skipping = true;
+                            let iter = ::core::iter::Iterator::flat_map(
+                                ::comp::__into_iter!(#expr),
+                                move |#param| if skipping && #cond {
+                                    ::core::option::Option::None
+                                } else {
+                                    skipping = false;
+                                    ::core::option::Option::Some({ #rest })
+                                },
+                            );
+                            ::core::iter::Iterator::flatten(iter)
+                        }
+                    };
+                }
+                if let Some(err) = otherwise {
+                    if self != Kind::Result || ty.is_some() {
+                        let span = located_at(err.span());
+                        return syn::Error::new(
+                            span,
+                            "`=> error` is only supported in `result!` without a type",
+                        )
+                        .to_compile_error();
+                    }
+                    return quote_spanned! {span=>
+                        (#expr).and_then(move |v| match ::core::convert::identity(v) {
+                            #pat => { #rest }
+                            _ => ::core::result::Result::Err(#err),
+                        })
+                    };
+                }
+                // a plain name binds the values as a closure parameter, which keeps the size of the
+                // source in `iter!`, while other patterns, including the name of a constant or a
+                // unit struct, may not match and filter the values
+                let irrefutable = is_plain_name(pat);
+                // the last binding of `iter!` maps its values to the yield directly, filtering them
+                // by the guards after it, instead of flattening an `Option` for each of them
+                let guards = tail
+                    .iter()
+                    .map(|clause| match clause {
+                        Clause::Guard(Guard {
+                            cond,
+                            negated,
+                            otherwise: None,
+                        }) => {
+                            let span = located_at(cond.span());
+                            if *negated {
+                                Some(quote_spanned!(span=> !(#cond)))
+                            } else {
+                                Some(quote!(#cond))
+                            }
+                        }
+                        _ => None,
+                    })
+                    .collect::<Option<Vec<_>>>();
+                if let (Kind::Iter, Some(guards)) = (self, guards) {
+                    let value = match ret {
+                        Some(expr) => quote!(#expr),
+                        None => quote!(()),
+                    };
+                    let yielded = if guards.is_empty() {
+                        quote!(::core::option::Option::Some(#value))
+                    } else {
+                        quote! {
+                            if #( #guards )&&* {
+                                ::core::option::Option::Some(#value)
+                            } else {
+                                ::core::option::Option::None
+                            }
+                        }
+                    };
+                    return match (irrefutable, guards.is_empty()) {
+                        (true, true) => quote_spanned! {span=>
+                            ::core::iter::Iterator::map(
+                                ::comp::__into_iter!(#expr),
+                                move |#param| #value,
+                            )
+                        },
+                        (true, false) => quote_spanned! {span=>
+                            ::core::iter::Iterator::filter_map(
+                                ::comp::__into_iter!(#expr),
+                                move |#param| #yielded,
+                            )
+                        },
+                        (false, _) => quote_spanned! {span=>
+                            ::core::iter::Iterator::filter_map(
+                                ::comp::__into_iter!(#expr),
+                                move |#arg| {
+                                    #[allow(unreachable_patterns)]
+                                    let rest = match ::core::convert::identity(v) {
+                                        #pat => #yielded,
+                                        _ => ::core::option::Option::None,
+                                    };
+                                    rest
+                                },
+                            )
+                        },
+                    };
+                }
+                match self {
+                    Kind::Option if ty.is_none() || !irrefutable => quote_spanned! {span=>
+                        (#expr).and_then(move |#arg| {
+                            #[allow(unreachable_patterns)]
+                            let rest = match ::core::convert::identity(v) {
+                                #pat => { #rest }
+                                _ => ::core::option::Option::None,
+                            };
+                            rest
+                        })
+                    },
+                    Kind::Option | Kind::Result => quote_spanned! {span=>
+                        (#expr).and_then(move |#param| { #rest })
+                    },
+                    Kind::Iter if !irrefutable => quote_spanned! {span=>
+                        ::core::iter::Iterator::flatten(::core::iter::Iterator::filter_map(
+                            ::comp::__into_iter!(#expr),
+                            move |#arg| {
+                                #[allow(unreachable_patterns)]
+                                let rest = match ::core::convert::identity(v) {
+                                    #pat => ::core::option::Option::Some({ #rest }),
+                                    _ => ::core::option::Option::None,
+                                };
+                                rest
+                            },
+                        ))
+                    },
+                    Kind::Iter => quote_spanned! {span=>
+                        ::core::iter::Iterator::flat_map(
+                            ::comp::__into_iter!(#expr),
+                            move |#param| { #rest },
+                        )
+                    },
+                    Kind::Comp => quote_spanned! {span=>
+                        ::comp::Bind::bind(#expr, move |#param| { #rest })
+                    },
+                }
+            }
+            Clause::Guard(Guard {
+                cond,
+                negated,
+                otherwise,
+            }) => {
+                let span = located_at(cond.span());
+                let cond = if *negated {
+                    quote_spanned!(span=> !(#cond))
+                } else {
+                    quote!(#cond)
+                };
+                match (self, otherwise) {
+                    (Kind::Result, Some(err)) => quote_spanned! {span=>
+                        if #cond { #rest } else { ::core::result::Result::Err(#err) }
+                    },
+                    (Kind::Result, None) => syn::Error::new(
+                        span,
+                        "`if` guard in `result!` requires an error: `if condition else error;`",
+                    )
+                    .to_compile_error(),
+                    (_, Some(err)) => {
+                        let span = located_at(err.span());
+                        syn::Error::new(span, "a guard error is only supported in `result!`")
+                            .to_compile_error()
+                    }
+                    (Kind::Iter, None) => quote_spanned! {span=>
+                        ::comp::__guard(if #cond {
+                            ::core::option::Option::Some({ #rest })
+                        } else {
+                            ::core::option::Option::None
+                        })
+                    },
+                    (Kind::Option, None) => quote_spanned! {span=>
+                        if #cond { #rest } else { ::core::option::Option::None }
+                    },
+                    (Kind::Comp, None) => syn::Error::new(
+                        span,
+                        "`if` guard is only supported in `iter!`, `option!` and `result!`",
+                    )
+                    .to_compile_error(),
+                }
+            }
+            Clause::Boxed(keyword) => {
+                let span = located_at(keyword.span());
+                if self != Kind::Iter {
+                    return syn::Error::new(span, "`boxed` is only supported in `iter!`")
+                        .to_compile_error();
+                }
+                quote_spanned! {span=>
+                    ::comp::__alloc::boxed::Box::new(#rest)
+                        as ::comp::__alloc::boxed::Box<dyn ::core::iter::Iterator<Item = _> + '_>
+                }
+            }
+            Clause::Yield(from, expr) => {
+                let span = located_at(expr.span());
+                if self != Kind::Iter {
+                    return syn::Error::new(span, "`yield` is only supported in `iter!`")
+                        .to_compile_error();
+                }
+                let yielded = match from {
+                    Some(_) => quote_spanned!(span=> ::comp::__into_iter!(#expr)),
+                    None => quote_spanned!(span=> ::core::iter::once(#expr)),
+                };
+                // a `yield` at the end doesn't yield `()` after it
+                if tail.is_empty() && ret.is_none() {
+                    yielded
+                } else {
+                    quote_spanned!(span=> ::core::iter::Iterator::chain(#yielded, #rest))
+                }
+            }
+            Clause::Exit(exit) => self.exit(exit),
+            Clause::Branch(branch) => {
+                let label = Lifetime::new("'exit", Span::mixed_site());
+                let mut chain = TokenStream2::new();
+                for (head, (brace, block)) in branch.heads.iter().zip(&branch.blocks) {
+                    chain.extend(head.clone());
+                    brace.surround(&mut chain, |tokens| {
+                        for clause in block {
+                            match clause {
+                                Clause::Exit(exit) => {
+                                    // a pass of `iter!` is dropped by breaking out with `None`
+                                    // instead of the iterator of the remaining clauses
+                                    let value = match (self, exit) {
+                                        (Kind::Iter, Exit::Continue(_)) => {
+                                            quote!(::core::option::Option::None)
+                                        }
+                                        _ => self.exit(exit),
+                                    };
+                                    tokens.extend(quote!(break #label #value;));
+                                }
+                                Clause::Stmt(stmt) => stmt.to_tokens(tokens),
+                                _ => unreachable!(),
+                            }
+                        }
+                    });
+                }
+                // without `;` at the end, the chain is the value of the comprehension
+                let (chain, value) = match (&branch.semi, tail.is_empty() && ret.is_none()) {
+                    (None, true) => (quote!(), self.pure(&Some(Expr::Verbatim(chain)))),
+                    (semi, _) => (quote!(#chain #semi), rest),
+                };
+                match self {
+                    Kind::Iter => quote! {
+                        ::comp::__guard(#label: {
+                            #chain
+                            ::core::option::Option::Some(#value)
+                        })
+                    },
+                    _ => quote!(#label: { #chain #value }),
+                }
+            }
+            Clause::Stmt(stmt) => quote!({ #stmt #rest }),
+        }
+    }
+
+    /// What the comprehension evaluates to on the exit.
+    fn exit(self, exit: &Exit) -> TokenStream2 {
+        let span = located_at(exit.span());
+        match (exit, self) {
+            (Exit::Return(_, expr), Kind::Option) => {
+                quote_spanned!(span=> ::core::option::Option::Some(#expr))
+            }
+            (Exit::Return(_, expr), Kind::Result) => {
+                quote_spanned!(span=> ::core::result::Result::Ok(#expr))
+            }
+            (Exit::Fail(_, None), Kind::Option) => {
+                quote_spanned!(span=> ::core::option::Option::None)
+            }
+            (Exit::Fail(_, Some(err)), Kind::Result) => {
+                quote_spanned!(span=> ::core::result::Result::Err(#err))
+            }
+            (Exit::Fail(_, Some(err)), Kind::Option) => {
+                let span = located_at(err.span());
+                syn::Error::new(span, "`fail` in `option!` takes no error").to_compile_error()
+            }
+            (Exit::Fail(_, None), Kind::Result) => {
+                let message = "`fail` in `result!` requires an error: `fail error;`";
+                syn::Error::new(span, message).to_compile_error()
+            }
+            (Exit::Return(..), Kind::Iter | Kind::Comp) => {
+                let message = "`return` is only supported in `option!` and `result!`";
+                syn::Error::new(span, message).to_compile_error()
+            }
+            (Exit::Fail(..), Kind::Iter | Kind::Comp) => {
+                let message = "`fail` is only supported in `option!` and `result!`";
+                syn::Error::new(span, message).to_compile_error()
+            }
+            (Exit::Continue(_), Kind::Iter) => quote_spanned!(span=> ::core::iter::empty()),
+            (Exit::Continue(_), _) => {
+                syn::Error::new(span, "`continue` is only supported in `iter!`").to_compile_error()
+            }
+        }
+    }
+
+    /// Applies the alternatives, the adapters and the error handling of a binding to its
+    /// expression.
+    fn source(self, binding: &Binding) -> Result<TokenStream2, TokenStream2> {
+        let Binding {
+            expr,
+            alternatives,
+            adapters,
+            memo,
+            context,
+            retry,
+            handler,
+            ..
+        } = binding;
+        let mut expr = quote!(#expr);
+        for alternative in alternatives {
+            let span = located_at(alternative.span());
+            expr = match self {
+                Kind::Option => quote_spanned!(span=> (#expr).or_else(|| #alternative)),
+                Kind::Result => quote_spanned!(span=> (#expr).or_else(|_| #alternative)),
+                Kind::Iter => quote_spanned! {span=>
+                    ::core::iter::Iterator::chain(
+                        ::comp::__into_iter!(#expr),
+                        #alternative,
+                    )
+                },
+                Kind::Comp => {
+                    return Err(syn::Error::new(
+                        span,
+                        "`or` is only supported in `option!`, `result!` and `iter!`",
+                    )
+                    .to_compile_error());
+                }
+            };
+        }
+        if let Some(memo) = memo {
+            if self != Kind::Iter {
+                return Err(syn::Error::new(memo.span(), "`memo` is only supported in `iter!`")
+                    .to_compile_error());
+            }
+        }
+        for (name, args) in adapters {
+            let span = located_at(name.span());
+            if (name == "inspect" || name == "timed") && self != Kind::Iter {
+                let ty = match self {
+                    Kind::Option => quote!(::core::option::Option),
+                    Kind::Result => quote!(::core::result::Result),
+                    Kind::Iter | Kind::Comp => {
+                        let message = format!(
+                            "`{}` is only supported in `option!`, `result!` and `iter!`",
+                            name,
+                        );
+                        return Err(syn::Error::new(span, message).to_compile_error());
+                    }
+                };
+                let f = &args[0];
+                expr = match name.to_string().as_str() {
+                    "inspect" => quote_spanned!(span=> #ty::inspect(#expr, #f)),
+                    _ => quote_spanned! {span=>
+                        ::comp::__timed_value(::comp::__std::time::Instant::now(), #expr, #f)
+                    },
+                };
+                continue;
+            }
+            if self != Kind::Iter {
+                let message = format!("`{}` is only supported in `iter!`", name);
+                return Err(syn::Error::new(span, message).to_compile_error());
+            }
+            let helpers = ["windows", "sorted_by_key", "unique", "chunks", "timed", "by_value"];
+            let (helper, args) = match args.first().and_then(window_tuple) {
+                Some(tuple) if name == "windows" => (Some(tuple.to_string()), &args[..0]),
+                _ if name == "join" => (Some("__hash_join".to_string()), &args[..]),
+                _ if name == "merge_join" => (Some("__merge_join".to_string()), &args[..]),
+                _ if helpers.iter().any(|h| name == h) => {
+                    (Some(format!("__{}", name)), &args[..])
+                }
+                _ => (None, &args[..]),
+            };
+            expr = match helper {
+                Some(helper) => {
+                    let helper = Ident::new(&helper, span);
+                    quote_spanned!(span=> ::comp::#helper(#expr #( , #args )*))
+                }
+                None => quote_spanned! {span=>
+                    ::core::iter::Iterator::#name(
+                        ::comp::__into_iter!(#expr)
+                        #( , #args )*
+                    )
+                },
+            };
+        }
+        if let Some(context) = context {
+            let span = located_at(context.span());
+            if self != Kind::Result {
+                return Err(syn::Error::new(span, "`context` is only supported in `result!`")
+                    .to_compile_error());
+            }
+            expr = quote_spanned! {span=>
+                ::comp::__anyhow::Context::context(#expr, #context)
+            };
+        }
+        if let Some(Retry { times, backoff }) = retry {
+            let span = located_at(times.span());
+            if self != Kind::Result {
+                return Err(syn::Error::new(span, "`retry` is only supported in `result!`")
+                    .to_compile_error());
+            }
+            let backoff = match backoff {
+                Some(backoff) => quote!(#backoff),
+                None => quote!(|_| ()),
+            };
+            expr = quote_spanned! {span=>
+                {
+                    #[allow(unused_mut)]
+                    let mut backoff = #backoff;
+                    let mut retries = 0;
+                    loop {
+                        match #expr {
+                            ::core::result::Result::Err(_) if retries < #times => {
+                                retries += 1;
+                                backoff(retries);
+                            }
+                            result => break result,
+                        }
+                    }
+                }
+            };
+        }
+        if let Some(handler) = handler {
+            let span = located_at(handler.span());
+            if self != Kind::Result {
+                return Err(syn::Error::new(span, "`catch` is only supported in `result!`")
+                    .to_compile_error());
+            }
+            expr = quote_spanned!(span=> (#expr).or_else(#handler));
+        }
+        if cfg!(feature = "trace") && matches!(self, Kind::Option | Kind::Result) {
+            let span = located_at(binding.expr.span());
+            expr = quote_spanned!(span=> ::comp::__trace!(#expr));
+        }
+        Ok(expr)
+    }
+
+    fn pure(self, ret: &Option<Expr>) -> TokenStream2 {
+        let (value, span) = match ret {
+            Some(expr) => (quote!(#expr), located_at(expr.span())),
+            None => (quote!(()), Span::call_site()),
+        };
+        match self {
+            Kind::Option => quote_spanned!(span=> ::core::option::Option::Some(#value)),
+            Kind::Iter => quote_spanned!(span=> ::core::iter::once(#value)),
+            Kind::Result => quote_spanned!(span=> ::core::result::Result::Ok(#value)),
+            Kind::Comp => quote_spanned!(span=> ::comp::Pure::pure(#value)),
+        }
+    }
+}
+
+/// `(type Error = type;)? (from_fn; | send; | strict;)* ((fold | find | partition)(...); | (sum
+/// | product | count | min | max | first | any | all | unzip); | collect into type;
+/// | extend target;)?
+/// (from_fn; | send; | strict;)*
+/// clause* expression?`
+struct Body {
+    error: Option<Type>,
+    from_fn: Option<Ident>,
+    send: Option<Ident>,
+    terminal: Option<(Ident, Vec<Expr>)>,
+    collection: Option<Type>,
+    clauses: Vec<Clause>,
+    ret: Option<Expr>,
+}
+
+enum Clause {
+    Bind(Box<Binding>),
+    Guard(Guard),
+    Boxed(Ident),
+    /// `yield expression;` or `yield from expression;`
+    Yield(Option<Ident>, Expr),
+    /// `return expression;`, `fail;`, `fail error;`, `continue;` or `skip;`
+    Exit(Exit),
+    /// an `if` chain whose blocks may exit the comprehension
+    Branch(Branch),
+    Stmt(Stmt),
+}
+
+/// `let pattern (: type)? <- memo? (enumerate | scan(initial, f) over)? expression
+/// (or expression)* (zip expression)*
+/// (, context(message) | , retry(...) | , step_by step | , chunks size | , windows size | , rev |
+/// , copied | , cloned | , by_value | , sorted_by_key(key))?
+/// (=> error | else default)?; ((take | skip) count; | unique; | (inspect | timed) f;
+/// | (join | merge_join) pattern in expression on key == key;)*
+/// (catch handler; | while condition; | skip_while condition;)?`
+struct Binding {
+    pat: Pat,
+    ty: Option<Type>,
+    expr: Expr,
+    alternatives: Vec<Expr>,
+    adapters: Vec<(Ident, Vec<Expr>)>,
+    memo: Option<Ident>,
+    context: Option<Expr>,
+    retry: Option<Retry>,
+    handler: Option<Expr>,
+    limit: Option<Expr>,
+    skip: Option<Expr>,
+    otherwise: Option<Expr>,
+    fallback: Option<Expr>,
+}
+
+/// `return expression`, `fail`, `fail error`, `continue` or `skip`, which stops evaluating the
+/// remaining clauses
+enum Exit {
+    Return(Token![return], Expr),
+    Fail(Ident, Option<Expr>),
+    Continue(Span),
+}
+
+/// `if condition { ... } (else if condition { ... })* (else { ... })? ;?`, where any statement
+/// of a block may be an exit
+struct Branch {
+    /// `if condition`, `else if condition` or `else` before each block
+    heads: Vec<TokenStream2>,
+    blocks: Vec<(token::Brace, Vec<Clause>)>,
+    semi: Option<Token![;]>,
+}
+
+/// `retry(times (, backoff)?)`
+struct Retry {
+    times: Expr,
+    backoff: Option<Expr>,
+}
+
+/// `(if | guard | unless) condition (=> error)?;`, `if condition else error;` or
+/// `ensure condition, error;`
+struct Guard {
+    cond: Expr,
+    negated: bool,
+    otherwise: Option<Expr>,
+}
+
+impl Body {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let error = if is_error_type(input) {
+            input.parse::<Token![type]>()?;
+            input.parse::<Ident>()?;
+            input.parse::<Token![=]>()?;
+            let error = input.parse()?;
+            input.parse::<Token![;]>()?;
+            Some(error)
+        } else {
+            None
+        };
+        let (mut from_fn, mut send, mut strict) = (None, None, None);
+        parse_modes(input, &mut from_fn, &mut send, &mut strict)?;
+        let mut collection = None;
+        let terminal = if is_terminal(input) {
+            let name = input.parse::<Ident>()?;
+            let mut args = Vec::new();
+            if name == "collect" {
+                input.parse::<Ident>()?;
+                collection = Some(input.parse()?);
+            } else if name == "extend" {
+                args.push(input.parse()?);
+            } else if !input.peek(Token![;]) {
+                let group;
+                syn::parenthesized!(group in input);
+                args.extend(Punctuated::<Expr, Token![,]>::parse_terminated(&group)?);
+            }
+            input.parse::<Token![;]>()?;
+            Some((name, args))
+        } else {
+            None
+        };
+        parse_modes(input, &mut from_fn, &mut send, &mut strict)?;
+        let mut clauses = Vec::new();
+        let mut clones = Vec::new();
+        let mut ret = None;
+
+        while !input.is_empty() {
+            if input.peek(Token![let]) && is_binding(input) {
+                clauses.push(Clause::Bind(Box::new(input.parse()?)));
+                continue;
+            }
+
+            if input.peek(Token![for]) && is_for_binding(input) {
+                clauses.push(Clause::Bind(Box::new(parse_for_binding(input)?)));
+                continue;
+            }
+
+            if input.peek(Token![while]) && is_take_while(input) {
+                let keyword = input.parse::<Token![while]>()?;
+                let cond = Expr::parse_without_eager_brace(input)?;
+                input.parse::<Token![;]>()?;
+                match clauses.last_mut() {
+                    Some(Clause::Bind(binding))
+                        if binding.limit.is_none() && binding.skip.is_none() =>
+                    {
+                        binding.limit = Some(cond);
+                    }
+                    _ => {
+                        return Err(syn::Error::new(
+                            keyword.span,
+                            "`while condition;` must follow a binding",
+                        ));
+                    }
+                }
+                continue;
+            }
+
+            if is_adapter(input) {
+                let name = input.parse::<Ident>()?;
+                let arg = if name == "unique" {
+                    None
+                } else {
+                    Some(input.parse()?)
+                };
+                let message = match arg {
+                    Some(_) if name == "inspect" || name == "timed" => {
+                        format!("`{} f;` must follow a binding", name)
+                    }
+                    Some(_) => format!("`{} count;` must follow a binding", name),
+                    None => format!("`{};` must follow a binding", name),
+                };
+                input.parse::<Token![;]>()?;
+                match clauses.last_mut() {
+                    Some(Clause::Bind(binding))
+                        if binding.limit.is_none() && binding.skip.is_none() =>
+                    {
+                        binding.adapters.push((name, arg.into_iter().collect()));
+                    }
+                    _ => {
+                        return Err(syn::Error::new(name.span(), message));
+                    }
+                }
+                continue;
+            }
+
+            if is_join(input) {
+                let keyword = input.parse::<Ident>()?;
+                let other = Pat::parse_single(input)?;
+                input.parse::<Token![in]>()?;
+                let source = input.parse::<Expr>()?;
+                input.parse::<Ident>()?;
+                let (left, right) = match input.parse()? {
+                    Expr::Binary(syn::ExprBinary {
+                        left,
+                        op: syn::BinOp::Eq(_),
+                        right,
+                        ..
+                    }) => (left, right),
+                    cond => return Err(syn::Error::new(cond.span(), "expected `key == key`")),
+                };
+                input.parse::<Token![;]>()?;
+                match clauses.last_mut() {
+                    Some(Clause::Bind(binding))
+                        if binding.limit.is_none() && binding.skip.is_none() =>
+                    {
+                        let pat = &binding.pat;
+                        let param = match &binding.ty {
+                            Some(ty) => quote!(#pat: #ty),
+                            None => quote!(#pat),
+                        };
+                        let span = located_at(keyword.span());
+                        let outer_key = syn::parse_quote_spanned!(span=> move |#param| #left);
+                        let inner_key = syn::parse_quote_spanned!(span=> move |#other| #right);
+                        binding.adapters.push((keyword, vec![source, outer_key, inner_key]));
+                        binding.pat = syn::parse_quote!((#pat, #other));
+                        if let Some(ty) = &binding.ty {
+                            binding.ty = Some(syn::parse_quote!((#ty, _)));
+                        }
+                    }
+                    _ => {
+                        let message = format!("`{}` must follow a binding", keyword);
+                        return Err(syn::Error::new(keyword.span(), message));
+                    }
+                }
+                continue;
+            }
+
+            if is_skip_while(input) {
+                let keyword = input.parse::<Ident>()?;
+                let cond = input.parse()?;
+                input.parse::<Token![;]>()?;
+                match clauses.last_mut() {
+                    Some(Clause::Bind(binding))
+                        if binding.limit.is_none() && binding.skip.is_none() =>
+                    {
+                        binding.skip = Some(cond);
+                    }
+                    _ => {
+                        return Err(syn::Error::new(
+                            keyword.span(),
+                            "`skip_while condition;` must follow a binding",
+                        ));
+                    }
+                }
+                continue;
+            }
+
+            if is_catch(input) {
+                let catch = input.parse::<Ident>()?;
+                match clauses.last_mut() {
+                    Some(Clause::Bind(binding)) if binding.handler.is_none() => {
+                        binding.handler = Some(input.parse()?);
+                        input.parse::<Token![;]>()?;
+                    }
+                    _ => {
+                        return Err(syn::Error::new(
+                            catch.span(),
+                            "`catch` must follow a binding",
+                        ));
+                    }
+                }
+                continue;
+            }
+
+            if input.peek(Token![ref]) {
+                input.parse::<Token![ref]>()?;
+                let names = Punctuated::<Ident, Token![,]>::parse_separated_nonempty(input)?;
+                input.parse::<Token![;]>()?;
+                for name in names {
+                    let span = located_at(name.span());
+                    let stmt = syn::parse_quote_spanned!(span=> let #name = &#name;);
+                    clauses.push(Clause::Stmt(stmt));
+                }
+                continue;
+            }
+
+            if is_clone(input) {
+                input.parse::<Ident>()?;
+                let names = Punctuated::<Ident, Token![,]>::parse_separated_nonempty(input)?;
+                input.parse::<Token![;]>()?;
+                for name in names {
+                    clones.push((clauses.len(), name));
+                }
+                continue;
+            }
+
+            if is_boxed(input) {
+                clauses.push(Clause::Boxed(input.parse()?));
+                input.parse::<Token![;]>()?;
+                continue;
+            }
+
+            if input.peek(Token![yield]) {
+                input.parse::<Token![yield]>()?;
+                let from = if is_yield_from(input) {
+                    Some(input.parse()?)
+                } else {
+                    None
+                };
+                clauses.push(Clause::Yield(from, input.parse()?));
+                input.parse::<Token![;]>()?;
+                continue;
+            }
+
+            if (input.peek(Token![if]) && is_guard(input)) || is_keyword_guard(input) {
+                clauses.push(Clause::Guard(input.parse()?));
+                continue;
+            }
+
+            if is_exit(input) {
+                clauses.push(Clause::Exit(input.parse()?));
+                input.parse::<Token![;]>()?;
+                continue;
+            }
+
+            if input.peek(Token![if]) && is_branch(input) {
+                clauses.push(Clause::Branch(input.parse()?));
+                continue;
+            }
+
+            if is_last_expr(input) {
+                ret = Some(input.parse()?);
+                break;
+            }
+
+            clauses.push(Clause::Stmt(input.parse()?));
+        }
+
+        let yields = match clauses.last() {
+            Some(Clause::Yield(..) | Clause::Exit(_)) => true,
+            Some(Clause::Branch(branch)) => branch.semi.is_none(),
+            _ => false,
+        };
+        if let (Some(strict), None, false) = (&strict, &ret, yields) {
+            let span = match clauses.last() {
+                Some(Clause::Bind(binding)) => binding.expr.span(),
+                Some(Clause::Guard(guard)) => guard.cond.span(),
+                Some(Clause::Boxed(boxed)) => boxed.span(),
+                Some(Clause::Yield(_, expr)) => expr.span(),
+                Some(Clause::Exit(exit)) => exit.span(),
+                Some(Clause::Branch(branch)) => branch.heads[0].span(),
+                Some(Clause::Stmt(stmt)) => stmt.span(),
+                None => {
+                    return Err(syn::Error::new(
+                        strict.span(),
+                        "the comprehension is empty and yields `()`, \
+                         yield `()` explicitly if intended",
+                    ));
+                }
+            };
+            return Err(syn::Error::new(
+                span,
+                "the comprehension yields `()` because it ends with `;`, \
+                 yield `()` explicitly if intended",
+            ));
+        }
+
+        if !clones.is_empty() {
+            clauses = insert_clones(clauses, &clones);
+        }
+        Ok(Body {
+            error,
+            from_fn,
+            send,
+            terminal,
+            collection,
+            clauses,
+            ret,
+        })
+    }
+}
+
+impl Parse for Binding {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        input.parse::<Token![let]>()?;
+
+        let mut binder = TokenStream2::new();
+        while !input.peek(Token![<-]) {
+            binder.extend(Some(input.parse::<TokenTree>()?));
+        }
+        let arrow: Token![<-] = input.parse()?;
+        if binder.is_empty() {
+            return Err(syn::Error::new(arrow.span(), "expected pattern before `<-`"));
+        }
+        let (pat, ty) = parse_binder.parse2(binder)?;
+
+        if input.is_empty() || input.peek(Token![;]) {
+            return Err(syn::Error::new(arrow.span(), "expected expression after `<-`"));
+        }
+        let mut adapters = Vec::new();
+        let memo = if is_memo(input) {
+            Some(input.parse::<Ident>()?)
+        } else {
+            None
+        };
+        if is_enumerate(input) {
+            adapters.push((input.parse::<Ident>()?, Vec::new()));
+        } else if is_scan(input) {
+            let scan = input.parse::<Ident>()?;
+            let args;
+            syn::parenthesized!(args in input);
+            let args = Punctuated::<Expr, Token![,]>::parse_terminated(&args)?;
+            input.parse::<Ident>()?;
+            adapters.push((scan, args.into_iter().collect()));
+        }
+        let expr = input.parse()?;
+        let mut alternatives = Vec::new();
+        while is_or(input) {
+            input.parse::<Ident>()?;
+            alternatives.push(input.parse()?);
+        }
+        while is_zip(input) {
+            adapters.push((input.parse::<Ident>()?, vec![input.parse()?]));
+        }
+        let mut context = None;
+        let mut retry = None;
+        if input.peek(Token![,]) {
+            input.parse::<Token![,]>()?;
+            let ident = input.parse::<Ident>()?;
+            if ident == "step_by" || ident == "chunks" || ident == "windows" {
+                let arg = input.parse()?;
+                adapters.push((ident, vec![arg]));
+            } else if ["rev", "copied", "cloned", "by_value"].iter().any(|name| ident == name) {
+                adapters.push((ident, Vec::new()));
+            } else if ident == "sorted_by_key" {
+                let args;
+                syn::parenthesized!(args in input);
+                adapters.push((ident, vec![args.parse()?]));
+            } else if ident == "context" {
+                let args;
+                syn::parenthesized!(args in input);
+                context = Some(args.parse()?);
+            } else if ident == "retry" {
+                let args;
+                syn::parenthesized!(args in input);
+                let times = args.parse()?;
+                let backoff = if args.is_empty() {
+                    None
+                } else {
+                    args.parse::<Token![,]>()?;
+                    Some(args.parse()?)
+                };
+                retry = Some(Retry { times, backoff });
+            } else {
+                return Err(syn::Error::new(
+                    ident.span(),
+                    "expected `context(message)`, `retry(times)`, `step_by step`, `chunks size`, \
+                     `windows size`, `rev`, `copied`, `cloned`, `by_value` or `sorted_by_key(key)`",
+                ));
+            }
+        }
+        let otherwise = if input.peek(Token![=>]) {
+            input.parse::<Token![=>]>()?;
+            Some(input.parse()?)
+        } else {
+            None
+        };
+        let fallback = if otherwise.is_none() && input.peek(Token![else]) {
+            input.parse::<Token![else]>()?;
+            Some(input.parse()?)
+        } else {
+            None
+        };
+        if !input.peek(Token![;]) {
+            return Err(input.error("expected `;` after the bound expression"));
+        }
+        input.parse::<Token![;]>()?;
+
+        Ok(Binding {
+            pat,
+            ty,
+            expr,
+            alternatives,
+            adapters,
+            memo,
+            context,
+            retry,
+            handler: None,
+            limit: None,
+            skip: None,
+            otherwise,
+            fallback,
+        })
+    }
+}
+
+impl Parse for Guard {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let keyword = if input.peek(Token![if]) {
+            input.parse::<Token![if]>()?;
+            None
+        } else {
+            Some(input.parse::<Ident>()?)
+        };
+        let negated = keyword.as_ref().is_some_and(|ident| ident == "unless");
+        let cond = Expr::parse_without_eager_brace(input)?;
+        let otherwise = if keyword.as_ref().is_some_and(|ident| ident == "ensure") {
+            input.parse::<Token![,]>()?;
+            Some(input.parse()?)
+        } else if input.peek(Token![=>]) {
+            input.parse::<Token![=>]>()?;
+            Some(input.parse()?)
+        } else if input.peek(Token![else]) {
+            input.parse::<Token![else]>()?;
+            Some(input.parse()?)
+        } else {
+            None
+        };
+        input.parse::<Token![;]>()?;
+
+        Ok(Guard {
+            cond,
+            negated,
+            otherwise,
+        })
+    }
+}
+
+impl Parse for Exit {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        if input.peek(Token![return]) {
+            return Ok(Exit::Return(input.parse()?, input.parse()?));
+        }
+        if input.peek(Token![continue]) {
+            return Ok(Exit::Continue(input.parse::<Token![continue]>()?.span));
+        }
+        let keyword = input.parse::<Ident>()?;
+        if keyword == "skip" {
+            return Ok(Exit::Continue(keyword.span()));
+        }
+        let error = if input.is_empty() || input.peek(Token![;]) {
+            None
+        } else {
+            Some(input.parse()?)
+        };
+        Ok(Exit::Fail(keyword, error))
+    }
+}
+
+impl Exit {
+    fn span(&self) -> Span {
+        match self {
+            Exit::Return(keyword, _) => keyword.span,
+            Exit::Fail(keyword, _) => keyword.span(),
+            Exit::Continue(span) => *span,
+        }
+    }
+}
+
+impl Parse for Branch {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut heads = Vec::new();
+        let mut blocks = Vec::new();
+        loop {
+            let mut head = TokenStream2::new();
+            if !blocks.is_empty() {
+                input.parse::<Token![else]>()?.to_tokens(&mut head);
+            }
+            if blocks.is_empty() || input.peek(Token![if]) {
+                input.parse::<Token![if]>()?.to_tokens(&mut head);
+                Expr::parse_without_eager_brace(input)?.to_tokens(&mut head);
+            }
+            let content;
+            let brace = syn::braced!(content in input);
+            let mut block = Vec::new();
+            while !content.is_empty() {
+                if is_exit(&content) {
+                    block.push(Clause::Exit(content.parse()?));
+                    if !content.is_empty() {
+                        content.parse::<Token![;]>()?;
+                    }
+                } else if is_last_expr(&content) {
+                    block.push(Clause::Stmt(Stmt::Expr(content.parse()?, None)));
+                } else {
+                    block.push(Clause::Stmt(content.parse()?));
+                }
+            }
+            heads.push(head);
+            blocks.push((brace, block));
+            if !input.peek(Token![else]) {
+                break;
+            }
+        }
+        let semi = input.parse()?;
+        Ok(Branch {
+            heads,
+            blocks,
+            semi,
+        })
+    }
+}
+
+/// `for pattern in expression;`, the same as `let pattern <- expression;`
+fn parse_for_binding(input: ParseStream) -> syn::Result<Binding> {
+    input.parse::<Token![for]>()?;
+    let pat = Pat::parse_multi(input)?;
+    input.parse::<Token![in]>()?;
+    let expr = Expr::parse_without_eager_brace(input)?;
+    input.parse::<Token![;]>()?;
+
+    Ok(Binding {
+        pat,
+        ty: None,
+        expr,
+        alternatives: Vec::new(),
+        adapters: Vec::new(),
+        memo: None,
+        context: None,
+        retry: None,
+        handler: None,
+        limit: None,
+        skip: None,
+        otherwise: None,
+        fallback: None,
+    })
+}
+
+fn parse_binder(input: ParseStream) -> syn::Result<(Pat, Option<Type>)> {
+    let pat = Pat::parse_single(input)?;
+    let ty = if input.peek(Token![:]) {
+        input.parse::<Token![:]>()?;
+        Some(input.parse()?)
+    } else {
+        None
+    };
+    if !input.is_empty() {
+        return Err(input.error("unexpected token in binding, expected `<-`"));
+    }
+
+    Ok((pat, ty))
+}
+
+/// `attribute* visibility struct name (<lifetime>)? (pattern: type, ...) -> type { clause*
+/// expression }`
+struct IterStruct {
+    attrs: Vec<Attribute>,
+    vis: Visibility,
+    name: Ident,
+    generics: Generics,
+    args: Punctuated<PatType, Token![,]>,
+    item: Type,
+    body: TokenStream2,
+    body_span: Span,
+}
+
+impl IterStruct {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let attrs = input.call(Attribute::parse_outer)?;
+        let vis = input.parse()?;
+        input.parse::<Token![struct]>()?;
+        let name = input.parse()?;
+        let generics: Generics = input.parse()?;
+        for (i, param) in generics.params.iter().enumerate() {
+            if i > 0 || !matches!(param, GenericParam::Lifetime(_)) {
+                let message = "`iter_struct!` only supports a single lifetime parameter";
+                return Err(syn::Error::new(param.span(), message));
+            }
+        }
+        let content;
+        syn::parenthesized!(content in input);
+        let args = content.parse_terminated(
+            |input| {
+                Ok(PatType {
+                    attrs: Vec::new(),
+                    pat: Box::new(Pat::parse_single(input)?),
+                    colon_token: input.parse()?,
+                    ty: input.parse()?,
+                })
+            },
+            Token![,],
+        )?;
+        input.parse::<Token![->]>()?;
+        let item = input.parse()?;
+        let content;
+        let brace = syn::braced!(content in input);
+        Ok(IterStruct {
+            attrs,
+            vis,
+            name,
+            generics,
+            args,
+            item,
+            body: content.parse()?,
+            body_span: brace.span.join(),
+        })
+    }
+
+    /// Expands into the struct boxing the comprehension behind a trait object, which also
+    /// requires `Clone` or `Debug` of the comprehension if they are derived.
+    fn expand(&self) -> TokenStream2 {
+        let (mut clone, mut debug) = (false, false);
+        for attr in self.attrs.iter().filter(|attr| attr.path().is_ident("derive")) {
+            let paths = attr.parse_args_with(Punctuated::<syn::Path, Token![,]>::parse_terminated);
+            for path in paths.iter().flatten() {
+                match path.segments.last() {
+                    Some(segment) if segment.ident == "Clone" => clone = true,
+                    Some(segment) if segment.ident == "Debug" => debug = true,
+                    _ => {}
+                }
+            }
+        }
+        let IterStruct { attrs, vis, name, generics, args, item, body, .. } = self;
+        let lifetime = match generics.lifetimes().next() {
+            Some(param) => param.lifetime.to_token_stream(),
+            None => quote!('static),
+        };
+        let inner = match (clone, debug) {
+            (false, false) => quote!(dyn ::core::iter::Iterator<Item = #item> + #lifetime),
+            (true, false) => quote!(dyn ::comp::__CloneIter<#lifetime, Item = #item> + #lifetime),
+            (false, true) => quote!(dyn ::comp::__DebugIter<Item = #item> + #lifetime),
+            (true, true) => {
+                quote!(dyn ::comp::__CloneDebugIter<#lifetime, Item = #item> + #lifetime)
+            }
+        };
+        let iter = quote_spanned! {located_at(self.body_span)=>
+            ::comp::__alloc::boxed::Box::new(::comp::spanned::iter! { #body })
+        };
+        quote! {
+            #( #attrs )*
+            #vis struct #name #generics {
+                inner: ::comp::__alloc::boxed::Box<#inner>,
+            }
+
+            impl #generics #name #generics {
+                /// Creates the iterator over the comprehension.
+                #vis fn new(#args) -> Self {
+                    #name { inner: #iter }
+                }
+            }
+
+            impl #generics ::core::iter::Iterator for #name #generics {
+                type Item = #item;
+
+                fn next(&mut self) -> ::core::option::Option<#item> {
+                    ::core::iter::Iterator::next(&mut *self.inner)
+                }
+
+                fn size_hint(&self) -> (usize, ::core::option::Option<usize>) {
+                    ::core::iter::Iterator::size_hint(&*self.inner)
+                }
+            }
+        }
+    }
+}
+
+/// Clones the names of `clone name, ...;` clauses where they are declared, and again at the
+/// beginning of the closure of every binding after them, so that each closure owns a clone.
+fn insert_clones(clauses: Vec<Clause>, clones: &[(usize, Ident)]) -> Vec<Clause> {
+    let clone = |name: &Ident| {
+        let span = located_at(name.span());
+        Clause::Stmt(syn::parse_quote_spanned! {span=>
+            let #name = ::core::clone::Clone::clone(&#name);
+        })
+    };
+    let len = clauses.len();
+    let mut result = Vec::new();
+    for (i, clause) in clauses.into_iter().enumerate() {
+        result.extend(clones.iter().filter(|(at, _)| *at == i).map(|(_, name)| clone(name)));
+        let bind = matches!(clause, Clause::Bind(_));
+        result.push(clause);
+        if bind {
+            result.extend(clones.iter().filter(|(at, _)| *at <= i).map(|(_, name)| clone(name)));
+        }
+    }
+    result.extend(clones.iter().filter(|(at, _)| *at == len).map(|(_, name)| clone(name)));
+    result
+}
+
+/// Generated tokens are reported at the location of `span`, but are still known as expanded
+/// from a macro, so that lints are not fired on them.
+fn located_at(span: Span) -> Span {
+    Span::mixed_site().located_at(span)
+}
+
+/// Compiles the clauses of `iter!` into a single `from_fn` state machine instead of nested
+/// closures, for `from_fn;`. Every binding has a level holding its iterator along with the names
+/// bound before it, which are cloned for each of its values, and the deepest level in progress
+/// is resumed on every call.
+fn state_machine(clauses: &[Clause], ret: &Option<Expr>) -> TokenStream2 {
+    let mut prefix = Vec::new();
+    let mut levels: Vec<(&Binding, Vec<&Clause>)> = Vec::new();
+    for clause in clauses {
+        match clause {
+            Clause::Bind(binding) => {
+                let span = match (&binding.skip, &binding.otherwise, &binding.fallback) {
+                    (Some(cond), ..) => Some((cond.span(), "`skip_while`")),
+                    (_, Some(err), _) => Some((err.span(), "`=> error`")),
+                    (.., Some(default)) => Some((default.span(), "`else default`")),
+                    _ => None,
+                };
+                if let Some((span, name)) = span {
+                    let message = format!("{} is not supported with `from_fn`", name);
+                    return syn::Error::new(located_at(span), message).to_compile_error();
+                }
+                levels.push((binding, Vec::new()));
+            }
+            Clause::Boxed(keyword) => {
+                return syn::Error::new(keyword.span(), "`boxed` is not supported with `from_fn`")
+                    .to_compile_error();
+            }
+            Clause::Yield(_, expr) => {
+                let span = located_at(expr.span());
+                return syn::Error::new(span, "`yield` is not supported with `from_fn`")
+                    .to_compile_error();
+            }
+            Clause::Exit(exit) => return Kind::Iter.exit(exit),
+            Clause::Branch(branch) => {
+                let span = located_at(branch.heads[0].span());
+                return syn::Error::new(span, "exits are not supported with `from_fn`")
+                    .to_compile_error();
+            }
+            Clause::Guard(Guard {
+                otherwise: Some(err),
+                ..
+            }) => {
+                let span = located_at(err.span());
+                return syn::Error::new(span, "a guard error is only supported in `result!`")
+                    .to_compile_error();
+            }
+            _ => match levels.last_mut() {
+                Some((_, tail)) => tail.push(clause),
+                None => prefix.push(clause),
+            },
+        }
+    }
+    if levels.is_empty() {
+        return Kind::Iter.expand(clauses, ret);
+    }
+
+    let names = (0..levels.len())
+        .map(|index| Ident::new(&format!("level{}", index), Span::mixed_site()))
+        .collect::<Vec<_>>();
+    let mut sources = Vec::new();
+    for (binding, _) in &levels {
+        match Kind::Iter.source(binding) {
+            Ok(source) => sources.push(source),
+            Err(err) => return err,
+        }
+    }
+
+    // the names bound before each level, which its values are resumed with
+    let mut bound = Vec::new();
+    for clause in &prefix {
+        clause_idents(clause, &mut bound);
+    }
+    let mut scopes = Vec::new();
+    for (binding, tail) in &levels {
+        scopes.push(bound.clone());
+        bound_idents(&binding.pat, &mut bound);
+        for clause in tail {
+            clause_idents(clause, &mut bound);
+        }
+    }
+
+    let source = &sources[0];
+    let scope = &scopes[0];
+    let mut start = quote_spanned! {Span::mixed_site()=>
+        let iter = ::comp::__into_iter!(#source);
+        ::core::option::Option::Some(((#( #scope, )*), iter))
+    };
+    for clause in prefix.iter().rev() {
+        start = match clause {
+            Clause::Guard(guard) => {
+                let cond = guard_cond(guard);
+                quote_spanned! {Span::mixed_site()=>
+                    if #cond { #start } else { ::core::option::Option::None }
+                }
+            }
+            Clause::Stmt(stmt) => quote!({ #stmt #start }),
+            Clause::Bind(_)
+            | Clause::Boxed(_)
+            | Clause::Yield(..)
+            | Clause::Exit(_)
+            | Clause::Branch(_) => unreachable!(),
+        };
+    }
+
+    let mut resumes = Vec::new();
+    for (index, (binding, tail)) in levels.iter().enumerate().rev() {
+        let level = &names[index];
+        let scope = &scopes[index];
+        let next = match (names.get(index + 1), sources.get(index + 1)) {
+            (Some(next), Some(source)) => {
+                let scope = &scopes[index + 1];
+                quote_spanned! {Span::mixed_site()=>
+                    let iter = ::comp::__into_iter!(#source);
+                    #next = ::core::option::Option::Some(((#( #scope, )*), iter));
+                    continue;
+                }
+            }
+            _ => {
+                let value = match ret {
+                    Some(expr) => quote!(#expr),
+                    None => quote!(()),
+                };
+                quote!(return ::core::option::Option::Some(#value);)
+            }
+        };
+        let mut body = Vec::new();
+        if let Some(cond) = &binding.limit {
+            body.push(quote_spanned! {located_at(cond.span())=>
+                if !(#cond) {
+                    #level = ::core::option::Option::None;
+                    continue;
+                }
+            });
+        }
+        for clause in tail {
+            body.push(match clause {
+                Clause::Guard(guard) => {
+                    let cond = guard_cond(guard);
+                    quote_spanned!(Span::mixed_site()=> if !(#cond) { continue; })
+                }
+                Clause::Stmt(stmt) => quote!(#stmt),
+                Clause::Bind(_)
+                | Clause::Boxed(_)
+                | Clause::Yield(..)
+                | Clause::Exit(_)
+                | Clause::Branch(_) => unreachable!(),
+            });
+        }
+        let Binding { pat, ty, .. } = &**binding;
+        let bind = match ty {
+            Some(ty) => quote_spanned! {Span::mixed_site()=>
+                let #pat: #ty = value;
+                #( #body )*
+                #next
+            },
+            None => quote_spanned! {Span::mixed_site()=>
+                #[allow(unreachable_patterns)]
+                match value {
+                    #pat => {
+                        #( #body )*
+                        #next
+                    }
+                    _ => {}
+                }
+                continue;
+            },
+        };
+        resumes.push(quote_spanned! {Span::mixed_site()=>
+            if let ::core::option::Option::Some((scope, iter)) = &mut #level {
+                if let ::core::option::Option::Some(value) = ::core::iter::Iterator::next(iter) {
+                    #[allow(unused_variables, unused_mut)]
+                    let ( #( mut #scope, )* ) = ::core::clone::Clone::clone(&*scope);
+                    #bind
+                }
+                #level = ::core::option::Option::None;
+                continue;
+            }
+        });
+    }
+
+    let first = &names[0];
+    let rest = &names[1..];
+    quote_spanned! {Span::mixed_site()=>
+        {
+            let mut #first = { #start };
+            #( let mut #rest = ::core::option::Option::None; )*
+            ::core::iter::from_fn(move || loop {
+                #( #resumes )*
+                return ::core::option::Option::None;
+            })
+        }
+    }
+}
+
+/// The condition a guard passes on.
+fn guard_cond(guard: &Guard) -> TokenStream2 {
+    let cond = &guard.cond;
+    if guard.negated {
+        quote_spanned!(located_at(cond.span())=> !(#cond))
+    } else {
+        quote!(#cond)
+    }
+}
+
+/// Collects the names bound by a `let` statement into `idents`.
+fn clause_idents(clause: &Clause, idents: &mut Vec<Ident>) {
+    if let Clause::Stmt(Stmt::Local(local)) = clause {
+        bound_idents(&local.pat, idents);
+    }
+}
+
+/// Collects the names bound by the pattern into `idents`.
+fn bound_idents(pat: &Pat, idents: &mut Vec<Ident>) {
+    match pat {
+        Pat::Ident(pat) => {
+            // capitalized names are constants or unit variants by convention
+            if !pat.ident.to_string().starts_with(char::is_uppercase) {
+                idents.push(pat.ident.clone());
+            }
+            if let Some((_, pat)) = &pat.subpat {
+                bound_idents(pat, idents);
+            }
+        }
+        Pat::Or(pat) => {
+            if let Some(pat) = pat.cases.first() {
+                bound_idents(pat, idents);
+            }
+        }
+        Pat::Paren(pat) => bound_idents(&pat.pat, idents),
+        Pat::Reference(pat) => bound_idents(&pat.pat, idents),
+        Pat::Type(pat) => bound_idents(&pat.pat, idents),
+        Pat::Slice(pat) => pat.elems.iter().for_each(|pat| bound_idents(pat, idents)),
+        Pat::Tuple(pat) => pat.elems.iter().for_each(|pat| bound_idents(pat, idents)),
+        Pat::TupleStruct(pat) => pat.elems.iter().for_each(|pat| bound_idents(pat, idents)),
+        Pat::Struct(pat) => pat.fields.iter().for_each(|field| bound_idents(&field.pat, idents)),
+        _ => {}
+    }
+}
+
+/// Moves the sources of the inner bindings of `iter!` out of the closures of the bindings they
+/// don't refer to, so that they are evaluated once and cloned for every value of those bindings
+/// instead of being rebuilt. Only the sources made of literals and paths are moved, along with
+/// the sources of `memo` bindings, which are buffered as well.
+fn hoist_sources(clauses: &mut Vec<Clause>) -> syn::Result<()> {
+    // the identifiers bound so far, with the position of the clause and the number of bindings
+    // enclosing the clauses after it
+    let mut bound = Vec::new();
+    // the declarations of the sources, with their position and the range of bindings whose
+    // closures own a clone of them
+    let mut sources = Vec::new();
+    let mut depth = 0;
+    for (index, clause) in clauses.iter_mut().enumerate() {
+        match clause {
+            Clause::Bind(binding) => {
+                if let Some(memo) = &binding.memo {
+                    let expr = &binding.expr;
+                    binding.expr = syn::parse_quote_spanned!(located_at(memo.span())=>
+                        ::comp::__memo(#expr)
+                    );
+                }
+                let tokens = binding.expr.to_token_stream();
+                let (position, level) = bound
+                    .iter()
+                    .filter(|(ident, _, _)| refers_to(tokens.clone(), ident))
+                    .map(|&(_, position, level)| (position + 1, level))
+                    .max()
+                    .unwrap_or((0, 0));
+                let plain = binding.memo.is_some() || is_plain_source(&binding.expr);
+                if level < depth && plain {
+                    // the clones are reported at the source when it isn't `Clone`
+                    let span = located_at(binding.expr.span());
+                    let name = Ident::new(&format!("source{}", sources.len()), span);
+                    let source = std::mem::replace(
+                        &mut binding.expr,
+                        syn::parse_quote_spanned!(span=> ::core::clone::Clone::clone(&#name)),
+                    );
+                    let decl: Stmt = syn::parse_quote_spanned!(span=> let #name = #source;);
+                    sources.push((name, decl, position, level..depth - 1));
+                } else if let (Some(memo), true) = (&binding.memo, depth > 0) {
+                    let message = "the source of `memo` must not refer to the binding before it";
+                    return Err(syn::Error::new(memo.span(), message));
+                }
+                depth += 1;
+                let mut idents = Vec::new();
+                collect_idents(binding.pat.to_token_stream(), &mut idents);
+                bound.extend(idents.into_iter().map(|ident| (ident, index, depth)));
+            }
+            Clause::Stmt(stmt) => {
+                let mut idents = Vec::new();
+                collect_idents(stmt.to_token_stream(), &mut idents);
+                bound.extend(idents.into_iter().map(|ident| (ident, index, depth)));
+            }
+            Clause::Guard(_) | Clause::Boxed(_) | Clause::Yield(..) => {}
+            Clause::Exit(_) | Clause::Branch(_) => {}
+        }
+    }
+
+    let mut depth = 0;
+    for (index, clause) in std::mem::take(clauses).into_iter().enumerate() {
+        for (_, decl, ..) in sources.iter().filter(|source| source.2 == index) {
+            clauses.push(Clause::Stmt(decl.clone()));
+        }
+        let is_binding = matches!(clause, Clause::Bind(_));
+        clauses.push(clause);
+        if is_binding {
+            // the closure of the next binding is created for every value of this one
+            for (name, ..) in sources.iter().filter(|source| source.3.contains(&depth)) {
+                clauses.push(Clause::Stmt(syn::parse_quote_spanned! {name.span()=>
+                    let #name = ::core::clone::Clone::clone(&#name);
+                }));
+            }
+            depth += 1;
+        }
+    }
+    Ok(())
+}
+
+/// Whether the source is made of literals and paths only, such as `0..n` or `&ys`, so that
+/// evaluating it once has no side effects to skip.
+fn is_plain_source(expr: &Expr) -> bool {
+    match expr {
+        Expr::Lit(_) | Expr::Path(_) => true,
+        Expr::Range(range) => range.start.iter().chain(&range.end).all(|e| is_plain_source(e)),
+        Expr::Reference(reference) => {
+            reference.mutability.is_none() && is_plain_source(&reference.expr)
+        }
+        Expr::Paren(paren) => is_plain_source(&paren.expr),
+        Expr::Array(array) => array.elems.iter().all(is_plain_source),
+        Expr::Tuple(tuple) => tuple.elems.iter().all(is_plain_source),
+        _ => false,
+    }
+}
+
+/// Collects the identifiers in the tokens into `idents`.
+fn collect_idents(tokens: TokenStream2, idents: &mut Vec<Ident>) {
+    for tt in tokens {
+        match tt {
+            TokenTree::Ident(ident) => idents.push(ident),
+            TokenTree::Group(group) => collect_idents(group.stream(), idents),
+            _ => {}
+        }
+    }
+}
+
+/// Whether the tokens contain the identifier.
+fn refers_to(tokens: TokenStream2, ident: &Ident) -> bool {
+    tokens.into_iter().any(|tt| match tt {
+        TokenTree::Ident(other) => other == *ident,
+        TokenTree::Group(group) => refers_to(group.stream(), ident),
+        _ => false,
+    })
+}
+
+/// Whether the input starts with `type Error = ...;` declaring the error type of `result!`.
+fn is_error_type(input: ParseStream) -> bool {
+    let fork = input.fork();
+    fork.parse::<Token![type]>().is_ok()
+        && fork.parse::<Ident>().is_ok_and(|ident| ident == "Error")
+        && fork.peek(Token![=])
+}
+
+/// Whether the input starts with `fold(initial, f);`, `find(predicate);`,
+/// `partition(predicate);`, `sum;`, `product;`, `count;`, `min;`, `max;`, `first;`, `any;`,
+/// `all;`, `unzip;`, `collect into type;` or `extend target;` reducing the yields of `iter!`.
+fn is_terminal(input: ParseStream) -> bool {
+    let fork = input.fork();
+    match fork.parse::<Ident>() {
+        Ok(ident) if ident == "collect" => fork.parse::<Ident>().is_ok_and(|ident| ident == "into"),
+        Ok(ident) if ident == "extend" => fork.parse::<Expr>().is_ok() && fork.peek(Token![;]),
+        Ok(ident) if ident == "fold" || ident == "find" || ident == "partition" => {
+            fork.parse::<TokenTree>().is_ok_and(|tt| match tt {
+                TokenTree::Group(group) => group.delimiter() == Delimiter::Parenthesis,
+                _ => false,
+            }) && fork.peek(Token![;])
+        }
+        Ok(ident) => {
+            let names = ["sum", "product", "count", "min", "max", "first", "any", "all", "unzip"];
+            names.iter().any(|name| ident == name) && fork.peek(Token![;])
+        }
+        Err(_) => false,
+    }
+}
+
+/// Whether the input starts with `let ... <-` rather than an ordinary `let` statement.
+fn is_binding(input: ParseStream) -> bool {
+    let fork = input.fork();
+    while !fork.is_empty() && !fork.peek(Token![;]) {
+        if fork.peek(Token![<-]) {
+            return true;
+        }
+        if fork.parse::<TokenTree>().is_err() {
+            return false;
+        }
+    }
+    false
+}
+
+/// Whether the input starts with `for pattern in expression;` rather than a for-loop.
+fn is_for_binding(input: ParseStream) -> bool {
+    let fork = input.fork();
+    fork.parse::<Token![for]>().is_ok()
+        && Pat::parse_multi(&fork).is_ok()
+        && fork.parse::<Token![in]>().is_ok()
+        && Expr::parse_without_eager_brace(&fork).is_ok()
+        && fork.peek(Token![;])
+}
+
+/// The helper yielding tuples for `, windows size` with a literal size of 2 or 3.
+fn window_tuple(size: &Expr) -> Option<&'static str> {
+    match size {
+        Expr::Lit(syn::ExprLit { lit: syn::Lit::Int(int), .. }) => match int.base10_digits() {
+            "2" => Some("__pairs"),
+            "3" => Some("__triples"),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Whether the input starts with the `or` separating alternatives of a binding.
+fn is_or(input: ParseStream) -> bool {
+    match input.fork().parse::<Ident>() {
+        Ok(ident) => ident == "or",
+        Err(_) => false,
+    }
+}
+
+/// Whether the input starts with the `zip` pairing the source of a binding with another.
+fn is_zip(input: ParseStream) -> bool {
+    match input.fork().parse::<Ident>() {
+        Ok(ident) => ident == "zip",
+        Err(_) => false,
+    }
+}
+
+/// Whether the input starts with `catch` followed by a closure handling the error of the
+/// preceding binding.
+fn is_catch(input: ParseStream) -> bool {
+    let fork = input.fork();
+    fork.parse::<Ident>().is_ok_and(|ident| ident == "catch")
+        && (fork.peek(Token![|]) || fork.peek(Token![||]) || fork.peek(Token![move]))
+}
+
+/// Whether the input starts with `while condition;` rather than a while-loop.
+fn is_take_while(input: ParseStream) -> bool {
+    let fork = input.fork();
+    fork.parse::<Token![while]>().is_ok()
+        && Expr::parse_without_eager_brace(&fork).is_ok()
+        && fork.peek(Token![;])
+}
+
+/// Whether the input starts with `enumerate expression` rather than an expression.
+fn is_enumerate(input: ParseStream) -> bool {
+    let fork = input.fork();
+    fork.parse::<Ident>().is_ok_and(|ident| ident == "enumerate") && fork.parse::<Expr>().is_ok()
+}
+
+/// Whether the input starts with `memo expression` rather than an expression.
+fn is_memo(input: ParseStream) -> bool {
+    let fork = input.fork();
+    fork.parse::<Ident>().is_ok_and(|ident| ident == "memo") && fork.parse::<Expr>().is_ok()
+}
+
+/// Whether the input starts with `scan(initial, f) over`, scanning the source of a binding.
+fn is_scan(input: ParseStream) -> bool {
+    let fork = input.fork();
+    fork.parse::<Ident>().is_ok_and(|ident| ident == "scan")
+        && fork.parse::<TokenTree>().is_ok_and(|tt| match tt {
+            TokenTree::Group(group) => group.delimiter() == Delimiter::Parenthesis,
+            _ => false,
+        })
+        && fork.parse::<Ident>().is_ok_and(|ident| ident == "over")
+}
+
+/// Whether the input starts with `take count;`, `skip count;`, `unique;`, `inspect f;` or
+/// `timed f;` rather than an expression statement, such as a call `take(x);` or `inspect(x);`.
+fn is_adapter(input: ParseStream) -> bool {
+    let fork = input.fork();
+    match fork.parse::<Ident>() {
+        Ok(ident) if ident == "unique" => fork.peek(Token![;]),
+        Ok(ident) if ident == "take" || ident == "skip" || ident == "inspect" => {
+            !fork.peek(token::Paren) && fork.parse::<Expr>().is_ok() && fork.peek(Token![;])
+        }
+        Ok(ident) if ident == "timed" => {
+            fork.parse::<Expr>().is_ok() && fork.peek(Token![;])
+        }
+        _ => false,
+    }
+}
+
+/// Whether the input starts with `join pattern in` or `merge_join pattern in`, joining the
+/// source of a binding with another.
+fn is_join(input: ParseStream) -> bool {
+    let fork = input.fork();
+    fork.parse::<Ident>().is_ok_and(|ident| ident == "join" || ident == "merge_join")
+        && Pat::parse_single(&fork).is_ok()
+        && fork.peek(Token![in])
+}
+
+/// Whether the input starts with `skip_while condition;` rather than an expression statement.
+fn is_skip_while(input: ParseStream) -> bool {
+    let fork = input.fork();
+    fork.parse::<Ident>().is_ok_and(|ident| ident == "skip_while")
+        && fork.parse::<Expr>().is_ok()
+        && fork.peek(Token![;])
+}
+
+/// Parses `from_fn;`, `send;` and `strict;` in any order if the input starts with them.
+fn parse_modes(
+    input: ParseStream,
+    from_fn: &mut Option<Ident>,
+    send: &mut Option<Ident>,
+    strict: &mut Option<Ident>,
+) -> syn::Result<()> {
+    loop {
+        let mode = match input.fork().parse::<Ident>() {
+            Ok(ident) if input.peek2(Token![;]) => ident,
+            _ => return Ok(()),
+        };
+        let slot = match mode.to_string().as_str() {
+            "from_fn" if from_fn.is_none() => &mut *from_fn,
+            "send" if send.is_none() => &mut *send,
+            "strict" if strict.is_none() => &mut *strict,
+            _ => return Ok(()),
+        };
+        *slot = Some(input.parse()?);
+        input.parse::<Token![;]>()?;
+    }
+}
+
+/// Whether the input starts with `clone name, ...;` rather than a statement.
+fn is_clone(input: ParseStream) -> bool {
+    let fork = input.fork();
+    fork.parse::<Ident>().is_ok_and(|ident| ident == "clone")
+        && Punctuated::<Ident, Token![,]>::parse_separated_nonempty(&fork).is_ok()
+        && fork.peek(Token![;])
+}
+
+/// Whether the input starts with `boxed;` rather than a statement.
+fn is_boxed(input: ParseStream) -> bool {
+    let fork = input.fork();
+    fork.parse::<Ident>().is_ok_and(|ident| ident == "boxed") && fork.peek(Token![;])
+}
+
+/// Whether the pattern is a single name binding a new variable, judging by its case, rather
+/// than the name of a constant or a unit struct to match against.
+fn is_plain_name(pat: &Pat) -> bool {
+    match pat {
+        Pat::Ident(ident) if ident.subpat.is_none() => {
+            let name = ident.ident.to_string();
+            name.starts_with(|c: char| c == '_' || c.is_lowercase())
+        }
+        _ => false,
+    }
+}
+
+/// Whether the input after `yield` starts with `from expression`, rather than a name `from`.
+fn is_yield_from(input: ParseStream) -> bool {
+    let fork = input.fork();
+    fork.parse::<Ident>().is_ok_and(|ident| ident == "from") && !fork.peek(Token![;])
+}
+
+/// Whether the input starts with `return`, `continue`, `skip;`, `fail;` or `fail error;`, rather
+/// than an expression statement, such as `fail.push(error);`.
+fn is_exit(input: ParseStream) -> bool {
+    if input.peek(Token![return]) || input.peek(Token![continue]) {
+        return true;
+    }
+    let fork = input.fork();
+    let ident = match fork.parse::<Ident>() {
+        Ok(ident) => ident,
+        Err(_) => return false,
+    };
+    let end = fork.is_empty() || fork.peek(Token![;]);
+    if ident == "skip" {
+        return end;
+    }
+    ident == "fail"
+        && (end || (fork.parse::<Expr>().is_ok() && (fork.is_empty() || fork.peek(Token![;]))))
+}
+
+/// Whether the input starts with an `if` chain which exits the comprehension in any of its
+/// blocks, rather than an if-expression.
+fn is_branch(input: ParseStream) -> bool {
+    let fork = input.fork();
+    fork.parse::<Branch>().is_ok_and(|branch| {
+        let mut clauses = branch.blocks.iter().flat_map(|(_, block)| block);
+        clauses.any(|clause| matches!(clause, Clause::Exit(_)))
+    })
+}
+
+/// Whether the input starts with `if condition;` or `if condition else error;` rather than an
+/// if-expression.
+fn is_guard(input: ParseStream) -> bool {
+    let fork = input.fork();
+    fork.parse::<Token![if]>().is_ok()
+        && Expr::parse_without_eager_brace(&fork).is_ok()
+        && (fork.peek(Token![;]) || fork.peek(Token![=>]) || fork.peek(Token![else]))
+}
+
+/// Whether the input starts with `guard condition;`, `unless condition;` or
+/// `ensure condition, error;` rather than an expression statement, such as `guard.unlock();`.
+fn is_keyword_guard(input: ParseStream) -> bool {
+    let fork = input.fork();
+    let is_keyword = match fork.parse::<Ident>() {
+        Ok(ident) => ident == "guard" || ident == "unless" || ident == "ensure",
+        Err(_) => false,
+    };
+    is_keyword
+        && Expr::parse_without_eager_brace(&fork).is_ok()
+        && (fork.peek(Token![;])
+            || fork.peek(Token![,])
+            || fork.peek(Token![=>])
+            || fork.peek(Token![else]))
+}
+
+/// Whether the rest of the input is a single expression, which is yielded by the comprehension.
+fn is_last_expr(input: ParseStream) -> bool {
+    let fork = input.fork();
+    fork.parse::<Expr>().is_ok() && fork.is_empty()
+}
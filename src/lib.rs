@@ -72,7 +72,7 @@
 //!
 //! **Native way**
 //!
-//! ```no_run
+//! ```no_run,edition2015
 //! use std::fs::File;
 //! use std::io;
 //! use std::io::prelude::*;
@@ -126,6 +126,105 @@
 //! # }
 //! ```
 //!
+//! The question mark can be used inside `result!` as well, which short-circuits the whole
+//! comprehension rather than the enclosing function.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # fn main() {
+//! use std::num::ParseIntError;
+//!
+//! fn sum(a: &str, b: &str) -> Option<i32> {
+//!     let result: Result<i32, ParseIntError> = result! {
+//!       let a = a.parse::<i32>()?;
+//!       let b <- b.parse::<i32>();
+//!       a + b
+//!     };
+//!     result.ok()
+//! }
+//!
+//! assert_eq!(sum("1", "2"), Some(3));
+//! assert_eq!(sum("x", "2"), None);
+//! # }
+//! ```
+//!
+//! Only when `?` appears before the first binding is the body wrapped in a closure, after which
+//! `.await`, `break` and `continue` before that binding can't reach the enclosing function or
+//! loop. Bodies without such `?` keep working with them as usual.
+//!
+//! **Error type**
+//!
+//! When the bound `Result`s have different error types, declare the error type of the
+//! comprehension with `type Error = ...;` as the first sentence. Then the error of every
+//! `let pattern <- expression;` is converted with `From::from`, just like `?` does. An `if`
+//! expression in such binding has to be parenthesized.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # fn main() {
+//! use std::num::ParseIntError;
+//! use std::str::Utf8Error;
+//!
+//! #[derive(Debug, PartialEq)]
+//! enum Error {
+//!     Utf8(Utf8Error),
+//!     Parse(ParseIntError),
+//! }
+//!
+//! impl From<Utf8Error> for Error {
+//!     fn from(err: Utf8Error) -> Self {
+//!         Error::Utf8(err)
+//!     }
+//! }
+//!
+//! impl From<ParseIntError> for Error {
+//!     fn from(err: ParseIntError) -> Self {
+//!         Error::Parse(err)
+//!     }
+//! }
+//!
+//! let result = result! {
+//!   type Error = Error;
+//!   let s <- std::str::from_utf8(b"42");
+//!   let n <- s.parse::<u32>();
+//!   n
+//! };
+//!
+//! assert_eq!(result, Ok(42));
+//! # }
+//! ```
+//!
+//! The error type can be left to inference with `type Error = _;`, which converts the errors
+//! into whatever the surrounding code expects, such as the return type of the function.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # fn main() {
+//! use std::error::Error;
+//!
+//! fn sum(a: &[u8], b: &str) -> Result<u32, Box<dyn Error>> {
+//!     result! {
+//!       type Error = _;
+//!       let a <- std::str::from_utf8(a);
+//!       let a <- a.parse::<u32>();
+//!       let b <- b.parse::<u32>();
+//!       let _ <- (if a + b < 100 { Ok(()) } else { Err("too large") });
+//!       a + b
+//!     }
+//! }
+//!
+//! assert_eq!(sum(b"1", "2").unwrap(), 3);
+//! assert_eq!(sum(b"1", "x").unwrap_err().to_string(), "invalid digit found in string");
+//! assert_eq!(sum(b"50", "50").unwrap_err().to_string(), "too large");
+//! # }
+//! ```
+//!
 //! # Syntax
 //!
 //! All three macros return wrapped type(`Option<T>`, `Result<T>` and
@@ -137,12 +236,212 @@
 
 //! * `let pattern <- expression;`: bind expression to pattern.
 //!
+//! * `let name: Type <- expression;`: bind expression to name of the given type, which may be
+//!   a reference with a lifetime.
+//!
+//! * `fold(initial, f);`: at the beginning, fold the yields into the result instead of
+//!   returning the iterator (`iter!` only).
+//!
+//! * `sum;`, `product;`, `count;`, `min;` and `max;`: at the beginning, reduce the yields into
+//!   the result instead of returning the iterator (`iter!` only).
+//!
+//! * `first;` and `find(predicate);`: at the beginning, return the first yield, or the first
+//!   one satisfying the predicate, as an `Option` without running the rest of the comprehension
+//!   (`iter!` only).
+//!
+//! * `any;` and `all;`: at the beginning, check whether any or all of the yields, which are
+//!   `bool`s, are `true`, stopping as soon as the answer is known (`iter!` only).
+//!
+//! * `partition(predicate);`: at the beginning, split the yields into two collections, those
+//!   satisfying the predicate and those not, in a single pass (`iter!` only).
+//!
+//! * `unzip;`: at the beginning, split the yields, which are pairs, into a pair of collections
+//!   in a single pass (`iter!` only).
+//!
+//! * `collect into type;`: at the beginning, collect the yields into the given collection
+//!   instead of returning the iterator (`iter!` only).
+//!
+//! * `extend target;`: at the beginning, append the yields to an existing collection without
+//!   an intermediate allocation (`iter!` only).
+//!
+//! * `boxed;`: box the rest of the comprehension behind a `Box<dyn Iterator>`, at the
+//!   beginning to give the result a nameable type, or after bindings to keep the nested types
+//!   short (`iter!` only, requires the `alloc` feature).
+//!
+//! * `from_fn;`: at the beginning, compile the comprehension into a single `from_fn` state
+//!   machine instead of nested closures (`spanned::iter!` only, see [Diagnostics](#diagnostics)).
+//!
+//! * `ref name, ...;`: at the beginning, capture the given locals by reference instead of moving
+//!   them into the comprehension.
+//!
+//! * `clone name, ...;`: clone the given names into the closure of every binding after it
+//!   (`iter!` only).
+//!
+//! * `send;`: at the beginning, assert that the result is `Send` (`iter!`, `future!`,
+//!   `try_future!`, `stream!`, `async_option!` and `async_result!` only, see [Async](#async)).
+//!
+//! * `strict;`: at the beginning, reject a comprehension ending with `;` other than `yield`,
+//!   `return`, `fail` or `continue`, which would yield `()` (`option!`, `result!` and `iter!`
+//!   only, see [Ignore](#ignore)).
+//!
+//! * `for pattern in expression;`: the same as `let pattern <- expression;`.
+//!
+//! * `let pattern <- expression => error;`: bind expression to a refutable pattern, failing with
+//!   `error` when it doesn't match (`result!` only).
+//!
+//! * `let pattern <- expression or expression;`: try the alternatives in turn until one is
+//!   `Some` or `Ok`, or chain them in `iter!`.
+//!
+//! * `let pattern <- expression else default;`: bind the default instead of jumping over when
+//!   the expression is `None` or `Err` (`option!` and `result!` only). In `result!`, an `Option`
+//!   can be bound as well, failing with the given error when it's `None`.
+//!
+//! * `let pattern <- expression, context(message);`: attach a context message to the error
+//!   with `anyhow` (`result!` only, requires the `anyhow` feature).
+//!
+//! * `let pattern <- expression, retry(times);`: evaluate the expression again on error, up to
+//!   `times` more times, optionally calling a backoff closure with the number of the retry
+//!   before each one, as in `retry(times, backoff)` (`result!` only).
+//!
+//! * `let (index, pattern) <- enumerate expression;`: bind the values of the source along with
+//!   their positions (`iter!` only).
+//!
+//! * `let (pattern, pattern) <- expression zip expression;`: bind the values of the sources in
+//!   lockstep, stopping at the shorter one (`iter!` only).
+//!
+//! * `let pattern <- memo expression;`: right after another binding, evaluate the source once
+//!   for all the values of that binding and replay the buffered values, which are cloned, for
+//!   each of them (`iter!` only, requires the `alloc` feature).
+//!
+//! * `let pattern <- scan(initial, f) over expression;`: bind the values produced by `f` from
+//!   the values of the source and a state starting at `initial`, which persists across them,
+//!   until `f` returns `None` (`iter!` only).
+//!
+//! * `let pattern <- expression, step_by step;`: bind every `step`-th value of the source
+//!   (`iter!` only).
+//!
+//! * `let pattern <- expression, chunks size;`: bind `Vec`s of `size` consecutive values of the
+//!   source, the last one possibly shorter (`iter!` only, requires the `alloc` feature).
+//!
+//! * `let pattern <- expression, windows size;`: bind the sliding windows of `size` adjacent
+//!   values of the source, as tuples when `size` is a literal 2 or 3, and as `Vec`s otherwise
+//!   (`iter!` only, the latter requires the `alloc` feature).
+//!
+//! * `let pattern <- expression, rev;`: bind the values of a double-ended source in reverse
+//!   order (`iter!` only).
+//!
+//! * `let pattern <- expression, copied;` and `let pattern <- expression, cloned;`: bind copies
+//!   or clones of the references yielded by the source (`iter!` only).
+//!
+//! * `let pattern <- expression, by_value;`: iterate an array source by value, binding owned
+//!   elements, whatever the edition of the calling crate (`iter!` only).
+//!
+//! * `let pattern <- expression, sorted_by_key(key);`: bind the values of the source in the
+//!   order of the key (`iter!` only, requires the `alloc` feature).
+//!
+//! * `catch |error| handler;`: following a binding, recover from its error by returning `Ok`
+//!   from the handler, or rethrow by returning `Err` (`result!` only).
+//!
 //! * `if filter_expression;`: filter by condition, and jump over when not satisfied.
 //!
+//! * `if condition else error;`: fail with `error` when the condition isn't satisfied
+//!   (`result!` only). `if condition => error;` is the same.
+//!
+//! * `while condition;`: right after a binding, stop consuming its source once the condition
+//!   isn't satisfied (`iter!` only).
+//!
+//! * `skip_while condition;`: right after a binding, drop the leading values of its source while
+//!   the condition is satisfied (`iter!` only).
+//!
+//! * `take count;` and `skip count;`: right after a binding, bound its source to the first
+//!   `count` values, or drop them (`iter!` only).
+//!
+//! * `unique;`: right after a binding, skip the values of its source which have been seen
+//!   before (`iter!` only, requires the `std` feature).
+//!
+//! * `inspect f;`: right after a binding, call `f` with a reference to every value of it,
+//!   without consuming it (`option!`, `result!` and `iter!` only).
+//!
+//! * `timed f;`: right after a binding, call `f` with the `Duration` its expression took to
+//!   evaluate (`option!`, `result!` and `iter!` only, requires the `std` feature).
+//!
+//! * `join pattern in expression on key == key;`: right after a binding, bind the values of
+//!   another source whose key equals the key of the value of the binding, indexing that source
+//!   in a `HashMap` up front rather than scanning it for every value (`iter!` only, requires the
+//!   `std` feature).
+//!
+//! * `merge_join pattern in expression on key == key;`: the same as `join`, but for sources
+//!   sorted by the key, walking both in lockstep without allocation, which requires the
+//!   iterator of the other source to be `Clone` (`iter!` only).
+//!
+//! * `guard condition;` and `unless condition;`: the same as `if condition;` and
+//!   `if !condition;`, which read better than a bare `if`.
+//!
+//! * `ensure condition, error;`: the same as `if condition else error;` (`result!` only).
+//!
+//! * `return expression;`: skip the remaining sentences and yield the expression (`option!` and
+//!   `result!` only).
+//!
+//! * `fail;` and `fail error;`: skip the remaining sentences and return `None` in `option!`, or
+//!   `Err(error)` in `result!`.
+//!
 //! * `statement;`: let assignment, value assignment, etc.
 //!
 //! * `{...}`: block and unsafe block.
 //!
+//! For quick transformations, `iter!` also accepts a compact one-line form: the yielded
+//! expression, then the comma-separated `for pattern in expression` and `if condition`
+//! qualifiers.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # fn main() {
+//! let iter = iter![x * y; for x in 0..3, for y in 0..3, if x != y];
+//!
+//! assert_eq!(iter.collect::<Vec<_>>(), vec![0, 0, 0, 2, 0, 2]);
+//! # }
+//! ```
+//!
+//! `hs_iter!` is yet another surface syntax for `iter!`, mirroring Haskell's list comprehension:
+//! the yielded expression, `|`, then the comma-separated qualifiers, which are
+//! `pattern <- expression` generators, `let` bindings or conditions. A `|` or `,` in the yielded
+//! expression or the qualifiers has to be parenthesized.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # fn main() {
+//! let iter = hs_iter![(x, y) | x <- 0..3, y <- 0..3, x < y];
+//!
+//! assert_eq!(iter.collect::<Vec<_>>(), vec![(0, 1), (0, 2), (1, 2)]);
+//! # }
+//! ```
+//!
+//! `for_comp!` mirrors Scala's for-comprehension instead: the `pattern <- expression`
+//! generators, `pattern = expression` definitions and `if condition` guards are separated by `;`
+//! in braces, followed by an explicit `yield` of the result.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # fn main() {
+//! let iter = for_comp! {
+//!   {
+//!     x <- 0..3;
+//!     y <- 0..3;
+//!     sum = x + y;
+//!     if x < y
+//!   } yield (x, y, sum)
+//! };
+//!
+//! assert_eq!(iter.collect::<Vec<_>>(), vec![(0, 1, 1), (0, 2, 2), (1, 2, 3)]);
+//! # }
+//! ```
+//!
 //! # Syntax Detail
 //!
 //! ## 1. Basic arrow(<-) syntax
@@ -216,775 +515,15156 @@
 //!
 //! let iter = {
 //!   (0..2).into_iter().flat_map(move |x| {
-//!     (vec!['a', 'b']).into_iter().flat_map(move |y| {
-//!       ::std::iter::once((x, y))
+//!     (vec!['a', 'b']).into_iter().map(move |y| {
+//!       (x, y)
 //!     })
 //!   })
 //! };
 //! # }
 //! ```
 //!
-//! ## 2. Yield
+//! Since every closure is `move`, the comprehension takes the ownership of the locals it uses.
+//! `ref a, b;` at the beginning shadows the given locals with references to them instead, so
+//! that they can still be used after the comprehension without being cloned.
 //!
-//! The last expression of the block will be yielded, similar to functions in rust.
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # fn main() {
+//! let names = vec!["alice".to_string(), "bob".to_string()];
+//! let iter = iter! {
+//!   ref names;
+//!   let i <- 0..names.len();
+//!   names[i].len()
+//! };
+//!
+//! assert_eq!(iter.collect::<Vec<_>>(), vec![5, 3]);
+//! assert_eq!(names.len(), 2);
+//! # }
+//! ```
+//!
+//! A value moved out by the closure of an inner binding has to be cloned for every value of the
+//! outer ones. `clone a, b;` in `iter!` clones the given names where it's declared, and again
+//! at the beginning of the closure of every binding after it, like `clone!` of glib, so that
+//! each closure owns a clone of its own.
 //!
 //! ```
 //! # #[macro_use]
 //! # extern crate comp;
 //! #
 //! # fn main() {
+//! use std::sync::Arc;
+//!
+//! let config = Arc::new("config");
 //! let iter = iter! {
+//!   clone config;
 //!   let x <- 0..2;
-//!   let y <- vec!['a', 'b'];
-//!
-//!   (x, y)    // <------- Yield
+//!   let y <- 0..2;
+//!   (x, y, config)
 //! };
+//!
+//! assert_eq!(iter.count(), 4);
+//! assert_eq!(Arc::strong_count(&config), 1);
 //! # }
 //! ```
 //!
-//! The block yields `()` while the last line is __*arrow statement*__ or statement
-//! with __*semicolon*__.
+//! As `FlatMap`, `Map` and `FilterMap` are all double-ended over double-ended sources, the
+//! result can be reversed with `rev()` as long as every source is a `DoubleEndedIterator`,
+//! If-Guards included. `while` conditions and the adapters which depend on the order of the
+//! values, such as `windows` and `unique`, only iterate forwards.
 //!
 //! ```
 //! # #[macro_use]
 //! # extern crate comp;
 //! #
 //! # fn main() {
-//! let option: Option<()> = option! {
-//!   let a <- Some(1);
-//!   let b <- Some(2);
+//! let iter = iter! {
+//!   let x <- 0..3;
+//!   let y <- x..3;
+//!   if x != y;
+//!   (x, y)
 //! };
 //!
-//! let option: Option<()> = option! {
-//!   let a <- Some(1);
-//!   let b <- Some(2);
-//!   a + b;
-//! };
+//! assert_eq!(iter.rev().collect::<Vec<_>>(), vec![(1, 2), (0, 2), (0, 1)]);
 //! # }
 //! ```
 //!
-//! ## 3. Pattern
+//! The same goes for `size_hint()`. The last binding forwards the size of its source unless
+//! it's filtered, either by If-Guards or by a pattern other than a plain name, which may not
+//! match, and If-Guards in front of the bindings forward the size of the rest. So collecting a
+//! comprehension over a single source, even a `zip`ped one, allocates once, and the result is
+//! an `ExactSizeIterator` when the source is. A name starting with an uppercase letter is taken
+//! for a constant or a unit struct to match against, like `let None <- options;`.
 //!
-//! In `comp-rs`, pattern is supported as it should be.
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # fn main() {
+//! let enabled = true;
+//! let iter = iter! {
+//!   if enabled;
+//!   let pair <- vec![1, 2, 3] zip vec!['a', 'b', 'c'];
+//!   pair
+//! };
 //!
-//! ## Tuple
+//! assert_eq!(iter.size_hint(), (3, Some(3)));
+//! # }
+//! ```
+//!
+//! Likewise, the result is a `FusedIterator` whenever the sources are, so `fuse()` on it
+//! costs nothing.
+//!
+//! For those who find the arrow unfamiliar, `for pattern in expression;` is accepted as
+//! well, and expands identically. A `for` followed by a block is still an ordinary for-loop, so
+//! an expression containing braces, such as a struct literal, has to be parenthesized.
 //!
 //! ```
 //! # #[macro_use]
 //! # extern crate comp;
 //! #
 //! # fn main() {
-//! let option = option! {
-//!   let (x, y) <- Some((1, 2));
-//!   (y, x)
+//! let iter = iter! {
+//!   for x in 0..2;
+//!   for (i, c) in vec!['a', 'b'].into_iter().enumerate();
+//!   (x, i, c)
 //! };
 //!
-//! assert_eq!(option, Some((2, 1)));
+//! assert_eq!(iter.collect::<Vec<_>>(), vec![(0, 0, 'a'), (0, 1, 'b'), (1, 0, 'a'), (1, 1, 'b')]);
 //! # }
 //! ```
 //!
-//! ## Struct
+//! In `option!` and `result!`, a binding can be given a default with `else`, which is bound
+//! when the expression is `None` or `Err`, so that the comprehension goes on instead of
+//! short-circuiting, like `unwrap_or`.
 //!
 //! ```
 //! # #[macro_use]
 //! # extern crate comp;
 //! #
 //! # fn main() {
-//! struct Struct { x: usize };
+//! let port: Option<u16> = None;
 //!
 //! let option = option! {
-//!   let Struct { x } <- Some(Struct { x: 1 });
-//!   x
+//!   let host <- Some("localhost");
+//!   let port <- port else 80;
+//!   format!("{}:{}", host, port)
 //! };
 //!
-//! assert_eq!(option, Some(1));
+//! assert_eq!(option, Some("localhost:80".to_string()));
 //! # }
 //! ```
 //!
-//! ## Ignore
+//! In `result!`, an `Option` can be bound with `else` too, but what follows is the error to
+//! fail with when it's `None`, like `ok_or_else`. The error is only evaluated when needed.
 //!
 //! ```
 //! # #[macro_use]
 //! # extern crate comp;
 //! #
 //! # fn main() {
-//! let option = option! {
-//!   let _ <- Some(1);
+//! use std::collections::HashMap;
+//!
+//! let env: HashMap<&str, &str> = vec![("PORT", "8080")].into_iter().collect();
+//! let env = &env;
+//!
+//! let result = result! {
+//!   let port <- env.get("PORT") else "PORT is not set".to_string();
+//!   let port <- port.parse::<u16>().map_err(|e| e.to_string());
+//!   let host <- env.get("HOST") else format!("HOST is not set, port {}", port);
+//!   (host, port)
 //! };
+//!
+//! assert_eq!(result, Err("HOST is not set, port 8080".to_string()));
 //! # }
 //! ```
 //!
-//! ## 4. If-Guard
-//!
-//! If-Guard is specific for `iter!` which translates condition into `filter()`.
-//!
-//! It wraps the following code into a block and call `filter()` on it.
+//! Alternatives are separated by `or`, and are only evaluated when the previous ones are
+//! `None` or `Err`. In `iter!`, they are chained one after another.
 //!
 //! ```
 //! # #[macro_use]
 //! # extern crate comp;
 //! #
 //! # fn main() {
-//! let iter = iter! {
-//!   let x <- 0..4;
-//!   let y <- 2..6;
+//! use std::collections::HashMap;
 //!
-//!   if x == y;
+//! let local: HashMap<&str, u32> = vec![("a", 1)].into_iter().collect();
+//! let global: HashMap<&str, u32> = vec![("a", 10), ("b", 20)].into_iter().collect();
+//! let (local, global) = (&local, &global);
 //!
-//!   // won't reach here if condition isn't satisfied
-//!   (x, y)
+//! let option = option! {
+//!   let a <- local.get("a") or global.get("a");
+//!   let b <- local.get("b") or global.get("b");
+//!   let c <- local.get("c") or global.get("c") else &0;
+//!   a + b + c
 //! };
 //!
-//! let expected = vec![(2, 2), (3, 3)];
-//! assert_eq!(expected, iter.collect::<Vec<_>>());
+//! assert_eq!(option, Some(21));
 //! # }
 //! ```
 //!
-//! ## 5. Statement & Block
-//!
-//! Statements and blocks are also supported.
+//! In `result!`, `, retry(times)` after the expression of a binding evaluates it again when
+//! it fails, until it succeeds or has been retried for the given times. A backoff closure can
+//! be given as the second argument, which is called with the number of the upcoming retry.
 //!
 //! ```
 //! # #[macro_use]
 //! # extern crate comp;
 //! #
 //! # fn main() {
-//! // statement
-//! let iter = iter! {
-//!   let start = 5;
-//!   let end;
-//!   end = start * 3;
+//! use std::cell::Cell;
+//! use std::time::Duration;
 //!
-//!   // 5, 6, ..., 13, 14
-//!   let x <- start..end;
-//!   x
+//! let attempts = Cell::new(0);
+//! let connect = || {
+//!     attempts.set(attempts.get() + 1);
+//!     if attempts.get() < 3 { Err("refused") } else { Ok("connection") }
 //! };
-//! let expected = 5..15;
-//! assert!(iter.eq(expected.into_iter()));
+//!
+//! let result = result! {
+//!   let conn <- connect(), retry(5, |n| std::thread::sleep(Duration::from_millis(n)));
+//!   conn
+//! };
+//!
+//! assert_eq!(result, Ok("connection"));
+//! assert_eq!(attempts.get(), 3);
 //! # }
 //! ```
+//!
+//! In `result!`, a binding can be followed by `catch |error| handler;`, which is called with
+//! the error of the binding, like `or_else`. The handler either recovers with `Ok(value)`,
+//! which is bound to the pattern, or rethrows with `Err(error)`.
+//!
 //! ```
 //! # #[macro_use]
 //! # extern crate comp;
 //! #
 //! # fn main() {
-//! let iter = iter! {
-//!     let mut a <- 0..5;
-//!
-//!     // block
-//!     {
-//!         fn double(x: u8) -> u8 { x * 2}
-//!         let tmp = double(a);
-//!         a = tmp;
-//!     };
+//! use std::io::{Error, ErrorKind};
 //!
-//!     // unsafe block
-//!     let count = unsafe {
-//!         static mut CALL_COUNT: u8 = 0;
-//!         CALL_COUNT += 1;
-//!         CALL_COUNT
-//!     };
+//! fn read(name: &str) -> Result<String, Error> {
+//!     match name {
+//!         "config" => Ok("port = 80".to_string()),
+//!         "secret" => Err(Error::new(ErrorKind::PermissionDenied, "denied")),
+//!         _ => Err(Error::new(ErrorKind::NotFound, "not found")),
+//!     }
+//! }
 //!
-//!     (a, count)
+//! let load = |name: &str| result! {
+//!   let text <- read(name);
+//!   catch |e| if e.kind() == ErrorKind::NotFound { Ok(String::new()) } else { Err(e) };
+//!   text.len()
 //! };
-//! let expected = vec![(0, 1), (2, 2), (4, 3), (6, 4), (8, 5)];
-//! assert!(iter.eq(expected.into_iter()));
+//!
+//! assert_eq!(load("config").unwrap(), 9);
+//! assert_eq!(load("missing").unwrap(), 0);
+//! assert_eq!(load("secret").unwrap_err().kind(), ErrorKind::PermissionDenied);
 //! # }
 //! ```
 //!
-//! # Array
-//!
-//! `Array` in rust behaves differently from other collections. It only iterates its
-//! content by reference.
-//! So `iter!` always binds *references* in `arrow(<-)` syntax, then you need to
-//! *deref* the bound value.
-//! And since one can't move any value out of an `array`, array should be placed
-//! outside the macro to satisfy lifetime.
+//! In `iter!`, `enumerate` before the expression of a binding pairs the values with their
+//! positions in the source, while `, step_by step` after it strides over the source, `, rev`
+//! traverses the source backwards, and `, copied` or `, cloned` binds values instead of the
+//! references yielded by a borrowed source.
 //!
 //! ```
 //! # #[macro_use]
 //! # extern crate comp;
 //! #
 //! # fn main() {
-//! let array = [0, 1, 2, 3];
 //! let iter = iter! {
-//!     let x <- array;
-//!     let y <- *x..4;
-//!     (*x, y)
+//!   let x <- 0..10, step_by 4;
+//!   let y <- x..10, step_by 4;
+//!   (x, y)
 //! };
-//! let expected = vec![(0, 0), (0, 1), (0, 2), (0, 3), (1, 1), (1, 2), (1, 3), (2, 2),
-//!                     (2, 3), (3, 3)];
-//! assert_eq!(expected, iter.collect::<Vec<_>>());
-//! # }
-//! ```
 //!
-//! # Contribution
+//! assert_eq!(iter.collect::<Vec<_>>(), vec![(0, 0), (0, 4), (0, 8), (4, 4), (4, 8), (8, 8)]);
 //!
-//! All kinds of contribution are welcome.
+//! let iter = iter! {
+//!   let x <- 1..4, rev;
+//!   x
+//! };
 //!
-//! - **Issue.** Feel free to open an issue when you find typos, bugs, or have any question.
-//! - **Pull requests**. Better implementation, more tests, more documents and typo fixes are all welcome.
+//! assert_eq!(iter.collect::<Vec<_>>(), vec![3, 2, 1]);
 //!
-//! # License
+//! let weights = vec![1, 2, 3];
+//! let iter = iter! {
+//!   let w <- &weights, copied;
+//!   w * 10
+//! };
 //!
-//! Licensed under MIT license ([LICENSE-MIT](LICENSE-MIT) or http://opensource.org/licenses/MIT)
+//! assert_eq!(iter.collect::<Vec<_>>(), vec![10, 20, 30]);
+//!
+//! let iter = iter! {
+//!   let (i, name) <- enumerate vec!["alice", "bob"];
+//!   format!("{}. {}", i + 1, name)
+//! };
+//!
+//! assert_eq!(iter.collect::<Vec<_>>(), vec!["1. alice", "2. bob"]);
+//! # }
+//! ```
+//!
+//! Since every binding in `iter!` is expanded into a closure, a local variable can't carry state
+//! from one value of a source to the next. `scan(initial, f) over source` keeps a state for the
+//! source instead, like `Iterator::scan()`, which makes running totals easy.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # fn main() {
+//! let iter = iter! {
+//!   let total <- scan(0, |acc, x| { *acc += x; Some(*acc) }) over vec![3, 1, 4, 1, 5];
+//!   total
+//! };
+//!
+//! assert_eq!(iter.collect::<Vec<_>>(), vec![3, 4, 8, 9, 14]);
+//! # }
+//! ```
+//!
+//! `, windows 2` and `, windows 3` bind the sliding windows over the source as tuples, which
+//! makes pairwise computations easy.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # fn main() {
+//! let prices = vec![10, 12, 9, 15];
+//!
+//! let iter = iter! {
+//!   let (prev, cur) <- prices, windows 2;
+//!   cur - prev
+//! };
+//!
+//! assert_eq!(iter.collect::<Vec<_>>(), vec![2, -3, 6]);
+//! # }
+//! ```
+//!
+//! With the `alloc` feature, `, chunks size` binds batches of the source collected into `Vec`s,
+//! `, windows size` of other sizes binds the sliding windows as `Vec`s, and
+//! `, sorted_by_key(key)` buffers the source and sorts it by the key before binding.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # fn main() {
+//! # #[cfg(feature = "alloc")]
+//! # {
+//! use std::cmp::Reverse;
+//!
+//! let players = vec![("alice", 30), ("bob", 45), ("carol", 12)];
+//!
+//! let iter = iter! {
+//!   let (name, score) <- players, sorted_by_key(|p| Reverse(p.1));
+//!   format!("{}: {}", name, score)
+//! };
+//!
+//! assert_eq!(iter.collect::<Vec<_>>(), vec!["bob: 45", "alice: 30", "carol: 12"]);
+//!
+//! let iter = iter! {
+//!   let batch <- 0..7, chunks 3;
+//!   batch.iter().sum::<i32>()
+//! };
+//!
+//! assert_eq!(iter.collect::<Vec<_>>(), vec![3, 12, 6]);
+//!
+//! let iter = iter! {
+//!   let window <- 0..6, windows 4;
+//!   window.iter().sum::<i32>()
+//! };
+//!
+//! assert_eq!(iter.collect::<Vec<_>>(), vec![6, 10, 14]);
+//! # }
+//! # }
+//! ```
+//!
+//! While consecutive bindings in `iter!` run through the Cartesian product of their sources,
+//! `zip` pairs up the values of several sources in lockstep instead.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # fn main() {
+//! let xs = vec![1, 2, 3];
+//! let ys = vec![10, 20, 30];
+//!
+//! let iter = iter! {
+//!   let (x, y) <- xs zip ys;
+//!   x * y
+//! };
+//!
+//! assert_eq!(iter.collect::<Vec<_>>(), vec![10, 40, 90]);
+//! # }
+//! ```
+//!
+//! With the `alloc` feature, `memo` evaluates an expensive source once for all the values of
+//! the binding before it, instead of once for each of them.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # fn main() {
+//! # #[cfg(feature = "alloc")]
+//! # {
+//! # use std::cell::Cell;
+//! let queries = Cell::new(0);
+//! let query = || {
+//!   queries.set(queries.get() + 1);
+//!   vec!["a", "b"]
+//! };
+//!
+//! let iter = iter! {
+//!   let x <- 0..3;
+//!   let y <- memo query();
+//!   (x, y)
+//! };
+//!
+//! assert_eq!(iter.count(), 6);
+//! assert_eq!(queries.get(), 1);
+//! # }
+//! # }
+//! ```
+//!
+//! ## 2. Yield
+//!
+//! The last expression of the block will be yielded, similar to functions in rust.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # fn main() {
+//! let iter = iter! {
+//!   let x <- 0..2;
+//!   let y <- vec!['a', 'b'];
+//!
+//!   (x, y)    // <------- Yield
+//! };
+//! # }
+//! ```
+//!
+//! The block yields `()` while the last line is __*arrow statement*__ or statement
+//! with __*semicolon*__.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # fn main() {
+//! let option: Option<()> = option! {
+//!   let a <- Some(1);
+//!   let b <- Some(2);
+//! };
+//!
+//! let option: Option<()> = option! {
+//!   let a <- Some(1);
+//!   let b <- Some(2);
+//!   a + b;
+//! };
+//! # }
+//! ```
+//!
+//! In `iter!`, `yield expression;` can be used to yield more than once per pass, in which case
+//! the block doesn't yield `()` at the end. An `if` without a trailing semicolon branches the
+//! comprehension, so that the branches may contain arrows and yields of their own.
+//! `spanned::iter!` accepts `yield expression;` and `yield from expression;` as clauses, but doesn't
+//! support such branches.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # fn main() {
+//! let iter = iter! {
+//!   let x <- 0..3;
+//!   yield x;
+//!   if x == 1 {
+//!     let y <- 0..2;
+//!     yield x * 10 + y;
+//!   }
+//! };
+//!
+//! assert_eq!(iter.collect::<Vec<_>>(), vec![0, 1, 10, 11, 2]);
+//! # }
+//! ```
+//!
+//! `continue;`, or `skip;`, starting any statement of an `if` block without `else` drops the
+//! current pass, so that it contributes nothing to the output.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # fn main() {
+//! let iter = iter! {
+//!   let x <- 0..5;
+//!   let y = x * x;
+//!   if y % 2 == 1 {
+//!     continue;
+//!   }
+//!   y
+//! };
+//!
+//! assert_eq!(iter.collect::<Vec<_>>(), vec![0, 4, 16]);
+//! # }
+//! ```
+//!
+//! `yield from expression;` yields all the items of another iterator at that point.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # fn main() {
+//! let iter = iter! {
+//!   yield "begin";
+//!   yield from vec!["a", "b"];
+//!   yield "end";
+//! };
+//!
+//! assert_eq!(iter.collect::<Vec<_>>(), vec!["begin", "a", "b", "end"]);
+//! # }
+//! ```
+//!
+//! `fold(initial, f);` at the beginning of `iter!` folds the yields with `f` starting from
+//! `initial`, and returns the result instead of the iterator.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # fn main() {
+//! let sum_of_odd_squares = iter! {
+//!   fold(0, |acc, y| acc + y);
+//!   let x <- 0..10;
+//!   if x % 2 == 1;
+//!   x * x
+//! };
+//!
+//! assert_eq!(sum_of_odd_squares, 165);
+//! # }
+//! ```
+//!
+//! The common reductions have their own clauses: `sum;`, `product;`, `count;`, `min;` and
+//! `max;`, the last two of which return `None` when nothing is yielded.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # fn main() {
+//! let sum: i32 = iter! {
+//!   sum;
+//!   let x <- 0..10;
+//!   if x % 2 == 1;
+//!   x * x
+//! };
+//!
+//! let longest = iter! {
+//!   max;
+//!   let word <- "the quick brown fox".split(' ');
+//!   word.len()
+//! };
+//!
+//! assert_eq!(sum, 165);
+//! assert_eq!(longest, Some(5));
+//! # }
+//! ```
+//!
+//! `first;` and `find(predicate);` stop at the first yield, or the first one satisfying the
+//! predicate, and return it as an `Option`.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # fn main() {
+//! let pythagorean = iter! {
+//!   first;
+//!   let c <- 1..;
+//!   let b <- 1..c;
+//!   let a <- 1..b;
+//!   if a * a + b * b == c * c;
+//!   (a, b, c)
+//! };
+//!
+//! let even_sum = iter! {
+//!   find(|(_, _, sum)| sum % 2 == 0);
+//!   let x <- 1..4;
+//!   let y <- 1..4;
+//!   (x, y, x * 10 + y)
+//! };
+//!
+//! assert_eq!(pythagorean, Some((3, 4, 5)));
+//! assert_eq!(even_sum, Some((1, 2, 12)));
+//! # }
+//! ```
+//!
+//! `any;` and `all;` check the yields, which are `bool`s, and stop as soon as the answer is
+//! known.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # fn main() {
+//! let has_sum_of_ten = iter! {
+//!   any;
+//!   let x <- vec![3, 8, 5];
+//!   let y <- vec![2, 7];
+//!   x + y == 10
+//! };
+//!
+//! let all_distinct = iter! {
+//!   all;
+//!   let x <- vec![3, 8, 5];
+//!   let y <- vec![2, 7];
+//!   x != y
+//! };
+//!
+//! assert!(has_sum_of_ten);
+//! assert!(all_distinct);
+//! # }
+//! ```
+//!
+//! `partition(predicate);` splits the yields into two collections of any type implementing
+//! `Default` and `Extend`, running the comprehension only once.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # fn main() {
+//! let (even, odd): (Vec<_>, Vec<_>) = iter! {
+//!   partition(|n| n % 2 == 0);
+//!   let x <- 1..4;
+//!   let y <- x..4;
+//!   x * y
+//! };
+//!
+//! assert_eq!(even, vec![2, 4, 6]);
+//! assert_eq!(odd, vec![1, 3, 9]);
+//! # }
+//! ```
+//!
+//! `unzip;` splits yielded pairs into a pair of collections, such as `(Vec<A>, Vec<B>)`.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # fn main() {
+//! let (words, lengths): (Vec<_>, Vec<_>) = iter! {
+//!   unzip;
+//!   let word <- vec!["apple", "fig", "banana"];
+//!   if word.len() > 3;
+//!   (word, word.len())
+//! };
+//!
+//! assert_eq!(words, vec!["apple", "banana"]);
+//! assert_eq!(lengths, vec![5, 6]);
+//! # }
+//! ```
+//!
+//! `collect into type;` collects the yields into any collection implementing `FromIterator`,
+//! so the result type reads at the top of the comprehension.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # use std::collections::BTreeMap;
+//! #
+//! # fn main() {
+//! let lengths = iter! {
+//!   collect into BTreeMap<_, _>;
+//!   let word <- vec!["banana", "apple", "fig"];
+//!   (word, word.len())
+//! };
+//!
+//! assert_eq!(lengths.keys().collect::<Vec<_>>(), vec![&"apple", &"banana", &"fig"]);
+//! # }
+//! ```
+//!
+//! `extend target;` appends the yields to an existing collection implementing `Extend`, such
+//! as a buffer reused across calls.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # fn main() {
+//! let mut buffer = Vec::with_capacity(16);
+//!
+//! for n in 1..3 {
+//!   iter! {
+//!     extend buffer;
+//!     let x <- 0..n;
+//!     x * 10
+//!   };
+//! }
+//!
+//! assert_eq!(buffer, vec![0, 0, 10]);
+//! # }
+//! ```
+//!
+//! `boxed;` boxes the rest of the comprehension into a `Box<dyn Iterator<Item = T> + '_>`. At
+//! the beginning, it gives the result a type that can be written down, such as in the return
+//! type of a function. After a binding, it boxes the inner comprehension created for every
+//! value, which stops the types from nesting deeper and deeper, at the cost of an allocation
+//! and dynamic dispatch, and keeps the compile times of deep comprehensions in check.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # #[cfg(feature = "alloc")]
+//! fn pairs(xs: &[u32]) -> Box<dyn Iterator<Item = (u32, u32)> + '_> {
+//!   iter! {
+//!     boxed;
+//!     let (i, x) <- enumerate xs;
+//!     boxed;
+//!     let y <- &xs[i + 1..];
+//!     (*x, *y)
+//!   }
+//! }
+//!
+//! # #[cfg(feature = "alloc")]
+//! # fn main() {
+//! assert_eq!(pairs(&[1, 2, 3]).collect::<Vec<_>>(), vec![(1, 2), (1, 3), (2, 3)]);
+//! # }
+//! # #[cfg(not(feature = "alloc"))]
+//! # fn main() {}
+//! ```
+//!
+//! ## 3. Pattern
+//!
+//! In `comp-rs`, pattern is supported as it should be.
+//!
+//! ## Tuple
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # fn main() {
+//! let option = option! {
+//!   let (x, y) <- Some((1, 2));
+//!   (y, x)
+//! };
+//!
+//! assert_eq!(option, Some((2, 1)));
+//! # }
+//! ```
+//!
+//! Like in an ordinary `let`, `mut` can be put before any name in the pattern.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # fn main() {
+//! let option = option! {
+//!   let (x, mut y) <- Some((1, 2));
+//!   y += x;
+//!   y
+//! };
+//!
+//! assert_eq!(option, Some(3));
+//! # }
+//! ```
+//!
+//! ## Struct
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # fn main() {
+//! struct Struct { x: usize };
+//!
+//! let option = option! {
+//!   let Struct { x } <- Some(Struct { x: 1 });
+//!   x
+//! };
+//!
+//! assert_eq!(option, Some(1));
+//! # }
+//! ```
+//!
+//! Rest patterns and paths to the type are passed through as they are.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # fn main() {
+//! mod config {
+//!     pub struct Config { pub host: &'static str, pub port: u16 }
+//! }
+//!
+//! let option = option! {
+//!   let config::Config { port, .. } <- Some(config::Config { host: "localhost", port: 80 });
+//!   let (first, ..) <- Some((port, 0, 0));
+//!   first
+//! };
+//!
+//! assert_eq!(option, Some(80));
+//! # }
+//! ```
+//!
+//! ## Binding
+//!
+//! `name @ pattern` binds the whole value to the name along with the parts matched by the
+//! pattern, and `name @ range` is refutable like any other range pattern.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # fn main() {
+//! #[derive(Debug, PartialEq, Clone, Copy)]
+//! struct Point { x: i32, y: i32 };
+//!
+//! let option = option! {
+//!   let whole @ Point { x, .. } <- Some(Point { x: 1, y: 2 });
+//!   (whole, x)
+//! };
+//!
+//! assert_eq!(option, Some((Point { x: 1, y: 2 }, 1)));
+//!
+//! let iter = iter! {
+//!   let digit @ 0..=9 <- vec![3, 42, 7];
+//!   digit
+//! };
+//!
+//! assert_eq!(iter.collect::<Vec<_>>(), vec![3, 7]);
+//! # }
+//! ```
+//!
+//! ## Slice
+//!
+//! Array and slice patterns work like tuples, and are refutable when matching slices of
+//! unknown length. A leading `&` matches through a reference.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # fn main() {
+//! let option = option! {
+//!   let [first, .., last] <- Some([1, 2, 3]);
+//!   first + last
+//! };
+//!
+//! assert_eq!(option, Some(4));
+//!
+//! let iter = iter! {
+//!   let &[x, y] <- vec![&[1, 2][..], &[3]];
+//!   x + y
+//! };
+//!
+//! assert_eq!(iter.collect::<Vec<_>>(), vec![3]);
+//! # }
+//! ```
+//!
+//! ## Ref
+//!
+//! `ref` and `ref mut` borrow from the bound value instead of moving out of it, just like in
+//! an ordinary `let`. The borrows live until the end of the comprehension, so they can't be
+//! captured by the inner sources of `iter!`.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # fn main() {
+//! let option = option! {
+//!   let ref mut s <- Some(String::from("comp"));
+//!   s.push_str("-rs");
+//!   s.len()
+//! };
+//!
+//! assert_eq!(option, Some(7));
+//! # }
+//! ```
+//!
+//! ## Type
+//!
+//! A name can be annotated with any type, including references with lifetimes.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # fn main() {
+//! fn first<'a>(words: &'a [&'a str]) -> Option<(&'a str, Vec<u8>)> {
+//!   option! {
+//!     let word: &'a str <- words.first().copied();
+//!     let bytes: Vec<u8> <- Some(word.bytes().collect());
+//!     (word, bytes)
+//!   }
+//! }
+//!
+//! assert_eq!(first(&["ab"]), Some(("ab", vec![b'a', b'b'])));
+//! # }
+//! ```
+//!
+//! ## Ignore
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # fn main() {
+//! let option = option! {
+//!   let _ <- Some(1);
+//! };
+//! # }
+//! ```
+//!
+//! A comprehension ending with `;` yields `()`, which is easy to do by accident. `strict;` at the
+//! beginning turns it into a compile error, so that `()` has to be yielded explicitly. An empty
+//! comprehension is rejected too, while an `iter!` ending with `yield …;` or `yield from …;` is
+//! accepted, and so is an `option!` or a `result!` ending with `return …;` or `fail …;`.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # fn main() {
+//! let option = option! {
+//!   strict;
+//!   let _ <- Some(1);
+//!   ()
+//! };
+//!
+//! assert_eq!(option, Some(()));
+//! # }
+//! ```
+//!
+//! ## Refutable
+//!
+//! Refutable patterns are allowed in `iter!` and `option!`. In `iter!`, the items not matching
+//! the pattern are skipped, and in `option!`, the comprehension returns `None`.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # fn main() {
+//! let iter = iter! {
+//!   let Some(x) <- vec![Some(1), None, Some(3)];
+//!   x
+//! };
+//!
+//! assert_eq!(iter.collect::<Vec<_>>(), vec![1, 3]);
+//! # }
+//! ```
+//!
+//! `result!` has no error to return on its own, so the error is given after `=>`.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # fn main() {
+//! let option = option! {
+//!   let Ok(x) <- Some("1".parse::<u8>());
+//!   x
+//! };
+//!
+//! assert_eq!(option, Some(1));
+//!
+//! let result = result! {
+//!   let Some(x) <- Ok(None::<u8>) => "missing";
+//!   x
+//! };
+//!
+//! assert_eq!(result, Err("missing"));
+//! # }
+//! ```
+//!
+//! ## 4. If-Guard
+//!
+//! If-Guard is supported in `iter!`, which translates condition into `filter()`.
+//!
+//! It wraps the following code into a block and call `filter()` on it.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # fn main() {
+//! let iter = iter! {
+//!   let x <- 0..4;
+//!   let y <- 2..6;
+//!
+//!   if x == y;
+//!
+//!   // won't reach here if condition isn't satisfied
+//!   (x, y)
+//! };
+//!
+//! let expected = vec![(2, 2), (3, 3)];
+//! assert_eq!(expected, iter.collect::<Vec<_>>());
+//! # }
+//! ```
+//!
+//! In `option!`, an unsatisfied condition returns `None`, just like `Option::filter()`.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # fn main() {
+//! let option = option! {
+//!   let x <- Some(3);
+//!   if x % 2 == 0;
+//!   x / 2
+//! };
+//!
+//! assert_eq!(option, None);
+//! # }
+//! ```
+//!
+//! In `result!`, the error is given after `else` (or `=>`), and it's returned if the condition
+//! isn't satisfied.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # fn main() {
+//! let result = result! {
+//!   let age <- "200".parse::<u8>().map_err(|_| "not a number");
+//!   if age < 150 else "too old";
+//!   age
+//! };
+//!
+//! assert_eq!(result, Err("too old"));
+//! # }
+//! ```
+//!
+//! Wherever `if` guards are supported, `guard condition;` can be used for the same, and
+//! `unless condition;` for the negation, both also with `=> error` or `else error` in `result!`.
+//! A statement which can't continue with a condition, like `guard.unlock();`, is still treated
+//! as such, whereas `unless (x > 3);` is the clause rather than a call to a function `unless`.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # fn main() {
+//! let iter = iter! {
+//!   let x <- 0..6;
+//!   guard x % 2 == 0;
+//!   unless x == 2;
+//!   x
+//! };
+//!
+//! assert_eq!(iter.collect::<Vec<_>>(), vec![0, 4]);
+//! # }
+//! ```
+//!
+//! In `iter!`, `while condition;` right after a binding stops consuming its source at the
+//! first value which doesn't satisfy the condition, like `take_while()`, whereas `if` keeps
+//! scanning the whole source. A while-loop is still accepted as a statement.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # fn main() {
+//! let iter = iter! {
+//!   let x <- 1..;
+//!   while x * x < 20;
+//!   let y <- x..5;
+//!   (x, y)
+//! };
+//!
+//! assert_eq!(iter.collect::<Vec<_>>(), vec![(1, 1), (1, 2), (1, 3), (1, 4), (2, 2), (2, 3), (2, 4), (3, 3), (3, 4), (4, 4)]);
+//! # }
+//! ```
+//!
+//! Likewise, `skip_while condition;` drops the leading values of the source it follows, like
+//! `skip_while()`, and lets through everything after the first value failing the condition.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # fn main() {
+//! let text = "# name, age\n# ---\nalice, 30\n# bob, 25\ncarol, 41";
+//!
+//! let iter = iter! {
+//!   let line <- text.lines();
+//!   skip_while line.starts_with('#');
+//!   line
+//! };
+//!
+//! assert_eq!(iter.collect::<Vec<_>>(), vec!["alice, 30", "# bob, 25", "carol, 41"]);
+//! # }
+//! ```
+//!
+//! `take count;` and `skip count;` bound the source they follow by position instead, so that
+//! infinite sources can be used without leaving the macro. A call such as `take(x);` is still a
+//! statement calling a function named `take`.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # fn main() {
+//! let iter = iter! {
+//!   let x <- 1..;
+//!   skip 1;
+//!   take 3;
+//!   let y <- (0..).step_by(x);
+//!   take 2;
+//!   (x, y)
+//! };
+//!
+//! assert_eq!(iter.collect::<Vec<_>>(), vec![(2, 0), (2, 2), (3, 0), (3, 3), (4, 0), (4, 4)]);
+//! # }
+//! ```
+//!
+//! With the `std` feature, `unique;` skips the values of the source it follows which have been
+//! seen before.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # fn main() {
+//! # #[cfg(feature = "std")]
+//! # {
+//! let iter = iter! {
+//!   let word <- "the cat saw the dog".split(' ');
+//!   unique;
+//!   word
+//! };
+//!
+//! assert_eq!(iter.collect::<Vec<_>>(), vec!["the", "cat", "saw", "dog"]);
+//! # }
+//! # }
+//! ```
+//!
+//! `inspect f;` peeks at the values of the binding it follows with `Iterator::inspect`, or
+//! `Option::inspect` and `Result::inspect` in `option!` and `result!`, which is handy for
+//! debugging and metrics. `f` is called with a reference to the value, before the clauses after
+//! it. Inner bindings of `iter!` take a `move` closure, like those of `Iterator::flat_map`. A call
+//! such as `inspect(x);` is still a statement calling a function named `inspect`.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # fn main() {
+//! use std::cell::Cell;
+//!
+//! let count = &Cell::new(0);
+//!
+//! let iter = iter! {
+//!   let x <- 0..4;
+//!   if x % 2 == 0;
+//!   let y <- 0..x;
+//!   inspect move |_| count.set(count.get() + 1);
+//!   (x, y)
+//! };
+//!
+//! assert_eq!(iter.collect::<Vec<_>>(), vec![(2, 0), (2, 1)]);
+//! assert_eq!(count.get(), 2);
+//! # }
+//! ```
+//!
+//! With the `std` feature, `timed f;` measures the binding it follows and calls `f` with the
+//! `Duration` it took, which points out the slow stages of a long comprehension. In `option!` and
+//! `result!` that's the evaluation of the bound expression, while in `iter!` `f` is called for
+//! every pull from the source, including the last one finding it exhausted.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # fn main() {
+//! # #[cfg(feature = "std")]
+//! # {
+//! use std::time::Duration;
+//!
+//! let option = option! {
+//!   let a <- Some(1);
+//!   timed |elapsed: Duration| println!("a took {:?}", elapsed);
+//!   let b <- Some(2);
+//!   timed |elapsed: Duration| println!("b took {:?}", elapsed);
+//!   a + b
+//! };
+//!
+//! assert_eq!(option, Some(3));
+//! # }
+//! # }
+//! ```
+//!
+//! With the `std` feature, `join` pairs up the values of two sources with equal keys in
+//! `O(n + m)` rather than filtering their Cartesian product with `if`. The values of both
+//! sources must be `Clone`, and the keys are `Eq + Hash`.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # fn main() {
+//! # #[cfg(feature = "std")]
+//! # {
+//! let users = vec![(1, "alice"), (2, "bob")];
+//! let orders = vec![(10, 2), (11, 1), (12, 2)];
+//!
+//! let iter = iter! {
+//!   let user <- users;
+//!   join order in orders on user.0 == order.1;
+//!   (user.1, order.0)
+//! };
+//!
+//! assert_eq!(iter.collect::<Vec<_>>(), vec![("alice", 11), ("bob", 10), ("bob", 12)]);
+//! # }
+//! # }
+//! ```
+//!
+//! When both sources are sorted by the key, `merge_join` walks them in lockstep instead, without
+//! allocating an index.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # fn main() {
+//! let users = [(1, "alice"), (2, "bob")];
+//! let orders = [(2, 10), (2, 12), (3, 11)];
+//!
+//! let iter = iter! {
+//!   let (id, name) <- users.iter();
+//!   merge_join (user, order) in orders.iter() on id == user;
+//!   (*name, *order)
+//! };
+//!
+//! assert_eq!(iter.collect::<Vec<_>>(), vec![("bob", 10), ("bob", 12)]);
+//! # }
+//! ```
+//!
+//! In `result!`, `ensure condition, error;` reads like the `ensure!` macro of the error
+//! handling crates, and fails with the error if the condition isn't satisfied.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # fn main() {
+//! let result = result! {
+//!   let width <- "80".parse::<u32>().map_err(|e| e.to_string());
+//!   let height <- "0".parse::<u32>().map_err(|e| e.to_string());
+//!   ensure width > 0, "zero width".to_string();
+//!   ensure height > 0, format!("zero height, width {}", width);
+//!   width * height
+//! };
+//!
+//! assert_eq!(result, Err("zero height, width 80".to_string()));
+//! # }
+//! ```
+//!
+//! ## 5. Return
+//!
+//! In `option!` and `result!`, `return expression;` stops evaluating the remaining clauses and
+//! returns `Some(expression)` or `Ok(expression)` immediately. It can start any statement in the
+//! blocks of an `if` chain without a trailing semicolon as well, `else` blocks included, in which
+//! case the remaining clauses are evaluated only if the chain doesn't return.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # fn main() {
+//! # let cache: std::collections::HashMap<u32, u32> = vec![(1, 10)].into_iter().collect();
+//! # let cache = &cache;
+//! let lookup = |key: u32| option! {
+//!   if let Some(value) = cache.get(&key) {
+//!     return *value;
+//!   }
+//!   let value <- key.checked_mul(10);
+//!   value
+//! };
+//!
+//! assert_eq!(lookup(1), Some(10));
+//! assert_eq!(lookup(2), Some(20));
+//! # }
+//! ```
+//!
+//! Similarly, `fail;` in `option!` and `fail error;` in `result!` return `None` and
+//! `Err(error)` immediately, and they can start any statement in the blocks of an `if` chain too.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # fn main() {
+//! let parse = |s: &str| result! {
+//!   let n <- s.parse::<i32>().map_err(|_| "not a number");
+//!   if n < 0 {
+//!     fail "negative";
+//!   } else if n > 100 {
+//!     let excess = n - 100;
+//!     fail if excess > 10 { "far too large" } else { "too large" };
+//!   }
+//!   n
+//! };
+//!
+//! assert_eq!(parse("1"), Ok(1));
+//! assert_eq!(parse("-1"), Err("negative"));
+//! assert_eq!(parse("105"), Err("too large"));
+//! assert_eq!(parse("x"), Err("not a number"));
+//! # }
+//! ```
+//!
+//! ## 6. Statement & Block
+//!
+//! Statements and blocks are also supported.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # fn main() {
+//! // statement
+//! let iter = iter! {
+//!   let start = 5;
+//!   let end;
+//!   end = start * 3;
+//!
+//!   // 5, 6, ..., 13, 14
+//!   let x <- start..end;
+//!   x
+//! };
+//! let expected = 5..15;
+//! assert!(iter.eq(expected.into_iter()));
+//! # }
+//! ```
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # fn main() {
+//! let iter = iter! {
+//!     let mut a <- 0..5;
+//!
+//!     // block
+//!     {
+//!         fn double(x: u8) -> u8 { x * 2}
+//!         let tmp = double(a);
+//!         a = tmp;
+//!     };
+//!
+//!     // unsafe block
+//!     let count = unsafe {
+//!         static mut CALL_COUNT: u8 = 0;
+//!         CALL_COUNT += 1;
+//!         CALL_COUNT
+//!     };
+//!
+//!     (a, count)
+//! };
+//! let expected = vec![(0, 1), (2, 2), (4, 3), (6, 4), (8, 5)];
+//! assert!(iter.eq(expected.into_iter()));
+//! # }
+//! ```
+//!
+//! # Array
+//!
+//! `Array` in rust behaves differently from other collections. It only iterates its
+//! content by reference.
+//! So `iter!` always binds *references* in `arrow(<-)` syntax, then you need to
+//! *deref* the bound value.
+//! And since one can't move any value out of an `array`, array should be placed
+//! outside the macro to satisfy lifetime.
+//! Append `, by_value` to the binding to iterate the array by value instead, such as
+//! `let s <- [String::new(), String::from("a")], by_value;`.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # fn main() {
+//! let array = [0, 1, 2, 3];
+//! let iter = iter! {
+//!     let x <- array;
+//!     let y <- *x..4;
+//!     (*x, y)
+//! };
+//! let expected = vec![(0, 0), (0, 1), (0, 2), (0, 3), (1, 1), (1, 2), (1, 3), (2, 2),
+//!                     (2, 3), (3, 3)];
+//! assert_eq!(expected, iter.collect::<Vec<_>>());
+//! # }
+//! ```
+//!
+//! `iter_ref!` accepts the same syntax as `iter!`, but binds every source with `iter()` instead,
+//! yielding references and leaving the collections untouched, so they can be used again after
+//! the comprehension. As the closures are still `move`, sources of inner bindings borrowing
+//! from locals need `ref name;` as well.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # fn main() {
+//! let names = vec!["alice".to_string(), "bob".to_string()];
+//! let grid = [[1, 2], [3, 4]];
+//! let iter = iter_ref! {
+//!     ref grid;
+//!     let (i, name) <- enumerate names;
+//!     let x <- grid[i];
+//!     format!("{}{}", name, x)
+//! };
+//! assert_eq!(iter.collect::<Vec<_>>(), vec!["alice1", "alice2", "bob3", "bob4"]);
+//! assert_eq!(names.len(), 2);
+//! # }
+//! ```
+//!
+//! `iter_mut!` binds every source with `iter_mut()` instead, and runs the comprehension right
+//! away in nested loops, evaluating the yielded expression for its side effects, so that the
+//! elements can be modified in place.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # fn main() {
+//! let mut scores = vec![3, -1, 4, -5];
+//! iter_mut! {
+//!     let x <- &mut scores;
+//!     if *x < 0;
+//!     *x = 0
+//! };
+//! assert_eq!(scores, vec![3, 0, 4, 0]);
+//! # }
+//! ```
+//!
+//! # Const
+//!
+//! `option!` and `result!` expand to `and_then()` with closures, which can't be called in
+//! `const fn`. `const_option!` and `const_result!` accept the same syntax, but expand to nested
+//! `match` instead, so that they can be used to compute constants at compile time.
+//!
+//! Note that `const_result!` doesn't convert the error type, as `From` can't be called in
+//! `const fn` either.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! const fn checked_area(w: u32, h: u32) -> Option<u32> {
+//!   const_option! {
+//!     let area <- w.checked_mul(h);
+//!     let area <- area.checked_add(1);
+//!     area - 1
+//!   }
+//! }
+//!
+//! const AREA: Option<u32> = checked_area(3, 4);
+//! const OVERFLOW: Option<u32> = checked_area(u32::MAX, 2);
+//!
+//! # fn main() {
+//! assert_eq!(AREA, Some(12));
+//! assert_eq!(OVERFLOW, None);
+//! # }
+//! ```
+//!
+//! As there are no closures, they also play well with the borrow checker: the clauses can mutate
+//! captured locals and borrow from the bound values of the previous clauses.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # fn main() {
+//! let mut visited = Vec::new();
+//! let names = vec!["alice".to_string(), "bob".to_string()];
+//!
+//! let first: Option<&str> = const_option! {
+//!   let name <- names.first();
+//!   let initial <- name.get(..1);
+//!   visited.push(initial);
+//!   &name[..]
+//! };
+//!
+//! assert_eq!(first, Some("alice"));
+//! assert_eq!(visited, vec!["a"]);
+//! # }
+//! ```
+//!
+//! # Collection
+//!
+//! Instead of returning an iterator, the following macros loop over the same syntax as
+//! `iter!` and collect the yields into a collection directly.
+//!
+//! `vec_comp!` (requires the `alloc` feature) collects into a `Vec`. Unless a guard filters the
+//! values, it reserves the capacity once at the first yield, from the product of the lengths of
+//! the sources when their `size_hint()`s are exact, so that a comprehension yielding once per
+//! combination of its sources is built with a single allocation.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # #[cfg(feature = "alloc")]
+//! # fn main() {
+//! let vec = vec_comp! {
+//!   let x <- 0..3;
+//!   let y <- vec!['a', 'b'];
+//!   (x, y)
+//! };
+//!
+//! assert_eq!(vec, vec![(0, 'a'), (0, 'b'), (1, 'a'), (1, 'b'), (2, 'a'), (2, 'b')]);
+//!
+//! let vec = vec_comp! {
+//!   let x <- 0..3;
+//!   let y <- 0..4;
+//!   x * y
+//! };
+//!
+//! assert_eq!(vec.capacity(), 12);
+//! # }
+//! # #[cfg(not(feature = "alloc"))]
+//! # fn main() {}
+//! ```
+//!
+//! `map_comp!` (requires the `std` feature) yields `key => value` pairs and collects them into a
+//! `HashMap`, like dict-comprehensions in python.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # #[cfg(feature = "std")]
+//! # fn main() {
+//! let map = map_comp! {
+//!   let word <- vec!["apple", "banana"];
+//!   word => word.len()
+//! };
+//!
+//! assert_eq!(map["apple"], 5);
+//! assert_eq!(map["banana"], 6);
+//! # }
+//! # #[cfg(not(feature = "std"))]
+//! # fn main() {}
+//! ```
+//!
+//! `group_comp!` (requires the `std` feature) yields `key => value` pairs as well, but groups
+//! the values by key into a `HashMap` of `Vec`s, like `GroupBy` in LINQ, instead of keeping the
+//! last one.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # #[cfg(feature = "std")]
+//! # fn main() {
+//! let groups = group_comp! {
+//!   let word <- vec!["apple", "avocado", "banana", "blueberry", "cherry"];
+//!   word.chars().next().unwrap() => word
+//! };
+//!
+//! assert_eq!(groups[&'a'], vec!["apple", "avocado"]);
+//! assert_eq!(groups[&'b'], vec!["banana", "blueberry"]);
+//! assert_eq!(groups[&'c'], vec!["cherry"]);
+//! # }
+//! # #[cfg(not(feature = "std"))]
+//! # fn main() {}
+//! ```
+//!
+//! `set_comp!` (requires the `std` feature) collects the yields into a `HashSet`, dropping the
+//! duplicates.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # #[cfg(feature = "std")]
+//! # fn main() {
+//! let set = set_comp! {
+//!   let x <- 0..10;
+//!   x % 3
+//! };
+//!
+//! assert_eq!(set.len(), 3);
+//! # }
+//! # #[cfg(not(feature = "std"))]
+//! # fn main() {}
+//! ```
+//!
+//! `btreemap_comp!` and `btreeset_comp!` (require the `alloc` feature only) are the sorted
+//! counterparts, collecting into a `BTreeMap` and a `BTreeSet` respectively.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # #[cfg(feature = "alloc")]
+//! # fn main() {
+//! let map = btreemap_comp! {
+//!   let word <- vec!["banana", "apple"];
+//!   word => word.len()
+//! };
+//!
+//! assert_eq!(map.into_iter().collect::<Vec<_>>(), vec![("apple", 5), ("banana", 6)]);
+//!
+//! let set = btreeset_comp! {
+//!   let x <- vec![3, 1, 2, 1];
+//!   x
+//! };
+//!
+//! assert_eq!(set.into_iter().collect::<Vec<_>>(), vec![1, 2, 3]);
+//! # }
+//! # #[cfg(not(feature = "alloc"))]
+//! # fn main() {}
+//! ```
+//!
+//! `string!` (requires the `alloc` feature) writes the yields, which can be anything
+//! implementing `Display`, into a `String`. An optional `sep separator;` clause at the beginning
+//! inserts the separator between the yields.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # #[cfg(feature = "alloc")]
+//! # fn main() {
+//! let s = string! {
+//!   let x <- 0..3;
+//!   x
+//! };
+//!
+//! assert_eq!(s, "012");
+//!
+//! let s = string! {
+//!   sep ", ";
+//!   let (name, age) <- vec![("alice", 30), ("bob", 25)];
+//!   format!("{}: {}", name, age)
+//! };
+//!
+//! assert_eq!(s, "alice: 30, bob: 25");
+//! # }
+//! # #[cfg(not(feature = "alloc"))]
+//! # fn main() {}
+//! ```
+//!
+//! `array_comp!` collects into a `[T; N]` without allocation, where the length `N` is given
+//! before the first clause. It returns `None` if the comprehension doesn't yield exactly `N`
+//! elements.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # fn main() {
+//! let squares = array_comp! {
+//!   4;
+//!   let i <- 0..4;
+//!   i * i
+//! };
+//!
+//! assert_eq!(squares, Some([0, 1, 4, 9]));
+//!
+//! let too_short = array_comp! {
+//!   4;
+//!   let i <- 0..3;
+//!   i
+//! };
+//!
+//! assert_eq!(too_short, None);
+//! # }
+//! ```
+//!
+//! # Parallel
+//!
+//! With the `rayon` feature enabled, `par_iter!` is the counterpart of `iter!` for rayon's
+//! `ParallelIterator`. Arrow statements are translated into *`flat_map()`*, except the last one,
+//! which is translated into *`map()`*, or *`filter_map()`* together with the If-Guards that
+//! follow it.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # #[cfg(feature = "rayon")]
+//! # fn main() {
+//! use rayon::prelude::*;
+//!
+//! let iter = par_iter! {
+//!   let x <- 0..100;
+//!   let y <- x..100;
+//!   if (x + y) % 10 == 0;
+//!   x * y
+//! };
+//!
+//! let sum: i32 = iter.sum();
+//! # assert_eq!(sum, iter! { let x <- 0..100; let y <- x..100; if (x + y) % 10 == 0; x * y }.sum());
+//! # }
+//! # #[cfg(not(feature = "rayon"))]
+//! # fn main() {}
+//! ```
+//!
+//! # Generic
+//!
+//! `comp!` works with any wrapper type implementing [`Bind`](trait.Bind.html) and
+//! [`Pure`](trait.Pure.html), which are implemented for `Option` and `Result` out of box.
+//! Arrow statements are expanded into `Bind::bind()` and the last expression into `Pure::pure()`.
+//!
+//! ```
+//! #[macro_use]
+//! extern crate comp;
+//!
+//! use comp::{Bind, Pure};
+//!
+//! #[derive(Debug, PartialEq)]
+//! struct Logged<T>(T, u32);
+//!
+//! impl<T, U> Bind<Logged<U>> for Logged<T> {
+//!     type Item = T;
+//!
+//!     fn bind<F: FnOnce(T) -> Logged<U>>(self, f: F) -> Logged<U> {
+//!         let Logged(u, n) = f(self.0);
+//!         Logged(u, self.1 + n)
+//!     }
+//! }
+//!
+//! impl<T> Pure for Logged<T> {
+//!     type Item = T;
+//!
+//!     fn pure(t: T) -> Self {
+//!         Logged(t, 0)
+//!     }
+//! }
+//!
+//! # fn main() {
+//! let logged = comp! {
+//!   let a <- Logged(1, 1);
+//!   let b <- Logged(2, 1);
+//!   a + b
+//! };
+//!
+//! assert_eq!(logged, Logged(3, 2));
+//!
+//! let option = comp! {
+//!   let a <- Some(1);
+//!   let b <- Some(2);
+//!   a + b
+//! };
+//!
+//! assert_eq!(option, Some(3));
+//! # }
+//! ```
+//!
+//! # Future
+//!
+//! With the `futures` feature enabled, `future!` binds the output of `Future`s with
+//! *`then()`*, and `try_future!` binds the `Ok` output of `TryFuture`s with *`and_then()`*,
+//! short-circuiting on the first error like `result!`.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! # extern crate futures;
+//! #
+//! # #[cfg(feature = "futures")]
+//! # fn main() {
+//! use futures::executor::block_on;
+//! use futures::future::{ok, ready};
+//!
+//! let future = future! {
+//!   let a <- ready(1);
+//!   let b <- ready(2);
+//!   a + b
+//! };
+//!
+//! assert_eq!(block_on(future), 3);
+//!
+//! let future = try_future! {
+//!   let a <- ok::<_, ()>(1);
+//!   let b <- ok(2);
+//!   a + b
+//! };
+//!
+//! assert_eq!(block_on(future), Ok(3));
+//! # }
+//! # #[cfg(not(feature = "futures"))]
+//! # fn main() {}
+//! ```
+//!
+//! `stream!` is the counterpart of `iter!` for `Stream`s, binding with *`flat_map()`* and
+//! translating If-Guard into *`filter()`*.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! # extern crate futures;
+//! #
+//! # #[cfg(feature = "futures")]
+//! # fn main() {
+//! use futures::executor::block_on;
+//! use futures::stream::{iter, StreamExt};
+//!
+//! let stream = stream! {
+//!   let x <- iter(0..4);
+//!   let y <- iter(x..4);
+//!   if x * 2 == y;
+//!   (x, y)
+//! };
+//!
+//! assert_eq!(block_on(stream.collect::<Vec<_>>()), vec![(0, 0), (1, 2)]);
+//! # }
+//! # #[cfg(not(feature = "futures"))]
+//! # fn main() {}
+//! ```
+//!
+//! # Async
+//!
+//! `async_option!` and `async_result!` expand into an `async move` block instead, where every
+//! bound expression is a future which is awaited, then short-circuited on `None`/`Err`.
+//! So they evaluate to `impl Future<Output = Option<T>>` and `impl Future<Output = Result<T, E>>`.
+//! Since the body is inside an `async` block, `.await` can also be used in statements directly.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # fn main() {
+//! # use futures::executor::block_on;
+//! async fn fetch(id: u32) -> Option<u32> {
+//!     Some(id * 10)
+//! }
+//!
+//! let future = async_option! {
+//!   let a <- fetch(1);
+//!   let b <- fetch(a);
+//!   a + b
+//! };
+//!
+//! assert_eq!(block_on(future), Some(110));
+//! # }
+//! ```
+//!
+//! `send;` at the beginning of `async_option!`, `async_result!`, `future!`, `try_future!`,
+//! `stream!` or `iter!` asserts that the result is `Send`, so that holding an `Rc` or a
+//! `MutexGuard` across a binding is reported by the comprehension itself, rather than by a
+//! `spawn` far away through layers of generated closures. `spanned::iter!` also asserts it on
+//! the source of every binding, which points the error at the offending one.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # fn main() {
+//! # use futures::executor::block_on;
+//! async fn fetch(id: u32) -> Option<u32> {
+//!     Some(id * 10)
+//! }
+//!
+//! fn spawn<F: std::future::Future + Send>(future: F) -> F::Output {
+//!     block_on(future)
+//! }
+//!
+//! let future = async_option! {
+//!   send;
+//!   let a <- fetch(1);
+//!   let b <- fetch(a);
+//!   a + b
+//! };
+//!
+//! assert_eq!(spawn(future), Some(110));
+//! # }
+//! ```
+//!
+//! # Either
+//!
+//! With the `either` feature enabled, `either!` binds over `Either<L, R>` from the
+//! [`either`](https://docs.rs/either/) crate, taking `Right` as the success path like `Ok` in
+//! `result!` and short-circuiting on the first `Left`.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # #[cfg(feature = "either")]
+//! # fn main() {
+//! use either::Either::{Left, Right};
+//!
+//! let either = either! {
+//!   let a <- Right::<(), _>(1);
+//!   let b <- Right(2);
+//!   a + b
+//! };
+//!
+//! assert_eq!(either, Right(3));
+//!
+//! let either = either! {
+//!   let a <- Left::<_, u8>("no");
+//!   let b <- Right(2);
+//!   a + b
+//! };
+//!
+//! assert_eq!(either, Left("no"));
+//! # }
+//! # #[cfg(not(feature = "either"))]
+//! # fn main() {}
+//! ```
+//!
+//! # Context
+//!
+//! With the `anyhow` feature enabled, a binding in `result!` can carry a context message for
+//! [`anyhow`](https://docs.rs/anyhow/), which is attached to the error before it's propagated.
+//! The bound expression can be an `Option` as well, turning `None` into an error.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # #[cfg(feature = "anyhow")]
+//! # fn main() {
+//! use std::collections::HashMap;
+//!
+//! fn port(config: &HashMap<&str, &str>) -> anyhow::Result<u16> {
+//!     result! {
+//!       let port <- config.get("port"), context("missing port");
+//!       let port <- port.parse::<u16>(), context(format!("invalid port {:?}", port));
+//!       port
+//!     }
+//! }
+//!
+//! let config = vec![("port", "80x")].into_iter().collect();
+//! let err = port(&config).unwrap_err();
+//!
+//! assert_eq!(err.to_string(), "invalid port \"80x\"");
+//! assert_eq!(err.root_cause().to_string(), "invalid digit found in string");
+//! # }
+//! # #[cfg(not(feature = "anyhow"))]
+//! # fn main() {}
+//! ```
+//!
+//! # ControlFlow
+//!
+//! `control_flow!` binds the `Continue` value of `core::ops::ControlFlow` and propagates
+//! `Break` early, just like `option!` and `result!`. It expands into nested `match`es rather
+//! than closures.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # fn main() {
+//! use std::ops::ControlFlow;
+//!
+//! fn check(x: u32) -> ControlFlow<&'static str, u32> {
+//!     if x < 10 { ControlFlow::Continue(x) } else { ControlFlow::Break("too large") }
+//! }
+//!
+//! let flow = control_flow! {
+//!   let a <- check(1);
+//!   let b <- check(a + 2);
+//!   a + b
+//! };
+//!
+//! assert_eq!(flow, ControlFlow::Continue(4));
+//!
+//! let flow = control_flow! {
+//!   let a <- check(8);
+//!   let b <- check(a + 2);
+//!   a + b
+//! };
+//!
+//! assert_eq!(flow, ControlFlow::Break("too large"));
+//! # }
+//! ```
+//!
+//! # Poll
+//!
+//! `poll!` binds the `Ready` value of `core::task::Poll` and propagates `Pending`, which is
+//! useful in hand-written `Future::poll` functions. `poll_next!` does the same for the
+//! `Poll<Option<T>>` returned by `Stream::poll_next`, propagating `Ready(None)` as well.
+//! Both expand into nested `match`es rather than closures.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # fn main() {
+//! use std::task::Poll;
+//!
+//! let poll = poll! {
+//!   let a <- Poll::Ready(1);
+//!   let b <- Poll::Ready(2);
+//!   a + b
+//! };
+//!
+//! assert_eq!(poll, Poll::Ready(3));
+//!
+//! let poll = poll_next! {
+//!   let a <- Poll::Ready(Some(1));
+//!   let b <- Poll::Ready(None::<i32>);
+//!   a + b
+//! };
+//!
+//! assert_eq!(poll, Poll::Ready(None));
+//! # }
+//! ```
+//!
+//! # Validated
+//!
+//! `result!` short-circuits on the first error. For validations, `validated!` evaluates every
+//! bound expression and collects all the errors into a [`Validated`](enum.Validated.html),
+//! whose `Invalid` holds a collection of errors, e.g. `Vec<E>`.
+//!
+//! Bound expressions can be either `Validated` or `Result`. Since all of them are evaluated
+//! up front, they can't refer to the values bound before. Arrow statements should come first,
+//! followed by statements and the yielded expression.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # fn main() {
+//! use comp::Validated;
+//!
+//! fn positive(x: i32) -> Result<i32, String> {
+//!     if x > 0 { Ok(x) } else { Err(format!("{} is not positive", x)) }
+//! }
+//!
+//! let validated: Validated<_, Vec<String>> = validated! {
+//!   let a <- positive(1);
+//!   let b <- positive(2);
+//!   a + b
+//! };
+//!
+//! assert_eq!(validated, Validated::Valid(3));
+//!
+//! let validated: Validated<_, Vec<String>> = validated! {
+//!   let a <- positive(-1);
+//!   let b <- positive(-2);
+//!   a + b
+//! };
+//!
+//! assert_eq!(validated, Validated::Invalid(vec![
+//!     "-1 is not positive".to_string(),
+//!     "-2 is not positive".to_string(),
+//! ]));
+//! # }
+//! ```
+//!
+//! # Writer
+//!
+//! `writer!` threads an accumulating log through the computation, and returns a tuple of the
+//! yielded value and the log. The log can be any collection implementing `Default` and `Extend`.
+//!
+//! * `let pattern <- expression;`: the expression returns a tuple of the value and an
+//!   iterator of log entries, which are appended to the log.
+//!
+//! * `tell expression;`: appends a single entry to the log.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # fn main() {
+//! fn double(x: i32) -> (i32, Option<String>) {
+//!     (x * 2, Some(format!("doubled {}", x)))
+//! }
+//!
+//! let (value, log): (_, Vec<String>) = writer! {
+//!   tell "start".to_string();
+//!   let a <- double(1);
+//!   let b <- double(a);
+//!   a + b
+//! };
+//!
+//! assert_eq!(value, 6);
+//! assert_eq!(log, vec!["start", "doubled 1", "doubled 2"]);
+//! # }
+//! ```
+//!
+//! # State
+//!
+//! `state!` threads a state through the computation. Every bound expression is a function
+//! taking the current state and returning a tuple of the value and the new state, i.e.
+//! `FnOnce(S) -> (A, S)`. The macro returns such a function as well, which yields the last
+//! expression together with the final state.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # fn main() {
+//! fn next(step: u32) -> impl FnOnce(u32) -> (u32, u32) {
+//!     move |counter| (counter, counter + step)
+//! }
+//!
+//! let run = state! {
+//!   let a <- next(1);
+//!   let b <- next(10);
+//!   (a, b)
+//! };
+//!
+//! assert_eq!(run(5), ((5, 6), 16));
+//! # }
+//! ```
+//!
+//! # Reader
+//!
+//! `reader!` passes a shared environment through the computation. Every bound expression is
+//! a function taking a reference to the environment, i.e. `FnOnce(&Env) -> A`. The macro
+//! returns a function taking the environment, i.e. `FnOnce(Env) -> T`.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # fn main() {
+//! struct Config {
+//!     host: &'static str,
+//!     port: u16,
+//! }
+//!
+//! fn host(config: &Config) -> &'static str {
+//!     config.host
+//! }
+//!
+//! fn port(config: &Config) -> u16 {
+//!     config.port
+//! }
+//!
+//! let address = reader! {
+//!   let host <- host;
+//!   let port <- port;
+//!   format!("{}:{}", host, port)
+//! };
+//!
+//! assert_eq!(address(Config { host: "localhost", port: 80 }), "localhost:80");
+//! # }
+//! ```
+//!
+//! # Diagnostics
+//!
+//! Errors inside `macro_rules` expansions are reported on the whole invocation, though the
+//! common mistakes have a dedicated message, such as a binding missing its `;` or ending the
+//! comprehension with `let pattern = expression` instead of `<-`.
+//!
+//! ```compile_fail
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # fn main() {
+//! let option = option! {
+//!   let a <- Some(1)  // error: expected `;` after `let pattern <- expression`, followed by
+//!   let b <- Some(2); //        the value to yield
+//!   a + b
+//! };
+//! # }
+//! ```
+//!
+//! ```compile_fail
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # fn main() {
+//! let option = option! {
+//!   let a <- Some(1);
+//!   let b = a + 1 // error: expected `<-` after the pattern to bind, or `;` and the value to
+//! };              //        yield after `let`
+//! # }
+//! ```
+//!
+//! Enabling the `proc-macro` feature provides procedural versions of all the macros in
+//! `comp::spanned`, which accept the same syntax but report type errors at the offending
+//! binding, and malformed syntax with a friendly message.
+//!
+//! ```toml
+//! [dependencies]
+//! comp = { version = "*", features = ["proc-macro"] }
+//! ```
+//!
+//! ```rust,ignore
+//! let option = comp::spanned::option! {
+//!   let a <- Some(1);
+//!   let b <- 2;      // error: no method named `and_then` found for type `{integer}`
+//!   a + b
+//! };
+//! ```
+//!
+//! Besides, `spanned::iter!` evaluates the source of an inner binding only once when it doesn't
+//! refer to anything bound before it and is made of literals and paths only, such as `0..n` or
+//! `&ys`, and clones it for every value of the outer bindings instead of rebuilding it, so such a
+//! source must be `Clone`. This also lets inner bindings borrow from local variables, such as
+//! `let y <- &ys;`. Other sources are evaluated again for every value of the outer bindings,
+//! unless they are marked with `memo`.
+//!
+//! `from_fn;` at the beginning of `spanned::iter!` compiles the whole comprehension into a
+//! single `core::iter::from_fn` state machine instead of nested closures, which keeps the type
+//! of the result flat however deep the comprehension is, and is easier to step through in a
+//! debugger. It holds the iterator of every binding in progress along with the values bound
+//! before it, which are cloned for each value of the binding, so they must be `Clone`.
+//! `skip_while` and `boxed;` aren't supported in this mode.
+//!
+//! ```
+//! # #[cfg(feature = "proc-macro")]
+//! # fn main() {
+//! let iter = comp::spanned::iter! {
+//!   from_fn;
+//!   let x <- 0..3;
+//!   let y <- x..3;
+//!   if x != y;
+//!   (x, y)
+//! };
+//!
+//! assert_eq!(iter.collect::<Vec<_>>(), vec![(0, 1), (0, 2), (1, 2)]);
+//! # }
+//! # #[cfg(not(feature = "proc-macro"))]
+//! # fn main() {}
+//! ```
+//!
+//! The type of an `iter!` can't be written down, so it can't be stored in a struct field or
+//! returned from a trait method. `spanned::iter_struct!` (also requiring the `alloc` feature)
+//! declares a named struct instead, whose `new` takes the given arguments and iterates over the
+//! comprehension behind a box. `#[derive(Clone)]` and `#[derive(Debug)]` on it require the
+//! comprehension to be `Clone` and `Debug`, which it is when the sources and the captured
+//! values are. The struct may borrow with a single lifetime parameter.
+//!
+//! ```
+//! # #[cfg(all(feature = "proc-macro", feature = "alloc"))]
+//! # fn main() {
+//! comp::spanned::iter_struct! {
+//!   #[derive(Clone, Debug)]
+//!   pub struct Pairs(n: u32) -> (u32, u32) {
+//!     let x <- 0..n;
+//!     let y <- x..n;
+//!     if x != y;
+//!     (x, y)
+//!   }
+//! }
+//!
+//! struct Schedule {
+//!   pairs: Pairs,
+//! }
+//!
+//! let schedule = Schedule { pairs: Pairs::new(3) };
+//! assert_eq!(schedule.pairs.clone().count(), 3);
+//! # }
+//! # #[cfg(not(all(feature = "proc-macro", feature = "alloc")))]
+//! # fn main() {}
+//! ```
+//!
+//! `spanned::comp_expand!` takes an invocation of `option!`, `result!`, `iter!` or `comp!` and
+//! returns the code it expands to as a string, so that a single comprehension can be inspected
+//! without running `cargo expand` on the whole crate.
+//!
+//! ```
+//! # #[cfg(feature = "proc-macro")]
+//! # fn main() {
+//! let expanded = comp::spanned::comp_expand!(option! {
+//!   let a <- Some(1);
+//!   let b <- Some(2);
+//!   a + b
+//! });
+//!
+//! println!("{}", expanded); // (Some(1)).and_then(move | v | { ... (Some(2)).and_then(...) ... })
+//! assert!(expanded.contains("and_then"));
+//! # }
+//! # #[cfg(not(feature = "proc-macro"))]
+//! # fn main() {}
+//! ```
+//!
+//! With the `trace` feature enabled, every bind of `option!` and `result!` (including their
+//! `spanned` versions) reports the source text of the bound expression, its location and whether
+//! it produced a value, `None` or `Err`, which tells which bind short-circuited the
+//! comprehension. The traces are printed to stderr unless a hook is set with
+//! `comp::set_trace_hook`. The feature pulls in `std`, and costs nothing when it's disabled.
+//!
+//! ```
+//! # #[macro_use]
+//! # extern crate comp;
+//! #
+//! # #[cfg(feature = "trace")]
+//! # fn main() {
+//! use comp::{Outcome, Trace};
+//!
+//! comp::set_trace_hook(|trace: &Trace| {
+//!   if trace.outcome != Outcome::Value {
+//!     assert_eq!(trace.source, "\"x\".parse::<u32>().ok()");
+//!   }
+//! });
+//!
+//! let option = option! {
+//!   let a <- "1".parse::<u32>().ok();
+//!   let b <- "x".parse::<u32>().ok(); // `"x".parse::<u32>().ok()` returned `None`
+//!   a + b
+//! };
+//!
+//! assert_eq!(option, None);
+//! # }
+//! # #[cfg(not(feature = "trace"))]
+//! # fn main() {}
+//! ```
+//!
+//! # Contribution
+//!
+//! All kinds of contribution are welcome.
+//!
+//! - **Issue.** Feel free to open an issue when you find typos, bugs, or have any question.
+//! - **Pull requests**. Better implementation, more tests, more documents and typo fixes are all welcome.
+//!
+//! # License
+//!
+//! Licensed under MIT license ([LICENSE-MIT](LICENSE-MIT) or http://opensource.org/licenses/MIT)
+
+#[cfg(feature = "proc-macro")]
+extern crate comp_macros;
+
+#[cfg(test)]
+extern crate self as comp;
+
+mod validated;
+
+#[cfg(feature = "trace")]
+mod trace;
+
+pub use crate::validated::Validated;
+
+#[cfg(feature = "trace")]
+pub use crate::trace::{set_trace_hook, Outcome, Trace};
+
+#[cfg(feature = "trace")]
+#[doc(hidden)]
+pub use crate::trace::{__trace, __Traced};
+
+#[cfg(feature = "alloc")]
+#[doc(hidden)]
+pub extern crate alloc as __alloc;
+
+#[cfg(feature = "std")]
+#[doc(hidden)]
+pub extern crate std as __std;
+
+#[cfg(feature = "futures")]
+#[doc(hidden)]
+pub extern crate futures as __futures;
+
+#[cfg(feature = "either")]
+#[doc(hidden)]
+pub extern crate either as __either;
+
+#[cfg(feature = "rayon")]
+#[doc(hidden)]
+pub extern crate rayon as __rayon;
+
+#[cfg(feature = "anyhow")]
+#[doc(hidden)]
+pub extern crate anyhow as __anyhow;
+
+/// Procedural versions of the macros, which point errors at the offending clause.
+///
+/// Enabled by the `proc-macro` feature.
+#[cfg(feature = "proc-macro")]
+pub mod spanned {
+    pub use comp_macros::{comp, comp_expand, iter, iter_struct, option, result};
+}
+
+#[cfg(feature = "trace")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __trace {
+    ($e: expr) => (
+        $crate::__trace(::core::stringify!($e), ::core::file!(), ::core::line!(), $e)
+    );
+}
+
+#[cfg(not(feature = "trace"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __trace {
+    ($e: expr) => ($e);
+}
+
+/// Calls `into_iter` on the source of a binding as a method, so that arrays are iterated by
+/// reference as in the edition of this crate, whatever the edition of the caller.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __into_iter {
+    ($e: expr) => ({
+        #[allow(unused_imports)]
+        use ::core::iter::IntoIterator as _;
+        $e.into_iter()
+    });
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __pat {
+    (@pat $m: ident [ $( $pre: tt )* ] ( $p: pat ) $( $t: tt )*) => (
+        $crate::$m! { $( $pre )* let $p $( $t )* }
+    );
+
+    (@ty $m: ident [ $( $pre: tt )* ] [ $( $p: tt )* ] ( $ty: ty ) $( $t: tt )*) => (
+        $crate::$m! { $( $pre )* let $( $p )* : $ty $( $t )* }
+    );
+
+    (@let $m: ident [ $( $pre: tt )* ] ( $p: pat ) $( $t: tt )*) => (
+        $crate::$m! { $( $pre )* let $p $( $t )* }
+    );
+
+    (@ty $m: ident [ $( $pre: tt )* ] [ $( $p: tt )* ] [ $( $ty: tt )* ] <- $( $t: tt )*) => (
+        $crate::__pat! { @ty $m [ $( $pre )* ] [ $( $p )* ] ( $( $ty )* ) <- $( $t )* }
+    );
+
+    (@ty $m: ident [ $( $pre: tt )* ] [ $( $p: tt )* ] [ $( $ty: tt )* ] ; $( $t: tt )*) => (
+        $crate::__pat! { @let $m [ $( $pre )* ] ( $( $p )* ) : $( $ty )* ; $( $t )* }
+    );
+
+    (@ty $m: ident [ $( $pre: tt )* ] [ $( $p: tt )* ] [ $( $ty: tt )* ] $x: tt $( $t: tt )*) => (
+        $crate::__pat! { @ty $m [ $( $pre )* ] [ $( $p )* ] [ $( $ty )* $x ] $( $t )* }
+    );
+
+    ($m: ident [ $( $pre: tt )* ] [ $( $p: tt )* ] <- $( $t: tt )*) => (
+        $crate::__pat! { @pat $m [ $( $pre )* ] ( $( $p )* ) <- $( $t )* }
+    );
+
+    ($m: ident [ $( $pre: tt )* ] [ $( $p: tt )* ] = $( $t: tt )*) => (
+        $crate::__pat! { @pat $m [ $( $pre )* ] ( $( $p )* ) = $( $t )* }
+    );
+
+    (@ty $m: ident [ $( $pre: tt )* ] [ $( $p: tt )* ] [ $( $ty: tt )* ]) => (
+        ::core::compile_error!("expected `<-` after the pattern to bind, or `;` and the value to yield after `let`")
+    );
+
+    ($m: ident [ $( $pre: tt )* ] [ $( $p: tt )* ]) => (
+        ::core::compile_error!("expected `<-` after the pattern to bind, or `;` and the value to yield after `let`")
+    );
+
+    ($m: ident [ $( $pre: tt )* ] [ $( $p: tt )* ] $x: tt $( $t: tt )*) => (
+        $crate::__pat! { $m [ $( $pre )* ] [ $( $p )* $x ] $( $t )* }
+    );
+}
+
+/// Checks that the comprehension of `$m` in strict mode doesn't end with `;` unless its last
+/// clause is a `yield`, a `return`, a `fail` or a `continue`, keeping the first token of the current clause in `[ $k ]` and munching
+/// eight tokens at a time up to the next `;` to keep the recursion shallow.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __strict {
+    (@clause $m: ident [ $( $a: tt )* ] [ $( $k: tt )* ]) => (
+        $crate::$m! { $( $a )* }
+    );
+
+    (@clause $m: ident [ $( $a: tt )* ] [ yield ] ;) => (
+        $crate::$m! { $( $a )* ; }
+    );
+
+    (@clause $m: ident [ $( $a: tt )* ] [ return ] ;) => (
+        $crate::$m! { $( $a )* ; }
+    );
+
+    (@clause $m: ident [ $( $a: tt )* ] [ fail ] ;) => (
+        $crate::$m! { $( $a )* ; }
+    );
+
+    (@clause $m: ident [ $( $a: tt )* ] [ continue ] ;) => (
+        $crate::$m! { $( $a )* ; }
+    );
+
+    (@clause $m: ident [ $( $a: tt )* ] [ $( $k: tt )* ] ; $( $t: tt )*) => (
+        $crate::__strict! { $m [ $( $a )* ; ] $( $t )* }
+    );
+
+    (@clause $m: ident [ $( $a: tt )* ] [ $( $k: tt )* ] $b: tt ; $( $t: tt )*) => (
+        $crate::__strict! { @clause $m [ $( $a )* $b ] [ $( $k )* ] ; $( $t )* }
+    );
+
+    (@clause $m: ident [ $( $a: tt )* ] [ $( $k: tt )* ] $b: tt $c: tt ; $( $t: tt )*) => (
+        $crate::__strict! { @clause $m [ $( $a )* $b $c ] [ $( $k )* ] ; $( $t )* }
+    );
+
+    (@clause $m: ident [ $( $a: tt )* ] [ $( $k: tt )* ] $b: tt $c: tt $d: tt ; $( $t: tt )*) => (
+        $crate::__strict! { @clause $m [ $( $a )* $b $c $d ] [ $( $k )* ] ; $( $t )* }
+    );
+
+    (@clause $m: ident [ $( $a: tt )* ] [ $( $k: tt )* ] $b: tt $c: tt $d: tt $e: tt ; $( $t: tt )*) => (
+        $crate::__strict! { @clause $m [ $( $a )* $b $c $d $e ] [ $( $k )* ] ; $( $t )* }
+    );
+
+    (@clause $m: ident [ $( $a: tt )* ] [ $( $k: tt )* ] $b: tt $c: tt $d: tt $e: tt $f: tt ; $( $t: tt )*) => (
+        $crate::__strict! { @clause $m [ $( $a )* $b $c $d $e $f ] [ $( $k )* ] ; $( $t )* }
+    );
+
+    (@clause $m: ident [ $( $a: tt )* ] [ $( $k: tt )* ] $b: tt $c: tt $d: tt $e: tt $f: tt $g: tt ; $( $t: tt )*) => (
+        $crate::__strict! { @clause $m [ $( $a )* $b $c $d $e $f $g ] [ $( $k )* ] ; $( $t )* }
+    );
+
+    (@clause $m: ident [ $( $a: tt )* ] [ $( $k: tt )* ] $b: tt $c: tt $d: tt $e: tt $f: tt $g: tt $h: tt ; $( $t: tt )*) => (
+        $crate::__strict! { @clause $m [ $( $a )* $b $c $d $e $f $g $h ] [ $( $k )* ] ; $( $t )* }
+    );
+
+    (
+        @clause $m: ident [ $( $a: tt )* ] [ $( $k: tt )* ]
+        $b: tt $c: tt $d: tt $e: tt $f: tt $g: tt $h: tt $i: tt $( $t: tt )*
+    ) => (
+        $crate::__strict! { @clause $m [ $( $a )* $b $c $d $e $f $g $h $i ] [ $( $k )* ] $( $t )* }
+    );
+
+    (@clause $m: ident [ $( $a: tt )* ] [ $( $k: tt )* ] $x: tt $( $t: tt )*) => (
+        $crate::__strict! { @clause $m [ $( $a )* $x ] [ $( $k )* ] $( $t )* }
+    );
+
+    ($m: ident []) => (
+        ::core::compile_error!("the comprehension is empty and yields `()`, yield `()` explicitly if intended")
+    );
+
+    ($m: ident [ $( $a: tt )* ]) => (
+        ::core::compile_error!("the comprehension yields `()` because it ends with `;`, yield `()` explicitly if intended")
+    );
+
+    ($m: ident [ $( $a: tt )* ] ; $( $t: tt )*) => (
+        $crate::__strict! { $m [ $( $a )* ; ] $( $t )* }
+    );
+
+    ($m: ident [ $( $a: tt )* ] $x: tt $( $t: tt )*) => (
+        $crate::__strict! { @clause $m [ $( $a )* $x ] [ $x ] $( $t )* }
+    );
+}
+
+/// Rewrites every `return expression;`, `fail;`, `fail error;`, `continue;` or `skip;` starting
+/// a statement in the blocks of the `if` chain into `break $l` with what the comprehension of kind `$k` evaluates
+/// to, then calls back `$m` with
+/// `@exit $l [ exit ]`, or `@exit $l []` if there's nothing to rewrite, followed by the chain and
+/// the clauses after it. Up to eight tokens are munched at a time in the middle of a statement to
+/// keep the recursion shallow.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __exit {
+    (@chain $env: tt $f: tt [ $( $o: tt )* ] { $( $b: tt )* } $( $t: tt )*) => (
+        $crate::__exit! { @stmt $env $f [ $( $o )* ] [ $( $t )* ] [] $( $b )* }
+    );
+
+    (@chain $env: tt $f: tt [ $( $o: tt )* ] $x: tt $( $t: tt )*) => (
+        $crate::__exit! { @chain $env $f [ $( $o )* $x ] $( $t )* }
+    );
+
+    (@chain [ $l: lifetime $k: ident [ $m: ident $( $pre: tt )* ] [ $( $t: tt )* ] ] $f: tt [ $( $o: tt )* ]) => (
+        $crate::$m! { $( $pre )* @exit $l $f [ $( $o )* ] $( $t )* }
+    );
+
+    (@end $env: tt $f: tt [ $( $o: tt )* ] [ $( $t: tt )* ] [ $( $a: tt )* ]) => (
+        $crate::__exit! { @chain $env $f [ $( $o )* { $( $a )* } ] $( $t )* }
+    );
+
+    (@stmt [ $l: lifetime option $( $x: tt )* ] $f: tt $o: tt $r: tt [ $( $a: tt )* ] return $v: expr ; $( $t: tt )*) => (
+        $crate::__exit! { @stmt [ $l option $( $x )* ] [ exit ] $o $r [ $( $a )* break $l ::core::option::Option::Some($v) ; ] $( $t )* }
+    );
+
+    (@stmt [ $l: lifetime option $( $x: tt )* ] $f: tt $o: tt $r: tt [ $( $a: tt )* ] return $v: expr) => (
+        $crate::__exit! { @end [ $l option $( $x )* ] [ exit ] $o $r [ $( $a )* break $l ::core::option::Option::Some($v) ] }
+    );
+
+    (@stmt [ $l: lifetime result $( $x: tt )* ] $f: tt $o: tt $r: tt [ $( $a: tt )* ] return $v: expr ; $( $t: tt )*) => (
+        $crate::__exit! { @stmt [ $l result $( $x )* ] [ exit ] $o $r [ $( $a )* break $l ::core::result::Result::Ok($v) ; ] $( $t )* }
+    );
+
+    (@stmt [ $l: lifetime result $( $x: tt )* ] $f: tt $o: tt $r: tt [ $( $a: tt )* ] return $v: expr) => (
+        $crate::__exit! { @end [ $l result $( $x )* ] [ exit ] $o $r [ $( $a )* break $l ::core::result::Result::Ok($v) ] }
+    );
+
+    (@stmt [ $l: lifetime option $( $x: tt )* ] $f: tt $o: tt $r: tt [ $( $a: tt )* ] fail ; $( $t: tt )*) => (
+        $crate::__exit! { @stmt [ $l option $( $x )* ] [ exit ] $o $r [ $( $a )* break $l ::core::option::Option::None ; ] $( $t )* }
+    );
+
+    (@stmt [ $l: lifetime option $( $x: tt )* ] $f: tt $o: tt $r: tt [ $( $a: tt )* ] fail) => (
+        $crate::__exit! { @end [ $l option $( $x )* ] [ exit ] $o $r [ $( $a )* break $l ::core::option::Option::None ] }
+    );
+
+    (@stmt [ $l: lifetime result $( $x: tt )* ] $f: tt $o: tt $r: tt [ $( $a: tt )* ] fail $v: expr ; $( $t: tt )*) => (
+        $crate::__exit! { @stmt [ $l result $( $x )* ] [ exit ] $o $r [ $( $a )* break $l ::core::result::Result::Err($v) ; ] $( $t )* }
+    );
+
+    (@stmt [ $l: lifetime result $( $x: tt )* ] $f: tt $o: tt $r: tt [ $( $a: tt )* ] fail $v: expr) => (
+        $crate::__exit! { @end [ $l result $( $x )* ] [ exit ] $o $r [ $( $a )* break $l ::core::result::Result::Err($v) ] }
+    );
+
+    (@stmt [ $l: lifetime iter $( $x: tt )* ] $f: tt $o: tt $r: tt [ $( $a: tt )* ] continue ; $( $t: tt )*) => (
+        $crate::__exit! { @stmt [ $l iter $( $x )* ] [ exit ] $o $r [ $( $a )* break $l ::core::option::Option::None ; ] $( $t )* }
+    );
+
+    (@stmt [ $l: lifetime iter $( $x: tt )* ] $f: tt $o: tt $r: tt [ $( $a: tt )* ] continue) => (
+        $crate::__exit! { @end [ $l iter $( $x )* ] [ exit ] $o $r [ $( $a )* break $l ::core::option::Option::None ] }
+    );
+
+    (@stmt [ $l: lifetime iter $( $x: tt )* ] $f: tt $o: tt $r: tt [ $( $a: tt )* ] skip ; $( $t: tt )*) => (
+        $crate::__exit! { @stmt [ $l iter $( $x )* ] [ exit ] $o $r [ $( $a )* break $l ::core::option::Option::None ; ] $( $t )* }
+    );
+
+    (@stmt [ $l: lifetime iter $( $x: tt )* ] $f: tt $o: tt $r: tt [ $( $a: tt )* ] skip) => (
+        $crate::__exit! { @end [ $l iter $( $x )* ] [ exit ] $o $r [ $( $a )* break $l ::core::option::Option::None ] }
+    );
+
+    (@stmt $env: tt $f: tt $o: tt $r: tt [ $( $a: tt )* ] ; $( $t: tt )*) => (
+        $crate::__exit! { @stmt $env $f $o $r [ $( $a )* ; ] $( $t )* }
+    );
+
+    (@stmt $env: tt $f: tt $o: tt $r: tt [ $( $a: tt )* ] { $( $b: tt )* } $( $t: tt )*) => (
+        $crate::__exit! { @stmt $env $f $o $r [ $( $a )* { $( $b )* } ] $( $t )* }
+    );
+
+    (@stmt $env: tt $f: tt $o: tt $r: tt [ $( $a: tt )* ] $x: tt $( $t: tt )*) => (
+        $crate::__exit! { @expr $env $f $o $r [ $( $a )* $x ] $( $t )* }
+    );
+
+    (@stmt $env: tt $f: tt $o: tt $r: tt [ $( $a: tt )* ]) => (
+        $crate::__exit! { @end $env $f $o $r [ $( $a )* ] }
+    );
+
+    (@expr $env: tt $f: tt $o: tt $r: tt [ $( $a: tt )* ] ; $( $t: tt )*) => (
+        $crate::__exit! { @stmt $env $f $o $r [ $( $a )* ; ] $( $t )* }
+    );
+
+    (@expr $env: tt $f: tt $o: tt $r: tt [ $( $a: tt )* ] { $( $x: tt )* } $( $t: tt )*) => (
+        $crate::__exit! { @stmt $env $f $o $r [ $( $a )* { $( $x )* } ] $( $t )* }
+    );
+
+    (@expr $env: tt $f: tt $o: tt $r: tt [ $( $a: tt )* ] $b: tt ; $( $t: tt )*) => (
+        $crate::__exit! { @stmt $env $f $o $r [ $( $a )* $b ; ] $( $t )* }
+    );
+
+    (@expr $env: tt $f: tt $o: tt $r: tt [ $( $a: tt )* ] $b: tt { $( $x: tt )* } $( $t: tt )*) => (
+        $crate::__exit! { @stmt $env $f $o $r [ $( $a )* $b { $( $x )* } ] $( $t )* }
+    );
+
+    (@expr $env: tt $f: tt $o: tt $r: tt [ $( $a: tt )* ] $b: tt $c: tt ; $( $t: tt )*) => (
+        $crate::__exit! { @stmt $env $f $o $r [ $( $a )* $b $c ; ] $( $t )* }
+    );
+
+    (@expr $env: tt $f: tt $o: tt $r: tt [ $( $a: tt )* ] $b: tt $c: tt { $( $x: tt )* } $( $t: tt )*) => (
+        $crate::__exit! { @stmt $env $f $o $r [ $( $a )* $b $c { $( $x )* } ] $( $t )* }
+    );
+
+    (@expr $env: tt $f: tt $o: tt $r: tt [ $( $a: tt )* ] $b: tt $c: tt $d: tt ; $( $t: tt )*) => (
+        $crate::__exit! { @stmt $env $f $o $r [ $( $a )* $b $c $d ; ] $( $t )* }
+    );
+
+    (@expr $env: tt $f: tt $o: tt $r: tt [ $( $a: tt )* ] $b: tt $c: tt $d: tt { $( $x: tt )* } $( $t: tt )*) => (
+        $crate::__exit! { @stmt $env $f $o $r [ $( $a )* $b $c $d { $( $x )* } ] $( $t )* }
+    );
+
+    (@expr $env: tt $f: tt $o: tt $r: tt [ $( $a: tt )* ] $b: tt $c: tt $d: tt $e: tt ; $( $t: tt )*) => (
+        $crate::__exit! { @stmt $env $f $o $r [ $( $a )* $b $c $d $e ; ] $( $t )* }
+    );
+
+    (@expr $env: tt $f: tt $o: tt $r: tt [ $( $a: tt )* ] $b: tt $c: tt $d: tt $e: tt { $( $x: tt )* } $( $t: tt )*) => (
+        $crate::__exit! { @stmt $env $f $o $r [ $( $a )* $b $c $d $e { $( $x )* } ] $( $t )* }
+    );
+
+    (@expr $env: tt $f: tt $o: tt $r: tt [ $( $a: tt )* ] $b: tt $c: tt $d: tt $e: tt $g: tt ; $( $t: tt )*) => (
+        $crate::__exit! { @stmt $env $f $o $r [ $( $a )* $b $c $d $e $g ; ] $( $t )* }
+    );
+
+    (@expr $env: tt $f: tt $o: tt $r: tt [ $( $a: tt )* ] $b: tt $c: tt $d: tt $e: tt $g: tt { $( $x: tt )* } $( $t: tt )*) => (
+        $crate::__exit! { @stmt $env $f $o $r [ $( $a )* $b $c $d $e $g { $( $x )* } ] $( $t )* }
+    );
+
+    (@expr $env: tt $f: tt $o: tt $r: tt [ $( $a: tt )* ] $b: tt $c: tt $d: tt $e: tt $g: tt $h: tt ; $( $t: tt )*) => (
+        $crate::__exit! { @stmt $env $f $o $r [ $( $a )* $b $c $d $e $g $h ; ] $( $t )* }
+    );
+
+    (@expr $env: tt $f: tt $o: tt $r: tt [ $( $a: tt )* ] $b: tt $c: tt $d: tt $e: tt $g: tt $h: tt { $( $x: tt )* } $( $t: tt )*) => (
+        $crate::__exit! { @stmt $env $f $o $r [ $( $a )* $b $c $d $e $g $h { $( $x )* } ] $( $t )* }
+    );
+
+    (@expr $env: tt $f: tt $o: tt $r: tt [ $( $a: tt )* ] $b: tt $c: tt $d: tt $e: tt $g: tt $h: tt $i: tt ; $( $t: tt )*) => (
+        $crate::__exit! { @stmt $env $f $o $r [ $( $a )* $b $c $d $e $g $h $i ; ] $( $t )* }
+    );
+
+    (@expr $env: tt $f: tt $o: tt $r: tt [ $( $a: tt )* ] $b: tt $c: tt $d: tt $e: tt $g: tt $h: tt $i: tt { $( $x: tt )* } $( $t: tt )*) => (
+        $crate::__exit! { @stmt $env $f $o $r [ $( $a )* $b $c $d $e $g $h $i { $( $x )* } ] $( $t )* }
+    );
+
+    (@expr $env: tt $f: tt $o: tt $r: tt [ $( $a: tt )* ] $b: tt $c: tt $d: tt $e: tt $g: tt $h: tt $i: tt $j: tt $( $t: tt )+) => (
+        $crate::__exit! { @expr $env $f $o $r [ $( $a )* $b $c $d $e $g $h $i $j ] $( $t )* }
+    );
+
+    (@expr $env: tt $f: tt $o: tt $r: tt [ $( $a: tt )* ] $( $t: tt )*) => (
+        $crate::__exit! { @end $env $f $o $r [ $( $a )* $( $t )* ] }
+    );
+
+    ($l: lifetime $k: ident [ $( $m: tt )* ] [ $( $t: tt )* ] $( $s: tt )*) => (
+        $crate::__exit! { @chain [ $l $k [ $( $m )* ] [ $( $t )* ] ] [] [] $( $s )* }
+    );
+}
+
+/// syntax extension specific for Option
+///
+/// See the module-level documentation for more details.
+#[macro_export]
+macro_rules! option {
+    (@as_pat $p: pat) => ($p);
+
+    (
+        let $( $b: ident )+ @ $( $t: tt )+
+    ) => (
+        $crate::__pat! { option [] [ $( $b )+ @ ] $( $t )* }
+    );
+
+    (
+        let & $( $t: tt )+
+    ) => (
+        $crate::__pat! { option [] [ & ] $( $t )* }
+    );
+
+    (
+        let ref $( $t: tt )+
+    ) => (
+        $crate::__pat! { option [] [ ref ] $( $t )* }
+    );
+
+    (
+        let $( $a: ident )? :: $( $t: tt )+
+    ) => (
+        $crate::__pat! { option [] [ $( $a )? :: ] $( $t )* }
+    );
+
+    (
+        ref $( $x: ident ),+ ; $( $t: tt )*
+    ) => ({
+        $( let $x = &$x; )+
+        $crate::option! { $( $t )* }
+    });
+
+    (
+        strict ; $( $t: tt )*
+    ) => (
+        $crate::__strict! { option [] $( $t )* }
+    );
+
+    (
+        @if [ $( $c: tt )* ] { $( $a: tt )* } $( $t: tt )*
+    ) => (
+        $crate::option! { @else [ if $( $c )* { $( $a )* } ] $( $t )* }
+    );
+
+    (
+        @if [ $( $c: tt )* ] ; $( $t: tt )*
+    ) => (
+        if $( $c )* { $crate::option! { $( $t )* } } else { ::core::option::Option::None }
+    );
+
+    (
+        @if [ $( $c: tt )* ] $x: tt $( $t: tt )*
+    ) => (
+        $crate::option! { @if [ $( $c )* $x ] $( $t )* }
+    );
+
+    (
+        @else_body [ $( $s: tt )* ] { $( $x: tt )* } $( $t: tt )*
+    ) => (
+        $crate::option! { @else [ $( $s )* { $( $x )* } ] $( $t )* }
+    );
+
+    (
+        @else_body [ $( $s: tt )* ] $x: tt $( $t: tt )*
+    ) => (
+        $crate::option! { @else_body [ $( $s )* $x ] $( $t )* }
+    );
+
+    (
+        @else [ $( $s: tt )* ] else $( $t: tt )*
+    ) => (
+        $crate::option! { @else_body [ $( $s )* else ] $( $t )* }
+    );
+
+    (
+        @else [ $( $s: tt )* ] $( $t: tt )*
+    ) => (
+        $crate::__exit! { 'exit option [ option ] [ $( $t )* ] $( $s )* }
+    );
+
+    (
+        @exit $l: lifetime [] [ $( $s: tt )* ] $( $t: tt )*
+    ) => (
+        $crate::option! { @end [ $( $s )* ] $( $t )* }
+    );
+
+    (
+        @exit $l: lifetime [ exit ] [ $( $s: tt )* ] $( $t: tt )*
+    ) => (
+        $l: { $crate::option! { @end [ $( $s )* ] $( $t )* } }
+    );
+
+    (
+        @end [ $( $s: tt )* ]
+    ) => (
+        ::core::option::Option::Some($( $s )*)
+    );
+
+    (
+        @end [ $( $s: tt )* ] ; $( $t: tt )*
+    ) => (
+        { $( $s )* ; $crate::option! { $( $t )* } }
+    );
+
+    (
+        @end [ $( $s: tt )* ] $( $t: tt )+
+    ) => (
+        { $( $s )* $crate::option! { $( $t )* } }
+    );
+
+    (
+        @for ($p: pat) [ $( $e: tt )* ] ; $( $t: tt )*
+    ) => (
+        $crate::option! { let $p <- $( $e )* ; $( $t )* }
+    );
+
+    (
+        @for ($p: pat) [ $( $e: tt )* ] { $( $body: tt )* } ; $( $t: tt )*
+    ) => (
+        { for $p in $( $e )* { $( $body )* } $crate::option! { $( $t )* } }
+    );
+
+    (
+        @for ($p: pat) [ $( $e: tt )* ] { $( $body: tt )* } $( $t: tt )*
+    ) => (
+        { for $p in $( $e )* { $( $body )* } $crate::option! { $( $t )* } }
+    );
+
+    (
+        @for ($p: pat) [ $( $e: tt )* ] $x: tt $( $t: tt )*
+    ) => (
+        $crate::option! { @for ($p) [ $( $e )* $x ] $( $t )* }
+    );
+
+    (
+        @let_else ( $( $p: tt )* ) [ $( $e: tt )* ] else $d: expr ; $( $t: tt )*
+    ) => (
+        {
+            let $( $p )* = match $crate::__trace!($( $e )*) {
+                ::core::option::Option::Some(v) => v,
+                ::core::option::Option::None => $d,
+            };
+            $crate::option! { $( $t )* }
+        }
+    );
+
+    (
+        @let_else ( $( $p: tt )* ) [ $( $e: tt )* ] or $( $t: tt )*
+    ) => (
+        $crate::option! { @let_or ( $( $p )* ) [ $( $e )* ] [] $( $t )* }
+    );
+
+    (
+        @let_else ( $( $p: tt )* ) [ $( $e: tt )* ]
+    ) => (
+        ::core::compile_error!("expected `;` after `let pattern <- expression`, followed by the value to yield")
+    );
+
+    (
+        @let_else ( $( $p: tt )* ) [ $( $e: tt )* ] $x: tt $( $t: tt )*
+    ) => (
+        $crate::option! { @let_else ( $( $p )* ) [ $( $e )* $x ] $( $t )* }
+    );
+
+    (@or_else [ $( $e: tt )* ] [ $( $a: tt )* ]) => (
+        ($( $e )*).or_else(|| $( $a )*)
+    );
+
+    (
+        @let_or ( $( $p: tt )* ) [ $( $e: tt )* ] [ $( $a: tt )* ] or $( $t: tt )*
+    ) => (
+        $crate::option! { @let_or ( $( $p )* ) [ $crate::option! { @or_else [ $( $e )* ] [ $( $a )* ] } ] [] $( $t )* }
+    );
+
+    (
+        @let_or ( $( $p: tt )* ) [ $( $e: tt )* ] [ $( $a: tt )* ] ; $( $t: tt )*
+    ) => (
+        $crate::option! { let $( $p )* <- $crate::option! { @or_else [ $( $e )* ] [ $( $a )* ] } ; $( $t )* }
+    );
+
+    (
+        @let_or ( $( $p: tt )* ) [ $( $e: tt )* ] [ $( $a: tt )* ] else $( $t: tt )*
+    ) => (
+        $crate::option! { @let_else ( $( $p )* ) [ $crate::option! { @or_else [ $( $e )* ] [ $( $a )* ] } ] else $( $t )* }
+    );
+
+    (
+        @let_or ( $( $p: tt )* ) [ $( $e: tt )* ] [ $( $a: tt )* ]
+    ) => (
+        ::core::compile_error!("expected `;` after `let pattern <- expression`, followed by the value to yield")
+    );
+
+    (
+        @let_or ( $( $p: tt )* ) [ $( $e: tt )* ] [ $( $a: tt )* ] $x: tt $( $t: tt )*
+    ) => (
+        $crate::option! { @let_or ( $( $p )* ) [ $( $e )* ] [ $( $a )* $x ] $( $t )* }
+    );
+
+    () => {
+        ::core::option::Option::Some(())
+    };
+
+    (
+        let mut $p: tt <- $e: expr ; timed $f: expr ; $( $t: tt )*
+    ) => (
+        $crate::option! { let mut $p <- $crate::__timed_value($crate::__std::time::Instant::now(), $e, $f) ; $( $t )* }
+    );
+
+    (
+        let mut $p: ident : $ty: tt <- $e: expr ; timed $f: expr ; $( $t: tt )*
+    ) => (
+        $crate::option! { let mut $p : $ty <- $crate::__timed_value($crate::__std::time::Instant::now(), $e, $f) ; $( $t )* }
+    );
+
+    (
+        let $p: tt <- $e: expr ; timed $f: expr ; $( $t: tt )*
+    ) => (
+        $crate::option! { let $p <- $crate::__timed_value($crate::__std::time::Instant::now(), $e, $f) ; $( $t )* }
+    );
+
+    (
+        let $p: ident : $ty: tt <- $e: expr ; timed $f: expr ; $( $t: tt )*
+    ) => (
+        $crate::option! { let $p : $ty <- $crate::__timed_value($crate::__std::time::Instant::now(), $e, $f) ; $( $t )* }
+    );
+
+    (
+        let mut $p: tt <- $e: expr ; $f: ident ( $( $a: tt )* ) $( $t: tt )*
+    ) => (
+        $crate::__trace!($e).and_then(move |v| {
+            #[allow(unreachable_patterns)]
+            let rest = match ::core::convert::identity(v) {
+                $crate::option! (@as_pat mut $p) => $crate::option! { $f ( $( $a )* ) $( $t )* },
+                _ => ::core::option::Option::None,
+            };
+            rest
+        })
+    );
+
+    (
+        let mut $p: ident : $ty: tt <- $e: expr ; $f: ident ( $( $a: tt )* ) $( $t: tt )*
+    ) => (
+        $crate::__trace!($e).and_then(move | mut $p : $ty | { $crate::option! { $f ( $( $a )* ) $( $t )* } } )
+    );
+
+    (
+        let $p: tt <- $e: expr ; $f: ident ( $( $a: tt )* ) $( $t: tt )*
+    ) => (
+        $crate::__trace!($e).and_then(move |v| {
+            #[allow(unreachable_patterns)]
+            let rest = match ::core::convert::identity(v) {
+                $crate::option! (@as_pat $p) => $crate::option! { $f ( $( $a )* ) $( $t )* },
+                _ => ::core::option::Option::None,
+            };
+            rest
+        })
+    );
+
+    (
+        let $p: ident : $ty: tt <- $e: expr ; $f: ident ( $( $a: tt )* ) $( $t: tt )*
+    ) => (
+        $crate::__trace!($e).and_then(move | $p : $ty | { $crate::option! { $f ( $( $a )* ) $( $t )* } } )
+    );
+
+    (
+        let mut $p: tt <- $e: expr ; inspect $f: expr ; $( $t: tt )*
+    ) => (
+        $crate::option! { let mut $p <- ::core::option::Option::inspect($e, $f) ; $( $t )* }
+    );
+
+    (
+        let mut $p: ident : $ty: tt <- $e: expr ; inspect $f: expr ; $( $t: tt )*
+    ) => (
+        $crate::option! { let mut $p : $ty <- ::core::option::Option::inspect($e, $f) ; $( $t )* }
+    );
+
+    (
+        let $p: tt <- $e: expr ; inspect $f: expr ; $( $t: tt )*
+    ) => (
+        $crate::option! { let $p <- ::core::option::Option::inspect($e, $f) ; $( $t )* }
+    );
+
+    (
+        let $p: ident : $ty: tt <- $e: expr ; inspect $f: expr ; $( $t: tt )*
+    ) => (
+        $crate::option! { let $p : $ty <- ::core::option::Option::inspect($e, $f) ; $( $t )* }
+    );
+
+    (
+        timed $f: expr ; $( $t: tt )*
+    ) => (
+        ::core::compile_error!("`timed f;` must follow a binding")
+    );
+
+    (
+        let mut $p: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::__trace!($e).and_then(move |v| {
+            #[allow(unreachable_patterns)]
+            let rest = match ::core::convert::identity(v) {
+                $crate::option! (@as_pat mut $p) => $crate::option! { $( $t )* },
+                _ => ::core::option::Option::None,
+            };
+            rest
+        })
+    );
+
+    (
+        let mut $p: ident : $ty: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::__trace!($e).and_then(move | mut $p : $ty | { $crate::option! { $( $t )* } } )
+    );
+
+    (
+        let $p: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::__trace!($e).and_then(move |v| {
+            #[allow(unreachable_patterns)]
+            let rest = match ::core::convert::identity(v) {
+                $crate::option! (@as_pat $p) => $crate::option! { $( $t )* },
+                _ => ::core::option::Option::None,
+            };
+            rest
+        })
+    );
+
+    (
+        let $p: tt ( $( $para: tt )* ) <- $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::__trace!($e).and_then(move |v| {
+            #[allow(unreachable_patterns)]
+            let rest = match ::core::convert::identity(v) {
+                $crate::option! (@as_pat $p ( $( $para )* ) ) => $crate::option! { $( $t )* },
+                _ => ::core::option::Option::None,
+            };
+            rest
+        })
+    );
+
+    (
+        let $p: tt { $( $para: tt )* } <- $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::__trace!($e).and_then(move |v| {
+            #[allow(unreachable_patterns)]
+            let rest = match ::core::convert::identity(v) {
+                $crate::option! (@as_pat $p { $( $para )* } ) => $crate::option! { $( $t )* },
+                _ => ::core::option::Option::None,
+            };
+            rest
+        })
+    );
+
+    (
+        let $p: ident : $ty: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::__trace!($e).and_then(move | $p : $ty | { $crate::option! { $( $t )* } } )
+    );
+
+    (
+        let $( $p: ident )+ : $( $t: tt )+
+    ) => (
+        $crate::__pat! { @ty option [] [ $( $p )+ ] [] $( $t )* }
+    );
+
+    (
+        let mut $p: tt <- $( $t: tt )+
+    ) => (
+        $crate::option! { @let_else ( mut $p ) [] $( $t )* }
+    );
+
+    (
+        let $p: tt <- $( $t: tt )+
+    ) => (
+        $crate::option! { @let_else ( $p ) [] $( $t )* }
+    );
+
+    (
+        let mut $p: tt = $e: expr
+    ) => (
+        ::core::compile_error!("expected `<-` after the pattern to bind, or `;` and the value to yield after `let`")
+    );
+
+    (
+        let $p: tt = $e: expr
+    ) => (
+        ::core::compile_error!("expected `<-` after the pattern to bind, or `;` and the value to yield after `let`")
+    );
+
+    (
+        for $p: pat in $( $t: tt )*
+    ) => (
+        $crate::option! { @for ($p) [] $( $t )* }
+    );
+
+    (
+        if $( $t: tt )+
+    ) => (
+        $crate::option! { @if [] $( $t )* }
+    );
+
+    (
+        return $e: expr ; $( $t: tt )*
+    ) => (
+        ::core::option::Option::Some($e)
+    );
+
+    (
+        fail ; $( $t: tt )*
+    ) => (
+        ::core::option::Option::None
+    );
+
+    (
+        guard $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::option! { if $e ; $( $t )* }
+    );
+
+    (
+        unless $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::option! { if !($e) ; $( $t )* }
+    );
+
+    (
+        $e: expr ; $( $t: tt )*
+    ) => (
+        { $e ; $crate::option! { $( $t )* } }
+    );
+
+    (
+        inspect $f: expr ; $( $t: tt )*
+    ) => (
+        ::core::compile_error!("`inspect f;` must follow a binding")
+    );
+
+    (
+        $stmt: stmt ; $( $t: tt )*
+    ) => (
+        { $stmt $crate::option! { $( $t )* } }
+    );
+
+    (
+        $e: expr
+    ) => (
+        ::core::option::Option::Some($e)
+    );
+
+    (
+        $b: block ; $( $t: tt )*
+    ) => (
+        $b ; $crate::option! { $( $t )* }
+    );
+}
+
+/// syntax extension specific for Result
+///
+/// See the module-level documentation for more details.
+#[macro_export]
+macro_rules! result {
+    (@as_pat $p: pat) => ($p);
+
+    (
+        @body let $( $b: ident )+ @ $( $t: tt )+
+    ) => (
+        $crate::__pat! { result [ @body ] [ $( $b )+ @ ] $( $t )* }
+    );
+
+    (
+        @body let & $( $t: tt )+
+    ) => (
+        $crate::__pat! { result [ @body ] [ & ] $( $t )* }
+    );
+
+    (
+        @body let ref $( $t: tt )+
+    ) => (
+        $crate::__pat! { result [ @body ] [ ref ] $( $t )* }
+    );
+
+    (
+        @body let $( $a: ident )? :: $( $t: tt )+
+    ) => (
+        $crate::__pat! { result [ @body ] [ $( $a )? :: ] $( $t )* }
+    );
+
+    (
+        ref $( $x: ident ),+ ; $( $t: tt )*
+    ) => ({
+        $( let $x = &$x; )+
+        $crate::result! { $( $t )* }
+    });
+
+    (
+        strict ; $( $t: tt )*
+    ) => (
+        $crate::__strict! { result [] $( $t )* }
+    );
+
+    (
+        @body @if [ $( $c: tt )* ] { $( $a: tt )* } $( $t: tt )*
+    ) => (
+        $crate::result! { @body @else [ if $( $c )* { $( $a )* } ] $( $t )* }
+    );
+
+    (
+        @body @if [ $( $c: tt )* ] else $err: expr ; $( $t: tt )*
+    ) => (
+        if $( $c )* { $crate::result! { @body $( $t )* } } else { ::core::result::Result::Err($err) }
+    );
+
+    (
+        @body @if [ $( $c: tt )* ] => $err: expr ; $( $t: tt )*
+    ) => (
+        if $( $c )* { $crate::result! { @body $( $t )* } } else { ::core::result::Result::Err($err) }
+    );
+
+    (
+        @body @if [ $( $c: tt )* ] $x: tt $( $t: tt )*
+    ) => (
+        $crate::result! { @body @if [ $( $c )* $x ] $( $t )* }
+    );
+
+    (
+        @body @else_body [ $( $s: tt )* ] { $( $x: tt )* } $( $t: tt )*
+    ) => (
+        $crate::result! { @body @else [ $( $s )* { $( $x )* } ] $( $t )* }
+    );
+
+    (
+        @body @else_body [ $( $s: tt )* ] $x: tt $( $t: tt )*
+    ) => (
+        $crate::result! { @body @else_body [ $( $s )* $x ] $( $t )* }
+    );
+
+    (
+        @body @else [ $( $s: tt )* ] else $( $t: tt )*
+    ) => (
+        $crate::result! { @body @else_body [ $( $s )* else ] $( $t )* }
+    );
+
+    (
+        @body @else [ $( $s: tt )* ] $( $t: tt )*
+    ) => (
+        $crate::__exit! { 'exit result [ result @body ] [ $( $t )* ] $( $s )* }
+    );
+
+    (
+        @body @exit $l: lifetime [] [ $( $s: tt )* ] $( $t: tt )*
+    ) => (
+        $crate::result! { @body @end [ $( $s )* ] $( $t )* }
+    );
+
+    (
+        @body @exit $l: lifetime [ exit ] [ $( $s: tt )* ] $( $t: tt )*
+    ) => (
+        $l: { $crate::result! { @body @end [ $( $s )* ] $( $t )* } }
+    );
+
+    (
+        @body @end [ $( $s: tt )* ]
+    ) => (
+        ::core::result::Result::Ok($( $s )*)
+    );
+
+    (
+        @body @end [ $( $s: tt )* ] ; $( $t: tt )*
+    ) => (
+        { $( $s )* ; $crate::result! { @body $( $t )* } }
+    );
+
+    (
+        @body @end [ $( $s: tt )* ] $( $t: tt )+
+    ) => (
+        { $( $s )* $crate::result! { @body $( $t )* } }
+    );
+
+    (
+        @body @for ($p: pat) [ $( $e: tt )* ] ; $( $t: tt )*
+    ) => (
+        $crate::result! { @body let $p <- $( $e )* ; $( $t )* }
+    );
+
+    (
+        @body @for ($p: pat) [ $( $e: tt )* ] { $( $body: tt )* } ; $( $t: tt )*
+    ) => (
+        { for $p in $( $e )* { $( $body )* } $crate::result! { @body $( $t )* } }
+    );
+
+    (
+        @body @for ($p: pat) [ $( $e: tt )* ] { $( $body: tt )* } $( $t: tt )*
+    ) => (
+        { for $p in $( $e )* { $( $body )* } $crate::result! { @body $( $t )* } }
+    );
+
+    (
+        @body @for ($p: pat) [ $( $e: tt )* ] $x: tt $( $t: tt )*
+    ) => (
+        $crate::result! { @body @for ($p) [ $( $e )* $x ] $( $t )* }
+    );
+
+    (
+        @body @let_else ( $( $p: tt )* ) [ $( $e: tt )* ] else $d: expr ; $( $t: tt )*
+    ) => (
+        $crate::Else::bind_else($crate::__trace!($( $e )*), || $d).and_then(move | $( $p )* | { $crate::result! { @body $( $t )* } })
+    );
+
+    (
+        @body @let_else ( $( $p: tt )* ) [ $( $e: tt )* ] or $( $t: tt )*
+    ) => (
+        $crate::result! { @body @let_or ( $( $p )* ) [ $( $e )* ] [] $( $t )* }
+    );
+
+    (
+        @body @let_else ( $( $p: tt )* ) [ $( $e: tt )* ]
+    ) => (
+        ::core::compile_error!("expected `;` after `let pattern <- expression`, followed by the value to yield")
+    );
+
+    (
+        @body @let_else ( $( $p: tt )* ) [ $( $e: tt )* ] $x: tt $( $t: tt )*
+    ) => (
+        $crate::result! { @body @let_else ( $( $p )* ) [ $( $e )* $x ] $( $t )* }
+    );
+
+    (@body @retry ($e: expr) ($n: expr) ($b: expr)) => (
+        {
+            #[allow(unused_mut)]
+            let mut backoff = $b;
+            let mut retries = 0;
+            loop {
+                match $e {
+                    ::core::result::Result::Err(_) if retries < $n => {
+                        retries += 1;
+                        backoff(retries);
+                    }
+                    result => break result,
+                }
+            }
+        }
+    );
+
+    (@body @or_else [ $( $e: tt )* ] [ $( $a: tt )* ]) => (
+        ($( $e )*).or_else(|_| $( $a )*)
+    );
+
+    (
+        @body @let_or ( $( $p: tt )* ) [ $( $e: tt )* ] [ $( $a: tt )* ] or $( $t: tt )*
+    ) => (
+        $crate::result! { @body @let_or ( $( $p )* ) [ $crate::result! { @body @or_else [ $( $e )* ] [ $( $a )* ] } ] [] $( $t )* }
+    );
+
+    (
+        @body @let_or ( $( $p: tt )* ) [ $( $e: tt )* ] [ $( $a: tt )* ] ; $( $t: tt )*
+    ) => (
+        $crate::result! { @body let $( $p )* <- $crate::result! { @body @or_else [ $( $e )* ] [ $( $a )* ] } ; $( $t )* }
+    );
+
+    (
+        @body @let_or ( $( $p: tt )* ) [ $( $e: tt )* ] [ $( $a: tt )* ] else $( $t: tt )*
+    ) => (
+        $crate::result! { @body @let_else ( $( $p )* ) [ $crate::result! { @body @or_else [ $( $e )* ] [ $( $a )* ] } ] else $( $t )* }
+    );
+
+    (
+        @body @let_or ( $( $p: tt )* ) [ $( $e: tt )* ] [ $( $a: tt )* ]
+    ) => (
+        ::core::compile_error!("expected `;` after `let pattern <- expression`, followed by the value to yield")
+    );
+
+    (
+        @body @let_or ( $( $p: tt )* ) [ $( $e: tt )* ] [ $( $a: tt )* ] $x: tt $( $t: tt )*
+    ) => (
+        $crate::result! { @body @let_or ( $( $p )* ) [ $( $e )* ] [ $( $a )* $x ] $( $t )* }
+    );
+
+    (@body @into_err ($err: ty) [ $( $e: tt )* ]) => (
+        ::core::result::Result::map_err($( $e )*, <$err as ::core::convert::From<_>>::from)
+    );
+
+    (
+        @body @typed ($err: ty) [ $( $d: tt )* ]
+    ) => (
+        ::core::convert::identity::<::core::result::Result<_, $err>>($crate::result! { @body $( $d )* })
+    );
+
+    (
+        @body @typed ($err: ty) [ $( $d: tt )* ] let $( $t: tt )*
+    ) => (
+        $crate::result! { @body @typed_let ($err) [ $( $d )* ] [ let ] $( $t )* }
+    );
+
+    (
+        @body @typed ($err: ty) [ $( $d: tt )* ] $( $t: tt )+
+    ) => (
+        $crate::result! { @body @typed_copy ($err) [ $( $d )* ] $( $t )* }
+    );
+
+    (
+        @body @typed_let ($err: ty) [ $( $d: tt )* ] [ $( $p: tt )* ] <- $( $t: tt )*
+    ) => (
+        $crate::result! { @body @typed_bind ($err) [ $( $d )* $( $p )* <- ] [] $( $t )* }
+    );
+
+    (
+        @body @typed_let ($err: ty) [ $( $d: tt )* ] [ $( $p: tt )* ] ; $( $t: tt )*
+    ) => (
+        $crate::result! { @body @typed ($err) [ $( $d )* $( $p )* ; ] $( $t )* }
+    );
+
+    (
+        @body @typed_let ($err: ty) [ $( $d: tt )* ] [ $( $p: tt )* ] $x: tt $( $t: tt )*
+    ) => (
+        $crate::result! { @body @typed_let ($err) [ $( $d )* ] [ $( $p )* $x ] $( $t )* }
+    );
+
+    (
+        @body @typed_bind ($err: ty) [ $( $d: tt )* ] [ $( $e: tt )* ] ; $( $t: tt )*
+    ) => (
+        $crate::result! { @body @typed ($err) [ $( $d )* $crate::result! { @body @into_err ($err) [ $( $e )* ] } ; ] $( $t )* }
+    );
+
+    (
+        @body @typed_bind ($err: ty) [ $( $d: tt )* ] [ $( $e: tt )* ] or $( $t: tt )*
+    ) => (
+        $crate::result! { @body @typed_bind ($err) [ $( $d )* $crate::result! { @body @into_err ($err) [ $( $e )* ] } or ] [] $( $t )* }
+    );
+
+    (
+        @body @typed_bind ($err: ty) [ $( $d: tt )* ] [ $( $e: tt )* ] , context $( $t: tt )*
+    ) => (
+        $crate::result! { @body @typed_copy ($err) [ $( $d )* $crate::result! { @body @into_err ($err) [ $( $e )* ] } , context ] $( $t )* }
+    );
+
+    (
+        @body @typed_bind ($err: ty) [ $( $d: tt )* ] [ $( $e: tt )* ] , retry $( $t: tt )*
+    ) => (
+        $crate::result! { @body @typed_copy ($err) [ $( $d )* $crate::result! { @body @into_err ($err) [ $( $e )* ] } , retry ] $( $t )* }
+    );
+
+    (
+        @body @typed_bind ($err: ty) [ $( $d: tt )* ] [ $( $e: tt )* ] else $( $t: tt )*
+    ) => (
+        $crate::result! { @body @typed_copy ($err) [ $( $d )* $( $e )* else ] $( $t )* }
+    );
+
+    (
+        @body @typed_bind ($err: ty) [ $( $d: tt )* ] [ $( $e: tt )* ] => $( $t: tt )*
+    ) => (
+        $crate::result! { @body @typed_copy ($err) [ $( $d )* $crate::result! { @body @into_err ($err) [ $( $e )* ] } => ] $( $t )* }
+    );
+
+    (
+        @body @typed_bind ($err: ty) [ $( $d: tt )* ] [ $( $e: tt )* ] $x: tt $( $t: tt )*
+    ) => (
+        $crate::result! { @body @typed_bind ($err) [ $( $d )* ] [ $( $e )* $x ] $( $t )* }
+    );
+
+    (
+        @body @typed_copy ($err: ty) [ $( $d: tt )* ]
+    ) => (
+        $crate::result! { @body @typed ($err) [ $( $d )* ] }
+    );
+
+    (
+        @body @typed_copy ($err: ty) [ $( $d: tt )* ] ; $( $t: tt )*
+    ) => (
+        $crate::result! { @body @typed ($err) [ $( $d )* ; ] $( $t )* }
+    );
+
+    (
+        @body @typed_copy ($err: ty) [ $( $d: tt )* ] { $( $b: tt )* } $( $t: tt )*
+    ) => (
+        $crate::result! { @body @typed ($err) [ $( $d )* { $( $b )* } ] $( $t )* }
+    );
+
+    (
+        @body @typed_copy ($err: ty) [ $( $d: tt )* ] $x: tt $( $t: tt )*
+    ) => (
+        $crate::result! { @body @typed_copy ($err) [ $( $d )* $x ] $( $t )* }
+    );
+
+    (@body) => {
+        ::core::result::Result::Ok(())
+    };
+
+    (
+        @body type Error = $err: ty ; $( $t: tt )*
+    ) => (
+        $crate::result! { @body @typed ($err) [] $( $t )* }
+    );
+
+    (
+        @body let mut $p: tt <- $e: expr ; catch $h: expr ; $( $t: tt )*
+    ) => (
+        $crate::result! { @body let mut $p <- ($e).or_else($h) ; $( $t )* }
+    );
+
+    (
+        @body let mut $p: ident : $ty: tt <- $e: expr ; catch $h: expr ; $( $t: tt )*
+    ) => (
+        $crate::result! { @body let mut $p : $ty <- ($e).or_else($h) ; $( $t )* }
+    );
+
+    (
+        @body let $p: tt <- $e: expr ; catch $h: expr ; $( $t: tt )*
+    ) => (
+        $crate::result! { @body let $p <- ($e).or_else($h) ; $( $t )* }
+    );
+
+    (
+        @body let $p: tt ( $( $para: tt )* ) <- $e: expr ; catch $h: expr ; $( $t: tt )*
+    ) => (
+        $crate::result! { @body let $p ( $( $para )* ) <- ($e).or_else($h) ; $( $t )* }
+    );
+
+    (
+        @body let $p: tt { $( $para: tt )* } <- $e: expr ; catch $h: expr ; $( $t: tt )*
+    ) => (
+        $crate::result! { @body let $p { $( $para )* } <- ($e).or_else($h) ; $( $t )* }
+    );
+
+    (
+        @body let $p: ident : $ty: tt <- $e: expr ; catch $h: expr ; $( $t: tt )*
+    ) => (
+        $crate::result! { @body let $p : $ty <- ($e).or_else($h) ; $( $t )* }
+    );
+
+    (
+        @body let mut $p: tt <- $e: expr ; timed $f: expr ; $( $t: tt )*
+    ) => (
+        $crate::result! { @body let mut $p <- $crate::__timed_value($crate::__std::time::Instant::now(), $e, $f) ; $( $t )* }
+    );
+
+    (
+        @body let mut $p: ident : $ty: tt <- $e: expr ; timed $f: expr ; $( $t: tt )*
+    ) => (
+        $crate::result! { @body let mut $p : $ty <- $crate::__timed_value($crate::__std::time::Instant::now(), $e, $f) ; $( $t )* }
+    );
+
+    (
+        @body let $p: tt <- $e: expr ; timed $f: expr ; $( $t: tt )*
+    ) => (
+        $crate::result! { @body let $p <- $crate::__timed_value($crate::__std::time::Instant::now(), $e, $f) ; $( $t )* }
+    );
+
+    (
+        @body let $p: ident : $ty: tt <- $e: expr ; timed $f: expr ; $( $t: tt )*
+    ) => (
+        $crate::result! { @body let $p : $ty <- $crate::__timed_value($crate::__std::time::Instant::now(), $e, $f) ; $( $t )* }
+    );
+
+    (
+        @body let mut $p: tt <- $e: expr ; $f: ident ( $( $a: tt )* ) $( $t: tt )*
+    ) => (
+        $crate::__trace!($e).and_then(move | $crate::result! (@as_pat mut $p) | { $crate::result! { @body $f ( $( $a )* ) $( $t )* } } )
+    );
+
+    (
+        @body let mut $p: ident : $ty: tt <- $e: expr ; $f: ident ( $( $a: tt )* ) $( $t: tt )*
+    ) => (
+        $crate::__trace!($e).and_then(move | mut $p : $ty | { $crate::result! { @body $f ( $( $a )* ) $( $t )* } } )
+    );
+
+    (
+        @body let $p: tt <- $e: expr ; $f: ident ( $( $a: tt )* ) $( $t: tt )*
+    ) => (
+        $crate::__trace!($e).and_then(move | $crate::result! (@as_pat $p) | { $crate::result! { @body $f ( $( $a )* ) $( $t )* } } )
+    );
+
+    (
+        @body let $p: ident : $ty: tt <- $e: expr ; $f: ident ( $( $a: tt )* ) $( $t: tt )*
+    ) => (
+        $crate::__trace!($e).and_then(move | $p : $ty | { $crate::result! { @body $f ( $( $a )* ) $( $t )* } } )
+    );
+
+    (
+        @body let mut $p: tt <- $e: expr ; inspect $f: expr ; $( $t: tt )*
+    ) => (
+        $crate::result! { @body let mut $p <- ::core::result::Result::inspect($e, $f) ; $( $t )* }
+    );
+
+    (
+        @body let mut $p: ident : $ty: tt <- $e: expr ; inspect $f: expr ; $( $t: tt )*
+    ) => (
+        $crate::result! { @body let mut $p : $ty <- ::core::result::Result::inspect($e, $f) ; $( $t )* }
+    );
+
+    (
+        @body let $p: tt <- $e: expr ; inspect $f: expr ; $( $t: tt )*
+    ) => (
+        $crate::result! { @body let $p <- ::core::result::Result::inspect($e, $f) ; $( $t )* }
+    );
+
+    (
+        @body let $p: ident : $ty: tt <- $e: expr ; inspect $f: expr ; $( $t: tt )*
+    ) => (
+        $crate::result! { @body let $p : $ty <- ::core::result::Result::inspect($e, $f) ; $( $t )* }
+    );
+
+    (
+        @body timed $f: expr ; $( $t: tt )*
+    ) => (
+        ::core::compile_error!("`timed f;` must follow a binding")
+    );
+
+    (
+        @body let mut $p: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::__trace!($e).and_then(move | $crate::result! (@as_pat mut $p) | { $crate::result! { @body $( $t )* } } )
+    );
+
+    (
+        @body let mut $p: ident : $ty: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::__trace!($e).and_then(move | mut $p : $ty | { $crate::result! { @body $( $t )* } } )
+    );
+
+    (
+        @body let $p: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::__trace!($e).and_then(move | $crate::result! (@as_pat $p) | { $crate::result! { @body $( $t )* } } )
+    );
+
+    (
+        @body let $p: tt ( $( $para: tt )* ) <- $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::__trace!($e).and_then(move | $crate::result! (@as_pat $p ( $( $para )* ) ) | { $crate::result! { @body $( $t )* } } )
+    );
+
+    (
+        @body let $p: tt { $( $para: tt )* } <- $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::__trace!($e).and_then(move | $crate::result! (@as_pat $p { $( $para )* } ) | { $crate::result! { @body $( $t )* } } )
+    );
+
+    (
+        @body let $p: tt <- $e: expr => $err: expr ; $( $t: tt )*
+    ) => (
+        $crate::__trace!($e).and_then(move |v| match ::core::convert::identity(v) {
+            $crate::result! (@as_pat $p) => $crate::result! { @body $( $t )* },
+            _ => ::core::result::Result::Err($err),
+        })
+    );
+
+    (
+        @body let $p: tt ( $( $para: tt )* ) <- $e: expr => $err: expr ; $( $t: tt )*
+    ) => (
+        $crate::__trace!($e).and_then(move |v| match ::core::convert::identity(v) {
+            $crate::result! (@as_pat $p ( $( $para )* ) ) => $crate::result! { @body $( $t )* },
+            _ => ::core::result::Result::Err($err),
+        })
+    );
+
+    (
+        @body let $p: tt { $( $para: tt )* } <- $e: expr => $err: expr ; $( $t: tt )*
+    ) => (
+        $crate::__trace!($e).and_then(move |v| match ::core::convert::identity(v) {
+            $crate::result! (@as_pat $p { $( $para )* } ) => $crate::result! { @body $( $t )* },
+            _ => ::core::result::Result::Err($err),
+        })
+    );
+
+    (
+        @body let $p: ident : $ty: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::__trace!($e).and_then(move | $p : $ty | { $crate::result! { @body $( $t )* } } )
+    );
+
+    (
+        @body let $( $p: ident )+ : $( $t: tt )+
+    ) => (
+        $crate::__pat! { @ty result [ @body ] [ $( $p )+ ] [] $( $t )* }
+    );
+
+    (
+        @body let mut $p: tt <- $e: expr , retry ( $n: expr ) ; $( $t: tt )*
+    ) => (
+        $crate::result! { @body let mut $p <- $e, retry($n, |_| ()) ; $( $t )* }
+    );
+
+    (
+        @body let mut $p: tt <- $e: expr , retry ( $n: expr , $b: expr ) ; $( $t: tt )*
+    ) => (
+        $crate::result! { @body let mut $p <- $crate::result! { @body @retry ($e) ($n) ($b) } ; $( $t )* }
+    );
+
+    (
+        @body let $p: tt <- $e: expr , retry ( $n: expr ) ; $( $t: tt )*
+    ) => (
+        $crate::result! { @body let $p <- $e, retry($n, |_| ()) ; $( $t )* }
+    );
+
+    (
+        @body let $p: tt <- $e: expr , retry ( $n: expr , $b: expr ) ; $( $t: tt )*
+    ) => (
+        $crate::result! { @body let $p <- $crate::result! { @body @retry ($e) ($n) ($b) } ; $( $t )* }
+    );
+
+    (
+        @body let mut $p: tt <- $e: expr , context ( $c: expr ) ; $( $t: tt )*
+    ) => (
+        $crate::result! { @body let mut $p <- $crate::__anyhow::Context::context($e, $c) ; $( $t )* }
+    );
+
+    (
+        @body let $p: tt <- $e: expr , context ( $c: expr ) ; $( $t: tt )*
+    ) => (
+        $crate::result! { @body let $p <- $crate::__anyhow::Context::context($e, $c) ; $( $t )* }
+    );
+
+    (
+        @body let mut $p: tt <- $( $t: tt )+
+    ) => (
+        $crate::result! { @body @let_else ( mut $p ) [] $( $t )* }
+    );
+
+    (
+        @body let $p: tt <- $( $t: tt )+
+    ) => (
+        $crate::result! { @body @let_else ( $p ) [] $( $t )* }
+    );
+
+    (
+        @body let mut $p: tt = $e: expr
+    ) => (
+        ::core::compile_error!("expected `<-` after the pattern to bind, or `;` and the value to yield after `let`")
+    );
+
+    (
+        @body let $p: tt = $e: expr
+    ) => (
+        ::core::compile_error!("expected `<-` after the pattern to bind, or `;` and the value to yield after `let`")
+    );
+
+    (
+        @body for $p: pat in $( $t: tt )*
+    ) => (
+        $crate::result! { @body @for ($p) [] $( $t )* }
+    );
+
+    (
+        @body if $( $t: tt )+
+    ) => (
+        $crate::result! { @body @if [] $( $t )* }
+    );
+
+    (
+        @body return $e: expr ; $( $t: tt )*
+    ) => (
+        ::core::result::Result::Ok($e)
+    );
+
+    (
+        @body guard $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::result! { @body if $e ; $( $t )* }
+    );
+
+    (
+        @body unless $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::result! { @body if !($e) ; $( $t )* }
+    );
+
+    (
+        @body guard $e: expr => $err: expr ; $( $t: tt )*
+    ) => (
+        $crate::result! { @body if $e => $err ; $( $t )* }
+    );
+
+    (
+        @body unless $e: expr => $err: expr ; $( $t: tt )*
+    ) => (
+        $crate::result! { @body if !($e) => $err ; $( $t )* }
+    );
+
+    (
+        @body $e: expr ; $( $t: tt )*
+    ) => (
+        { $e ; $crate::result! { @body $( $t )* } }
+    );
+
+    (
+        @body inspect $f: expr ; $( $t: tt )*
+    ) => (
+        ::core::compile_error!("`inspect f;` must follow a binding")
+    );
+
+    (
+        @body guard $( $t: tt )+
+    ) => (
+        $crate::result! { @body if $( $t )* }
+    );
+
+    (
+        @body unless $( $t: tt )+
+    ) => (
+        $crate::result! { @body @unless [] $( $t )* }
+    );
+
+    (
+        @body @unless [ $( $c: tt )* ] else $( $t: tt )*
+    ) => (
+        $crate::result! { @body if !($( $c )*) else $( $t )* }
+    );
+
+    (
+        @body @unless [ $( $c: tt )* ] $x: tt $( $t: tt )*
+    ) => (
+        $crate::result! { @body @unless [ $( $c )* $x ] $( $t )* }
+    );
+
+    (
+        @body fail $e: expr ; $( $t: tt )*
+    ) => (
+        ::core::result::Result::Err($e)
+    );
+
+    (
+        @body ensure $e: expr , $err: expr ; $( $t: tt )*
+    ) => (
+        $crate::result! { @body if $e => $err ; $( $t )* }
+    );
+
+    (
+        @body $stmt: stmt ; $( $t: tt )*
+    ) => (
+        { $stmt $crate::result! { @body $( $t )* } }
+    );
+
+    (
+        @body $e: expr
+    ) => (
+        ::core::result::Result::Ok($e)
+    );
+
+    (
+        @body $b: block ; $( $t: tt )*
+    ) => (
+        $b ; $crate::result! { @body $( $t )* }
+    );
+
+    (
+        @scope [ $( $b: tt )* ] ? $( $t: tt )*
+    ) => (
+        $crate::__scope(|| { $crate::result! { @body $( $b )* } })
+    );
+
+    (
+        @scope [ $( $b: tt )* ] <- $( $t: tt )*
+    ) => (
+        $crate::result! { @body $( $b )* }
+    );
+
+    (
+        @scope [ $( $b: tt )* ] type Error = $err: ty ; $( $t: tt )*
+    ) => (
+        $crate::result! { @scope [ $( $b )* ] $( $t )* }
+    );
+
+    (
+        @scope [ $( $b: tt )* ] let $p: tt <- $( $t: tt )*
+    ) => (
+        $crate::result! { @body $( $b )* }
+    );
+
+    (
+        @scope [ $( $b: tt )* ] let $p: tt $q: tt <- $( $t: tt )*
+    ) => (
+        $crate::result! { @body $( $b )* }
+    );
+
+    (
+        @scope [ $( $b: tt )* ] ( $( $x: tt )* ) $( $t: tt )*
+    ) => (
+        $crate::result! { @scope [ $( $b )* ] $( $x )* $( $t )* }
+    );
+
+    (
+        @scope [ $( $b: tt )* ] [ $( $x: tt )* ] $( $t: tt )*
+    ) => (
+        $crate::result! { @scope [ $( $b )* ] $( $x )* $( $t )* }
+    );
+
+    (
+        @scope [ $( $b: tt )* ] { $( $x: tt )* } $( $t: tt )*
+    ) => (
+        $crate::result! { @scope [ $( $b )* ] $( $x )* $( $t )* }
+    );
+
+    (
+        @scope [ $( $b: tt )* ] $x: tt $( $t: tt )*
+    ) => (
+        $crate::result! { @scope [ $( $b )* ] $( $t )* }
+    );
+
+    (
+        @scope [ $( $b: tt )* ]
+    ) => (
+        $crate::result! { @body $( $b )* }
+    );
+
+    (
+        $( $t: tt )*
+    ) => (
+        $crate::result! { @scope [ $( $t )* ] $( $t )* }
+    );
+}
+
+/// syntax extension specific for Option, usable in `const fn`
+///
+/// See the module-level documentation for more details.
+#[macro_export]
+macro_rules! const_option {
+    (@as_pat $p: pat) => ($p);
+
+    (
+        let $( $b: ident )+ @ $( $t: tt )+
+    ) => (
+        $crate::__pat! { const_option [] [ $( $b )+ @ ] $( $t )* }
+    );
+
+    (
+        let & $( $t: tt )+
+    ) => (
+        $crate::__pat! { const_option [] [ & ] $( $t )* }
+    );
+
+    (
+        let ref $( $t: tt )+
+    ) => (
+        $crate::__pat! { const_option [] [ ref ] $( $t )* }
+    );
+
+    (
+        let $( $a: ident )? :: $( $t: tt )+
+    ) => (
+        $crate::__pat! { const_option [] [ $( $a )? :: ] $( $t )* }
+    );
+
+    (
+        @if [ $( $c: tt )* ] { $( $a: tt )* } $( $t: tt )*
+    ) => (
+        $crate::const_option! { @else [ if $( $c )* { $( $a )* } ] $( $t )* }
+    );
+
+    (
+        @if [ $( $c: tt )* ] ; $( $t: tt )*
+    ) => (
+        if $( $c )* { $crate::const_option! { $( $t )* } } else { ::core::option::Option::None }
+    );
+
+    (
+        @if [ $( $c: tt )* ] $x: tt $( $t: tt )*
+    ) => (
+        $crate::const_option! { @if [ $( $c )* $x ] $( $t )* }
+    );
+
+    (
+        @else_body [ $( $s: tt )* ] { $( $x: tt )* } $( $t: tt )*
+    ) => (
+        $crate::const_option! { @else [ $( $s )* { $( $x )* } ] $( $t )* }
+    );
+
+    (
+        @else_body [ $( $s: tt )* ] $x: tt $( $t: tt )*
+    ) => (
+        $crate::const_option! { @else_body [ $( $s )* $x ] $( $t )* }
+    );
+
+    (
+        @else [ $( $s: tt )* ] else $( $t: tt )*
+    ) => (
+        $crate::const_option! { @else_body [ $( $s )* else ] $( $t )* }
+    );
+
+    (
+        @else [ $( $s: tt )* ] $( $t: tt )*
+    ) => (
+        $crate::__exit! { 'exit option [ const_option ] [ $( $t )* ] $( $s )* }
+    );
+
+    (
+        @exit $l: lifetime [] [ $( $s: tt )* ] $( $t: tt )*
+    ) => (
+        $crate::const_option! { @end [ $( $s )* ] $( $t )* }
+    );
+
+    (
+        @exit $l: lifetime [ exit ] [ $( $s: tt )* ] $( $t: tt )*
+    ) => (
+        $l: { $crate::const_option! { @end [ $( $s )* ] $( $t )* } }
+    );
+
+    (
+        @end [ $( $s: tt )* ]
+    ) => (
+        ::core::option::Option::Some($( $s )*)
+    );
+
+    (
+        @end [ $( $s: tt )* ] ; $( $t: tt )*
+    ) => (
+        { $( $s )* ; $crate::const_option! { $( $t )* } }
+    );
+
+    (
+        @end [ $( $s: tt )* ] $( $t: tt )+
+    ) => (
+        { $( $s )* $crate::const_option! { $( $t )* } }
+    );
+
+    (
+        @let_else ( $( $p: tt )* ) [ $( $e: tt )* ] else $d: expr ; $( $t: tt )*
+    ) => (
+        {
+            let $( $p )* = match $( $e )* {
+                ::core::option::Option::Some(v) => v,
+                ::core::option::Option::None => $d,
+            };
+            $crate::const_option! { $( $t )* }
+        }
+    );
+
+    (
+        @let_else ( $( $p: tt )* ) [ $( $e: tt )* ] or $( $t: tt )*
+    ) => (
+        $crate::const_option! { @let_or ( $( $p )* ) [ $( $e )* ] [] $( $t )* }
+    );
+
+    (
+        @let_else ( $( $p: tt )* ) [ $( $e: tt )* ]
+    ) => (
+        ::core::compile_error!("expected `;` after `let pattern <- expression`, followed by the value to yield")
+    );
+
+    (
+        @let_else ( $( $p: tt )* ) [ $( $e: tt )* ] $x: tt $( $t: tt )*
+    ) => (
+        $crate::const_option! { @let_else ( $( $p )* ) [ $( $e )* $x ] $( $t )* }
+    );
+
+    (@or_else [ $( $e: tt )* ] [ $( $a: tt )* ]) => (
+        match $( $e )* {
+            ::core::option::Option::Some(v) => ::core::option::Option::Some(v),
+            ::core::option::Option::None => $( $a )*,
+        }
+    );
+
+    (
+        @let_or ( $( $p: tt )* ) [ $( $e: tt )* ] [ $( $a: tt )* ] or $( $t: tt )*
+    ) => (
+        $crate::const_option! { @let_or ( $( $p )* ) [ $crate::const_option! { @or_else [ $( $e )* ] [ $( $a )* ] } ] [] $( $t )* }
+    );
+
+    (
+        @let_or ( $( $p: tt )* ) [ $( $e: tt )* ] [ $( $a: tt )* ] ; $( $t: tt )*
+    ) => (
+        $crate::const_option! { let $( $p )* <- $crate::const_option! { @or_else [ $( $e )* ] [ $( $a )* ] } ; $( $t )* }
+    );
+
+    (
+        @let_or ( $( $p: tt )* ) [ $( $e: tt )* ] [ $( $a: tt )* ] else $( $t: tt )*
+    ) => (
+        $crate::const_option! { @let_else ( $( $p )* ) [ $crate::const_option! { @or_else [ $( $e )* ] [ $( $a )* ] } ] else $( $t )* }
+    );
+
+    (
+        @let_or ( $( $p: tt )* ) [ $( $e: tt )* ] [ $( $a: tt )* ]
+    ) => (
+        ::core::compile_error!("expected `;` after `let pattern <- expression`, followed by the value to yield")
+    );
+
+    (
+        @let_or ( $( $p: tt )* ) [ $( $e: tt )* ] [ $( $a: tt )* ] $x: tt $( $t: tt )*
+    ) => (
+        $crate::const_option! { @let_or ( $( $p )* ) [ $( $e )* ] [ $( $a )* $x ] $( $t )* }
+    );
+
+    () => {
+        ::core::option::Option::Some(())
+    };
+
+    (
+        let mut $p: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        match $e {
+            ::core::option::Option::Some(v) => { let $crate::const_option! (@as_pat mut $p) = v; $crate::const_option! { $( $t )* } }
+            ::core::option::Option::None => ::core::option::Option::None,
+        }
+    );
+
+    (
+        let mut $p: ident : $ty: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        match $e {
+            ::core::option::Option::Some(v) => { let mut $p : $ty = v; $crate::const_option! { $( $t )* } }
+            ::core::option::Option::None => ::core::option::Option::None,
+        }
+    );
+
+    (
+        let $p: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        match $e {
+            ::core::option::Option::Some(v) => { let $crate::const_option! (@as_pat $p) = ::core::convert::identity(v); $crate::const_option! { $( $t )* } }
+            ::core::option::Option::None => ::core::option::Option::None,
+        }
+    );
+
+    (
+        let $p: tt ( $( $para: tt )* ) <- $e: expr ; $( $t: tt )*
+    ) => (
+        match $e {
+            ::core::option::Option::Some(v) => { let $crate::const_option! (@as_pat $p ( $( $para )* ) ) = ::core::convert::identity(v); $crate::const_option! { $( $t )* } }
+            ::core::option::Option::None => ::core::option::Option::None,
+        }
+    );
+
+    (
+        let $p: tt { $( $para: tt )* } <- $e: expr ; $( $t: tt )*
+    ) => (
+        match $e {
+            ::core::option::Option::Some(v) => { let $crate::const_option! (@as_pat $p { $( $para )* } ) = ::core::convert::identity(v); $crate::const_option! { $( $t )* } }
+            ::core::option::Option::None => ::core::option::Option::None,
+        }
+    );
+
+    (
+        let $p: ident : $ty: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        match $e {
+            ::core::option::Option::Some(v) => { let $p : $ty = v; $crate::const_option! { $( $t )* } }
+            ::core::option::Option::None => ::core::option::Option::None,
+        }
+    );
+
+    (
+        let $( $p: ident )+ : $( $t: tt )+
+    ) => (
+        $crate::__pat! { @ty const_option [] [ $( $p )+ ] [] $( $t )* }
+    );
+
+    (
+        let mut $p: tt <- $( $t: tt )+
+    ) => (
+        $crate::const_option! { @let_else ( mut $p ) [] $( $t )* }
+    );
+
+    (
+        let $p: tt <- $( $t: tt )+
+    ) => (
+        $crate::const_option! { @let_else ( $p ) [] $( $t )* }
+    );
+
+    (
+        let mut $p: tt = $e: expr
+    ) => (
+        ::core::compile_error!("expected `<-` after the pattern to bind, or `;` and the value to yield after `let`")
+    );
+
+    (
+        let $p: tt = $e: expr
+    ) => (
+        ::core::compile_error!("expected `<-` after the pattern to bind, or `;` and the value to yield after `let`")
+    );
+
+    (
+        if $( $t: tt )+
+    ) => (
+        $crate::const_option! { @if [] $( $t )* }
+    );
+
+    (
+        return $e: expr ; $( $t: tt )*
+    ) => (
+        ::core::option::Option::Some($e)
+    );
+
+    (
+        fail ; $( $t: tt )*
+    ) => (
+        ::core::option::Option::None
+    );
+
+    (
+        guard $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::const_option! { if $e ; $( $t )* }
+    );
+
+    (
+        unless $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::const_option! { if !($e) ; $( $t )* }
+    );
+
+    (
+        $e: expr ; $( $t: tt )*
+    ) => (
+        { $e ; $crate::const_option! { $( $t )* } }
+    );
+
+    (
+        $stmt: stmt ; $( $t: tt )*
+    ) => (
+        { $stmt $crate::const_option! { $( $t )* } }
+    );
+
+    (
+        $e: expr
+    ) => (
+        ::core::option::Option::Some($e)
+    );
+
+    (
+        $b: block ; $( $t: tt )*
+    ) => (
+        $b ; $crate::const_option! { $( $t )* }
+    );
+}
+
+/// syntax extension specific for Result, usable in `const fn`
+///
+/// See the module-level documentation for more details.
+#[macro_export]
+macro_rules! const_result {
+    (@as_pat $p: pat) => ($p);
+
+    (
+        let $( $b: ident )+ @ $( $t: tt )+
+    ) => (
+        $crate::__pat! { const_result [] [ $( $b )+ @ ] $( $t )* }
+    );
+
+    (
+        let & $( $t: tt )+
+    ) => (
+        $crate::__pat! { const_result [] [ & ] $( $t )* }
+    );
+
+    (
+        let ref $( $t: tt )+
+    ) => (
+        $crate::__pat! { const_result [] [ ref ] $( $t )* }
+    );
+
+    (
+        let $( $a: ident )? :: $( $t: tt )+
+    ) => (
+        $crate::__pat! { const_result [] [ $( $a )? :: ] $( $t )* }
+    );
+
+    (
+        @if [ $( $c: tt )* ] { $( $a: tt )* } $( $t: tt )*
+    ) => (
+        $crate::const_result! { @else [ if $( $c )* { $( $a )* } ] $( $t )* }
+    );
+
+    (
+        @if [ $( $c: tt )* ] else $err: expr ; $( $t: tt )*
+    ) => (
+        if $( $c )* { $crate::const_result! { $( $t )* } } else { ::core::result::Result::Err($err) }
+    );
+
+    (
+        @if [ $( $c: tt )* ] => $err: expr ; $( $t: tt )*
+    ) => (
+        if $( $c )* { $crate::const_result! { $( $t )* } } else { ::core::result::Result::Err($err) }
+    );
+
+    (
+        @if [ $( $c: tt )* ] $x: tt $( $t: tt )*
+    ) => (
+        $crate::const_result! { @if [ $( $c )* $x ] $( $t )* }
+    );
+
+    (
+        @else_body [ $( $s: tt )* ] { $( $x: tt )* } $( $t: tt )*
+    ) => (
+        $crate::const_result! { @else [ $( $s )* { $( $x )* } ] $( $t )* }
+    );
+
+    (
+        @else_body [ $( $s: tt )* ] $x: tt $( $t: tt )*
+    ) => (
+        $crate::const_result! { @else_body [ $( $s )* $x ] $( $t )* }
+    );
+
+    (
+        @else [ $( $s: tt )* ] else $( $t: tt )*
+    ) => (
+        $crate::const_result! { @else_body [ $( $s )* else ] $( $t )* }
+    );
+
+    (
+        @else [ $( $s: tt )* ] $( $t: tt )*
+    ) => (
+        $crate::__exit! { 'exit result [ const_result ] [ $( $t )* ] $( $s )* }
+    );
+
+    (
+        @exit $l: lifetime [] [ $( $s: tt )* ] $( $t: tt )*
+    ) => (
+        $crate::const_result! { @end [ $( $s )* ] $( $t )* }
+    );
+
+    (
+        @exit $l: lifetime [ exit ] [ $( $s: tt )* ] $( $t: tt )*
+    ) => (
+        $l: { $crate::const_result! { @end [ $( $s )* ] $( $t )* } }
+    );
+
+    (
+        @end [ $( $s: tt )* ]
+    ) => (
+        ::core::result::Result::Ok($( $s )*)
+    );
+
+    (
+        @end [ $( $s: tt )* ] ; $( $t: tt )*
+    ) => (
+        { $( $s )* ; $crate::const_result! { $( $t )* } }
+    );
+
+    (
+        @end [ $( $s: tt )* ] $( $t: tt )+
+    ) => (
+        { $( $s )* $crate::const_result! { $( $t )* } }
+    );
+
+    (
+        @let_else ( $( $p: tt )* ) [ $( $e: tt )* ] else $d: expr ; $( $t: tt )*
+    ) => (
+        {
+            let $( $p )* = match $( $e )* {
+                ::core::result::Result::Ok(v) => v,
+                ::core::result::Result::Err(_) => $d,
+            };
+            $crate::const_result! { $( $t )* }
+        }
+    );
+
+    (
+        @let_else ( $( $p: tt )* ) [ $( $e: tt )* ] or $( $t: tt )*
+    ) => (
+        $crate::const_result! { @let_or ( $( $p )* ) [ $( $e )* ] [] $( $t )* }
+    );
+
+    (
+        @let_else ( $( $p: tt )* ) [ $( $e: tt )* ]
+    ) => (
+        ::core::compile_error!("expected `;` after `let pattern <- expression`, followed by the value to yield")
+    );
+
+    (
+        @let_else ( $( $p: tt )* ) [ $( $e: tt )* ] $x: tt $( $t: tt )*
+    ) => (
+        $crate::const_result! { @let_else ( $( $p )* ) [ $( $e )* $x ] $( $t )* }
+    );
+
+    (@or_else [ $( $e: tt )* ] [ $( $a: tt )* ]) => (
+        match $( $e )* {
+            ::core::result::Result::Ok(v) => ::core::result::Result::Ok(v),
+            ::core::result::Result::Err(_) => $( $a )*,
+        }
+    );
+
+    (
+        @let_or ( $( $p: tt )* ) [ $( $e: tt )* ] [ $( $a: tt )* ] or $( $t: tt )*
+    ) => (
+        $crate::const_result! { @let_or ( $( $p )* ) [ $crate::const_result! { @or_else [ $( $e )* ] [ $( $a )* ] } ] [] $( $t )* }
+    );
+
+    (
+        @let_or ( $( $p: tt )* ) [ $( $e: tt )* ] [ $( $a: tt )* ] ; $( $t: tt )*
+    ) => (
+        $crate::const_result! { let $( $p )* <- $crate::const_result! { @or_else [ $( $e )* ] [ $( $a )* ] } ; $( $t )* }
+    );
+
+    (
+        @let_or ( $( $p: tt )* ) [ $( $e: tt )* ] [ $( $a: tt )* ] else $( $t: tt )*
+    ) => (
+        $crate::const_result! { @let_else ( $( $p )* ) [ $crate::const_result! { @or_else [ $( $e )* ] [ $( $a )* ] } ] else $( $t )* }
+    );
+
+    (
+        @let_or ( $( $p: tt )* ) [ $( $e: tt )* ] [ $( $a: tt )* ]
+    ) => (
+        ::core::compile_error!("expected `;` after `let pattern <- expression`, followed by the value to yield")
+    );
+
+    (
+        @let_or ( $( $p: tt )* ) [ $( $e: tt )* ] [ $( $a: tt )* ] $x: tt $( $t: tt )*
+    ) => (
+        $crate::const_result! { @let_or ( $( $p )* ) [ $( $e )* ] [ $( $a )* $x ] $( $t )* }
+    );
+
+    () => {
+        ::core::result::Result::Ok(())
+    };
+
+    (
+        let mut $p: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        match $e {
+            ::core::result::Result::Ok(v) => { let $crate::const_result! (@as_pat mut $p) = v; $crate::const_result! { $( $t )* } }
+            ::core::result::Result::Err(e) => ::core::result::Result::Err(e),
+        }
+    );
+
+    (
+        let mut $p: ident : $ty: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        match $e {
+            ::core::result::Result::Ok(v) => { let mut $p : $ty = v; $crate::const_result! { $( $t )* } }
+            ::core::result::Result::Err(e) => ::core::result::Result::Err(e),
+        }
+    );
+
+    (
+        let $p: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        match $e {
+            ::core::result::Result::Ok(v) => { let $crate::const_result! (@as_pat $p) = ::core::convert::identity(v); $crate::const_result! { $( $t )* } }
+            ::core::result::Result::Err(e) => ::core::result::Result::Err(e),
+        }
+    );
+
+    (
+        let $p: tt ( $( $para: tt )* ) <- $e: expr ; $( $t: tt )*
+    ) => (
+        match $e {
+            ::core::result::Result::Ok(v) => { let $crate::const_result! (@as_pat $p ( $( $para )* ) ) = ::core::convert::identity(v); $crate::const_result! { $( $t )* } }
+            ::core::result::Result::Err(e) => ::core::result::Result::Err(e),
+        }
+    );
+
+    (
+        let $p: tt { $( $para: tt )* } <- $e: expr ; $( $t: tt )*
+    ) => (
+        match $e {
+            ::core::result::Result::Ok(v) => { let $crate::const_result! (@as_pat $p { $( $para )* } ) = ::core::convert::identity(v); $crate::const_result! { $( $t )* } }
+            ::core::result::Result::Err(e) => ::core::result::Result::Err(e),
+        }
+    );
+
+    (
+        let $p: ident : $ty: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        match $e {
+            ::core::result::Result::Ok(v) => { let $p : $ty = v; $crate::const_result! { $( $t )* } }
+            ::core::result::Result::Err(e) => ::core::result::Result::Err(e),
+        }
+    );
+
+    (
+        let $( $p: ident )+ : $( $t: tt )+
+    ) => (
+        $crate::__pat! { @ty const_result [] [ $( $p )+ ] [] $( $t )* }
+    );
+
+    (
+        let mut $p: tt <- $( $t: tt )+
+    ) => (
+        $crate::const_result! { @let_else ( mut $p ) [] $( $t )* }
+    );
+
+    (
+        let $p: tt <- $( $t: tt )+
+    ) => (
+        $crate::const_result! { @let_else ( $p ) [] $( $t )* }
+    );
+
+    (
+        let mut $p: tt = $e: expr
+    ) => (
+        ::core::compile_error!("expected `<-` after the pattern to bind, or `;` and the value to yield after `let`")
+    );
+
+    (
+        let $p: tt = $e: expr
+    ) => (
+        ::core::compile_error!("expected `<-` after the pattern to bind, or `;` and the value to yield after `let`")
+    );
+
+    (
+        if $( $t: tt )+
+    ) => (
+        $crate::const_result! { @if [] $( $t )* }
+    );
+
+    (
+        return $e: expr ; $( $t: tt )*
+    ) => (
+        ::core::result::Result::Ok($e)
+    );
+
+    (
+        guard $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::const_result! { if $e ; $( $t )* }
+    );
+
+    (
+        unless $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::const_result! { if !($e) ; $( $t )* }
+    );
+
+    (
+        guard $e: expr => $err: expr ; $( $t: tt )*
+    ) => (
+        $crate::const_result! { if $e => $err ; $( $t )* }
+    );
+
+    (
+        unless $e: expr => $err: expr ; $( $t: tt )*
+    ) => (
+        $crate::const_result! { if !($e) => $err ; $( $t )* }
+    );
+
+    (
+        $e: expr ; $( $t: tt )*
+    ) => (
+        { $e ; $crate::const_result! { $( $t )* } }
+    );
+
+    (
+        guard $( $t: tt )+
+    ) => (
+        $crate::const_result! { if $( $t )* }
+    );
+
+    (
+        unless $( $t: tt )+
+    ) => (
+        $crate::const_result! { @unless [] $( $t )* }
+    );
+
+    (
+        @unless [ $( $c: tt )* ] else $( $t: tt )*
+    ) => (
+        $crate::const_result! { if !($( $c )*) else $( $t )* }
+    );
+
+    (
+        @unless [ $( $c: tt )* ] $x: tt $( $t: tt )*
+    ) => (
+        $crate::const_result! { @unless [ $( $c )* $x ] $( $t )* }
+    );
+
+    (
+        fail $e: expr ; $( $t: tt )*
+    ) => (
+        ::core::result::Result::Err($e)
+    );
+
+    (
+        ensure $e: expr , $err: expr ; $( $t: tt )*
+    ) => (
+        $crate::const_result! { if $e => $err ; $( $t )* }
+    );
+
+    (
+        $stmt: stmt ; $( $t: tt )*
+    ) => (
+        { $stmt $crate::const_result! { $( $t )* } }
+    );
+
+    (
+        $e: expr
+    ) => (
+        ::core::result::Result::Ok($e)
+    );
+
+    (
+        $b: block ; $( $t: tt )*
+    ) => (
+        $b ; $crate::const_result! { $( $t )* }
+    );
+}
+
+/// syntax extension specific for Iterator
+///
+/// See the module-level documentation for more details.
+#[macro_export]
+macro_rules! iter {
+    (@as_pat $p: pat) => ($p);
+
+    (
+        let $( $b: ident )+ @ $( $t: tt )+
+    ) => (
+        $crate::__pat! { iter [] [ $( $b )+ @ ] $( $t )* }
+    );
+
+    (
+        let & $( $t: tt )+
+    ) => (
+        $crate::__pat! { iter [] [ & ] $( $t )* }
+    );
+
+    (
+        let ref $( $t: tt )+
+    ) => (
+        $crate::__pat! { iter [] [ ref ] $( $t )* }
+    );
+
+    (
+        let $( $a: ident )? :: $( $t: tt )+
+    ) => (
+        $crate::__pat! { iter [] [ $( $a )? :: ] $( $t )* }
+    );
+
+    (
+        ref $( $x: ident ),+ ; $( $t: tt )*
+    ) => ({
+        $( let $x = &$x; )+
+        $crate::iter! { $( $t )* }
+    });
+
+    (
+        strict ; $( $t: tt )*
+    ) => (
+        $crate::__strict! { iter [] $( $t )* }
+    );
+
+    (
+        clone $( $c: ident ),+ ; $( $t: tt )*
+    ) => ({
+        $( let $c = ::core::clone::Clone::clone(&$c); )+
+        $crate::iter! { @clone [ $( $c )+ ] [] [] $( $t )* }
+    });
+
+    (
+        @clone $c: tt $b: tt [ $( $a: tt )* ]
+    ) => (
+        $crate::iter! { $( $a )* }
+    );
+
+    (
+        @clone $c: tt $b: tt [ $( $a: tt )* ] ; while $( $t: tt )*
+    ) => (
+        $crate::iter! { @clone $c $b [ $( $a )* ; while ] $( $t )* }
+    );
+
+    (
+        @clone $c: tt $b: tt [ $( $a: tt )* ] ; take $( $t: tt )*
+    ) => (
+        $crate::iter! { @clone $c $b [ $( $a )* ; take ] $( $t )* }
+    );
+
+    (
+        @clone $c: tt $b: tt [ $( $a: tt )* ] ; skip $( $t: tt )*
+    ) => (
+        $crate::iter! { @clone $c $b [ $( $a )* ; skip ] $( $t )* }
+    );
+
+    (
+        @clone $c: tt $b: tt [ $( $a: tt )* ] ; unique $( $t: tt )*
+    ) => (
+        $crate::iter! { @clone $c $b [ $( $a )* ; unique ] $( $t )* }
+    );
+
+    (
+        @clone $c: tt $b: tt [ $( $a: tt )* ] ; inspect $( $t: tt )*
+    ) => (
+        $crate::iter! { @clone $c $b [ $( $a )* ; inspect ] $( $t )* }
+    );
+
+    (
+        @clone $c: tt $b: tt [ $( $a: tt )* ] ; timed $( $t: tt )*
+    ) => (
+        $crate::iter! { @clone $c $b [ $( $a )* ; timed ] $( $t )* }
+    );
+
+    (
+        @clone $c: tt $b: tt [ $( $a: tt )* ] ; join $( $t: tt )*
+    ) => (
+        $crate::iter! { @clone $c $b [ $( $a )* ; join ] $( $t )* }
+    );
+
+    (
+        @clone $c: tt $b: tt [ $( $a: tt )* ] ; merge_join $( $t: tt )*
+    ) => (
+        $crate::iter! { @clone $c $b [ $( $a )* ; merge_join ] $( $t )* }
+    );
+
+    (
+        @clone $c: tt $b: tt [ $( $a: tt )* ] ; skip_while $( $t: tt )*
+    ) => (
+        $crate::iter! { @clone $c $b [ $( $a )* ; skip_while ] $( $t )* }
+    );
+
+    (
+        @clone [ $( $c: ident )+ ] [<-] [ $( $a: tt )* ] ; $x: tt $( $t: tt )*
+    ) => (
+        $crate::iter! { $( $a )* ; clone $( $c ),+ ; $x $( $t )* }
+    );
+
+    (
+        @clone $c: tt $b: tt [ $( $a: tt )* ] <- $( $t: tt )*
+    ) => (
+        $crate::iter! { @clone $c [<-] [ $( $a )* <- ] $( $t )* }
+    );
+
+    (
+        @clone $c: tt $b: tt [ $( $a: tt )* ] $x: tt $( $t: tt )*
+    ) => (
+        $crate::iter! { @clone $c $b [ $( $a )* $x ] $( $t )* }
+    );
+
+    (
+        @for ($p: pat) [ $( $e: tt )* ] ; $( $t: tt )*
+    ) => (
+        $crate::iter! { let $p <- $( $e )* ; $( $t )* }
+    );
+
+    (
+        @for ($p: pat) [ $( $e: tt )* ] { $( $body: tt )* } ; $( $t: tt )*
+    ) => (
+        { for $p in $( $e )* { $( $body )* } $crate::iter! { $( $t )* } }
+    );
+
+    (
+        @for ($p: pat) [ $( $e: tt )* ] { $( $body: tt )* } $( $t: tt )*
+    ) => (
+        { for $p in $( $e )* { $( $body )* } $crate::iter! { $( $t )* } }
+    );
+
+    (
+        @for ($p: pat) [ $( $e: tt )* ] $x: tt $( $t: tt )*
+    ) => (
+        $crate::iter! { @for ($p) [ $( $e )* $x ] $( $t )* }
+    );
+
+    (
+        @inline ($y: expr) [ $( $c: tt )* ] for $p: pat in $e: expr $( , $( $t: tt )* )?
+    ) => (
+        $crate::iter! { @inline ($y) [ $( $c )* for $p in $e ; ] $( $( $t )* )? }
+    );
+
+    (
+        @inline ($y: expr) [ $( $c: tt )* ] if $e: expr $( , $( $t: tt )* )?
+    ) => (
+        $crate::iter! { @inline ($y) [ $( $c )* if $e ; ] $( $( $t )* )? }
+    );
+
+    (
+        @inline ($y: expr) [ $( $c: tt )* ]
+    ) => (
+        $crate::iter! { $( $c )* $y }
+    );
+
+    (
+        @branch [ $( $c: tt )* ] [] []
+    ) => (
+        ::core::iter::empty()
+    );
+
+    (
+        @branch [ $( $c: tt )* ] [ $( $a: tt )+ ] []
+    ) => (
+        $crate::__guard(
+            if $( $c )* { ::core::option::Option::Some($crate::iter! { $( $a )* }) } else { ::core::option::Option::None }
+        )
+    );
+
+    (
+        @branch [ $( $c: tt )* ] [] [ $( $b: tt )+ ]
+    ) => (
+        $crate::__guard(
+            if $( $c )* { ::core::option::Option::None } else { ::core::option::Option::Some($crate::iter! { $( $b )* }) }
+        )
+    );
+
+    (
+        @branch [ $( $c: tt )* ] [ $( $a: tt )+ ] [ $( $b: tt )+ ]
+    ) => ({
+        let (then, otherwise) = if $( $c )* {
+            (::core::option::Option::Some($crate::iter! { $( $a )* }), ::core::option::Option::None)
+        } else {
+            (::core::option::Option::None, ::core::option::Option::Some($crate::iter! { $( $b )* }))
+        };
+        ::core::iter::Iterator::chain(
+            $crate::__guard(then),
+            $crate::__guard(otherwise),
+        )
+    });
+
+    (
+        @if [ $( $c: tt )* ] { $( $a: tt )* } else if $( $t: tt )*
+    ) => (
+        $crate::iter! { @elif [ $( $c )* ] [ $( $a )* ] [ if ] $( $t )* }
+    );
+
+    (
+        @if [ $( $c: tt )* ] { $( $a: tt )* } else { $( $b: tt )* } ; $( $t: tt )*
+    ) => (
+        { if $( $c )* { $( $a )* } else { $( $b )* } ; $crate::iter! { $( $t )* } }
+    );
+
+    (
+        @if [ $( $c: tt )* ] { $( $a: tt )* } else { $( $b: tt )* }
+    ) => (
+        $crate::iter! { @branch [ $( $c )* ] [ $( $a )* ] [ $( $b )* ] }
+    );
+
+    (
+        @if [ $( $c: tt )* ] { $( $a: tt )* } else { $( $b: tt )* } $( $t: tt )+
+    ) => (
+        ::core::iter::Iterator::chain(
+            $crate::iter! { @branch [ $( $c )* ] [ $( $a )* ] [ $( $b )* ] },
+            $crate::iter! { $( $t )* }
+        )
+    );
+
+    (
+        @if [ $( $c: tt )* ] { $( $a: tt )* } $( $t: tt )*
+    ) => (
+        $crate::__exit! { 'exit iter [ iter ] [ [ $( $c )* ] [ $( $a )* ] $( $t )* ] if $( $c )* { $( $a )* } }
+    );
+
+    (
+        @exit $l: lifetime [] [ $( $s: tt )* ] [ $( $c: tt )* ] [ $( $a: tt )* ] ; $( $t: tt )*
+    ) => (
+        { $( $s )* ; $crate::iter! { $( $t )* } }
+    );
+
+    (
+        @exit $l: lifetime [] [ $( $s: tt )* ] [ $( $c: tt )* ] [ $( $a: tt )* ]
+    ) => (
+        $crate::iter! { @branch [ $( $c )* ] [ $( $a )* ] [] }
+    );
+
+    (
+        @exit $l: lifetime [] [ $( $s: tt )* ] [ $( $c: tt )* ] [ $( $a: tt )* ] $( $t: tt )+
+    ) => (
+        ::core::iter::Iterator::chain(
+            $crate::iter! { @branch [ $( $c )* ] [ $( $a )* ] [] },
+            $crate::iter! { $( $t )* }
+        )
+    );
+
+    (
+        @exit $l: lifetime [ exit ] [ $( $s: tt )* ] [ $( $c: tt )* ] [ $( $a: tt )* ] ; $( $t: tt )*
+    ) => (
+        $crate::__guard($l: { $( $s )* ::core::option::Option::Some($crate::iter! { $( $t )* }) })
+    );
+
+    (
+        @exit $l: lifetime [ exit ] [ $( $s: tt )* ] [ $( $c: tt )* ] [ $( $a: tt )* ] $( $t: tt )*
+    ) => (
+        $crate::__guard($l: { $( $s )* ::core::option::Option::Some($crate::iter! { $( $t )* }) })
+    );
+
+    (
+        @if [ $( $c: tt )* ] ; $( $t: tt )*
+    ) => (
+        $crate::__guard(
+            if $( $c )* { ::core::option::Option::Some($crate::iter! { $( $t )* }) } else { ::core::option::Option::None }
+        )
+    );
+
+    (
+        @if [ $( $c: tt )* ] $x: tt $( $t: tt )*
+    ) => (
+        $crate::iter! { @if [ $( $c )* $x ] $( $t )* }
+    );
+
+    (
+        @elif [ $( $c: tt )* ] [ $( $a: tt )* ] [ $( $b: tt )* ] { $( $x: tt )* } else $( $t: tt )*
+    ) => (
+        $crate::iter! { @elif [ $( $c )* ] [ $( $a )* ] [ $( $b )* { $( $x )* } else ] $( $t )* }
+    );
+
+    (
+        @elif [ $( $c: tt )* ] [ $( $a: tt )* ] [ $( $b: tt )* ] { $( $x: tt )* } $( $t: tt )*
+    ) => (
+        $crate::iter! { @if [ $( $c )* ] { $( $a )* } else { $( $b )* { $( $x )* } } $( $t )* }
+    );
+
+    (
+        @elif [ $( $c: tt )* ] [ $( $a: tt )* ] [ $( $b: tt )* ] $x: tt $( $t: tt )*
+    ) => (
+        $crate::iter! { @elif [ $( $c )* ] [ $( $a )* ] [ $( $b )* $x ] $( $t )* }
+    );
+
+    (@or_else [ $( $e: tt )* ] [ $( $a: tt )* ]) => (
+        ::core::iter::Iterator::chain($crate::__into_iter!($( $e )*), $( $a )*)
+    );
+
+    (
+        @let_or ( $( $p: tt )* ) [ $( $e: tt )* ] [ $( $a: tt )* ] or $( $t: tt )*
+    ) => (
+        $crate::iter! { @let_or ( $( $p )* ) [ $crate::iter! { @or_else [ $( $e )* ] [ $( $a )* ] } ] [] $( $t )* }
+    );
+
+    (
+        @let_or ( $( $p: tt )* ) [ $( $e: tt )* ] [ $( $a: tt )* ] ; $( $t: tt )*
+    ) => (
+        $crate::iter! { let $( $p )* <- $crate::iter! { @or_else [ $( $e )* ] [ $( $a )* ] } ; $( $t )* }
+    );
+
+    (
+        @let_or ( $( $p: tt )* ) [ $( $e: tt )* ] [ $( $a: tt )* ]
+    ) => (
+        ::core::compile_error!("expected `;` after `let pattern <- expression`, followed by the value to yield")
+    );
+
+    (
+        @let_or ( $( $p: tt )* ) [ $( $e: tt )* ] [ $( $a: tt )* ] $x: tt $( $t: tt )*
+    ) => (
+        $crate::iter! { @let_or ( $( $p )* ) [ $( $e )* ] [ $( $a )* $x ] $( $t )* }
+    );
+
+    (
+        @let_alt ( $( $p: tt )* ) [ $( $e: tt )* ] or $( $t: tt )*
+    ) => (
+        $crate::iter! { @let_or ( $( $p )* ) [ $( $e )* ] [] $( $t )* }
+    );
+
+    (
+        @let_alt ( $( $p: tt )* ) [ $( $e: tt )* ] zip $( $t: tt )*
+    ) => (
+        $crate::iter! { @let_zip ( $( $p )* ) [ $( $e )* ] [] $( $t )* }
+    );
+
+    (
+        @let_alt ( $( $p: tt )* ) [ $( $e: tt )* ]
+    ) => (
+        ::core::compile_error!("expected `;` after `let pattern <- expression`, followed by the value to yield")
+    );
+
+    (
+        @let_alt ( $( $p: tt )* ) [ $( $e: tt )* ] $x: tt $( $t: tt )*
+    ) => (
+        $crate::iter! { @let_alt ( $( $p )* ) [ $( $e )* $x ] $( $t )* }
+    );
+
+    (@zip [ $( $e: tt )* ] [ $( $a: tt )* ]) => (
+        ::core::iter::Iterator::zip($crate::__into_iter!($( $e )*), $( $a )*)
+    );
+
+    (
+        @let_zip ( $( $p: tt )* ) [ $( $e: tt )* ] [ $( $a: tt )* ] zip $( $t: tt )*
+    ) => (
+        $crate::iter! { @let_zip ( $( $p )* ) [ $crate::iter! { @zip [ $( $e )* ] [ $( $a )* ] } ] [] $( $t )* }
+    );
+
+    (
+        @let_zip ( $( $p: tt )* ) [ $( $e: tt )* ] [ $( $a: tt )* ] ; $( $t: tt )*
+    ) => (
+        $crate::iter! { let $( $p )* <- $crate::iter! { @zip [ $( $e )* ] [ $( $a )* ] } ; $( $t )* }
+    );
+
+    (
+        @let_zip ( $( $p: tt )* ) [ $( $e: tt )* ] [ $( $a: tt )* ] $x: tt $( $t: tt )*
+    ) => (
+        $crate::iter! { @let_zip ( $( $p )* ) [ $( $e )* ] [ $( $a )* $x ] $( $t )* }
+    );
+
+    (
+        @tail $k: ident $src: tt ( $( $p: tt )* ) [ $( $c: tt )* ] [ $( $all: tt )* ] if $cond: expr ; $( $t: tt )*
+    ) => (
+        $crate::iter! { @tail $k $src ( $( $p )* ) [ $( $c )* ($cond) ] [ $( $all )* ] $( $t )* }
+    );
+
+    (
+        @tail $k: ident $src: tt ( $( $p: tt )* ) [ $( $c: tt )* ] [ $( $all: tt )* ] guard $cond: expr ; $( $t: tt )*
+    ) => (
+        $crate::iter! { @tail $k $src ( $( $p )* ) [ $( $c )* ($cond) ] [ $( $all )* ] $( $t )* }
+    );
+
+    (
+        @tail $k: ident $src: tt ( $( $p: tt )* ) [ $( $c: tt )* ] [ $( $all: tt )* ] unless $cond: expr ; $( $t: tt )*
+    ) => (
+        $crate::iter! { @tail $k $src ( $( $p )* ) [ $( $c )* (!($cond)) ] [ $( $all )* ] $( $t )* }
+    );
+
+    (
+        @tail $k: ident $src: tt ( $( $p: tt )* ) [ $( $c: tt )* ] [ $( $all: tt )* ] if $( $t: tt )*
+    ) => (
+        $crate::iter! { @flat_map $k $src ( $( $p )* ) [ $( $all )* ] }
+    );
+
+    (
+        @tail $k: ident $src: tt ( $( $p: tt )* ) [ $( $c: tt )* ] [ $( $all: tt )* ] while $( $t: tt )*
+    ) => (
+        $crate::iter! { @flat_map $k $src ( $( $p )* ) [ $( $all )* ] }
+    );
+
+    (
+        @tail $k: ident $src: tt ( $( $p: tt )* ) [ $( $c: tt )* ] [ $( $all: tt )* ] for $( $t: tt )*
+    ) => (
+        $crate::iter! { @flat_map $k $src ( $( $p )* ) [ $( $all )* ] }
+    );
+
+    (
+        @tail $k: ident $src: tt ( $( $p: tt )* ) [ $( $c: tt )* ] [ $( $all: tt )* ] yield $( $t: tt )*
+    ) => (
+        $crate::iter! { @flat_map $k $src ( $( $p )* ) [ $( $all )* ] }
+    );
+
+    (
+        @tail $k: ident $src: tt ( $( $p: tt )* ) [ $( $c: tt )* ] [ $( $all: tt )* ] let $( $t: tt )*
+    ) => (
+        $crate::iter! { @flat_map $k $src ( $( $p )* ) [ $( $all )* ] }
+    );
+
+    (
+        @tail param $src: tt ( $( $p: tt )* ) [] [ $( $all: tt )* ] $y: expr
+    ) => (
+        ::core::iter::Iterator::map($crate::__into_iter!($src), move | $( $p )* | $y)
+    );
+
+    (
+        @tail param $src: tt ( $( $p: tt )* ) [ $( ( $c: expr ) )+ ] [ $( $all: tt )* ] $y: expr
+    ) => (
+        ::core::iter::Iterator::filter_map(
+            $crate::__into_iter!($src),
+            move | $( $p )* | if $( $c )&&+ { ::core::option::Option::Some($y) } else { ::core::option::Option::None },
+        )
+    );
+
+    (
+        @tail name $src: tt ( $p: ident ) [] [ $( $all: tt )* ] $y: expr
+    ) => (
+        $crate::__bind::<_, _, _, { $crate::__is_binding(::core::stringify!($p)) }>($crate::__into_iter!($src), move |v| {
+            #[allow(unreachable_patterns)]
+            let rest = match ::core::convert::identity(v) {
+                $p => ::core::option::Option::Some($y),
+                _ => ::core::option::Option::None,
+            };
+            rest
+        })
+    );
+
+    (
+        @tail name $src: tt ( $p: ident ) [ $( $c: tt )+ ] [ $( $all: tt )* ] $y: expr
+    ) => (
+        $crate::iter! { @tail pattern $src ( $p ) [ $( $c )* ] [ $( $all )* ] $y }
+    );
+
+    (
+        @tail pattern $src: tt ( $( $p: tt )* ) [] [ $( $all: tt )* ] $y: expr
+    ) => (
+        ::core::iter::Iterator::filter_map($crate::__into_iter!($src), move |v| {
+            #[allow(unreachable_patterns)]
+            let rest = match ::core::convert::identity(v) {
+                $crate::iter! (@as_pat $( $p )*) => ::core::option::Option::Some($y),
+                _ => ::core::option::Option::None,
+            };
+            rest
+        })
+    );
+
+    (
+        @tail pattern $src: tt ( $( $p: tt )* ) [ $( ( $c: expr ) )+ ] [ $( $all: tt )* ] $y: expr
+    ) => (
+        ::core::iter::Iterator::filter_map($crate::__into_iter!($src), move |v| {
+            #[allow(unreachable_patterns)]
+            let rest = match ::core::convert::identity(v) {
+                $crate::iter! (@as_pat $( $p )*) => if $( $c )&&+ { ::core::option::Option::Some($y) } else { ::core::option::Option::None },
+                _ => ::core::option::Option::None,
+            };
+            rest
+        })
+    );
+
+    (
+        @tail $k: ident $src: tt ( $( $p: tt )* ) [ $( $c: tt )* ] [ $( $all: tt )* ] $( $t: tt )*
+    ) => (
+        $crate::iter! { @flat_map $k $src ( $( $p )* ) [ $( $all )* ] }
+    );
+
+    (
+        @flat_map param $src: tt ( $( $p: tt )* ) [ $( $t: tt )* ]
+    ) => (
+        ::core::iter::Iterator::flat_map(
+            $crate::__into_iter!($src),
+            move | $( $p )* | { $crate::iter! { $( $t )* } },
+        )
+    );
+
+    (
+        @flat_map name $src: tt ( $p: ident ) [ $( $t: tt )* ]
+    ) => (
+        $crate::iter! { @flat_map pattern $src ( $p ) [ $( $t )* ] }
+    );
+
+    (
+        @flat_map pattern $src: tt ( $( $p: tt )* ) [ $( $t: tt )* ]
+    ) => (
+        ::core::iter::Iterator::flatten(::core::iter::Iterator::filter_map(
+            $crate::__into_iter!($src),
+            move |v| {
+                #[allow(unreachable_patterns)]
+                let rest = match ::core::convert::identity(v) {
+                    $crate::iter! (@as_pat $( $p )*) => ::core::option::Option::Some($crate::iter! { $( $t )* }),
+                    _ => ::core::option::Option::None,
+                };
+                rest
+            },
+        ))
+    );
+
+    (
+        @join $h: ident ( $p: tt ) ( $q: tt ) [ $e: expr ] [ $( $s: tt )* ] on $( $t: tt )*
+    ) => (
+        $crate::iter! { @join_on $h ( $p ) ( $q ) [ $e ] [ $( $s )* ] [] $( $t )* }
+    );
+
+    (
+        @join $h: ident ( $p: tt ) ( $q: tt ) [ $e: expr ] [ $( $s: tt )* ] $x: tt $( $t: tt )*
+    ) => (
+        $crate::iter! { @join $h ( $p ) ( $q ) [ $e ] [ $( $s )* $x ] $( $t )* }
+    );
+
+    (
+        @join_on $h: ident ( $p: tt ) ( $q: tt ) [ $e: expr ] [ $( $s: tt )* ] [ $( $k: tt )* ] == $( $t: tt )*
+    ) => (
+        $crate::iter! { @join_key $h ( $p ) ( $q ) [ $e ] [ $( $s )* ] [ $( $k )* ] [] $( $t )* }
+    );
+
+    (
+        @join_on $h: ident ( $p: tt ) ( $q: tt ) [ $e: expr ] [ $( $s: tt )* ] [ $( $k: tt )* ] $x: tt $( $t: tt )*
+    ) => (
+        $crate::iter! { @join_on $h ( $p ) ( $q ) [ $e ] [ $( $s )* ] [ $( $k )* $x ] $( $t )* }
+    );
+
+    (
+        @join_key $h: ident ( $p: tt ) ( $q: tt ) [ $e: expr ] [ $( $s: tt )* ] [ $( $k: tt )* ] [ $( $l: tt )* ] ; $( $t: tt )*
+    ) => (
+        $crate::iter! {
+            let ( $p, $q ) <- $crate::$h(
+                $e,
+                $( $s )*,
+                move |$p| $( $k )*,
+                move |$q| $( $l )*,
+            ) ;
+            $( $t )*
+        }
+    );
+
+    (
+        @join_key $h: ident ( $p: tt ) ( $q: tt ) [ $e: expr ] [ $( $s: tt )* ] [ $( $k: tt )* ] [ $( $l: tt )* ] $x: tt $( $t: tt )*
+    ) => (
+        $crate::iter! { @join_key $h ( $p ) ( $q ) [ $e ] [ $( $s )* ] [ $( $k )* ] [ $( $l )* $x ] $( $t )* }
+    );
+
+    (
+        @take_while ( $( $p: tt )* ) ($e: expr) [ $( $c: tt )* ] ; $( $t: tt )*
+    ) => ({
+        let iter = ::core::iter::Iterator::map(
+            $crate::__into_iter!($e),
+            move | $( $p )* | if $( $c )* {
+                ::core::option::Option::Some($crate::iter! { $( $t )* })
+            } else {
+                ::core::option::Option::None
+            },
+        );
+        let iter = ::core::iter::Iterator::take_while(iter, ::core::option::Option::is_some);
+        ::core::iter::Iterator::flatten(::core::iter::Iterator::flatten(iter))
+    });
+
+    (
+        @take_while ( $( $p: tt )* ) ($e: expr) [ $( $c: tt )* ] { $( $b: tt )* } ; $( $t: tt )*
+    ) => (
+        $crate::iter! { let $( $p )* <- $e ; { while $( $c )* { $( $b )* } } ; $( $t )* }
+    );
+
+    (
+        @take_while ( $( $p: tt )* ) ($e: expr) [ $( $c: tt )* ] { $( $b: tt )* } $( $t: tt )*
+    ) => (
+        $crate::iter! { let $( $p )* <- $e ; { while $( $c )* { $( $b )* } } ; $( $t )* }
+    );
+
+    (
+        @take_while ( $( $p: tt )* ) ($e: expr) [ $( $c: tt )* ] $x: tt $( $t: tt )*
+    ) => (
+        $crate::iter! { @take_while ( $( $p )* ) ($e) [ $( $c )* $x ] $( $t )* }
+    );
+
+    (
+        @skip_while ( $( $p: tt )* ) ($e: expr) ($c: expr) $( $t: tt )*
+    ) => ({
+        let mut skipping = true;
+        let iter = ::core::iter::Iterator::flat_map(
+            $crate::__into_iter!($e),
+            move | $( $p )* | if skipping && $c {
+                ::core::option::Option::None
+            } else {
+                skipping = false;
+                ::core::option::Option::Some($crate::iter! { $( $t )* })
+            },
+        );
+        ::core::iter::Iterator::flatten(iter)
+    });
+
+    (
+        @while [ $( $c: tt )* ] ; $( $t: tt )*
+    ) => (
+        ::core::compile_error!("`while condition;` must follow a binding in `iter!`")
+    );
+
+    (
+        @while [ $( $c: tt )* ] { $( $b: tt )* } ; $( $t: tt )*
+    ) => (
+        { while $( $c )* { $( $b )* } $crate::iter! { $( $t )* } }
+    );
+
+    (
+        @while [ $( $c: tt )* ] { $( $b: tt )* } $( $t: tt )*
+    ) => (
+        { while $( $c )* { $( $b )* } $crate::iter! { $( $t )* } }
+    );
+
+    (
+        @while [ $( $c: tt )* ] $x: tt $( $t: tt )*
+    ) => (
+        $crate::iter! { @while [ $( $c )* $x ] $( $t )* }
+    );
+
+    () => {
+        ::core::iter::once(())
+    };
+
+    (
+        sum ; $( $t: tt )*
+    ) => (
+        ::core::iter::Iterator::sum($crate::__into_iter!($crate::iter! { $( $t )* }))
+    );
+
+    (
+        product ; $( $t: tt )*
+    ) => (
+        ::core::iter::Iterator::product($crate::__into_iter!($crate::iter! { $( $t )* }))
+    );
+
+    (
+        count ; $( $t: tt )*
+    ) => (
+        ::core::iter::Iterator::count($crate::__into_iter!($crate::iter! { $( $t )* }))
+    );
+
+    (
+        min ; $( $t: tt )*
+    ) => (
+        ::core::iter::Iterator::min($crate::__into_iter!($crate::iter! { $( $t )* }))
+    );
+
+    (
+        max ; $( $t: tt )*
+    ) => (
+        ::core::iter::Iterator::max($crate::__into_iter!($crate::iter! { $( $t )* }))
+    );
+
+    (
+        any ; $( $t: tt )*
+    ) => (
+        ::core::iter::Iterator::any(
+            &mut $crate::__into_iter!($crate::iter! { $( $t )* }),
+            ::core::convert::identity,
+        )
+    );
+
+    (
+        all ; $( $t: tt )*
+    ) => (
+        ::core::iter::Iterator::all(
+            &mut $crate::__into_iter!($crate::iter! { $( $t )* }),
+            ::core::convert::identity,
+        )
+    );
+
+    (
+        first ; $( $t: tt )*
+    ) => (
+        ::core::iter::Iterator::next(&mut $crate::__into_iter!($crate::iter! { $( $t )* }))
+    );
+
+    (
+        find ( $f: expr ) ; $( $t: tt )*
+    ) => (
+        ::core::iter::Iterator::find(
+            &mut $crate::__into_iter!($crate::iter! { $( $t )* }),
+            $f,
+        )
+    );
+
+    (
+        extend $target: expr ; $( $t: tt )*
+    ) => (
+        ::core::iter::Extend::extend(&mut $target, $crate::iter! { $( $t )* })
+    );
+
+    (
+        from_fn ; $( $t: tt )*
+    ) => (
+        ::core::compile_error!("`from_fn;` is only supported in `spanned::iter!`")
+    );
+
+    (
+        send ; $( $t: tt )*
+    ) => (
+        $crate::__send($crate::iter! { $( $t )* })
+    );
+
+    (
+        boxed ; $( $t: tt )*
+    ) => (
+        $crate::__alloc::boxed::Box::new($crate::iter! { $( $t )* })
+            as $crate::__alloc::boxed::Box<dyn ::core::iter::Iterator<Item = _> + '_>
+    );
+
+    (
+        collect into $ty: ty ; $( $t: tt )*
+    ) => (
+        ::core::iter::Iterator::collect::<$ty>(
+            $crate::__into_iter!($crate::iter! { $( $t )* })
+        )
+    );
+
+    (
+        unzip ; $( $t: tt )*
+    ) => (
+        ::core::iter::Iterator::unzip($crate::__into_iter!($crate::iter! { $( $t )* }))
+    );
+
+    (
+        partition ( $f: expr ) ; $( $t: tt )*
+    ) => (
+        ::core::iter::Iterator::partition(
+            $crate::__into_iter!($crate::iter! { $( $t )* }),
+            $f,
+        )
+    );
+
+    (
+        fold ( $init: expr , $f: expr ) ; $( $t: tt )*
+    ) => (
+        ::core::iter::Iterator::fold(
+            $crate::__into_iter!($crate::iter! { $( $t )* }),
+            $init,
+            $f,
+        )
+    );
+
+    (
+        let mut $p: tt <- scan ( $init: expr , $f: expr ) over $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::iter! { let mut $p <- ::core::iter::Iterator::scan($crate::__into_iter!($e), $init, $f) ; $( $t )* }
+    );
+
+    (
+        let mut $p: tt <- scan ( $init: expr , $f: expr ) over $e: expr , $( $t: tt )*
+    ) => (
+        $crate::iter! { let mut $p <- ::core::iter::Iterator::scan($crate::__into_iter!($e), $init, $f) , $( $t )* }
+    );
+
+    (
+        let mut $p: ident : $ty: tt <- scan ( $init: expr , $f: expr ) over $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::iter! { let mut $p : $ty <- ::core::iter::Iterator::scan($crate::__into_iter!($e), $init, $f) ; $( $t )* }
+    );
+
+    (
+        let mut $p: ident : $ty: tt <- scan ( $init: expr , $f: expr ) over $e: expr , $( $t: tt )*
+    ) => (
+        $crate::iter! { let mut $p : $ty <- ::core::iter::Iterator::scan($crate::__into_iter!($e), $init, $f) , $( $t )* }
+    );
+
+    (
+        let $p: tt <- scan ( $init: expr , $f: expr ) over $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::iter! { let $p <- ::core::iter::Iterator::scan($crate::__into_iter!($e), $init, $f) ; $( $t )* }
+    );
+
+    (
+        let $p: tt <- scan ( $init: expr , $f: expr ) over $e: expr , $( $t: tt )*
+    ) => (
+        $crate::iter! { let $p <- ::core::iter::Iterator::scan($crate::__into_iter!($e), $init, $f) , $( $t )* }
+    );
+
+    (
+        let $p: ident : $ty: tt <- scan ( $init: expr , $f: expr ) over $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::iter! { let $p : $ty <- ::core::iter::Iterator::scan($crate::__into_iter!($e), $init, $f) ; $( $t )* }
+    );
+
+    (
+        let $p: ident : $ty: tt <- scan ( $init: expr , $f: expr ) over $e: expr , $( $t: tt )*
+    ) => (
+        $crate::iter! { let $p : $ty <- ::core::iter::Iterator::scan($crate::__into_iter!($e), $init, $f) , $( $t )* }
+    );
+
+    (
+        let mut $p: tt <- enumerate $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::iter! { let mut $p <- ::core::iter::Iterator::enumerate($crate::__into_iter!($e)) ; $( $t )* }
+    );
+
+    (
+        let mut $p: tt <- enumerate $e: expr , $( $t: tt )*
+    ) => (
+        $crate::iter! { let mut $p <- ::core::iter::Iterator::enumerate($crate::__into_iter!($e)) , $( $t )* }
+    );
+
+    (
+        let mut $p: ident : $ty: tt <- enumerate $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::iter! { let mut $p : $ty <- ::core::iter::Iterator::enumerate($crate::__into_iter!($e)) ; $( $t )* }
+    );
+
+    (
+        let mut $p: ident : $ty: tt <- enumerate $e: expr , $( $t: tt )*
+    ) => (
+        $crate::iter! { let mut $p : $ty <- ::core::iter::Iterator::enumerate($crate::__into_iter!($e)) , $( $t )* }
+    );
+
+    (
+        let $p: tt <- enumerate $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::iter! { let $p <- ::core::iter::Iterator::enumerate($crate::__into_iter!($e)) ; $( $t )* }
+    );
+
+    (
+        let $p: tt <- enumerate $e: expr , $( $t: tt )*
+    ) => (
+        $crate::iter! { let $p <- ::core::iter::Iterator::enumerate($crate::__into_iter!($e)) , $( $t )* }
+    );
+
+    (
+        let $p: ident : $ty: tt <- enumerate $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::iter! { let $p : $ty <- ::core::iter::Iterator::enumerate($crate::__into_iter!($e)) ; $( $t )* }
+    );
+
+    (
+        let $p: ident : $ty: tt <- enumerate $e: expr , $( $t: tt )*
+    ) => (
+        $crate::iter! { let $p : $ty <- ::core::iter::Iterator::enumerate($crate::__into_iter!($e)) , $( $t )* }
+    );
+
+    (
+        let mut $p: tt <- $e: expr , sorted_by_key ( $f: expr ) ; $( $t: tt )*
+    ) => (
+        $crate::iter! { let mut $p <- $crate::__sorted_by_key($e, $f) ; $( $t )* }
+    );
+
+    (
+        let mut $p: ident : $ty: tt <- $e: expr , sorted_by_key ( $f: expr ) ; $( $t: tt )*
+    ) => (
+        $crate::iter! { let mut $p : $ty <- $crate::__sorted_by_key($e, $f) ; $( $t )* }
+    );
+
+    (
+        let $p: tt <- $e: expr , sorted_by_key ( $f: expr ) ; $( $t: tt )*
+    ) => (
+        $crate::iter! { let $p <- $crate::__sorted_by_key($e, $f) ; $( $t )* }
+    );
+
+    (
+        let $p: ident : $ty: tt <- $e: expr , sorted_by_key ( $f: expr ) ; $( $t: tt )*
+    ) => (
+        $crate::iter! { let $p : $ty <- $crate::__sorted_by_key($e, $f) ; $( $t )* }
+    );
+
+    (
+        let mut $p: tt <- $e: expr , chunks $n: expr ; $( $t: tt )*
+    ) => (
+        $crate::iter! { let mut $p <- $crate::__chunks($e, $n) ; $( $t )* }
+    );
+
+    (
+        let mut $p: ident : $ty: tt <- $e: expr , chunks $n: expr ; $( $t: tt )*
+    ) => (
+        $crate::iter! { let mut $p : $ty <- $crate::__chunks($e, $n) ; $( $t )* }
+    );
+
+    (
+        let $p: tt <- $e: expr , chunks $n: expr ; $( $t: tt )*
+    ) => (
+        $crate::iter! { let $p <- $crate::__chunks($e, $n) ; $( $t )* }
+    );
+
+    (
+        let $p: ident : $ty: tt <- $e: expr , chunks $n: expr ; $( $t: tt )*
+    ) => (
+        $crate::iter! { let $p : $ty <- $crate::__chunks($e, $n) ; $( $t )* }
+    );
+
+    (
+        let mut $p: tt <- $e: expr , windows $n: tt ; $( $t: tt )*
+    ) => (
+        $crate::iter! { let mut $p <- $crate::__windows!($e, $n) ; $( $t )* }
+    );
+
+    (
+        let mut $p: ident : $ty: tt <- $e: expr , windows $n: tt ; $( $t: tt )*
+    ) => (
+        $crate::iter! { let mut $p : $ty <- $crate::__windows!($e, $n) ; $( $t )* }
+    );
+
+    (
+        let $p: tt <- $e: expr , windows $n: tt ; $( $t: tt )*
+    ) => (
+        $crate::iter! { let $p <- $crate::__windows!($e, $n) ; $( $t )* }
+    );
+
+    (
+        let $p: ident : $ty: tt <- $e: expr , windows $n: tt ; $( $t: tt )*
+    ) => (
+        $crate::iter! { let $p : $ty <- $crate::__windows!($e, $n) ; $( $t )* }
+    );
+
+    (
+        let mut $p: tt <- $e: expr , windows $n: expr ; $( $t: tt )*
+    ) => (
+        $crate::iter! { let mut $p <- $crate::__windows!($e, $n) ; $( $t )* }
+    );
+
+    (
+        let mut $p: ident : $ty: tt <- $e: expr , windows $n: expr ; $( $t: tt )*
+    ) => (
+        $crate::iter! { let mut $p : $ty <- $crate::__windows!($e, $n) ; $( $t )* }
+    );
+
+    (
+        let $p: tt <- $e: expr , windows $n: expr ; $( $t: tt )*
+    ) => (
+        $crate::iter! { let $p <- $crate::__windows!($e, $n) ; $( $t )* }
+    );
+
+    (
+        let $p: ident : $ty: tt <- $e: expr , windows $n: expr ; $( $t: tt )*
+    ) => (
+        $crate::iter! { let $p : $ty <- $crate::__windows!($e, $n) ; $( $t )* }
+    );
+
+    (
+        let mut $p: tt <- $e: expr , rev ; $( $t: tt )*
+    ) => (
+        $crate::iter! { let mut $p <- ::core::iter::Iterator::rev($crate::__into_iter!($e)) ; $( $t )* }
+    );
+
+    (
+        let mut $p: ident : $ty: tt <- $e: expr , rev ; $( $t: tt )*
+    ) => (
+        $crate::iter! { let mut $p : $ty <- ::core::iter::Iterator::rev($crate::__into_iter!($e)) ; $( $t )* }
+    );
+
+    (
+        let $p: tt <- $e: expr , rev ; $( $t: tt )*
+    ) => (
+        $crate::iter! { let $p <- ::core::iter::Iterator::rev($crate::__into_iter!($e)) ; $( $t )* }
+    );
+
+    (
+        let $p: ident : $ty: tt <- $e: expr , rev ; $( $t: tt )*
+    ) => (
+        $crate::iter! { let $p : $ty <- ::core::iter::Iterator::rev($crate::__into_iter!($e)) ; $( $t )* }
+    );
+
+    (
+        let mut $p: tt <- $e: expr , copied ; $( $t: tt )*
+    ) => (
+        $crate::iter! { let mut $p <- ::core::iter::Iterator::copied($crate::__into_iter!($e)) ; $( $t )* }
+    );
+
+    (
+        let mut $p: ident : $ty: tt <- $e: expr , copied ; $( $t: tt )*
+    ) => (
+        $crate::iter! { let mut $p : $ty <- ::core::iter::Iterator::copied($crate::__into_iter!($e)) ; $( $t )* }
+    );
+
+    (
+        let $p: tt <- $e: expr , copied ; $( $t: tt )*
+    ) => (
+        $crate::iter! { let $p <- ::core::iter::Iterator::copied($crate::__into_iter!($e)) ; $( $t )* }
+    );
+
+    (
+        let $p: ident : $ty: tt <- $e: expr , copied ; $( $t: tt )*
+    ) => (
+        $crate::iter! { let $p : $ty <- ::core::iter::Iterator::copied($crate::__into_iter!($e)) ; $( $t )* }
+    );
+
+    (
+        let mut $p: tt <- $e: expr , cloned ; $( $t: tt )*
+    ) => (
+        $crate::iter! { let mut $p <- ::core::iter::Iterator::cloned($crate::__into_iter!($e)) ; $( $t )* }
+    );
+
+    (
+        let mut $p: ident : $ty: tt <- $e: expr , cloned ; $( $t: tt )*
+    ) => (
+        $crate::iter! { let mut $p : $ty <- ::core::iter::Iterator::cloned($crate::__into_iter!($e)) ; $( $t )* }
+    );
+
+    (
+        let $p: tt <- $e: expr , cloned ; $( $t: tt )*
+    ) => (
+        $crate::iter! { let $p <- ::core::iter::Iterator::cloned($crate::__into_iter!($e)) ; $( $t )* }
+    );
+
+    (
+        let $p: ident : $ty: tt <- $e: expr , cloned ; $( $t: tt )*
+    ) => (
+        $crate::iter! { let $p : $ty <- ::core::iter::Iterator::cloned($crate::__into_iter!($e)) ; $( $t )* }
+    );
+
+    (
+        let mut $p: tt <- $e: expr , by_value ; $( $t: tt )*
+    ) => (
+        $crate::iter! { let mut $p <- $crate::__by_value($e) ; $( $t )* }
+    );
+
+    (
+        let mut $p: ident : $ty: tt <- $e: expr , by_value ; $( $t: tt )*
+    ) => (
+        $crate::iter! { let mut $p : $ty <- $crate::__by_value($e) ; $( $t )* }
+    );
+
+    (
+        let $p: tt <- $e: expr , by_value ; $( $t: tt )*
+    ) => (
+        $crate::iter! { let $p <- $crate::__by_value($e) ; $( $t )* }
+    );
+
+    (
+        let $p: ident : $ty: tt <- $e: expr , by_value ; $( $t: tt )*
+    ) => (
+        $crate::iter! { let $p : $ty <- $crate::__by_value($e) ; $( $t )* }
+    );
+
+    (
+        let mut $p: tt <- $e: expr , step_by $n: expr ; $( $t: tt )*
+    ) => (
+        $crate::iter! { let mut $p <- ::core::iter::Iterator::step_by($crate::__into_iter!($e), $n) ; $( $t )* }
+    );
+
+    (
+        let mut $p: ident : $ty: tt <- $e: expr , step_by $n: expr ; $( $t: tt )*
+    ) => (
+        $crate::iter! { let mut $p : $ty <- ::core::iter::Iterator::step_by($crate::__into_iter!($e), $n) ; $( $t )* }
+    );
+
+    (
+        let $p: tt <- $e: expr , step_by $n: expr ; $( $t: tt )*
+    ) => (
+        $crate::iter! { let $p <- ::core::iter::Iterator::step_by($crate::__into_iter!($e), $n) ; $( $t )* }
+    );
+
+    (
+        let $p: ident : $ty: tt <- $e: expr , step_by $n: expr ; $( $t: tt )*
+    ) => (
+        $crate::iter! { let $p : $ty <- ::core::iter::Iterator::step_by($crate::__into_iter!($e), $n) ; $( $t )* }
+    );
+
+    (
+        let $p: tt <- $e: expr ; $( clone $( $c: ident ),+ ; )? let $q: tt <- memo $f: expr ; $( $t: tt )*
+    ) => (
+        {
+            let memo = $crate::__memo($f);
+            $crate::iter! { let $p <- $e ; $( clone $( $c ),+ ; )? let $q <- ::core::clone::Clone::clone(&memo) ; $( $t )* }
+        }
+    );
+
+    (
+        let $p: tt <- $e: expr ; $( clone $( $c: ident ),+ ; )? let $q: ident : $qty: tt <- memo $f: expr ; $( $t: tt )*
+    ) => (
+        {
+            let memo = $crate::__memo($f);
+            $crate::iter! { let $p <- $e ; $( clone $( $c ),+ ; )? let $q : $qty <- ::core::clone::Clone::clone(&memo) ; $( $t )* }
+        }
+    );
+
+    (
+        let $p: ident : $ty: tt <- $e: expr ; $( clone $( $c: ident ),+ ; )? let $q: tt <- memo $f: expr ; $( $t: tt )*
+    ) => (
+        {
+            let memo = $crate::__memo($f);
+            $crate::iter! { let $p : $ty <- $e ; $( clone $( $c ),+ ; )? let $q <- ::core::clone::Clone::clone(&memo) ; $( $t )* }
+        }
+    );
+
+    (
+        let $p: ident : $ty: tt <- $e: expr ; $( clone $( $c: ident ),+ ; )? let $q: ident : $qty: tt <- memo $f: expr ; $( $t: tt )*
+    ) => (
+        {
+            let memo = $crate::__memo($f);
+            $crate::iter! { let $p : $ty <- $e ; $( clone $( $c ),+ ; )? let $q : $qty <- ::core::clone::Clone::clone(&memo) ; $( $t )* }
+        }
+    );
+
+    (
+        let $q: tt <- memo $f: expr ; $( $t: tt )*
+    ) => (
+        $crate::iter! { let $q <- $crate::__memo($f) ; $( $t )* }
+    );
+
+    (
+        let $q: ident : $qty: tt <- memo $f: expr ; $( $t: tt )*
+    ) => (
+        $crate::iter! { let $q : $qty <- $crate::__memo($f) ; $( $t )* }
+    );
+
+    (
+        let $p: tt <- $e: expr ; join $q: tt in $( $t: tt )*
+    ) => (
+        $crate::iter! { @join __hash_join ( $p ) ( $q ) [ $e ] [] $( $t )* }
+    );
+
+    (
+        let $p: tt <- $e: expr ; merge_join $q: tt in $( $t: tt )*
+    ) => (
+        $crate::iter! { @join __merge_join ( $p ) ( $q ) [ $e ] [] $( $t )* }
+    );
+
+    (
+        let mut $p: tt <- $e: expr ; timed $f: expr ; $( $t: tt )*
+    ) => (
+        $crate::iter! { let mut $p <- $crate::__timed($e, $f) ; $( $t )* }
+    );
+
+    (
+        let mut $p: ident : $ty: tt <- $e: expr ; timed $f: expr ; $( $t: tt )*
+    ) => (
+        $crate::iter! { let mut $p : $ty <- $crate::__timed($e, $f) ; $( $t )* }
+    );
+
+    (
+        let $p: tt <- $e: expr ; timed $f: expr ; $( $t: tt )*
+    ) => (
+        $crate::iter! { let $p <- $crate::__timed($e, $f) ; $( $t )* }
+    );
+
+    (
+        let $p: ident : $ty: tt <- $e: expr ; timed $f: expr ; $( $t: tt )*
+    ) => (
+        $crate::iter! { let $p : $ty <- $crate::__timed($e, $f) ; $( $t )* }
+    );
+
+    (
+        let mut $p: tt <- $e: expr ; unique ; $( $t: tt )*
+    ) => (
+        $crate::iter! { let mut $p <- $crate::__unique($e) ; $( $t )* }
+    );
+
+    (
+        let mut $p: ident : $ty: tt <- $e: expr ; unique ; $( $t: tt )*
+    ) => (
+        $crate::iter! { let mut $p : $ty <- $crate::__unique($e) ; $( $t )* }
+    );
+
+    (
+        let $p: tt <- $e: expr ; unique ; $( $t: tt )*
+    ) => (
+        $crate::iter! { let $p <- $crate::__unique($e) ; $( $t )* }
+    );
+
+    (
+        let $p: ident : $ty: tt <- $e: expr ; unique ; $( $t: tt )*
+    ) => (
+        $crate::iter! { let $p : $ty <- $crate::__unique($e) ; $( $t )* }
+    );
+
+    (
+        let mut $p: tt <- $e: expr ; skip_while $c: expr ; $( $t: tt )*
+    ) => (
+        $crate::iter! { @skip_while (mut $p) ($e) ($c) $( $t )* }
+    );
+
+    (
+        let mut $p: ident : $ty: tt <- $e: expr ; skip_while $c: expr ; $( $t: tt )*
+    ) => (
+        $crate::iter! { @skip_while (mut $p : $ty) ($e) ($c) $( $t )* }
+    );
+
+    (
+        let $p: tt <- $e: expr ; skip_while $c: expr ; $( $t: tt )*
+    ) => (
+        $crate::iter! { @skip_while ($p) ($e) ($c) $( $t )* }
+    );
+
+    (
+        let $p: ident : $ty: tt <- $e: expr ; skip_while $c: expr ; $( $t: tt )*
+    ) => (
+        $crate::iter! { @skip_while ($p : $ty) ($e) ($c) $( $t )* }
+    );
+
+    (
+        let mut $p: tt <- $e: expr ; while $( $t: tt )*
+    ) => (
+        $crate::iter! { @take_while (mut $p) ($e) [] $( $t )* }
+    );
+
+    (
+        let mut $p: ident : $ty: tt <- $e: expr ; while $( $t: tt )*
+    ) => (
+        $crate::iter! { @take_while (mut $p : $ty) ($e) [] $( $t )* }
+    );
+
+    (
+        let $p: tt <- $e: expr ; while $( $t: tt )*
+    ) => (
+        $crate::iter! { @take_while ($p) ($e) [] $( $t )* }
+    );
+
+    (
+        let $p: ident : $ty: tt <- $e: expr ; while $( $t: tt )*
+    ) => (
+        $crate::iter! { @take_while ($p : $ty) ($e) [] $( $t )* }
+    );
+
+    (
+        let mut $p: tt <- $e: expr ; $f: ident ( $( $a: tt )* ) $( $t: tt )*
+    ) => (
+        $crate::iter! { @tail param ($e) (mut $p) [] [ $f ( $( $a )* ) $( $t )* ] $f ( $( $a )* ) $( $t )* }
+    );
+
+    (
+        let mut $p: ident : $ty: tt <- $e: expr ; $f: ident ( $( $a: tt )* ) $( $t: tt )*
+    ) => (
+        $crate::iter! { @tail param ($e) (mut $p : $ty) [] [ $f ( $( $a )* ) $( $t )* ] $f ( $( $a )* ) $( $t )* }
+    );
+
+    (
+        let $p: tt <- $e: expr ; $f: ident ( $( $a: tt )* ) $( $t: tt )*
+    ) => (
+        $crate::iter! { @tail pattern ($e) ($p) [] [ $f ( $( $a )* ) $( $t )* ] $f ( $( $a )* ) $( $t )* }
+    );
+
+    (
+        let $p: ident : $ty: tt <- $e: expr ; $f: ident ( $( $a: tt )* ) $( $t: tt )*
+    ) => (
+        $crate::iter! { @tail param ($e) ($p : $ty) [] [ $f ( $( $a )* ) $( $t )* ] $f ( $( $a )* ) $( $t )* }
+    );
+
+    (
+        let mut $p: tt <- $e: expr ; inspect $f: expr ; $( $t: tt )*
+    ) => (
+        $crate::iter! { let mut $p <- ::core::iter::Iterator::inspect($crate::__into_iter!($e), $f) ; $( $t )* }
+    );
+
+    (
+        let mut $p: ident : $ty: tt <- $e: expr ; inspect $f: expr ; $( $t: tt )*
+    ) => (
+        $crate::iter! { let mut $p : $ty <- ::core::iter::Iterator::inspect($crate::__into_iter!($e), $f) ; $( $t )* }
+    );
+
+    (
+        let $p: tt <- $e: expr ; inspect $f: expr ; $( $t: tt )*
+    ) => (
+        $crate::iter! { let $p <- ::core::iter::Iterator::inspect($crate::__into_iter!($e), $f) ; $( $t )* }
+    );
+
+    (
+        let $p: ident : $ty: tt <- $e: expr ; inspect $f: expr ; $( $t: tt )*
+    ) => (
+        $crate::iter! { let $p : $ty <- ::core::iter::Iterator::inspect($crate::__into_iter!($e), $f) ; $( $t )* }
+    );
+
+    (
+        let mut $p: tt <- $e: expr ; take $n: expr ; $( $t: tt )*
+    ) => (
+        $crate::iter! { let mut $p <- ::core::iter::Iterator::take($crate::__into_iter!($e), $n) ; $( $t )* }
+    );
+
+    (
+        let mut $p: ident : $ty: tt <- $e: expr ; take $n: expr ; $( $t: tt )*
+    ) => (
+        $crate::iter! { let mut $p : $ty <- ::core::iter::Iterator::take($crate::__into_iter!($e), $n) ; $( $t )* }
+    );
+
+    (
+        let $p: tt <- $e: expr ; take $n: expr ; $( $t: tt )*
+    ) => (
+        $crate::iter! { let $p <- ::core::iter::Iterator::take($crate::__into_iter!($e), $n) ; $( $t )* }
+    );
+
+    (
+        let $p: ident : $ty: tt <- $e: expr ; take $n: expr ; $( $t: tt )*
+    ) => (
+        $crate::iter! { let $p : $ty <- ::core::iter::Iterator::take($crate::__into_iter!($e), $n) ; $( $t )* }
+    );
+
+    (
+        let mut $p: tt <- $e: expr ; skip $n: expr ; $( $t: tt )*
+    ) => (
+        $crate::iter! { let mut $p <- ::core::iter::Iterator::skip($crate::__into_iter!($e), $n) ; $( $t )* }
+    );
+
+    (
+        let mut $p: ident : $ty: tt <- $e: expr ; skip $n: expr ; $( $t: tt )*
+    ) => (
+        $crate::iter! { let mut $p : $ty <- ::core::iter::Iterator::skip($crate::__into_iter!($e), $n) ; $( $t )* }
+    );
+
+    (
+        let $p: tt <- $e: expr ; skip $n: expr ; $( $t: tt )*
+    ) => (
+        $crate::iter! { let $p <- ::core::iter::Iterator::skip($crate::__into_iter!($e), $n) ; $( $t )* }
+    );
+
+    (
+        let $p: ident : $ty: tt <- $e: expr ; skip $n: expr ; $( $t: tt )*
+    ) => (
+        $crate::iter! { let $p : $ty <- ::core::iter::Iterator::skip($crate::__into_iter!($e), $n) ; $( $t )* }
+    );
+
+    (
+        let mut $p: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::iter! { @tail param ($e) (mut $p) [] [ $( $t )* ] $( $t )* }
+    );
+
+    (
+        let mut $p: ident : $ty: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::iter! { @tail param ($e) (mut $p : $ty) [] [ $( $t )* ] $( $t )* }
+    );
+
+    (
+        let true <- $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::iter! { @tail pattern ($e) (true) [] [ $( $t )* ] $( $t )* }
+    );
+
+    (
+        let false <- $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::iter! { @tail pattern ($e) (false) [] [ $( $t )* ] $( $t )* }
+    );
+
+    (
+        let $p: ident <- $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::iter! { @tail name ($e) ($p) [] [ $( $t )* ] $( $t )* }
+    );
+
+    (
+        let $p: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::iter! { @tail pattern ($e) ($p) [] [ $( $t )* ] $( $t )* }
+    );
+
+    (
+        let $p: tt ( $( $para: tt )* ) <- $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::iter! { @tail pattern ($e) ($p ( $( $para )* )) [] [ $( $t )* ] $( $t )* }
+    );
+
+    (
+        let $p: tt { $( $para: tt )* } <- $e: expr; $( $t: tt )*
+    ) => (
+        $crate::iter! { @tail pattern ($e) ($p { $( $para )* }) [] [ $( $t )* ] $( $t )* }
+    );
+
+    (
+        let $p: ident : $ty: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::iter! { @tail param ($e) ($p : $ty) [] [ $( $t )* ] $( $t )* }
+    );
+
+    (
+        let $( $p: ident )+ : $( $t: tt )+
+    ) => (
+        $crate::__pat! { @ty iter [] [ $( $p )+ ] [] $( $t )* }
+    );
+
+    (
+        let mut $p: tt <- $( $t: tt )+
+    ) => (
+        $crate::iter! { @let_alt ( mut $p ) [] $( $t )* }
+    );
+
+    (
+        let $p: tt <- $( $t: tt )+
+    ) => (
+        $crate::iter! { @let_alt ( $p ) [] $( $t )* }
+    );
+
+    (
+        let mut $p: tt = $e: expr
+    ) => (
+        ::core::compile_error!("expected `<-` after the pattern to bind, or `;` and the value to yield after `let`")
+    );
+
+    (
+        let $p: tt = $e: expr
+    ) => (
+        ::core::compile_error!("expected `<-` after the pattern to bind, or `;` and the value to yield after `let`")
+    );
+
+    (
+        continue ; $( $t: tt )*
+    ) => (
+        ::core::iter::empty()
+    );
+
+    (
+        skip ; $( $t: tt )*
+    ) => (
+        ::core::iter::empty()
+    );
+
+    (
+        yield from $e: expr ;
+    ) => (
+        $crate::__into_iter!($e)
+    );
+
+    (
+        yield from $e: expr ; $( $t: tt )+
+    ) => (
+        ::core::iter::Iterator::chain($crate::__into_iter!($e), $crate::iter! { $( $t )* })
+    );
+
+    (
+        yield $e: expr ;
+    ) => (
+        ::core::iter::once($e)
+    );
+
+    (
+        yield $e: expr ; $( $t: tt )+
+    ) => (
+        ::core::iter::Iterator::chain(::core::iter::once($e), $crate::iter! { $( $t )* })
+    );
+
+    (
+        for $p: pat in $( $t: tt )*
+    ) => (
+        $crate::iter! { @for ($p) [] $( $t )* }
+    );
+
+    (
+        if $( $t: tt )+
+    ) => (
+        $crate::iter! { @if [] $( $t )* }
+    );
+
+    (
+        $y: expr ; for $p: pat in $e: expr $( , $( $t: tt )* )?
+    ) => (
+        $crate::iter! { @inline ($y) [] for $p in $e $( , $( $t )* )? }
+    );
+
+    (
+        while $( $t: tt )+
+    ) => (
+        $crate::iter! { @while [] $( $t )* }
+    );
+
+    (
+        skip_while $c: expr ; $( $t: tt )*
+    ) => (
+        ::core::compile_error!("`skip_while condition;` must follow a binding in `iter!`")
+    );
+
+    (
+        timed $f: expr ; $( $t: tt )*
+    ) => (
+        ::core::compile_error!("`timed f;` must follow a binding")
+    );
+
+    (
+        unique ; $( $t: tt )*
+    ) => (
+        ::core::compile_error!("`unique;` must follow a binding in `iter!`")
+    );
+
+    (
+        join $q: tt in $( $t: tt )*
+    ) => (
+        ::core::compile_error!("`join` must follow a binding in `iter!`")
+    );
+
+    (
+        merge_join $q: tt in $( $t: tt )*
+    ) => (
+        ::core::compile_error!("`merge_join` must follow a binding in `iter!`")
+    );
+
+    (
+        guard $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::iter! { if $e ; $( $t )* }
+    );
+
+    (
+        unless $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::iter! { if !($e) ; $( $t )* }
+    );
+
+    (
+        $e: expr ; $( $t: tt )*
+    ) => (
+        { $e ; $crate::iter! { $( $t )* } }
+    );
+
+    (
+        inspect $f: expr ; $( $t: tt )*
+    ) => (
+        ::core::compile_error!("`inspect f;` must follow a binding")
+    );
+
+    (
+        take $n: expr ; $( $t: tt )*
+    ) => (
+        ::core::compile_error!("`take count;` must follow a binding in `iter!`")
+    );
+
+    (
+        skip $n: expr ; $( $t: tt )*
+    ) => (
+        ::core::compile_error!("`skip count;` must follow a binding in `iter!`")
+    );
+
+    (
+        $stmt: stmt ; $( $t: tt )*
+    ) => (
+        { $stmt $crate::iter! { $( $t )* } }
+    );
+
+    (
+        $e: expr
+    ) => (
+        ::core::iter::once($e)
+    );
+
+    (
+        $b: block ; $( $t: tt )*
+    ) => (
+        $b ; $crate::iter! { $( $t )* }
+    );    
+}
+
+/// syntax extension specific for Iterator, binding the sources by reference with `iter()`
+///
+/// See the module-level documentation for more details.
+#[macro_export]
+macro_rules! iter_ref {
+    (
+        $( $t: tt )*
+    ) => (
+        $crate::__bind_with! { @clause iter [] $( $t )* }
+    );
+}
+
+/// syntax extension running a comprehension right away, binding the sources by mutable
+/// reference with `iter_mut()` to modify them in place
+///
+/// See the module-level documentation for more details.
+#[macro_export]
+macro_rules! iter_mut {
+    (@reserve $acc: ident $n: expr) => ();
+
+    (@yield $acc: ident $e: expr) => ({
+        $e;
+    });
+
+    (
+        $( $t: tt )*
+    ) => (
+        $crate::__bind_with! { @clause iter_mut [] $( $t )* }
+    );
+}
+
+/// Rewrites the sources of the bindings of a comprehension into `(source).$m()`, for
+/// `iter_ref!` and `iter_mut!`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __bind_with {
+    (
+        @clause iter [ $( $a: tt )* ]
+    ) => (
+        $crate::iter! { $( $a )* }
+    );
+
+    (
+        @clause iter_mut [ $( $a: tt )* ]
+    ) => ({
+        let reserve = ::core::option::Option::Some(1);
+        $crate::__comp_loop! { iter_mut reserve reserve $( $a )* }
+    });
+
+    (
+        @clause $m: ident [ $( $a: tt )* ] let $( $t: tt )*
+    ) => (
+        $crate::__bind_with! { @let $m [ $( $a )* let ] $( $t )* }
+    );
+
+    (
+        @clause $m: ident [ $( $a: tt )* ] for $p: pat in $( $t: tt )*
+    ) => (
+        $crate::__bind_with! { @for $m [ $( $a )* for $p in ] [] $( $t )* }
+    );
+
+    (
+        @clause $m: ident [ $( $a: tt )* ] join $q: tt in $( $t: tt )*
+    ) => (
+        $crate::__bind_with! { @join $m [ $( $a )* join $q in ] [] $( $t )* }
+    );
+
+    (
+        @clause $m: ident [ $( $a: tt )* ] merge_join $q: tt in $( $t: tt )*
+    ) => (
+        $crate::__bind_with! { @join $m [ $( $a )* merge_join $q in ] [] $( $t )* }
+    );
+
+    (
+        @clause $m: ident [ $( $a: tt )* ] $x: tt $( $t: tt )*
+    ) => (
+        $crate::__bind_with! { @clause $m [ $( $a )* $x ] $( $t )* }
+    );
+
+    (
+        @let $m: ident [ $( $a: tt )* ] <- $( $t: tt )*
+    ) => (
+        $crate::__bind_with! { @src $m [ $( $a )* <- ] [] $( $t )* }
+    );
+
+    (
+        @let $m: ident [ $( $a: tt )* ] = $( $t: tt )*
+    ) => (
+        $crate::__bind_with! { @clause $m [ $( $a )* = ] $( $t )* }
+    );
+
+    (
+        @let $m: ident [ $( $a: tt )* ] $x: tt $( $t: tt )*
+    ) => (
+        $crate::__bind_with! { @let $m [ $( $a )* $x ] $( $t )* }
+    );
+
+    (
+        @src $m: ident [ $( $a: tt )* ] [] enumerate $( $t: tt )*
+    ) => (
+        $crate::__bind_with! { @src $m [ $( $a )* enumerate ] [] $( $t )* }
+    );
+
+    (
+        @src $m: ident [ $( $a: tt )* ] [] memo $( $t: tt )*
+    ) => (
+        $crate::__bind_with! { @src $m [ $( $a )* memo ] [] $( $t )* }
+    );
+
+    (
+        @src $m: ident [ $( $a: tt )* ] [] scan $args: tt over $( $t: tt )*
+    ) => (
+        $crate::__bind_with! { @src $m [ $( $a )* scan $args over ] [] $( $t )* }
+    );
+
+    (
+        @src $m: ident [ $( $a: tt )* ] [ $( $e: tt )+ ] or $( $t: tt )*
+    ) => (
+        $crate::__bind_with! { @src $m [ $( $a )* ( $( $e )+ ).$m() or ] [] $( $t )* }
+    );
+
+    (
+        @src $m: ident [ $( $a: tt )* ] [ $( $e: tt )+ ] zip $( $t: tt )*
+    ) => (
+        $crate::__bind_with! { @src $m [ $( $a )* ( $( $e )+ ).$m() zip ] [] $( $t )* }
+    );
+
+    (
+        @src $m: ident [ $( $a: tt )* ] [ $( $e: tt )+ ] , $( $t: tt )*
+    ) => (
+        $crate::__bind_with! { @clause $m [ $( $a )* ( $( $e )+ ).$m() , ] $( $t )* }
+    );
+
+    (
+        @src $m: ident [ $( $a: tt )* ] [ $( $e: tt )+ ] ; $( $t: tt )*
+    ) => (
+        $crate::__bind_with! { @clause $m [ $( $a )* ( $( $e )+ ).$m() ; ] $( $t )* }
+    );
+
+    (
+        @src $m: ident [ $( $a: tt )* ] [ $( $e: tt )* ] $x: tt $( $t: tt )*
+    ) => (
+        $crate::__bind_with! { @src $m [ $( $a )* ] [ $( $e )* $x ] $( $t )* }
+    );
+
+    (
+        @for $m: ident [ $( $a: tt )* ] [ $( $e: tt )* ] { $( $body: tt )* } $( $t: tt )*
+    ) => (
+        $crate::__bind_with! { @clause $m [ $( $a )* $( $e )* { $( $body )* } ] $( $t )* }
+    );
+
+    (
+        @for $m: ident [ $( $a: tt )* ] [ $( $e: tt )+ ] ; $( $t: tt )*
+    ) => (
+        $crate::__bind_with! { @clause $m [ $( $a )* ( $( $e )+ ).$m() ; ] $( $t )* }
+    );
+
+    (
+        @for $m: ident [ $( $a: tt )* ] [ $( $e: tt )* ] $x: tt $( $t: tt )*
+    ) => (
+        $crate::__bind_with! { @for $m [ $( $a )* ] [ $( $e )* $x ] $( $t )* }
+    );
+
+    (
+        @join $m: ident [ $( $a: tt )* ] [ $( $e: tt )+ ] on $( $t: tt )*
+    ) => (
+        $crate::__bind_with! { @clause $m [ $( $a )* ( $( $e )+ ).$m() on ] $( $t )* }
+    );
+
+    (
+        @join $m: ident [ $( $a: tt )* ] [ $( $e: tt )* ] $x: tt $( $t: tt )*
+    ) => (
+        $crate::__bind_with! { @join $m [ $( $a )* ] [ $( $e )* $x ] $( $t )* }
+    );
+}
+
+/// syntax extension mirroring Haskell's list comprehension, `[yield | qualifiers]`
+///
+/// See the module-level documentation for more details.
+#[macro_export]
+macro_rules! hs_iter {
+    (
+        @quals [ $( $y: tt )* ] [ $( $c: tt )* ] []
+    ) => (
+        $crate::iter! { $( $c )* $( $y )* }
+    );
+
+    (
+        @quals $y: tt [ $( $c: tt )* ] [ $( $q: tt )+ ]
+    ) => (
+        $crate::hs_iter!(@qual $y [ $( $c )* ] [ $( $q )+ ] [])
+    );
+
+    (
+        @quals $y: tt [ $( $c: tt )* ] [ $( $q: tt )* ] , $( $t: tt )*
+    ) => (
+        $crate::hs_iter!(@qual $y [ $( $c )* ] [ $( $q )* ] [ $( $t )* ])
+    );
+
+    (
+        @quals $y: tt [ $( $c: tt )* ] [ $( $q: tt )* ] $x: tt $( $t: tt )*
+    ) => (
+        $crate::hs_iter!(@quals $y [ $( $c )* ] [ $( $q )* $x ] $( $t )*)
+    );
+
+    (
+        @qual $y: tt [ $( $c: tt )* ] [] [ $( $t: tt )* ]
+    ) => (
+        $crate::hs_iter!(@quals $y [ $( $c )* ] [] $( $t )*)
+    );
+
+    (
+        @qual $y: tt [ $( $c: tt )* ] [ mut $p: tt <- $( $e: tt )+ ] [ $( $t: tt )* ]
+    ) => (
+        $crate::hs_iter!(@quals $y [ $( $c )* let mut $p <- $( $e )+ ; ] [] $( $t )*)
+    );
+
+    (
+        @qual $y: tt [ $( $c: tt )* ] [ $p: tt <- $( $e: tt )+ ] [ $( $t: tt )* ]
+    ) => (
+        $crate::hs_iter!(@quals $y [ $( $c )* let $p <- $( $e )+ ; ] [] $( $t )*)
+    );
+
+    (
+        @qual $y: tt [ $( $c: tt )* ] [ $p: tt ( $( $para: tt )* ) <- $( $e: tt )+ ] [ $( $t: tt )* ]
+    ) => (
+        $crate::hs_iter!(@quals $y [ $( $c )* let $p ( $( $para )* ) <- $( $e )+ ; ] [] $( $t )*)
+    );
+
+    (
+        @qual $y: tt [ $( $c: tt )* ] [ $p: tt { $( $para: tt )* } <- $( $e: tt )+ ] [ $( $t: tt )* ]
+    ) => (
+        $crate::hs_iter!(@quals $y [ $( $c )* let $p { $( $para )* } <- $( $e )+ ; ] [] $( $t )*)
+    );
+
+    (
+        @qual $y: tt [ $( $c: tt )* ] [ let $( $l: tt )+ ] [ $( $t: tt )* ]
+    ) => (
+        $crate::hs_iter!(@quals $y [ $( $c )* let $( $l )+ ; ] [] $( $t )*)
+    );
+
+    (
+        @qual $y: tt [ $( $c: tt )* ] [ $( $g: tt )+ ] [ $( $t: tt )* ]
+    ) => (
+        $crate::hs_iter!(@quals $y [ $( $c )* if $( $g )+ ; ] [] $( $t )*)
+    );
+
+    (
+        @head [ $( $y: tt )* ] | $( $t: tt )*
+    ) => (
+        $crate::hs_iter!(@quals [ $( $y )* ] [] [] $( $t )*)
+    );
+
+    (
+        @head [ $( $y: tt )* ] $x: tt $( $t: tt )*
+    ) => (
+        $crate::hs_iter!(@head [ $( $y )* $x ] $( $t )*)
+    );
+
+    (
+        $( $t: tt )*
+    ) => (
+        $crate::hs_iter!(@head [] $( $t )*)
+    );
+}
+
+/// syntax extension mirroring Scala's for-comprehension, `{ generators } yield expression`
+///
+/// See the module-level documentation for more details.
+#[macro_export]
+macro_rules! for_comp {
+    (
+        @split [ $( $y: tt )* ] [ $( $c: tt )* ] []
+    ) => (
+        $crate::iter! { $( $c )* $( $y )* }
+    );
+
+    (
+        @split $y: tt [ $( $c: tt )* ] [ $( $q: tt )+ ]
+    ) => (
+        $crate::for_comp!(@clause $y [ $( $c )* ] [ $( $q )+ ] [])
+    );
+
+    (
+        @split $y: tt [ $( $c: tt )* ] [ $( $q: tt )* ] ; $( $t: tt )*
+    ) => (
+        $crate::for_comp!(@clause $y [ $( $c )* ] [ $( $q )* ] [ $( $t )* ])
+    );
+
+    (
+        @split $y: tt [ $( $c: tt )* ] [ $( $q: tt )* ] $x: tt $( $t: tt )*
+    ) => (
+        $crate::for_comp!(@split $y [ $( $c )* ] [ $( $q )* $x ] $( $t )*)
+    );
+
+    (
+        @clause $y: tt [ $( $c: tt )* ] [  ] [ $( $t: tt )* ]
+    ) => (
+        $crate::for_comp!(@split $y [ $( $c )*  ] [] $( $t )*)
+    );
+
+    (
+        @clause $y: tt [ $( $c: tt )* ] [ if $( $g: tt )+ ] [ $( $t: tt )* ]
+    ) => (
+        $crate::for_comp!(@split $y [ $( $c )* if $( $g )+ ; ] [] $( $t )*)
+    );
+
+    (
+        @clause $y: tt [ $( $c: tt )* ] [ mut $p: tt <- $( $e: tt )+ ] [ $( $t: tt )* ]
+    ) => (
+        $crate::for_comp!(@split $y [ $( $c )* let mut $p <- $( $e )+ ; ] [] $( $t )*)
+    );
+
+    (
+        @clause $y: tt [ $( $c: tt )* ] [ $p: tt <- $( $e: tt )+ ] [ $( $t: tt )* ]
+    ) => (
+        $crate::for_comp!(@split $y [ $( $c )* let $p <- $( $e )+ ; ] [] $( $t )*)
+    );
+
+    (
+        @clause $y: tt [ $( $c: tt )* ] [ $p: tt ( $( $para: tt )* ) <- $( $e: tt )+ ] [ $( $t: tt )* ]
+    ) => (
+        $crate::for_comp!(@split $y [ $( $c )* let $p ( $( $para )* ) <- $( $e )+ ; ] [] $( $t )*)
+    );
+
+    (
+        @clause $y: tt [ $( $c: tt )* ] [ $p: tt { $( $para: tt )* } <- $( $e: tt )+ ] [ $( $t: tt )* ]
+    ) => (
+        $crate::for_comp!(@split $y [ $( $c )* let $p { $( $para )* } <- $( $e )+ ; ] [] $( $t )*)
+    );
+
+    (
+        @clause $y: tt [ $( $c: tt )* ] [ $p: tt = $( $e: tt )+ ] [ $( $t: tt )* ]
+    ) => (
+        $crate::for_comp!(@split $y [ $( $c )* let $p = $( $e )+ ; ] [] $( $t )*)
+    );
+
+    (
+        @clause $y: tt [ $( $c: tt )* ] [ $p: tt ( $( $para: tt )* ) = $( $e: tt )+ ] [ $( $t: tt )* ]
+    ) => (
+        $crate::for_comp!(@split $y [ $( $c )* let $p ( $( $para )* ) = $( $e )+ ; ] [] $( $t )*)
+    );
+
+    (
+        @clause $y: tt [ $( $c: tt )* ] [ $( $s: tt )+ ] [ $( $t: tt )* ]
+    ) => (
+        $crate::for_comp!(@split $y [ $( $c )* $( $s )+ ; ] [] $( $t )*)
+    );
+
+    (
+        { $( $b: tt )* } yield $( $y: tt )+
+    ) => (
+        $crate::for_comp!(@split [ $( $y )+ ] [] [] $( $b )*)
+    );
+}
+
+/// syntax extension specific for rayon's ParallelIterator
+///
+/// Requires the `rayon` feature.
+///
+/// See the module-level documentation for more details.
+#[cfg(feature = "rayon")]
+#[macro_export]
+macro_rules! par_iter {
+    (
+        let $( $b: ident )+ @ $( $t: tt )+
+    ) => (
+        $crate::__pat! { par_iter [] [ $( $b )+ @ ] $( $t )* }
+    );
+
+    (
+        let & $( $t: tt )+
+    ) => (
+        $crate::__pat! { par_iter [] [ & ] $( $t )* }
+    );
+
+    (
+        let ref $( $t: tt )+
+    ) => (
+        $crate::__pat! { par_iter [] [ ref ] $( $t )* }
+    );
+
+    (
+        let $( $a: ident )? :: $( $t: tt )+
+    ) => (
+        $crate::__pat! { par_iter [] [ $( $a )? :: ] $( $t )* }
+    );
+
+    (
+        ref $( $x: ident ),+ ; $( $t: tt )*
+    ) => ({
+        $( let $x = &$x; )+
+        $crate::par_iter! { $( $t )* }
+    });
+
+    (
+        @tail $src: tt ( $( $p: tt )* ) [] [ $( $all: tt )* ]
+    ) => (
+        $crate::__rayon::iter::ParallelIterator::map(
+            $crate::__rayon::iter::IntoParallelIterator::into_par_iter $src,
+            move | $( $p )* | ()
+        )
+    );
+
+    (
+        @tail $src: tt ( $( $p: tt )* ) [ $( $c: tt )* ] [ $( $all: tt )* ] if $cond: expr ; $( $t: tt )*
+    ) => (
+        $crate::par_iter!(@tail $src ( $( $p )* ) [ $( $c )* ($cond) ] [ $( $all )* ] $( $t )*)
+    );
+
+    (
+        @tail $src: tt ( $( $p: tt )* ) [ $( $c: tt )* ] [ $( $all: tt )* ] let $( $t: tt )*
+    ) => (
+        $crate::par_iter!(@flat_map $src ( $( $p )* ) [ $( $all )* ])
+    );
+
+    (
+        @tail $src: tt ( $( $p: tt )* ) [] [ $( $all: tt )* ] $y: expr
+    ) => (
+        $crate::__rayon::iter::ParallelIterator::map(
+            $crate::__rayon::iter::IntoParallelIterator::into_par_iter $src,
+            move | $( $p )* | $y
+        )
+    );
+
+    (
+        @tail $src: tt ( $( $p: tt )* ) [ $( ( $c: expr ) )+ ] [ $( $all: tt )* ] $y: expr
+    ) => (
+        $crate::__rayon::iter::ParallelIterator::filter_map(
+            $crate::__rayon::iter::IntoParallelIterator::into_par_iter $src,
+            move | $( $p )* | if $( $c )&&+ { ::core::option::Option::Some($y) } else { ::core::option::Option::None }
+        )
+    );
+
+    (
+        @tail $src: tt ( $( $p: tt )* ) [ $( $c: tt )* ] [ $( $all: tt )* ] $x: expr ; $( $t: tt )*
+    ) => (
+        $crate::par_iter!(@flat_map $src ( $( $p )* ) [ $( $all )* ])
+    );
+
+    (
+        @tail $src: tt ( $( $p: tt )* ) [ $( $c: tt )* ] [ $( $all: tt )* ] guard $cond: expr ; $( $t: tt )*
+    ) => (
+        $crate::par_iter!(@tail $src ( $( $p )* ) [ $( $c )* ($cond) ] [ $( $all )* ] $( $t )*)
+    );
+
+    (
+        @tail $src: tt ( $( $p: tt )* ) [ $( $c: tt )* ] [ $( $all: tt )* ] unless $cond: expr ; $( $t: tt )*
+    ) => (
+        $crate::par_iter!(@tail $src ( $( $p )* ) [ $( $c )* (!($cond)) ] [ $( $all )* ] $( $t )*)
+    );
+
+    (
+        @tail $src: tt ( $( $p: tt )* ) [ $( $c: tt )* ] [ $( $all: tt )* ] $( $t: tt )*
+    ) => (
+        $crate::par_iter!(@flat_map $src ( $( $p )* ) [ $( $all )* ])
+    );
+
+    (
+        @flat_map $src: tt ( $( $p: tt )* ) [ $( $t: tt )* ]
+    ) => (
+        $crate::__rayon::iter::ParallelIterator::flat_map(
+            $crate::__rayon::iter::IntoParallelIterator::into_par_iter $src,
+            move | $( $p )* | { $crate::par_iter! { $( $t )* } }
+        )
+    );
+
+    () => {
+        ::core::option::Option::Some(())
+    };
+
+    (
+        let mut $p: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::par_iter!(@tail ($e) (mut $p) [] [ $( $t )* ] $( $t )*)
+    );
+
+    (
+        let mut $p: ident : $ty: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::par_iter!(@tail ($e) (mut $p : $ty) [] [ $( $t )* ] $( $t )*)
+    );
+
+    (
+        let $p: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::par_iter!(@tail ($e) ($p) [] [ $( $t )* ] $( $t )*)
+    );
+
+    (
+        let $p: tt ( $( $para: tt )* ) <- $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::par_iter!(@tail ($e) ($p ( $( $para )* )) [] [ $( $t )* ] $( $t )*)
+    );
+
+    (
+        let $p: tt { $( $para: tt )* } <- $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::par_iter!(@tail ($e) ($p { $( $para )* }) [] [ $( $t )* ] $( $t )*)
+    );
+
+    (
+        let $p: ident : $ty: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::par_iter!(@tail ($e) ($p : $ty) [] [ $( $t )* ] $( $t )*)
+    );
+
+    (
+        let $( $p: ident )+ : $( $t: tt )+
+    ) => (
+        $crate::__pat! { @ty par_iter [] [ $( $p )+ ] [] $( $t )* }
+    );
+
+    (
+        if $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::__rayon::iter::ParallelIterator::filter(
+            $crate::__rayon::iter::IntoParallelIterator::into_par_iter($crate::par_iter! { $( $t )* }),
+            move |_| $e
+        )
+    );
+
+    (
+        guard $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::par_iter! { if $e ; $( $t )* }
+    );
+
+    (
+        unless $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::par_iter! { if !($e) ; $( $t )* }
+    );
+
+    (
+        $e: expr ; $( $t: tt )*
+    ) => (
+        { $e ; $crate::par_iter! { $( $t )* } }
+    );
+
+    (
+        $stmt: stmt ; $( $t: tt )*
+    ) => (
+        { $stmt $crate::par_iter! { $( $t )* } }
+    );
+
+    (
+        $e: expr
+    ) => (
+        ::core::option::Option::Some($e)
+    );
+
+    (
+        $b: block ; $( $t: tt )*
+    ) => (
+        $b ; $crate::par_iter! { $( $t )* }
+    );
+}
+
+/// Loops over the clauses of a comprehension, and hands over every yield to `$sink`.
+///
+/// `$reserve` is the capacity to reserve in `$acc` at the first yield: the product of the exact
+/// lengths of the sources bound so far, or `None` once one of them isn't exact or a filter follows.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __comp_loop {
+    (@as_pat $p: pat) => ($p);
+
+    (
+        $sink: ident $acc: ident $reserve: ident let $( $b: ident )+ @ $( $t: tt )+
+    ) => (
+        $crate::__pat! { __comp_loop [ $sink $acc $reserve ] [ $( $b )+ @ ] $( $t )* }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let & $( $t: tt )+
+    ) => (
+        $crate::__pat! { __comp_loop [ $sink $acc $reserve ] [ & ] $( $t )* }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let ref $( $t: tt )+
+    ) => (
+        $crate::__pat! { __comp_loop [ $sink $acc $reserve ] [ ref ] $( $t )* }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let $( $a: ident )? :: $( $t: tt )+
+    ) => (
+        $crate::__pat! { __comp_loop [ $sink $acc $reserve ] [ $( $a )? :: ] $( $t )* }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident @for ($p: pat) [ $( $e: tt )* ] ; $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! { $sink $acc $reserve let $p <- $( $e )* ; $( $t )* }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident @for ($p: pat) [ $( $e: tt )* ] { $( $body: tt )* } ; $( $t: tt )*
+    ) => (
+        { for $p in $( $e )* { $( $body )* } $crate::__comp_loop! { $sink $acc $reserve $( $t )* } }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident @for ($p: pat) [ $( $e: tt )* ] { $( $body: tt )* } $( $t: tt )*
+    ) => (
+        { for $p in $( $e )* { $( $body )* } $crate::__comp_loop! { $sink $acc $reserve $( $t )* } }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident @for ($p: pat) [ $( $e: tt )* ] $x: tt $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! { $sink $acc $reserve @for ($p) [ $( $e )* $x ] $( $t )* }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident @skip_while $s: ident ($c: expr) $( $t: tt )*
+    ) => ({
+        let $reserve = $crate::__capacity($reserve, (0, ::core::option::Option::None));
+        if $s && $c {
+            continue;
+        }
+        $s = false;
+        $crate::__comp_loop! { $sink $acc $reserve $( $t )* }
+    });
+
+    (
+        $sink: ident $acc: ident $reserve: ident @while [ $( $c: tt )* ] ; $( $t: tt )*
+    ) => ({
+        let $reserve = $crate::__capacity($reserve, (0, ::core::option::Option::None));
+        if !($( $c )*) { break; }
+        $crate::__comp_loop! { $sink $acc $reserve $( $t )* }
+    });
+
+    (
+        $sink: ident $acc: ident $reserve: ident @while [ $( $c: tt )* ] { $( $b: tt )* } ; $( $t: tt )*
+    ) => (
+        { while $( $c )* { $( $b )* } $crate::__comp_loop! { $sink $acc $reserve $( $t )* } }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident @while [ $( $c: tt )* ] { $( $b: tt )* } $( $t: tt )*
+    ) => (
+        { while $( $c )* { $( $b )* } $crate::__comp_loop! { $sink $acc $reserve $( $t )* } }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident @while [ $( $c: tt )* ] $x: tt $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! { $sink $acc $reserve @while [ $( $c )* $x ] $( $t )* }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident
+    ) => ({
+        if let ::core::option::Option::Some(n) = $reserve {
+            $crate::$sink!(@reserve $acc n);
+        }
+        $crate::$sink!(@yield $acc ())
+    });
+
+    (
+        $sink: ident $acc: ident $reserve: ident
+        let mut $p: tt <- scan ( $init: expr , $f: expr ) over $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! {
+            $sink $acc $reserve
+            let mut $p <- ::core::iter::Iterator::scan($crate::__into_iter!($e), $init, $f) ; $( $t )*
+        }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident
+        let mut $p: tt <- scan ( $init: expr , $f: expr ) over $e: expr , $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! {
+            $sink $acc $reserve
+            let mut $p <- ::core::iter::Iterator::scan($crate::__into_iter!($e), $init, $f) , $( $t )*
+        }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident
+        let mut $p: ident : $ty: tt <- scan ( $init: expr , $f: expr ) over $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! {
+            $sink $acc $reserve
+            let mut $p : $ty <- ::core::iter::Iterator::scan($crate::__into_iter!($e), $init, $f) ; $( $t )*
+        }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident
+        let mut $p: ident : $ty: tt <- scan ( $init: expr , $f: expr ) over $e: expr , $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! {
+            $sink $acc $reserve
+            let mut $p : $ty <- ::core::iter::Iterator::scan($crate::__into_iter!($e), $init, $f) , $( $t )*
+        }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident
+        let $p: tt <- scan ( $init: expr , $f: expr ) over $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! {
+            $sink $acc $reserve
+            let $p <- ::core::iter::Iterator::scan($crate::__into_iter!($e), $init, $f) ; $( $t )*
+        }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident
+        let $p: tt <- scan ( $init: expr , $f: expr ) over $e: expr , $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! {
+            $sink $acc $reserve
+            let $p <- ::core::iter::Iterator::scan($crate::__into_iter!($e), $init, $f) , $( $t )*
+        }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident
+        let $p: ident : $ty: tt <- scan ( $init: expr , $f: expr ) over $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! {
+            $sink $acc $reserve
+            let $p : $ty <- ::core::iter::Iterator::scan($crate::__into_iter!($e), $init, $f) ; $( $t )*
+        }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident
+        let $p: ident : $ty: tt <- scan ( $init: expr , $f: expr ) over $e: expr , $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! {
+            $sink $acc $reserve
+            let $p : $ty <- ::core::iter::Iterator::scan($crate::__into_iter!($e), $init, $f) , $( $t )*
+        }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let mut $p: tt <- enumerate $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! {
+            $sink $acc $reserve let mut $p <- ::core::iter::Iterator::enumerate($crate::__into_iter!($e)) ; $( $t )*
+        }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let mut $p: tt <- enumerate $e: expr , $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! {
+            $sink $acc $reserve let mut $p <- ::core::iter::Iterator::enumerate($crate::__into_iter!($e)) , $( $t )*
+        }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let mut $p: ident : $ty: tt <- enumerate $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! {
+            $sink $acc $reserve let mut $p : $ty <- ::core::iter::Iterator::enumerate($crate::__into_iter!($e)) ; $( $t )*
+        }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let mut $p: ident : $ty: tt <- enumerate $e: expr , $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! {
+            $sink $acc $reserve let mut $p : $ty <- ::core::iter::Iterator::enumerate($crate::__into_iter!($e)) , $( $t )*
+        }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let $p: tt <- enumerate $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! {
+            $sink $acc $reserve let $p <- ::core::iter::Iterator::enumerate($crate::__into_iter!($e)) ; $( $t )*
+        }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let $p: tt <- enumerate $e: expr , $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! {
+            $sink $acc $reserve let $p <- ::core::iter::Iterator::enumerate($crate::__into_iter!($e)) , $( $t )*
+        }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let $p: ident : $ty: tt <- enumerate $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! {
+            $sink $acc $reserve let $p : $ty <- ::core::iter::Iterator::enumerate($crate::__into_iter!($e)) ; $( $t )*
+        }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let $p: ident : $ty: tt <- enumerate $e: expr , $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! {
+            $sink $acc $reserve let $p : $ty <- ::core::iter::Iterator::enumerate($crate::__into_iter!($e)) , $( $t )*
+        }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let mut $p: tt <- $e: expr , sorted_by_key ( $f: expr ) ; $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! { $sink $acc $reserve let mut $p <- $crate::__sorted_by_key($e, $f) ; $( $t )* }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let mut $p: ident : $ty: tt <- $e: expr , sorted_by_key ( $f: expr ) ; $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! { $sink $acc $reserve let mut $p : $ty <- $crate::__sorted_by_key($e, $f) ; $( $t )* }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let $p: tt <- $e: expr , sorted_by_key ( $f: expr ) ; $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! { $sink $acc $reserve let $p <- $crate::__sorted_by_key($e, $f) ; $( $t )* }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let $p: ident : $ty: tt <- $e: expr , sorted_by_key ( $f: expr ) ; $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! { $sink $acc $reserve let $p : $ty <- $crate::__sorted_by_key($e, $f) ; $( $t )* }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let mut $p: tt <- $e: expr , chunks $n: expr ; $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! { $sink $acc $reserve let mut $p <- $crate::__chunks($e, $n) ; $( $t )* }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let mut $p: ident : $ty: tt <- $e: expr , chunks $n: expr ; $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! { $sink $acc $reserve let mut $p : $ty <- $crate::__chunks($e, $n) ; $( $t )* }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let $p: tt <- $e: expr , chunks $n: expr ; $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! { $sink $acc $reserve let $p <- $crate::__chunks($e, $n) ; $( $t )* }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let $p: ident : $ty: tt <- $e: expr , chunks $n: expr ; $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! { $sink $acc $reserve let $p : $ty <- $crate::__chunks($e, $n) ; $( $t )* }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let mut $p: tt <- $e: expr , windows $n: tt ; $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! { $sink $acc $reserve let mut $p <- $crate::__windows!($e, $n) ; $( $t )* }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let mut $p: ident : $ty: tt <- $e: expr , windows $n: tt ; $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! { $sink $acc $reserve let mut $p : $ty <- $crate::__windows!($e, $n) ; $( $t )* }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let $p: tt <- $e: expr , windows $n: tt ; $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! { $sink $acc $reserve let $p <- $crate::__windows!($e, $n) ; $( $t )* }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let $p: ident : $ty: tt <- $e: expr , windows $n: tt ; $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! { $sink $acc $reserve let $p : $ty <- $crate::__windows!($e, $n) ; $( $t )* }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let mut $p: tt <- $e: expr , windows $n: expr ; $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! { $sink $acc $reserve let mut $p <- $crate::__windows!($e, $n) ; $( $t )* }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let mut $p: ident : $ty: tt <- $e: expr , windows $n: expr ; $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! { $sink $acc $reserve let mut $p : $ty <- $crate::__windows!($e, $n) ; $( $t )* }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let $p: tt <- $e: expr , windows $n: expr ; $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! { $sink $acc $reserve let $p <- $crate::__windows!($e, $n) ; $( $t )* }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let $p: ident : $ty: tt <- $e: expr , windows $n: expr ; $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! { $sink $acc $reserve let $p : $ty <- $crate::__windows!($e, $n) ; $( $t )* }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let mut $p: tt <- $e: expr , rev ; $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! {
+            $sink $acc $reserve let mut $p <- ::core::iter::Iterator::rev($crate::__into_iter!($e)) ; $( $t )*
+        }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let mut $p: ident : $ty: tt <- $e: expr , rev ; $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! {
+            $sink $acc $reserve let mut $p : $ty <- ::core::iter::Iterator::rev($crate::__into_iter!($e)) ; $( $t )*
+        }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let $p: tt <- $e: expr , rev ; $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! {
+            $sink $acc $reserve let $p <- ::core::iter::Iterator::rev($crate::__into_iter!($e)) ; $( $t )*
+        }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let $p: ident : $ty: tt <- $e: expr , rev ; $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! {
+            $sink $acc $reserve let $p : $ty <- ::core::iter::Iterator::rev($crate::__into_iter!($e)) ; $( $t )*
+        }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let mut $p: tt <- $e: expr , copied ; $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! {
+            $sink $acc $reserve let mut $p <- ::core::iter::Iterator::copied($crate::__into_iter!($e)) ; $( $t )*
+        }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let mut $p: ident : $ty: tt <- $e: expr , copied ; $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! {
+            $sink $acc $reserve let mut $p : $ty <- ::core::iter::Iterator::copied($crate::__into_iter!($e)) ; $( $t )*
+        }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let $p: tt <- $e: expr , copied ; $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! {
+            $sink $acc $reserve let $p <- ::core::iter::Iterator::copied($crate::__into_iter!($e)) ; $( $t )*
+        }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let $p: ident : $ty: tt <- $e: expr , copied ; $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! {
+            $sink $acc $reserve let $p : $ty <- ::core::iter::Iterator::copied($crate::__into_iter!($e)) ; $( $t )*
+        }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let mut $p: tt <- $e: expr , cloned ; $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! {
+            $sink $acc $reserve let mut $p <- ::core::iter::Iterator::cloned($crate::__into_iter!($e)) ; $( $t )*
+        }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let mut $p: ident : $ty: tt <- $e: expr , cloned ; $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! {
+            $sink $acc $reserve let mut $p : $ty <- ::core::iter::Iterator::cloned($crate::__into_iter!($e)) ; $( $t )*
+        }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let $p: tt <- $e: expr , cloned ; $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! {
+            $sink $acc $reserve let $p <- ::core::iter::Iterator::cloned($crate::__into_iter!($e)) ; $( $t )*
+        }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let $p: ident : $ty: tt <- $e: expr , cloned ; $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! {
+            $sink $acc $reserve let $p : $ty <- ::core::iter::Iterator::cloned($crate::__into_iter!($e)) ; $( $t )*
+        }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let mut $p: tt <- $e: expr , by_value ; $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! {
+            $sink $acc $reserve let mut $p <- $crate::__by_value($e) ; $( $t )*
+        }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let mut $p: ident : $ty: tt <- $e: expr , by_value ; $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! {
+            $sink $acc $reserve let mut $p : $ty <- $crate::__by_value($e) ; $( $t )*
+        }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let $p: tt <- $e: expr , by_value ; $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! {
+            $sink $acc $reserve let $p <- $crate::__by_value($e) ; $( $t )*
+        }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let $p: ident : $ty: tt <- $e: expr , by_value ; $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! {
+            $sink $acc $reserve let $p : $ty <- $crate::__by_value($e) ; $( $t )*
+        }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let mut $p: tt <- $e: expr , step_by $n: expr ; $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! {
+            $sink $acc $reserve let mut $p <- ::core::iter::Iterator::step_by($crate::__into_iter!($e), $n) ; $( $t )*
+        }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let mut $p: ident : $ty: tt <- $e: expr , step_by $n: expr ; $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! {
+            $sink $acc $reserve let mut $p : $ty <- ::core::iter::Iterator::step_by($crate::__into_iter!($e), $n) ; $( $t )*
+        }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let $p: tt <- $e: expr , step_by $n: expr ; $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! {
+            $sink $acc $reserve let $p <- ::core::iter::Iterator::step_by($crate::__into_iter!($e), $n) ; $( $t )*
+        }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let $p: ident : $ty: tt <- $e: expr , step_by $n: expr ; $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! {
+            $sink $acc $reserve let $p : $ty <- ::core::iter::Iterator::step_by($crate::__into_iter!($e), $n) ; $( $t )*
+        }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let mut $p: tt <- $e: expr ; timed $f: expr ; $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! { $sink $acc $reserve let mut $p <- $crate::__timed($e, $f) ; $( $t )* }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let mut $p: ident : $ty: tt <- $e: expr ; timed $f: expr ; $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! { $sink $acc $reserve let mut $p : $ty <- $crate::__timed($e, $f) ; $( $t )* }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let $p: tt <- $e: expr ; timed $f: expr ; $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! { $sink $acc $reserve let $p <- $crate::__timed($e, $f) ; $( $t )* }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let $p: ident : $ty: tt <- $e: expr ; timed $f: expr ; $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! { $sink $acc $reserve let $p : $ty <- $crate::__timed($e, $f) ; $( $t )* }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let mut $p: tt <- $e: expr ; unique ; $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! { $sink $acc $reserve let mut $p <- $crate::__unique($e) ; $( $t )* }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let mut $p: ident : $ty: tt <- $e: expr ; unique ; $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! { $sink $acc $reserve let mut $p : $ty <- $crate::__unique($e) ; $( $t )* }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let $p: tt <- $e: expr ; unique ; $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! { $sink $acc $reserve let $p <- $crate::__unique($e) ; $( $t )* }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let $p: ident : $ty: tt <- $e: expr ; unique ; $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! { $sink $acc $reserve let $p : $ty <- $crate::__unique($e) ; $( $t )* }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let mut $p: tt <- $e: expr ; skip_while $c: expr ; $( $t: tt )*
+    ) => ({
+        let mut skipping = true;
+        $crate::__comp_loop! { $sink $acc $reserve let mut $p <- $e ; @skip_while skipping ($c) $( $t )* }
+    });
+
+    (
+        $sink: ident $acc: ident $reserve: ident let mut $p: ident : $ty: tt <- $e: expr ; skip_while $c: expr ; $( $t: tt )*
+    ) => ({
+        let mut skipping = true;
+        $crate::__comp_loop! { $sink $acc $reserve let mut $p : $ty <- $e ; @skip_while skipping ($c) $( $t )* }
+    });
+
+    (
+        $sink: ident $acc: ident $reserve: ident let $p: tt <- $e: expr ; skip_while $c: expr ; $( $t: tt )*
+    ) => ({
+        let mut skipping = true;
+        $crate::__comp_loop! { $sink $acc $reserve let $p <- $e ; @skip_while skipping ($c) $( $t )* }
+    });
+
+    (
+        $sink: ident $acc: ident $reserve: ident let $p: ident : $ty: tt <- $e: expr ; skip_while $c: expr ; $( $t: tt )*
+    ) => ({
+        let mut skipping = true;
+        $crate::__comp_loop! { $sink $acc $reserve let $p : $ty <- $e ; @skip_while skipping ($c) $( $t )* }
+    });
+
+    (
+        $sink: ident $acc: ident $reserve: ident let mut $p: tt <- $e: expr ; $f: ident ( $( $a: tt )* ) $( $t: tt )*
+    ) => (
+        {
+            let mut iter = $crate::__into_iter!($e);
+            let $reserve = $crate::__capacity($reserve, ::core::iter::Iterator::size_hint(&iter));
+            while let ::core::option::Option::Some(v) = ::core::iter::Iterator::next(&mut iter) {
+                let $crate::__comp_loop! (@as_pat mut $p) = v;
+                $crate::__comp_loop! { $sink $acc $reserve $f ( $( $a )* ) $( $t )* }
+            }
+        }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let mut $p: ident : $ty: tt <- $e: expr ; $f: ident ( $( $a: tt )* ) $( $t: tt )*
+    ) => (
+        {
+            let mut iter = $crate::__into_iter!($e);
+            let $reserve = $crate::__capacity($reserve, ::core::iter::Iterator::size_hint(&iter));
+            while let ::core::option::Option::Some(v) = ::core::iter::Iterator::next(&mut iter) {
+                let mut $p : $ty = v;
+                $crate::__comp_loop! { $sink $acc $reserve $f ( $( $a )* ) $( $t )* }
+            }
+        }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let $p: tt <- $e: expr ; $f: ident ( $( $a: tt )* ) $( $t: tt )*
+    ) => (
+        {
+            let mut iter = $crate::__into_iter!($e);
+            let $reserve = $crate::__capacity($reserve, ::core::iter::Iterator::size_hint(&iter));
+            while let ::core::option::Option::Some(v) = ::core::iter::Iterator::next(&mut iter) {
+                let $crate::__comp_loop! (@as_pat $p) = ::core::convert::identity(v);
+                $crate::__comp_loop! { $sink $acc $reserve $f ( $( $a )* ) $( $t )* }
+            }
+        }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let $p: ident : $ty: tt <- $e: expr ; $f: ident ( $( $a: tt )* ) $( $t: tt )*
+    ) => (
+        {
+            let mut iter = $crate::__into_iter!($e);
+            let $reserve = $crate::__capacity($reserve, ::core::iter::Iterator::size_hint(&iter));
+            while let ::core::option::Option::Some(v) = ::core::iter::Iterator::next(&mut iter) {
+                let $p : $ty = v;
+                $crate::__comp_loop! { $sink $acc $reserve $f ( $( $a )* ) $( $t )* }
+            }
+        }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let mut $p: tt <- $e: expr ; inspect $f: expr ; $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! { $sink $acc $reserve let mut $p <- ::core::iter::Iterator::inspect($crate::__into_iter!($e), $f) ; $( $t )* }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let mut $p: ident : $ty: tt <- $e: expr ; inspect $f: expr ; $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! { $sink $acc $reserve let mut $p : $ty <- ::core::iter::Iterator::inspect($crate::__into_iter!($e), $f) ; $( $t )* }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let $p: tt <- $e: expr ; inspect $f: expr ; $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! { $sink $acc $reserve let $p <- ::core::iter::Iterator::inspect($crate::__into_iter!($e), $f) ; $( $t )* }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let $p: ident : $ty: tt <- $e: expr ; inspect $f: expr ; $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! { $sink $acc $reserve let $p : $ty <- ::core::iter::Iterator::inspect($crate::__into_iter!($e), $f) ; $( $t )* }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let mut $p: tt <- $e: expr ; take $n: expr ; $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! {
+            $sink $acc $reserve let mut $p <- ::core::iter::Iterator::take($crate::__into_iter!($e), $n) ; $( $t )*
+        }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let mut $p: ident : $ty: tt <- $e: expr ; take $n: expr ; $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! {
+            $sink $acc $reserve let mut $p : $ty <- ::core::iter::Iterator::take($crate::__into_iter!($e), $n) ; $( $t )*
+        }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let $p: tt <- $e: expr ; take $n: expr ; $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! {
+            $sink $acc $reserve let $p <- ::core::iter::Iterator::take($crate::__into_iter!($e), $n) ; $( $t )*
+        }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let $p: ident : $ty: tt <- $e: expr ; take $n: expr ; $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! {
+            $sink $acc $reserve let $p : $ty <- ::core::iter::Iterator::take($crate::__into_iter!($e), $n) ; $( $t )*
+        }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let mut $p: tt <- $e: expr ; skip $n: expr ; $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! {
+            $sink $acc $reserve let mut $p <- ::core::iter::Iterator::skip($crate::__into_iter!($e), $n) ; $( $t )*
+        }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let mut $p: ident : $ty: tt <- $e: expr ; skip $n: expr ; $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! {
+            $sink $acc $reserve let mut $p : $ty <- ::core::iter::Iterator::skip($crate::__into_iter!($e), $n) ; $( $t )*
+        }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let $p: tt <- $e: expr ; skip $n: expr ; $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! {
+            $sink $acc $reserve let $p <- ::core::iter::Iterator::skip($crate::__into_iter!($e), $n) ; $( $t )*
+        }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let $p: ident : $ty: tt <- $e: expr ; skip $n: expr ; $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! {
+            $sink $acc $reserve let $p : $ty <- ::core::iter::Iterator::skip($crate::__into_iter!($e), $n) ; $( $t )*
+        }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let mut $p: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        {
+            let mut iter = $crate::__into_iter!($e);
+            let $reserve = $crate::__capacity($reserve, ::core::iter::Iterator::size_hint(&iter));
+            while let ::core::option::Option::Some(v) = ::core::iter::Iterator::next(&mut iter) {
+                let $crate::__comp_loop! (@as_pat mut $p) = v;
+                $crate::__comp_loop! { $sink $acc $reserve $( $t )* }
+            }
+        }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let mut $p: ident : $ty: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        {
+            let mut iter = $crate::__into_iter!($e);
+            let $reserve = $crate::__capacity($reserve, ::core::iter::Iterator::size_hint(&iter));
+            while let ::core::option::Option::Some(v) = ::core::iter::Iterator::next(&mut iter) {
+                let mut $p : $ty = v;
+                $crate::__comp_loop! { $sink $acc $reserve $( $t )* }
+            }
+        }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let $p: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        {
+            let mut iter = $crate::__into_iter!($e);
+            let $reserve = $crate::__capacity($reserve, ::core::iter::Iterator::size_hint(&iter));
+            while let ::core::option::Option::Some(v) = ::core::iter::Iterator::next(&mut iter) {
+                let $crate::__comp_loop! (@as_pat $p) = ::core::convert::identity(v);
+                $crate::__comp_loop! { $sink $acc $reserve $( $t )* }
+            }
+        }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let $p: tt ( $( $para: tt )* ) <- $e: expr ; $( $t: tt )*
+    ) => (
+        {
+            let mut iter = $crate::__into_iter!($e);
+            let $reserve = $crate::__capacity($reserve, ::core::iter::Iterator::size_hint(&iter));
+            while let ::core::option::Option::Some(v) = ::core::iter::Iterator::next(&mut iter) {
+                let $crate::__comp_loop! (@as_pat $p ( $( $para )* ) ) = ::core::convert::identity(v);
+                $crate::__comp_loop! { $sink $acc $reserve $( $t )* }
+            }
+        }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let $p: tt { $( $para: tt )* } <- $e: expr ; $( $t: tt )*
+    ) => (
+        {
+            let mut iter = $crate::__into_iter!($e);
+            let $reserve = $crate::__capacity($reserve, ::core::iter::Iterator::size_hint(&iter));
+            while let ::core::option::Option::Some(v) = ::core::iter::Iterator::next(&mut iter) {
+                let $crate::__comp_loop! (@as_pat $p { $( $para )* } ) = ::core::convert::identity(v);
+                $crate::__comp_loop! { $sink $acc $reserve $( $t )* }
+            }
+        }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let $p: ident : $ty: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        {
+            let mut iter = $crate::__into_iter!($e);
+            let $reserve = $crate::__capacity($reserve, ::core::iter::Iterator::size_hint(&iter));
+            while let ::core::option::Option::Some(v) = ::core::iter::Iterator::next(&mut iter) {
+                let $p : $ty = v;
+                $crate::__comp_loop! { $sink $acc $reserve $( $t )* }
+            }
+        }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident let $( $p: ident )+ : $( $t: tt )+
+    ) => (
+        $crate::__pat! { @ty __comp_loop [ $sink $acc $reserve ] [ $( $p )+ ] [] $( $t )* }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident for $p: pat in $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! { $sink $acc $reserve @for ($p) [] $( $t )* }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident if $e: expr ; $( $t: tt )*
+    ) => ({
+        let $reserve = $crate::__capacity($reserve, (0, ::core::option::Option::None));
+        if $e { $crate::__comp_loop! { $sink $acc $reserve $( $t )* } }
+    });
+
+    (
+        $sink: ident $acc: ident $reserve: ident while $( $t: tt )+
+    ) => (
+        $crate::__comp_loop! { $sink $acc $reserve @while [] $( $t )* }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident skip_while $c: expr ; $( $t: tt )*
+    ) => (
+        ::core::compile_error!("`skip_while condition;` must follow a binding")
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident timed $f: expr ; $( $t: tt )*
+    ) => (
+        ::core::compile_error!("`timed f;` must follow a binding")
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident unique ; $( $t: tt )*
+    ) => (
+        ::core::compile_error!("`unique;` must follow a binding")
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident guard $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! { $sink $acc $reserve if $e ; $( $t )* }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident unless $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::__comp_loop! { $sink $acc $reserve if !($e) ; $( $t )* }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident $e: expr ; $( $t: tt )*
+    ) => (
+        { $e ; $crate::__comp_loop! { $sink $acc $reserve $( $t )* } }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident inspect $f: expr ; $( $t: tt )*
+    ) => (
+        ::core::compile_error!("`inspect f;` must follow a binding")
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident take $n: expr ; $( $t: tt )*
+    ) => (
+        ::core::compile_error!("`take count;` must follow a binding")
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident skip $n: expr ; $( $t: tt )*
+    ) => (
+        ::core::compile_error!("`skip count;` must follow a binding")
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident $stmt: stmt ; $( $t: tt )*
+    ) => (
+        { $stmt $crate::__comp_loop! { $sink $acc $reserve $( $t )* } }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident $b: block ; $( $t: tt )*
+    ) => (
+        { $b ; $crate::__comp_loop! { $sink $acc $reserve $( $t )* } }
+    );
+
+    (
+        $sink: ident $acc: ident $reserve: ident $( $t: tt )+
+    ) => ({
+        if let ::core::option::Option::Some(n) = $reserve {
+            $crate::$sink!(@reserve $acc n);
+        }
+        $crate::$sink!(@yield $acc $( $t )+)
+    });
+}
+
+/// Picks the sliding windows of `, windows size`: tuples for a literal size of 2 or 3, and `Vec`s
+/// otherwise.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __windows {
+    ($e: expr, 2) => ($crate::__pairs($e));
+    ($e: expr, 3) => ($crate::__triples($e));
+    ($e: expr, $n: expr) => ($crate::__windows($e, $n));
+}
+
+/// syntax extension collecting the yields of a comprehension into a Vec
+///
+/// Requires the `alloc` feature.
+///
+/// See the module-level documentation for more details.
+#[cfg(feature = "alloc")]
+#[macro_export]
+macro_rules! vec_comp {
+    (@reserve $vec: ident $n: expr) => ({
+        // The product of the lengths may not fit, and then the Vec grows as it goes.
+        if $vec.is_empty() {
+            let _ = $vec.try_reserve_exact($n);
+        }
+    });
+
+    (@yield $vec: ident $e: expr) => ({
+        $vec.push($e);
+    });
+
+    (
+        $( $t: tt )*
+    ) => ({
+        let mut vec = $crate::__alloc::vec::Vec::new();
+        let reserve = ::core::option::Option::Some(1);
+        $crate::__comp_loop! { vec_comp vec reserve $( $t )* }
+        vec
+    });
+}
+
+/// syntax extension collecting the `key => value` yields of a comprehension into a HashMap
+///
+/// Requires the `std` feature.
+///
+/// See the module-level documentation for more details.
+#[cfg(feature = "std")]
+#[macro_export]
+macro_rules! map_comp {
+    (@reserve $map: ident $n: expr) => ({
+        if $map.is_empty() {
+            let _ = $map.try_reserve($n);
+        }
+    });
+
+    (@yield $map: ident $k: expr => $v: expr) => ({
+        $map.insert($k, $v);
+    });
+
+    (
+        $( $t: tt )*
+    ) => ({
+        let mut map = $crate::__std::collections::HashMap::new();
+        let reserve = ::core::option::Option::Some(1);
+        $crate::__comp_loop! { map_comp map reserve $( $t )* }
+        map
+    });
+}
+
+/// syntax extension grouping the `key => value` yields of a comprehension by key into a
+/// HashMap of Vecs
+///
+/// Requires the `std` feature.
+///
+/// See the module-level documentation for more details.
+#[cfg(feature = "std")]
+#[macro_export]
+macro_rules! group_comp {
+    (@reserve $acc: ident $n: expr) => ();
+
+    (@yield $map: ident $k: expr => $v: expr) => ({
+        $map.entry($k).or_insert_with($crate::__std::vec::Vec::new).push($v);
+    });
+
+    (
+        $( $t: tt )*
+    ) => ({
+        let mut map = $crate::__std::collections::HashMap::new();
+        let reserve = ::core::option::Option::Some(1);
+        $crate::__comp_loop! { group_comp map reserve $( $t )* }
+        map
+    });
+}
+
+/// syntax extension collecting the yields of a comprehension into a HashSet
+///
+/// Requires the `std` feature.
+///
+/// See the module-level documentation for more details.
+#[cfg(feature = "std")]
+#[macro_export]
+macro_rules! set_comp {
+    (@reserve $set: ident $n: expr) => ({
+        if $set.is_empty() {
+            let _ = $set.try_reserve($n);
+        }
+    });
+
+    (@yield $set: ident $e: expr) => ({
+        $set.insert($e);
+    });
+
+    (
+        $( $t: tt )*
+    ) => ({
+        let mut set = $crate::__std::collections::HashSet::new();
+        let reserve = ::core::option::Option::Some(1);
+        $crate::__comp_loop! { set_comp set reserve $( $t )* }
+        set
+    });
+}
+
+/// syntax extension collecting the `key => value` yields of a comprehension into a BTreeMap
+///
+/// Requires the `alloc` feature.
+///
+/// See the module-level documentation for more details.
+#[cfg(feature = "alloc")]
+#[macro_export]
+macro_rules! btreemap_comp {
+    (@reserve $acc: ident $n: expr) => ();
+
+    (@yield $map: ident $k: expr => $v: expr) => ({
+        $map.insert($k, $v);
+    });
+
+    (
+        $( $t: tt )*
+    ) => ({
+        let mut map = $crate::__alloc::collections::BTreeMap::new();
+        let reserve = ::core::option::Option::Some(1);
+        $crate::__comp_loop! { btreemap_comp map reserve $( $t )* }
+        map
+    });
+}
+
+/// syntax extension collecting the yields of a comprehension into a BTreeSet
+///
+/// Requires the `alloc` feature.
+///
+/// See the module-level documentation for more details.
+#[cfg(feature = "alloc")]
+#[macro_export]
+macro_rules! btreeset_comp {
+    (@reserve $acc: ident $n: expr) => ();
+
+    (@yield $set: ident $e: expr) => ({
+        $set.insert($e);
+    });
+
+    (
+        $( $t: tt )*
+    ) => ({
+        let mut set = $crate::__alloc::collections::BTreeSet::new();
+        let reserve = ::core::option::Option::Some(1);
+        $crate::__comp_loop! { btreeset_comp set reserve $( $t )* }
+        set
+    });
+}
+
+/// syntax extension concatenating the displayed yields of a comprehension into a String
+///
+/// Requires the `alloc` feature.
+///
+/// See the module-level documentation for more details.
+#[cfg(feature = "alloc")]
+#[macro_export]
+macro_rules! string {
+    (@reserve $acc: ident $n: expr) => ();
+
+    (@yield $acc: ident $e: expr) => ({
+        if !$acc.2 {
+            $acc.0.push_str($acc.1);
+        }
+        $acc.2 = false;
+        let _ = ::core::fmt::Write::write_fmt(&mut $acc.0, ::core::format_args!("{}", $e));
+    });
+
+    (
+        sep $sep: expr ; $( $t: tt )*
+    ) => ({
+        let mut acc = ($crate::__alloc::string::String::new(), $sep, true);
+        let reserve = ::core::option::Option::Some(1);
+        $crate::__comp_loop! { string acc reserve $( $t )* }
+        acc.0
+    });
+
+    (
+        $( $t: tt )*
+    ) => (
+        $crate::string! { sep ""; $( $t )* }
+    );
+}
+
+/// syntax extension collecting the yields of a comprehension into a fixed-size array
+///
+/// See the module-level documentation for more details.
+#[macro_export]
+macro_rules! array_comp {
+    (@reserve $acc: ident $n: expr) => ();
+
+    (@yield $acc: ident $e: expr) => ({
+        if $acc.1 < $acc.0.len() {
+            $acc.0[$acc.1] = ::core::option::Option::Some($e);
+        }
+        $acc.1 += 1;
+    });
+
+    (
+        $n: expr ; $( $t: tt )*
+    ) => ({
+        let mut acc: ([::core::option::Option<_>; $n], usize) = (::core::array::from_fn(|_| ::core::option::Option::None), 0);
+        let reserve = ::core::option::Option::Some(1);
+        $crate::__comp_loop! { array_comp acc reserve $( $t )* }
+        if acc.1 == $n {
+            ::core::option::Option::Some(acc.0.map(|v| match v {
+                ::core::option::Option::Some(v) => v,
+                ::core::option::Option::None => ::core::unreachable!(),
+            }))
+        } else {
+            ::core::option::Option::None
+        }
+    });
+}
+
+/// syntax extension for any type implementing `Bind` and `Pure`
+///
+/// See the module-level documentation for more details.
+#[macro_export]
+macro_rules! comp {
+    (@as_pat $p: pat) => ($p);
+
+    (
+        let $( $b: ident )+ @ $( $t: tt )+
+    ) => (
+        $crate::__pat! { comp [] [ $( $b )+ @ ] $( $t )* }
+    );
+
+    (
+        let & $( $t: tt )+
+    ) => (
+        $crate::__pat! { comp [] [ & ] $( $t )* }
+    );
+
+    (
+        let ref $( $t: tt )+
+    ) => (
+        $crate::__pat! { comp [] [ ref ] $( $t )* }
+    );
+
+    (
+        let $( $a: ident )? :: $( $t: tt )+
+    ) => (
+        $crate::__pat! { comp [] [ $( $a )? :: ] $( $t )* }
+    );
+
+    (
+        ref $( $x: ident ),+ ; $( $t: tt )*
+    ) => ({
+        $( let $x = &$x; )+
+        $crate::comp! { $( $t )* }
+    });
+
+    () => {
+        $crate::Pure::pure(())
+    };
+
+    (
+        let mut $p: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::Bind::bind($e, move | $crate::comp! (@as_pat mut $p) | { $crate::comp! { $( $t )* } } )
+    );
+
+    (
+        let mut $p: ident : $ty: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::Bind::bind($e, move | mut $p : $ty | { $crate::comp! { $( $t )* } } )
+    );
+
+    (
+        let $p: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::Bind::bind($e, move | $crate::comp! (@as_pat $p) | { $crate::comp! { $( $t )* } } )
+    );
+
+    (
+        let $p: tt ( $( $para: tt )* ) <- $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::Bind::bind($e, move | $crate::comp! (@as_pat $p ( $( $para )* ) ) | { $crate::comp! { $( $t )* } } )
+    );
+
+    (
+        let $p: tt { $( $para: tt )* } <- $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::Bind::bind($e, move | $crate::comp! (@as_pat $p { $( $para )* } ) | { $crate::comp! { $( $t )* } } )
+    );
+
+    (
+        let $p: ident : $ty: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::Bind::bind($e, move | $p : $ty | { $crate::comp! { $( $t )* } } )
+    );
+
+    (
+        let $( $p: ident )+ : $( $t: tt )+
+    ) => (
+        $crate::__pat! { @ty comp [] [ $( $p )+ ] [] $( $t )* }
+    );
+
+    (
+        $e: expr ; $( $t: tt )*
+    ) => (
+        { $e ; $crate::comp! { $( $t )* } }
+    );
+
+    (
+        $stmt: stmt ; $( $t: tt )*
+    ) => (
+        { $stmt $crate::comp! { $( $t )* } }
+    );
+
+    (
+        $e: expr
+    ) => (
+        $crate::Pure::pure($e)
+    );
+
+    (
+        $b: block ; $( $t: tt )*
+    ) => (
+        $b ; $crate::comp! { $( $t )* }
+    );
+}
+
+/// syntax extension specific for Future
+///
+/// Requires the `futures` feature.
+///
+/// See the module-level documentation for more details.
+#[cfg(feature = "futures")]
+#[macro_export]
+macro_rules! future {
+    (@as_pat $p: pat) => ($p);
+
+    (
+        let $( $b: ident )+ @ $( $t: tt )+
+    ) => (
+        $crate::__pat! { future [] [ $( $b )+ @ ] $( $t )* }
+    );
+
+    (
+        let & $( $t: tt )+
+    ) => (
+        $crate::__pat! { future [] [ & ] $( $t )* }
+    );
+
+    (
+        let ref $( $t: tt )+
+    ) => (
+        $crate::__pat! { future [] [ ref ] $( $t )* }
+    );
+
+    (
+        let $( $a: ident )? :: $( $t: tt )+
+    ) => (
+        $crate::__pat! { future [] [ $( $a )? :: ] $( $t )* }
+    );
+
+    (
+        ref $( $x: ident ),+ ; $( $t: tt )*
+    ) => ({
+        $( let $x = &$x; )+
+        $crate::future! { $( $t )* }
+    });
+
+    () => {
+        $crate::__futures::future::ready(())
+    };
+
+    (
+        send ; $( $t: tt )*
+    ) => (
+        $crate::__send($crate::future! { $( $t )* })
+    );
+
+    (
+        let mut $p: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::__futures::FutureExt::then($e, move | $crate::future! (@as_pat mut $p) | { $crate::future! { $( $t )* } } )
+    );
+
+    (
+        let mut $p: ident : $ty: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::__futures::FutureExt::then($e, move | mut $p : $ty | { $crate::future! { $( $t )* } } )
+    );
+
+    (
+        let $p: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::__futures::FutureExt::then($e, move | $crate::future! (@as_pat $p) | { $crate::future! { $( $t )* } } )
+    );
+
+    (
+        let $p: tt ( $( $para: tt )* ) <- $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::__futures::FutureExt::then($e, move | $crate::future! (@as_pat $p ( $( $para )* ) ) | { $crate::future! { $( $t )* } } )
+    );
+
+    (
+        let $p: tt { $( $para: tt )* } <- $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::__futures::FutureExt::then($e, move | $crate::future! (@as_pat $p { $( $para )* } ) | { $crate::future! { $( $t )* } } )
+    );
+
+    (
+        let $p: ident : $ty: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::__futures::FutureExt::then($e, move | $p : $ty | { $crate::future! { $( $t )* } } )
+    );
+
+    (
+        let $( $p: ident )+ : $( $t: tt )+
+    ) => (
+        $crate::__pat! { @ty future [] [ $( $p )+ ] [] $( $t )* }
+    );
+
+    (
+        $e: expr ; $( $t: tt )*
+    ) => (
+        { $e ; $crate::future! { $( $t )* } }
+    );
+
+    (
+        $stmt: stmt ; $( $t: tt )*
+    ) => (
+        { $stmt $crate::future! { $( $t )* } }
+    );
+
+    (
+        $e: expr
+    ) => (
+        $crate::__futures::future::ready($e)
+    );
+
+    (
+        $b: block ; $( $t: tt )*
+    ) => (
+        $b ; $crate::future! { $( $t )* }
+    );
+}
+
+/// syntax extension specific for TryFuture
+///
+/// Requires the `futures` feature.
+///
+/// See the module-level documentation for more details.
+#[cfg(feature = "futures")]
+#[macro_export]
+macro_rules! try_future {
+    (@as_pat $p: pat) => ($p);
+
+    (
+        let $( $b: ident )+ @ $( $t: tt )+
+    ) => (
+        $crate::__pat! { try_future [] [ $( $b )+ @ ] $( $t )* }
+    );
+
+    (
+        let & $( $t: tt )+
+    ) => (
+        $crate::__pat! { try_future [] [ & ] $( $t )* }
+    );
+
+    (
+        let ref $( $t: tt )+
+    ) => (
+        $crate::__pat! { try_future [] [ ref ] $( $t )* }
+    );
+
+    (
+        let $( $a: ident )? :: $( $t: tt )+
+    ) => (
+        $crate::__pat! { try_future [] [ $( $a )? :: ] $( $t )* }
+    );
+
+    (
+        ref $( $x: ident ),+ ; $( $t: tt )*
+    ) => ({
+        $( let $x = &$x; )+
+        $crate::try_future! { $( $t )* }
+    });
+
+    () => {
+        $crate::__futures::future::ok(())
+    };
+
+    (
+        send ; $( $t: tt )*
+    ) => (
+        $crate::__send($crate::try_future! { $( $t )* })
+    );
+
+    (
+        let mut $p: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::__futures::TryFutureExt::and_then($e, move | $crate::try_future! (@as_pat mut $p) | { $crate::try_future! { $( $t )* } } )
+    );
+
+    (
+        let mut $p: ident : $ty: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::__futures::TryFutureExt::and_then($e, move | mut $p : $ty | { $crate::try_future! { $( $t )* } } )
+    );
+
+    (
+        let $p: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::__futures::TryFutureExt::and_then($e, move | $crate::try_future! (@as_pat $p) | { $crate::try_future! { $( $t )* } } )
+    );
+
+    (
+        let $p: tt ( $( $para: tt )* ) <- $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::__futures::TryFutureExt::and_then($e, move | $crate::try_future! (@as_pat $p ( $( $para )* ) ) | { $crate::try_future! { $( $t )* } } )
+    );
+
+    (
+        let $p: tt { $( $para: tt )* } <- $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::__futures::TryFutureExt::and_then($e, move | $crate::try_future! (@as_pat $p { $( $para )* } ) | { $crate::try_future! { $( $t )* } } )
+    );
+
+    (
+        let $p: ident : $ty: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::__futures::TryFutureExt::and_then($e, move | $p : $ty | { $crate::try_future! { $( $t )* } } )
+    );
+
+    (
+        let $( $p: ident )+ : $( $t: tt )+
+    ) => (
+        $crate::__pat! { @ty try_future [] [ $( $p )+ ] [] $( $t )* }
+    );
+
+    (
+        $e: expr ; $( $t: tt )*
+    ) => (
+        { $e ; $crate::try_future! { $( $t )* } }
+    );
+
+    (
+        $stmt: stmt ; $( $t: tt )*
+    ) => (
+        { $stmt $crate::try_future! { $( $t )* } }
+    );
+
+    (
+        $e: expr
+    ) => (
+        $crate::__futures::future::ok($e)
+    );
+
+    (
+        $b: block ; $( $t: tt )*
+    ) => (
+        $b ; $crate::try_future! { $( $t )* }
+    );
+}
+
+/// syntax extension specific for Stream
+///
+/// Requires the `futures` feature.
+///
+/// See the module-level documentation for more details.
+#[cfg(feature = "futures")]
+#[macro_export]
+macro_rules! stream {
+    (@as_pat $p: pat) => ($p);
+
+    (
+        let $( $b: ident )+ @ $( $t: tt )+
+    ) => (
+        $crate::__pat! { stream [] [ $( $b )+ @ ] $( $t )* }
+    );
+
+    (
+        let & $( $t: tt )+
+    ) => (
+        $crate::__pat! { stream [] [ & ] $( $t )* }
+    );
+
+    (
+        let ref $( $t: tt )+
+    ) => (
+        $crate::__pat! { stream [] [ ref ] $( $t )* }
+    );
+
+    (
+        let $( $a: ident )? :: $( $t: tt )+
+    ) => (
+        $crate::__pat! { stream [] [ $( $a )? :: ] $( $t )* }
+    );
+
+    (
+        ref $( $x: ident ),+ ; $( $t: tt )*
+    ) => ({
+        $( let $x = &$x; )+
+        $crate::stream! { $( $t )* }
+    });
+
+    () => {
+        $crate::__futures::stream::once($crate::__futures::future::ready(()))
+    };
+
+    (
+        send ; $( $t: tt )*
+    ) => (
+        $crate::__send($crate::stream! { $( $t )* })
+    );
+
+    (
+        let mut $p: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::__futures::StreamExt::flat_map($e, move | $crate::stream! (@as_pat mut $p) | { $crate::stream! { $( $t )* } } )
+    );
+
+    (
+        let mut $p: ident : $ty: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::__futures::StreamExt::flat_map($e, move | mut $p : $ty | { $crate::stream! { $( $t )* } } )
+    );
+
+    (
+        let $p: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::__futures::StreamExt::flat_map($e, move | $crate::stream! (@as_pat $p) | { $crate::stream! { $( $t )* } } )
+    );
+
+    (
+        let $p: tt ( $( $para: tt )* ) <- $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::__futures::StreamExt::flat_map($e, move | $crate::stream! (@as_pat $p ( $( $para )* ) ) | { $crate::stream! { $( $t )* } } )
+    );
+
+    (
+        let $p: tt { $( $para: tt )* } <- $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::__futures::StreamExt::flat_map($e, move | $crate::stream! (@as_pat $p { $( $para )* } ) | { $crate::stream! { $( $t )* } } )
+    );
+
+    (
+        let $p: ident : $ty: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::__futures::StreamExt::flat_map($e, move | $p : $ty | { $crate::stream! { $( $t )* } } )
+    );
+
+    (
+        let $( $p: ident )+ : $( $t: tt )+
+    ) => (
+        $crate::__pat! { @ty stream [] [ $( $p )+ ] [] $( $t )* }
+    );
+
+    (
+        if $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::__futures::StreamExt::filter( $crate::stream! { $( $t )* }, move |_| $crate::__futures::future::ready($e))
+    );
+
+    (
+        guard $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::stream! { if $e ; $( $t )* }
+    );
+
+    (
+        unless $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::stream! { if !($e) ; $( $t )* }
+    );
+
+    (
+        $e: expr ; $( $t: tt )*
+    ) => (
+        { $e ; $crate::stream! { $( $t )* } }
+    );
+
+    (
+        $stmt: stmt ; $( $t: tt )*
+    ) => (
+        { $stmt $crate::stream! { $( $t )* } }
+    );
+
+    (
+        $e: expr
+    ) => (
+        $crate::__futures::stream::once($crate::__futures::future::ready($e))
+    );
+
+    (
+        $b: block ; $( $t: tt )*
+    ) => (
+        $b ; $crate::stream! { $( $t )* }
+    );
+}
+
+/// syntax extension specific for Option in async context
+///
+/// See the module-level documentation for more details.
+#[macro_export]
+macro_rules! async_option {
+    (@as_pat $p: pat) => ($p);
+
+    (
+        @body let $( $b: ident )+ @ $( $t: tt )+
+    ) => (
+        $crate::__pat! { async_option [ @body ] [ $( $b )+ @ ] $( $t )* }
+    );
+
+    (
+        @body let & $( $t: tt )+
+    ) => (
+        $crate::__pat! { async_option [ @body ] [ & ] $( $t )* }
+    );
+
+    (
+        @body let ref $( $t: tt )+
+    ) => (
+        $crate::__pat! { async_option [ @body ] [ ref ] $( $t )* }
+    );
+
+    (
+        @body let $( $a: ident )? :: $( $t: tt )+
+    ) => (
+        $crate::__pat! { async_option [ @body ] [ $( $a )? :: ] $( $t )* }
+    );
+
+    (
+        ref $( $x: ident ),+ ; $( $t: tt )*
+    ) => ({
+        $( let $x = &$x; )+
+        $crate::async_option! { $( $t )* }
+    });
+
+    (
+        @body
+    ) => (
+        ::core::option::Option::Some(())
+    );
+
+    (
+        @body let mut $p: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        { let $crate::async_option! (@as_pat mut $p) = match $e.await { ::core::option::Option::Some(v) => v, ::core::option::Option::None => return ::core::option::Option::None }; $crate::async_option! { @body $( $t )* } }
+    );
+
+    (
+        @body let mut $p: ident : $ty: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        { let mut $p : $ty = match $e.await { ::core::option::Option::Some(v) => v, ::core::option::Option::None => return ::core::option::Option::None }; $crate::async_option! { @body $( $t )* } }
+    );
+
+    (
+        @body let $p: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        { let $crate::async_option! (@as_pat $p) = match $e.await { ::core::option::Option::Some(v) => v, ::core::option::Option::None => return ::core::option::Option::None }; $crate::async_option! { @body $( $t )* } }
+    );
+
+    (
+        @body let $p: tt ( $( $para: tt )* ) <- $e: expr ; $( $t: tt )*
+    ) => (
+        { let $crate::async_option! (@as_pat $p ( $( $para )* ) ) = match $e.await { ::core::option::Option::Some(v) => v, ::core::option::Option::None => return ::core::option::Option::None }; $crate::async_option! { @body $( $t )* } }
+    );
+
+    (
+        @body let $p: tt { $( $para: tt )* } <- $e: expr ; $( $t: tt )*
+    ) => (
+        { let $crate::async_option! (@as_pat $p { $( $para )* } ) = match $e.await { ::core::option::Option::Some(v) => v, ::core::option::Option::None => return ::core::option::Option::None }; $crate::async_option! { @body $( $t )* } }
+    );
+
+    (
+        @body let $p: ident : $ty: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        { let $p : $ty = match $e.await { ::core::option::Option::Some(v) => v, ::core::option::Option::None => return ::core::option::Option::None }; $crate::async_option! { @body $( $t )* } }
+    );
+
+    (
+        @body let $( $p: ident )+ : $( $t: tt )+
+    ) => (
+        $crate::__pat! { @ty async_option [ @body ] [ $( $p )+ ] [] $( $t )* }
+    );
+
+    (
+        @body $e: expr ; $( $t: tt )*
+    ) => (
+        { $e ; $crate::async_option! { @body $( $t )* } }
+    );
+
+    (
+        @body $stmt: stmt ; $( $t: tt )*
+    ) => (
+        { $stmt $crate::async_option! { @body $( $t )* } }
+    );
+
+    (
+        @body $e: expr
+    ) => (
+        ::core::option::Option::Some($e)
+    );
+
+    (
+        @body $b: block ; $( $t: tt )*
+    ) => (
+        $b ; $crate::async_option! { @body $( $t )* }
+    );
+
+    (
+        send ; $( $t: tt )*
+    ) => (
+        $crate::__send($crate::async_option! { $( $t )* })
+    );
+
+    (
+        $( $t: tt )*
+    ) => (
+        async move { $crate::async_option! { @body $( $t )* } }
+    );
+}
+
+/// syntax extension specific for Result in async context
+///
+/// See the module-level documentation for more details.
+#[macro_export]
+macro_rules! async_result {
+    (@as_pat $p: pat) => ($p);
+
+    (
+        @body let $( $b: ident )+ @ $( $t: tt )+
+    ) => (
+        $crate::__pat! { async_result [ @body ] [ $( $b )+ @ ] $( $t )* }
+    );
+
+    (
+        @body let & $( $t: tt )+
+    ) => (
+        $crate::__pat! { async_result [ @body ] [ & ] $( $t )* }
+    );
+
+    (
+        @body let ref $( $t: tt )+
+    ) => (
+        $crate::__pat! { async_result [ @body ] [ ref ] $( $t )* }
+    );
+
+    (
+        @body let $( $a: ident )? :: $( $t: tt )+
+    ) => (
+        $crate::__pat! { async_result [ @body ] [ $( $a )? :: ] $( $t )* }
+    );
+
+    (
+        ref $( $x: ident ),+ ; $( $t: tt )*
+    ) => ({
+        $( let $x = &$x; )+
+        $crate::async_result! { $( $t )* }
+    });
+
+    (
+        @body
+    ) => (
+        ::core::result::Result::Ok(())
+    );
+
+    (
+        @body let mut $p: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        { let $crate::async_result! (@as_pat mut $p) = match $e.await { ::core::result::Result::Ok(v) => v, ::core::result::Result::Err(e) => return ::core::result::Result::Err(e) }; $crate::async_result! { @body $( $t )* } }
+    );
+
+    (
+        @body let mut $p: ident : $ty: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        { let mut $p : $ty = match $e.await { ::core::result::Result::Ok(v) => v, ::core::result::Result::Err(e) => return ::core::result::Result::Err(e) }; $crate::async_result! { @body $( $t )* } }
+    );
+
+    (
+        @body let $p: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        { let $crate::async_result! (@as_pat $p) = match $e.await { ::core::result::Result::Ok(v) => v, ::core::result::Result::Err(e) => return ::core::result::Result::Err(e) }; $crate::async_result! { @body $( $t )* } }
+    );
+
+    (
+        @body let $p: tt ( $( $para: tt )* ) <- $e: expr ; $( $t: tt )*
+    ) => (
+        { let $crate::async_result! (@as_pat $p ( $( $para )* ) ) = match $e.await { ::core::result::Result::Ok(v) => v, ::core::result::Result::Err(e) => return ::core::result::Result::Err(e) }; $crate::async_result! { @body $( $t )* } }
+    );
+
+    (
+        @body let $p: tt { $( $para: tt )* } <- $e: expr ; $( $t: tt )*
+    ) => (
+        { let $crate::async_result! (@as_pat $p { $( $para )* } ) = match $e.await { ::core::result::Result::Ok(v) => v, ::core::result::Result::Err(e) => return ::core::result::Result::Err(e) }; $crate::async_result! { @body $( $t )* } }
+    );
+
+    (
+        @body let $p: ident : $ty: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        { let $p : $ty = match $e.await { ::core::result::Result::Ok(v) => v, ::core::result::Result::Err(e) => return ::core::result::Result::Err(e) }; $crate::async_result! { @body $( $t )* } }
+    );
+
+    (
+        @body let $( $p: ident )+ : $( $t: tt )+
+    ) => (
+        $crate::__pat! { @ty async_result [ @body ] [ $( $p )+ ] [] $( $t )* }
+    );
+
+    (
+        @body $e: expr ; $( $t: tt )*
+    ) => (
+        { $e ; $crate::async_result! { @body $( $t )* } }
+    );
+
+    (
+        @body $stmt: stmt ; $( $t: tt )*
+    ) => (
+        { $stmt $crate::async_result! { @body $( $t )* } }
+    );
+
+    (
+        @body $e: expr
+    ) => (
+        ::core::result::Result::Ok($e)
+    );
+
+    (
+        @body $b: block ; $( $t: tt )*
+    ) => (
+        $b ; $crate::async_result! { @body $( $t )* }
+    );
+
+    (
+        send ; $( $t: tt )*
+    ) => (
+        $crate::__send($crate::async_result! { $( $t )* })
+    );
+
+    (
+        $( $t: tt )*
+    ) => (
+        async move { $crate::async_result! { @body $( $t )* } }
+    );
+}
+
+/// syntax extension specific for Either
+///
+/// Requires the `either` feature.
+///
+/// See the module-level documentation for more details.
+#[cfg(feature = "either")]
+#[macro_export]
+macro_rules! either {
+    (@as_pat $p: pat) => ($p);
+
+    (
+        let $( $b: ident )+ @ $( $t: tt )+
+    ) => (
+        $crate::__pat! { either [] [ $( $b )+ @ ] $( $t )* }
+    );
+
+    (
+        let & $( $t: tt )+
+    ) => (
+        $crate::__pat! { either [] [ & ] $( $t )* }
+    );
+
+    (
+        let ref $( $t: tt )+
+    ) => (
+        $crate::__pat! { either [] [ ref ] $( $t )* }
+    );
+
+    (
+        let $( $a: ident )? :: $( $t: tt )+
+    ) => (
+        $crate::__pat! { either [] [ $( $a )? :: ] $( $t )* }
+    );
+
+    (
+        ref $( $x: ident ),+ ; $( $t: tt )*
+    ) => ({
+        $( let $x = &$x; )+
+        $crate::either! { $( $t )* }
+    });
+
+    () => {
+        $crate::__either::Either::Right(())
+    };
+
+    (
+        let mut $p: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::__either::Either::right_and_then($e, move | $crate::either! (@as_pat mut $p) | { $crate::either! { $( $t )* } } )
+    );
+
+    (
+        let mut $p: ident : $ty: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::__either::Either::right_and_then($e, move | mut $p : $ty | { $crate::either! { $( $t )* } } )
+    );
+
+    (
+        let $p: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::__either::Either::right_and_then($e, move | $crate::either! (@as_pat $p) | { $crate::either! { $( $t )* } } )
+    );
+
+    (
+        let $p: tt ( $( $para: tt )* ) <- $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::__either::Either::right_and_then($e, move | $crate::either! (@as_pat $p ( $( $para )* ) ) | { $crate::either! { $( $t )* } } )
+    );
+
+    (
+        let $p: tt { $( $para: tt )* } <- $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::__either::Either::right_and_then($e, move | $crate::either! (@as_pat $p { $( $para )* } ) | { $crate::either! { $( $t )* } } )
+    );
+
+    (
+        let $p: ident : $ty: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::__either::Either::right_and_then($e, move | $p : $ty | { $crate::either! { $( $t )* } } )
+    );
+
+    (
+        let $( $p: ident )+ : $( $t: tt )+
+    ) => (
+        $crate::__pat! { @ty either [] [ $( $p )+ ] [] $( $t )* }
+    );
+
+    (
+        $e: expr ; $( $t: tt )*
+    ) => (
+        { $e ; $crate::either! { $( $t )* } }
+    );
+
+    (
+        $stmt: stmt ; $( $t: tt )*
+    ) => (
+        { $stmt $crate::either! { $( $t )* } }
+    );
+
+    (
+        $e: expr
+    ) => (
+        $crate::__either::Either::Right($e)
+    );
+
+    (
+        $b: block ; $( $t: tt )*
+    ) => (
+        $b ; $crate::either! { $( $t )* }
+    );
+}
+
+/// syntax extension specific for ControlFlow
+///
+/// See the module-level documentation for more details.
+#[macro_export]
+macro_rules! control_flow {
+    (@as_pat $p: pat) => ($p);
+
+    (
+        let $( $b: ident )+ @ $( $t: tt )+
+    ) => (
+        $crate::__pat! { control_flow [] [ $( $b )+ @ ] $( $t )* }
+    );
+
+    (
+        let & $( $t: tt )+
+    ) => (
+        $crate::__pat! { control_flow [] [ & ] $( $t )* }
+    );
+
+    (
+        let ref $( $t: tt )+
+    ) => (
+        $crate::__pat! { control_flow [] [ ref ] $( $t )* }
+    );
+
+    (
+        let $( $a: ident )? :: $( $t: tt )+
+    ) => (
+        $crate::__pat! { control_flow [] [ $( $a )? :: ] $( $t )* }
+    );
+
+    () => {
+        ::core::ops::ControlFlow::Continue(())
+    };
+
+    (
+        let mut $p: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        match $e {
+            ::core::ops::ControlFlow::Continue(v) => { let $crate::control_flow! (@as_pat mut $p) = v; $crate::control_flow! { $( $t )* } }
+            ::core::ops::ControlFlow::Break(b) => ::core::ops::ControlFlow::Break(b),
+        }
+    );
+
+    (
+        let mut $p: ident : $ty: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        match $e {
+            ::core::ops::ControlFlow::Continue(v) => { let mut $p : $ty = v; $crate::control_flow! { $( $t )* } }
+            ::core::ops::ControlFlow::Break(b) => ::core::ops::ControlFlow::Break(b),
+        }
+    );
+
+    (
+        let $p: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        match $e {
+            ::core::ops::ControlFlow::Continue(v) => { let $crate::control_flow! (@as_pat $p) = ::core::convert::identity(v); $crate::control_flow! { $( $t )* } }
+            ::core::ops::ControlFlow::Break(b) => ::core::ops::ControlFlow::Break(b),
+        }
+    );
+
+    (
+        let $p: tt ( $( $para: tt )* ) <- $e: expr ; $( $t: tt )*
+    ) => (
+        match $e {
+            ::core::ops::ControlFlow::Continue(v) => { let $crate::control_flow! (@as_pat $p ( $( $para )* ) ) = ::core::convert::identity(v); $crate::control_flow! { $( $t )* } }
+            ::core::ops::ControlFlow::Break(b) => ::core::ops::ControlFlow::Break(b),
+        }
+    );
+
+    (
+        let $p: tt { $( $para: tt )* } <- $e: expr ; $( $t: tt )*
+    ) => (
+        match $e {
+            ::core::ops::ControlFlow::Continue(v) => { let $crate::control_flow! (@as_pat $p { $( $para )* } ) = ::core::convert::identity(v); $crate::control_flow! { $( $t )* } }
+            ::core::ops::ControlFlow::Break(b) => ::core::ops::ControlFlow::Break(b),
+        }
+    );
+
+    (
+        let $p: ident : $ty: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        match $e {
+            ::core::ops::ControlFlow::Continue(v) => { let $p : $ty = v; $crate::control_flow! { $( $t )* } }
+            ::core::ops::ControlFlow::Break(b) => ::core::ops::ControlFlow::Break(b),
+        }
+    );
+
+    (
+        let $( $p: ident )+ : $( $t: tt )+
+    ) => (
+        $crate::__pat! { @ty control_flow [] [ $( $p )+ ] [] $( $t )* }
+    );
+
+    (
+        $e: expr ; $( $t: tt )*
+    ) => (
+        { $e ; $crate::control_flow! { $( $t )* } }
+    );
+
+    (
+        $stmt: stmt ; $( $t: tt )*
+    ) => (
+        { $stmt $crate::control_flow! { $( $t )* } }
+    );
+
+    (
+        $e: expr
+    ) => (
+        ::core::ops::ControlFlow::Continue($e)
+    );
+
+    (
+        $b: block ; $( $t: tt )*
+    ) => (
+        $b ; $crate::control_flow! { $( $t )* }
+    );
+}
+
+/// syntax extension specific for Poll
+///
+/// See the module-level documentation for more details.
+#[macro_export]
+macro_rules! poll {
+    (@as_pat $p: pat) => ($p);
+
+    (
+        let $( $b: ident )+ @ $( $t: tt )+
+    ) => (
+        $crate::__pat! { poll [] [ $( $b )+ @ ] $( $t )* }
+    );
+
+    (
+        let & $( $t: tt )+
+    ) => (
+        $crate::__pat! { poll [] [ & ] $( $t )* }
+    );
+
+    (
+        let ref $( $t: tt )+
+    ) => (
+        $crate::__pat! { poll [] [ ref ] $( $t )* }
+    );
+
+    (
+        let $( $a: ident )? :: $( $t: tt )+
+    ) => (
+        $crate::__pat! { poll [] [ $( $a )? :: ] $( $t )* }
+    );
+
+    () => {
+        ::core::task::Poll::Ready(())
+    };
+
+    (
+        let mut $p: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        match $e {
+            ::core::task::Poll::Ready(v) => { let $crate::poll! (@as_pat mut $p) = v; $crate::poll! { $( $t )* } }
+            ::core::task::Poll::Pending => ::core::task::Poll::Pending,
+        }
+    );
+
+    (
+        let mut $p: ident : $ty: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        match $e {
+            ::core::task::Poll::Ready(v) => { let mut $p : $ty = v; $crate::poll! { $( $t )* } }
+            ::core::task::Poll::Pending => ::core::task::Poll::Pending,
+        }
+    );
+
+    (
+        let $p: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        match $e {
+            ::core::task::Poll::Ready(v) => { let $crate::poll! (@as_pat $p) = ::core::convert::identity(v); $crate::poll! { $( $t )* } }
+            ::core::task::Poll::Pending => ::core::task::Poll::Pending,
+        }
+    );
+
+    (
+        let $p: tt ( $( $para: tt )* ) <- $e: expr ; $( $t: tt )*
+    ) => (
+        match $e {
+            ::core::task::Poll::Ready(v) => { let $crate::poll! (@as_pat $p ( $( $para )* ) ) = ::core::convert::identity(v); $crate::poll! { $( $t )* } }
+            ::core::task::Poll::Pending => ::core::task::Poll::Pending,
+        }
+    );
+
+    (
+        let $p: tt { $( $para: tt )* } <- $e: expr ; $( $t: tt )*
+    ) => (
+        match $e {
+            ::core::task::Poll::Ready(v) => { let $crate::poll! (@as_pat $p { $( $para )* } ) = ::core::convert::identity(v); $crate::poll! { $( $t )* } }
+            ::core::task::Poll::Pending => ::core::task::Poll::Pending,
+        }
+    );
+
+    (
+        let $p: ident : $ty: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        match $e {
+            ::core::task::Poll::Ready(v) => { let $p : $ty = v; $crate::poll! { $( $t )* } }
+            ::core::task::Poll::Pending => ::core::task::Poll::Pending,
+        }
+    );
+
+    (
+        let $( $p: ident )+ : $( $t: tt )+
+    ) => (
+        $crate::__pat! { @ty poll [] [ $( $p )+ ] [] $( $t )* }
+    );
+
+    (
+        $e: expr ; $( $t: tt )*
+    ) => (
+        { $e ; $crate::poll! { $( $t )* } }
+    );
+
+    (
+        $stmt: stmt ; $( $t: tt )*
+    ) => (
+        { $stmt $crate::poll! { $( $t )* } }
+    );
+
+    (
+        $e: expr
+    ) => (
+        ::core::task::Poll::Ready($e)
+    );
+
+    (
+        $b: block ; $( $t: tt )*
+    ) => (
+        $b ; $crate::poll! { $( $t )* }
+    );
+}
+
+/// syntax extension specific for `Poll<Option<T>>`, as returned by `Stream::poll_next`
+///
+/// See the module-level documentation for more details.
+#[macro_export]
+macro_rules! poll_next {
+    (@as_pat $p: pat) => ($p);
+
+    (
+        let $( $b: ident )+ @ $( $t: tt )+
+    ) => (
+        $crate::__pat! { poll_next [] [ $( $b )+ @ ] $( $t )* }
+    );
+
+    (
+        let & $( $t: tt )+
+    ) => (
+        $crate::__pat! { poll_next [] [ & ] $( $t )* }
+    );
+
+    (
+        let ref $( $t: tt )+
+    ) => (
+        $crate::__pat! { poll_next [] [ ref ] $( $t )* }
+    );
+
+    (
+        let $( $a: ident )? :: $( $t: tt )+
+    ) => (
+        $crate::__pat! { poll_next [] [ $( $a )? :: ] $( $t )* }
+    );
+
+    () => {
+        ::core::task::Poll::Ready(::core::option::Option::Some(()))
+    };
+
+    (
+        let mut $p: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        match $e {
+            ::core::task::Poll::Ready(::core::option::Option::Some(v)) => { let $crate::poll_next! (@as_pat mut $p) = v; $crate::poll_next! { $( $t )* } }
+            ::core::task::Poll::Ready(::core::option::Option::None) => {
+                ::core::task::Poll::Ready(::core::option::Option::None)
+            }
+            ::core::task::Poll::Pending => ::core::task::Poll::Pending,
+        }
+    );
+
+    (
+        let mut $p: ident : $ty: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        match $e {
+            ::core::task::Poll::Ready(::core::option::Option::Some(v)) => { let mut $p : $ty = v; $crate::poll_next! { $( $t )* } }
+            ::core::task::Poll::Ready(::core::option::Option::None) => {
+                ::core::task::Poll::Ready(::core::option::Option::None)
+            }
+            ::core::task::Poll::Pending => ::core::task::Poll::Pending,
+        }
+    );
+
+    (
+        let $p: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        match $e {
+            ::core::task::Poll::Ready(::core::option::Option::Some(v)) => { let $crate::poll_next! (@as_pat $p) = ::core::convert::identity(v); $crate::poll_next! { $( $t )* } }
+            ::core::task::Poll::Ready(::core::option::Option::None) => {
+                ::core::task::Poll::Ready(::core::option::Option::None)
+            }
+            ::core::task::Poll::Pending => ::core::task::Poll::Pending,
+        }
+    );
+
+    (
+        let $p: tt ( $( $para: tt )* ) <- $e: expr ; $( $t: tt )*
+    ) => (
+        match $e {
+            ::core::task::Poll::Ready(::core::option::Option::Some(v)) => { let $crate::poll_next! (@as_pat $p ( $( $para )* ) ) = ::core::convert::identity(v); $crate::poll_next! { $( $t )* } }
+            ::core::task::Poll::Ready(::core::option::Option::None) => {
+                ::core::task::Poll::Ready(::core::option::Option::None)
+            }
+            ::core::task::Poll::Pending => ::core::task::Poll::Pending,
+        }
+    );
+
+    (
+        let $p: tt { $( $para: tt )* } <- $e: expr ; $( $t: tt )*
+    ) => (
+        match $e {
+            ::core::task::Poll::Ready(::core::option::Option::Some(v)) => { let $crate::poll_next! (@as_pat $p { $( $para )* } ) = ::core::convert::identity(v); $crate::poll_next! { $( $t )* } }
+            ::core::task::Poll::Ready(::core::option::Option::None) => {
+                ::core::task::Poll::Ready(::core::option::Option::None)
+            }
+            ::core::task::Poll::Pending => ::core::task::Poll::Pending,
+        }
+    );
+
+    (
+        let $p: ident : $ty: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        match $e {
+            ::core::task::Poll::Ready(::core::option::Option::Some(v)) => { let $p : $ty = v; $crate::poll_next! { $( $t )* } }
+            ::core::task::Poll::Ready(::core::option::Option::None) => {
+                ::core::task::Poll::Ready(::core::option::Option::None)
+            }
+            ::core::task::Poll::Pending => ::core::task::Poll::Pending,
+        }
+    );
+
+    (
+        let $( $p: ident )+ : $( $t: tt )+
+    ) => (
+        $crate::__pat! { @ty poll_next [] [ $( $p )+ ] [] $( $t )* }
+    );
+
+    (
+        $e: expr ; $( $t: tt )*
+    ) => (
+        { $e ; $crate::poll_next! { $( $t )* } }
+    );
+
+    (
+        $stmt: stmt ; $( $t: tt )*
+    ) => (
+        { $stmt $crate::poll_next! { $( $t )* } }
+    );
+
+    (
+        $e: expr
+    ) => (
+        ::core::task::Poll::Ready(::core::option::Option::Some($e))
+    );
+
+    (
+        $b: block ; $( $t: tt )*
+    ) => (
+        $b ; $crate::poll_next! { $( $t )* }
+    );
+}
+
+/// syntax extension for `Validated`, accumulating all errors
+///
+/// See the module-level documentation for more details.
+#[macro_export]
+macro_rules! validated {
+    (@as_pat $p: pat) => ($p);
+
+    (
+        @bind [ $( $ps: tt )* ] [ $( $es: tt )* ] let $( $b: ident )+ @ $( $t: tt )+
+    ) => (
+        $crate::__pat! { validated [ @bind [ $( $ps )* ] [ $( $es )* ] ] [ $( $b )+ @ ] $( $t )* }
+    );
+
+    (
+        @bind [ $( $ps: tt )* ] [ $( $es: tt )* ] let & $( $t: tt )+
+    ) => (
+        $crate::__pat! { validated [ @bind [ $( $ps )* ] [ $( $es )* ] ] [ & ] $( $t )* }
+    );
+
+    (
+        @bind [ $( $ps: tt )* ] [ $( $es: tt )* ] let ref $( $t: tt )+
+    ) => (
+        $crate::__pat! { validated [ @bind [ $( $ps )* ] [ $( $es )* ] ] [ ref ] $( $t )* }
+    );
+
+    (
+        @bind [ $( $ps: tt )* ] [ $( $es: tt )* ] let $( $a: ident )? :: $( $t: tt )+
+    ) => (
+        $crate::__pat! { validated [ @bind [ $( $ps )* ] [ $( $es )* ] ] [ $( $a )? :: ] $( $t )* }
+    );
+
+    (@zip []) => (
+        $crate::Validated::Valid(())
+    );
+
+    (@zip [ ( $e: expr ) $( $rest: tt )* ]) => (
+        $crate::Validated::zip(::core::convert::Into::into($e), $crate::validated!(@zip [ $( $rest )* ]))
+    );
+
+    (@pat []) => (
+        ()
+    );
+
+    (@pat [ ( $( $p: tt )* ) $( $rest: tt )* ]) => (
+        ($crate::validated!(@as_pat $( $p )*), $crate::validated!(@pat [ $( $rest )* ]))
+    );
+
+    (
+        @bind [ $( $ps: tt )* ] [ $( $es: tt )* ] let mut $p: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::validated!(@bind [ $( $ps )* (mut $p) ] [ $( $es )* ($e) ] $( $t )*)
+    );
+
+    (
+        @bind [ $( $ps: tt )* ] [ $( $es: tt )* ] let mut $p: ident : $ty: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::validated!(@bind [ $( $ps )* (mut $p) ] [ $( $es )* ($crate::validated!(@typed $ty, $e)) ] $( $t )*)
+    );
+
+    (
+        @bind [ $( $ps: tt )* ] [ $( $es: tt )* ] let $p: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::validated!(@bind [ $( $ps )* ($p) ] [ $( $es )* ($e) ] $( $t )*)
+    );
+
+    (
+        @bind [ $( $ps: tt )* ] [ $( $es: tt )* ] let $p: tt ( $( $para: tt )* ) <- $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::validated!(@bind [ $( $ps )* ($p ( $( $para )* )) ] [ $( $es )* ($e) ] $( $t )*)
+    );
+
+    (
+        @bind [ $( $ps: tt )* ] [ $( $es: tt )* ] let $p: tt { $( $para: tt )* } <- $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::validated!(@bind [ $( $ps )* ($p { $( $para )* }) ] [ $( $es )* ($e) ] $( $t )*)
+    );
+
+    (
+        @bind [ $( $ps: tt )* ] [ $( $es: tt )* ] let $p: ident : $ty: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        $crate::validated!(@bind [ $( $ps )* ($p) ] [ $( $es )* ($crate::validated!(@typed $ty, $e)) ] $( $t )*)
+    );
+
+    (
+        @bind [ $( $ps: tt )* ] [ $( $es: tt )* ] let $( $p: ident )+ : $( $t: tt )+
+    ) => (
+        $crate::__pat! { @ty validated [ @bind [ $( $ps )* ] [ $( $es )* ] ] [ $( $p )+ ] [] $( $t )* }
+    );
+
+    (
+        @bind [ $( $ps: tt )* ] [ $( $es: tt )* ] $( $t: tt )*
+    ) => (
+        $crate::Validated::map(
+            $crate::validated!(@zip [ $( $es )* ]),
+            move | $crate::validated!(@pat [ $( $ps )* ]) | { $( $t )* }
+        )
+    );
+
+    (@typed $ty: tt, $e: expr) => ({
+        let v: $crate::Validated<$ty, _> = ::core::convert::Into::into($e);
+        v
+    });
+
+    (
+        $( $t: tt )*
+    ) => (
+        $crate::validated!(@bind [] [] $( $t )*)
+    );
+}
+
+/// syntax extension for computations writing to an accumulating log
+///
+/// See the module-level documentation for more details.
+#[macro_export]
+macro_rules! writer {
+    (@as_pat $p: pat) => ($p);
+
+    (
+        @body $log: ident let $( $b: ident )+ @ $( $t: tt )+
+    ) => (
+        $crate::__pat! { writer [ @body $log ] [ $( $b )+ @ ] $( $t )* }
+    );
+
+    (
+        @body $log: ident let & $( $t: tt )+
+    ) => (
+        $crate::__pat! { writer [ @body $log ] [ & ] $( $t )* }
+    );
+
+    (
+        @body $log: ident let ref $( $t: tt )+
+    ) => (
+        $crate::__pat! { writer [ @body $log ] [ ref ] $( $t )* }
+    );
+
+    (
+        @body $log: ident let $( $a: ident )? :: $( $t: tt )+
+    ) => (
+        $crate::__pat! { writer [ @body $log ] [ $( $a )? :: ] $( $t )* }
+    );
+
+    (
+        @body $log: ident
+    ) => (
+        ()
+    );
+
+    (
+        @body $log: ident let mut $p: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        {
+            let (v, w) = $e;
+            ::core::iter::Extend::extend(&mut $log, w);
+            let $crate::writer! (@as_pat mut $p) = v;
+            $crate::writer! { @body $log $( $t )* }
+        }
+    );
+
+    (
+        @body $log: ident let mut $p: ident : $ty: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        {
+            let (v, w) = $e;
+            ::core::iter::Extend::extend(&mut $log, w);
+            let mut $p : $ty = v;
+            $crate::writer! { @body $log $( $t )* }
+        }
+    );
+
+    (
+        @body $log: ident let $p: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        {
+            let (v, w) = $e;
+            ::core::iter::Extend::extend(&mut $log, w);
+            let $crate::writer! (@as_pat $p) = ::core::convert::identity(v);
+            $crate::writer! { @body $log $( $t )* }
+        }
+    );
+
+    (
+        @body $log: ident let $p: tt ( $( $para: tt )* ) <- $e: expr ; $( $t: tt )*
+    ) => (
+        {
+            let (v, w) = $e;
+            ::core::iter::Extend::extend(&mut $log, w);
+            let $crate::writer! (@as_pat $p ( $( $para )* ) ) = ::core::convert::identity(v);
+            $crate::writer! { @body $log $( $t )* }
+        }
+    );
+
+    (
+        @body $log: ident let $p: tt { $( $para: tt )* } <- $e: expr ; $( $t: tt )*
+    ) => (
+        {
+            let (v, w) = $e;
+            ::core::iter::Extend::extend(&mut $log, w);
+            let $crate::writer! (@as_pat $p { $( $para )* } ) = ::core::convert::identity(v);
+            $crate::writer! { @body $log $( $t )* }
+        }
+    );
+
+    (
+        @body $log: ident let $p: ident : $ty: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        {
+            let (v, w) = $e;
+            ::core::iter::Extend::extend(&mut $log, w);
+            let $p : $ty = v;
+            $crate::writer! { @body $log $( $t )* }
+        }
+    );
+
+    (
+        @body $log: ident let $( $p: ident )+ : $( $t: tt )+
+    ) => (
+        $crate::__pat! { @ty writer [ @body $log ] [ $( $p )+ ] [] $( $t )* }
+    );
+
+    (
+        @body $log: ident tell $e: expr ; $( $t: tt )*
+    ) => (
+        {
+            ::core::iter::Extend::extend(&mut $log, ::core::iter::once($e));
+            $crate::writer! { @body $log $( $t )* }
+        }
+    );
+
+    (
+        @body $log: ident $e: expr ; $( $t: tt )*
+    ) => (
+        { $e ; $crate::writer! { @body $log $( $t )* } }
+    );
+
+    (
+        @body $log: ident $stmt: stmt ; $( $t: tt )*
+    ) => (
+        { $stmt $crate::writer! { @body $log $( $t )* } }
+    );
+
+    (
+        @body $log: ident $e: expr
+    ) => (
+        $e
+    );
+
+    (
+        @body $log: ident $b: block ; $( $t: tt )*
+    ) => (
+        $b ; $crate::writer! { @body $log $( $t )* }
+    );
+
+    (
+        $( $t: tt )*
+    ) => (
+        {
+            let mut log = ::core::default::Default::default();
+            let value = $crate::writer! { @body log $( $t )* };
+            (value, log)
+        }
+    );
+}
+
+/// syntax extension for computations threading a state
+///
+/// See the module-level documentation for more details.
+#[macro_export]
+macro_rules! state {
+    (@as_pat $p: pat) => ($p);
+
+    (
+        @body $s: ident let $( $b: ident )+ @ $( $t: tt )+
+    ) => (
+        $crate::__pat! { state [ @body $s ] [ $( $b )+ @ ] $( $t )* }
+    );
+
+    (
+        @body $s: ident let & $( $t: tt )+
+    ) => (
+        $crate::__pat! { state [ @body $s ] [ & ] $( $t )* }
+    );
+
+    (
+        @body $s: ident let ref $( $t: tt )+
+    ) => (
+        $crate::__pat! { state [ @body $s ] [ ref ] $( $t )* }
+    );
+
+    (
+        @body $s: ident let $( $a: ident )? :: $( $t: tt )+
+    ) => (
+        $crate::__pat! { state [ @body $s ] [ $( $a )? :: ] $( $t )* }
+    );
+
+    (
+        @body $s: ident
+    ) => (
+        ((), $s)
+    );
+
+    (
+        @body $s: ident let mut $p: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        {
+            let (v, $s) = ($e)($s);
+            let $crate::state! (@as_pat mut $p) = v;
+            $crate::state! { @body $s $( $t )* }
+        }
+    );
+
+    (
+        @body $s: ident let mut $p: ident : $ty: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        {
+            let (v, $s) = ($e)($s);
+            let mut $p : $ty = v;
+            $crate::state! { @body $s $( $t )* }
+        }
+    );
+
+    (
+        @body $s: ident let $p: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        {
+            let (v, $s) = ($e)($s);
+            let $crate::state! (@as_pat $p) = ::core::convert::identity(v);
+            $crate::state! { @body $s $( $t )* }
+        }
+    );
+
+    (
+        @body $s: ident let $p: tt ( $( $para: tt )* ) <- $e: expr ; $( $t: tt )*
+    ) => (
+        {
+            let (v, $s) = ($e)($s);
+            let $crate::state! (@as_pat $p ( $( $para )* ) ) = ::core::convert::identity(v);
+            $crate::state! { @body $s $( $t )* }
+        }
+    );
+
+    (
+        @body $s: ident let $p: tt { $( $para: tt )* } <- $e: expr ; $( $t: tt )*
+    ) => (
+        {
+            let (v, $s) = ($e)($s);
+            let $crate::state! (@as_pat $p { $( $para )* } ) = ::core::convert::identity(v);
+            $crate::state! { @body $s $( $t )* }
+        }
+    );
+
+    (
+        @body $s: ident let $p: ident : $ty: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        {
+            let (v, $s) = ($e)($s);
+            let $p : $ty = v;
+            $crate::state! { @body $s $( $t )* }
+        }
+    );
+
+    (
+        @body $s: ident let $( $p: ident )+ : $( $t: tt )+
+    ) => (
+        $crate::__pat! { @ty state [ @body $s ] [ $( $p )+ ] [] $( $t )* }
+    );
+
+    (
+        @body $s: ident $e: expr ; $( $t: tt )*
+    ) => (
+        { $e ; $crate::state! { @body $s $( $t )* } }
+    );
+
+    (
+        @body $s: ident $stmt: stmt ; $( $t: tt )*
+    ) => (
+        { $stmt $crate::state! { @body $s $( $t )* } }
+    );
+
+    (
+        @body $s: ident $e: expr
+    ) => (
+        ($e, $s)
+    );
+
+    (
+        @body $s: ident $b: block ; $( $t: tt )*
+    ) => (
+        $b ; $crate::state! { @body $s $( $t )* }
+    );
+
+    (
+        $( $t: tt )*
+    ) => (
+        move |state| {
+            $crate::state! { @body state $( $t )* }
+        }
+    );
+}
+
+/// syntax extension for computations reading a shared environment
+///
+/// See the module-level documentation for more details.
+#[macro_export]
+macro_rules! reader {
+    (@as_pat $p: pat) => ($p);
+
+    (
+        @body $env: ident let $( $b: ident )+ @ $( $t: tt )+
+    ) => (
+        $crate::__pat! { reader [ @body $env ] [ $( $b )+ @ ] $( $t )* }
+    );
+
+    (
+        @body $env: ident let & $( $t: tt )+
+    ) => (
+        $crate::__pat! { reader [ @body $env ] [ & ] $( $t )* }
+    );
+
+    (
+        @body $env: ident let ref $( $t: tt )+
+    ) => (
+        $crate::__pat! { reader [ @body $env ] [ ref ] $( $t )* }
+    );
+
+    (
+        @body $env: ident let $( $a: ident )? :: $( $t: tt )+
+    ) => (
+        $crate::__pat! { reader [ @body $env ] [ $( $a )? :: ] $( $t )* }
+    );
+
+    (
+        @body $env: ident
+    ) => (
+        ()
+    );
+
+    (
+        @body $env: ident let mut $p: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        {
+            let $crate::reader! (@as_pat mut $p) = ($e)(&$env);
+            $crate::reader! { @body $env $( $t )* }
+        }
+    );
+
+    (
+        @body $env: ident let mut $p: ident : $ty: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        {
+            let mut $p : $ty = ($e)(&$env);
+            $crate::reader! { @body $env $( $t )* }
+        }
+    );
+
+    (
+        @body $env: ident let $p: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        {
+            let $crate::reader! (@as_pat $p) = ($e)(&$env);
+            $crate::reader! { @body $env $( $t )* }
+        }
+    );
+
+    (
+        @body $env: ident let $p: tt ( $( $para: tt )* ) <- $e: expr ; $( $t: tt )*
+    ) => (
+        {
+            let $crate::reader! (@as_pat $p ( $( $para )* ) ) = ($e)(&$env);
+            $crate::reader! { @body $env $( $t )* }
+        }
+    );
+
+    (
+        @body $env: ident let $p: tt { $( $para: tt )* } <- $e: expr ; $( $t: tt )*
+    ) => (
+        {
+            let $crate::reader! (@as_pat $p { $( $para )* } ) = ($e)(&$env);
+            $crate::reader! { @body $env $( $t )* }
+        }
+    );
+
+    (
+        @body $env: ident let $p: ident : $ty: tt <- $e: expr ; $( $t: tt )*
+    ) => (
+        {
+            let $p : $ty = ($e)(&$env);
+            $crate::reader! { @body $env $( $t )* }
+        }
+    );
+
+    (
+        @body $env: ident let $( $p: ident )+ : $( $t: tt )+
+    ) => (
+        $crate::__pat! { @ty reader [ @body $env ] [ $( $p )+ ] [] $( $t )* }
+    );
+
+    (
+        @body $env: ident $e: expr ; $( $t: tt )*
+    ) => (
+        { $e ; $crate::reader! { @body $env $( $t )* } }
+    );
+
+    (
+        @body $env: ident $stmt: stmt ; $( $t: tt )*
+    ) => (
+        { $stmt $crate::reader! { @body $env $( $t )* } }
+    );
+
+    (
+        @body $env: ident $e: expr
+    ) => (
+        $e
+    );
+
+    (
+        @body $env: ident $b: block ; $( $t: tt )*
+    ) => (
+        $b ; $crate::reader! { @body $env $( $t )* }
+    );
+
+    (
+        $( $t: tt )*
+    ) => (
+        move |env| {
+            $crate::reader! { @body env $( $t )* }
+        }
+    );
+}
+
+/// Wrapper types which can be bound by arrow statements in `comp!`.
+///
+/// `B` is the wrapper type yielded by the rest of the comprehension, e.g. `Option<U>`
+/// for `Option<T>`.
+pub trait Bind<B> {
+    /// The value bound to the pattern on the left of the arrow.
+    type Item;
+
+    /// Feeds the wrapped value into `f`, or short-circuits.
+    fn bind<F: FnOnce(Self::Item) -> B>(self, f: F) -> B;
+}
+
+/// Wrapper types which can be yielded from `comp!`.
+pub trait Pure {
+    /// The type of the yielded expression.
+    type Item;
+
+    /// Wraps a plain value.
+    fn pure(t: Self::Item) -> Self;
+}
+
+impl<T, U> Bind<Option<U>> for Option<T> {
+    type Item = T;
+
+    fn bind<F: FnOnce(T) -> Option<U>>(self, f: F) -> Option<U> {
+        self.and_then(f)
+    }
+}
+
+impl<T> Pure for Option<T> {
+    type Item = T;
+
+    fn pure(t: T) -> Self {
+        Some(t)
+    }
+}
+
+impl<T, U, E> Bind<Result<U, E>> for Result<T, E> {
+    type Item = T;
+
+    fn bind<F: FnOnce(T) -> Result<U, E>>(self, f: F) -> Result<U, E> {
+        self.and_then(f)
+    }
+}
+
+impl<T, E> Pure for Result<T, E> {
+    type Item = T;
+
+    fn pure(t: T) -> Self {
+        Ok(t)
+    }
+}
+
+/// Wrapper types which can be bound with `else` in `result!`.
+///
+/// `Result` falls back to the value given after `else`, while `Option` is lifted into a
+/// `Result` failing with it.
+#[doc(hidden)]
+pub trait Else<D, E> {
+    /// The value bound to the pattern on the left of the arrow.
+    type Item;
+
+    /// Unwraps the value, or calls `f` when it's missing.
+    fn bind_else<F: FnOnce() -> D>(self, f: F) -> Result<Self::Item, E>;
+}
+
+impl<T, E, F> Else<T, F> for Result<T, E> {
+    type Item = T;
+
+    fn bind_else<G: FnOnce() -> T>(self, f: G) -> Result<T, F> {
+        Ok(self.unwrap_or_else(|_| f()))
+    }
+}
+
+impl<T, E> Else<E, E> for Option<T> {
+    type Item = T;
+
+    fn bind_else<F: FnOnce() -> E>(self, f: F) -> Result<T, E> {
+        self.ok_or_else(f)
+    }
+}
+
+/// Runs the body of `result!` in a closure when `?` is used before its first binding, so that
+/// it short-circuits the comprehension rather than the enclosing function.
+#[doc(hidden)]
+#[inline]
+pub fn __scope<T, F: FnOnce() -> T>(f: F) -> T {
+    f()
+}
+
+/// Asserts that a comprehension is `Send`, for `send;`, or the source of a binding in it in
+/// `spanned::iter!`.
+#[doc(hidden)]
+#[inline]
+pub fn __send<T: Send>(t: T) -> T {
+    t
+}
+
+/// Buffers the source of a binding in `iter!` sorted by the key, for `, sorted_by_key(key)`.
+#[cfg(feature = "alloc")]
+#[doc(hidden)]
+pub fn __sorted_by_key<I, K, F>(iter: I, f: F) -> __alloc::vec::IntoIter<I::Item>
+where
+    I: IntoIterator,
+    K: Ord,
+    F: FnMut(&I::Item) -> K,
+{
+    let mut vec: __alloc::vec::Vec<_> = iter.into_iter().collect();
+    vec.sort_by_key(f);
+    vec.into_iter()
+}
+
+/// Pairs up the adjacent values of the source of a binding in `iter!`, for `, windows 2`.
+#[doc(hidden)]
+pub fn __pairs<I>(iter: I) -> impl core::iter::FusedIterator<Item = (I::Item, I::Item)>
+where
+    I: IntoIterator,
+    I::Item: Clone,
+{
+    let mut iter = iter.into_iter();
+    let mut prev = iter.next();
+    core::iter::from_fn(move || {
+        let cur = iter.next()?;
+        let prev = prev.replace(cur.clone())?;
+        Some((prev, cur))
+    })
+    .fuse()
+}
+
+/// Groups every three adjacent values of the source of a binding in `iter!`, for `, windows 3`.
+#[doc(hidden)]
+pub fn __triples<I>(
+    iter: I,
+) -> impl core::iter::FusedIterator<Item = (I::Item, I::Item, I::Item)>
+where
+    I: IntoIterator,
+    I::Item: Clone,
+{
+    let mut iter = iter.into_iter();
+    let mut first = iter.next();
+    let mut second = iter.next();
+    core::iter::from_fn(move || {
+        let third = iter.next()?;
+        let prev = first.take()?;
+        let cur = second.replace(third.clone())?;
+        first = Some(cur.clone());
+        Some((prev, cur, third))
+    })
+    .fuse()
+}
+
+/// Buffers the sliding windows of `size` values of the source of a binding in `iter!` into
+/// `Vec`s, for `, windows size`.
+#[cfg(feature = "alloc")]
+#[doc(hidden)]
+pub fn __windows<I>(
+    iter: I,
+    size: usize,
+) -> impl core::iter::FusedIterator<Item = __alloc::vec::Vec<I::Item>>
+where
+    I: IntoIterator,
+    I::Item: Clone,
+{
+    assert!(size != 0, "window size must be non-zero");
+    let mut iter = iter.into_iter();
+    let mut window = __alloc::collections::VecDeque::with_capacity(size);
+    core::iter::from_fn(move || {
+        if window.len() == size {
+            window.pop_front();
+        }
+        while window.len() < size {
+            window.push_back(iter.next()?);
+        }
+        Some(window.iter().cloned().collect())
+    })
+    .fuse()
+}
+
+/// Splits the source of a binding in `iter!` into `Vec`s of `size` values, for `, chunks size`.
+#[cfg(feature = "alloc")]
+#[doc(hidden)]
+pub fn __chunks<I: IntoIterator>(
+    iter: I,
+    size: usize,
+) -> impl core::iter::FusedIterator<Item = __alloc::vec::Vec<I::Item>> {
+    assert!(size != 0, "chunk size must be non-zero");
+    let mut iter = iter.into_iter();
+    core::iter::from_fn(move || {
+        let chunk: __alloc::vec::Vec<_> = iter.by_ref().take(size).collect();
+        if chunk.is_empty() {
+            None
+        } else {
+            Some(chunk)
+        }
+    })
+    .fuse()
+}
+
+/// Iterates the source of a binding in `iter!` by value, for `, by_value`. Unlike `__into_iter!`,
+/// the trait is resolved on the generic type, which takes arrays by value in any edition.
+#[doc(hidden)]
+#[inline]
+pub fn __by_value<I: IntoIterator>(source: I) -> I::IntoIter {
+    source.into_iter()
+}
+
+/// Filters out the values of the source of a binding in `iter!` which have been seen before,
+/// for `unique;`.
+#[cfg(feature = "std")]
+#[doc(hidden)]
+pub fn __unique<I>(iter: I) -> impl core::iter::FusedIterator<Item = I::Item>
+where
+    I: IntoIterator,
+    I::Item: Eq + core::hash::Hash + Clone,
+{
+    let mut seen = __std::collections::HashSet::new();
+    iter.into_iter().fuse().filter(move |item| seen.insert(item.clone()))
+}
+
+/// Multiplies the exact length of the source of a binding in `vec_comp!` and the other collecting
+/// macros into the capacity they reserve at their first yield.
+#[doc(hidden)]
+pub fn __capacity(capacity: Option<usize>, size_hint: (usize, Option<usize>)) -> Option<usize> {
+    match size_hint {
+        (lower, Some(upper)) if lower == upper => capacity?.checked_mul(lower),
+        _ => None,
+    }
+}
+
+/// Reports how long every pull from the source of a binding in `iter!` takes, for `timed f;`.
+#[cfg(feature = "std")]
+#[doc(hidden)]
+pub fn __timed<I, F>(iter: I, mut f: F) -> impl Iterator<Item = I::Item>
+where
+    I: IntoIterator,
+    F: FnMut(core::time::Duration),
+{
+    let mut iter = iter.into_iter();
+    core::iter::from_fn(move || {
+        let start = __std::time::Instant::now();
+        let item = iter.next();
+        f(start.elapsed());
+        item
+    })
+}
+
+/// Reports how long the bound expression of `option!` or `result!` took since `start`, for
+/// `timed f;`.
+#[cfg(feature = "std")]
+#[doc(hidden)]
+pub fn __timed_value<T, F>(start: __std::time::Instant, value: T, f: F) -> T
+where
+    F: FnOnce(core::time::Duration),
+{
+    f(start.elapsed());
+    value
+}
+
+/// The values of an inner `iter!` behind an If-Guard, which unlike flattening an `Option`
+/// forwards the exact size and the double-endedness of the comprehension.
+#[doc(hidden)]
+#[derive(Clone, Debug)]
+pub struct __Guard<I>(Option<I>);
+
+impl<I: Iterator> Iterator for __Guard<I> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        self.0.as_mut()?.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match &self.0 {
+            Some(iter) => iter.size_hint(),
+            None => (0, Some(0)),
+        }
+    }
+}
+
+impl<I: DoubleEndedIterator> DoubleEndedIterator for __Guard<I> {
+    fn next_back(&mut self) -> Option<I::Item> {
+        self.0.as_mut()?.next_back()
+    }
+}
+
+impl<I: ExactSizeIterator> ExactSizeIterator for __Guard<I> {}
+
+impl<I: core::iter::FusedIterator> core::iter::FusedIterator for __Guard<I> {}
+
+/// Wraps the comprehension after an If-Guard in `iter!`, which is `None` when it fails.
+#[doc(hidden)]
+pub fn __guard<I: IntoIterator>(iter: Option<I>) -> __Guard<I::IntoIter> {
+    __Guard(iter.map(IntoIterator::into_iter))
+}
+
+/// The values of the last binding of `iter!` to a single name, which may as well be a constant
+/// or a unit struct to match against. When `BINDING` tells the name is a new variable, it
+/// forwards the exact size of the source like `Map`, and otherwise it skips the values not
+/// matching like `FilterMap`.
+#[doc(hidden)]
+#[derive(Clone)]
+pub struct __Bind<I, F, const BINDING: bool> {
+    iter: I,
+    f: F,
+}
+
+impl<I: core::fmt::Debug, F, const BINDING: bool> core::fmt::Debug for __Bind<I, F, BINDING> {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.debug_struct("__Bind").field("iter", &self.iter).finish()
+    }
+}
+
+impl<B, I: Iterator, F, const BINDING: bool> Iterator for __Bind<I, F, BINDING>
+where
+    F: FnMut(I::Item) -> Option<B>,
+{
+    type Item = B;
+
+    fn next(&mut self) -> Option<B> {
+        self.iter.find_map(&mut self.f)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (lower, upper) = self.iter.size_hint();
+        (if BINDING { lower } else { 0 }, upper)
+    }
+}
+
+impl<B, I: DoubleEndedIterator, F, const BINDING: bool> DoubleEndedIterator for __Bind<I, F, BINDING>
+where
+    F: FnMut(I::Item) -> Option<B>,
+{
+    fn next_back(&mut self) -> Option<B> {
+        self.iter.by_ref().rev().find_map(&mut self.f)
+    }
+}
+
+impl<B, I: ExactSizeIterator, F> ExactSizeIterator for __Bind<I, F, true> where F: FnMut(I::Item) -> Option<B> {}
+
+impl<B, I: core::iter::FusedIterator, F, const BINDING: bool> core::iter::FusedIterator for __Bind<I, F, BINDING> where
+    F: FnMut(I::Item) -> Option<B>
+{
+}
+
+/// Binds the values of the last binding of `iter!` to a single name.
+#[doc(hidden)]
+pub fn __bind<B, I, F, const BINDING: bool>(iter: I, f: F) -> __Bind<I::IntoIter, F, BINDING>
+where
+    I: IntoIterator,
+    F: FnMut(I::Item) -> Option<B>,
+{
+    __Bind {
+        iter: iter.into_iter(),
+        f,
+    }
+}
+
+/// Whether a single name in a pattern is a new variable rather than a constant or a unit struct,
+/// judging by its case.
+#[doc(hidden)]
+pub const fn __is_binding(name: &str) -> bool {
+    let first = name.as_bytes()[0];
+    first == b'_' || first.is_ascii_lowercase()
+}
+
+/// The boxed comprehension of a struct from `spanned::iter_struct!` deriving `Clone`.
+#[cfg(feature = "alloc")]
+#[doc(hidden)]
+pub trait __CloneIter<'a>: Iterator + 'a {
+    fn __clone_box(&self) -> __alloc::boxed::Box<dyn __CloneIter<'a, Item = Self::Item> + 'a>;
+}
+
+#[cfg(feature = "alloc")]
+impl<'a, I: Iterator + Clone + 'a> __CloneIter<'a> for I {
+    fn __clone_box(&self) -> __alloc::boxed::Box<dyn __CloneIter<'a, Item = I::Item> + 'a> {
+        __alloc::boxed::Box::new(self.clone())
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<'a, T: 'a> Clone for __alloc::boxed::Box<dyn __CloneIter<'a, Item = T> + 'a> {
+    fn clone(&self) -> Self {
+        (**self).__clone_box()
+    }
+}
+
+/// The boxed comprehension of a struct from `spanned::iter_struct!` deriving `Debug`.
+#[cfg(feature = "alloc")]
+#[doc(hidden)]
+pub trait __DebugIter: Iterator + core::fmt::Debug {}
+
+#[cfg(feature = "alloc")]
+impl<I: Iterator + core::fmt::Debug> __DebugIter for I {}
+
+/// The boxed comprehension of a struct from `spanned::iter_struct!` deriving both `Clone` and
+/// `Debug`.
+#[cfg(feature = "alloc")]
+#[doc(hidden)]
+pub trait __CloneDebugIter<'a>: Iterator + core::fmt::Debug + 'a {
+    fn __clone_box(&self)
+        -> __alloc::boxed::Box<dyn __CloneDebugIter<'a, Item = Self::Item> + 'a>;
+}
+
+#[cfg(feature = "alloc")]
+impl<'a, I: Iterator + Clone + core::fmt::Debug + 'a> __CloneDebugIter<'a> for I {
+    fn __clone_box(&self)
+        -> __alloc::boxed::Box<dyn __CloneDebugIter<'a, Item = I::Item> + 'a> {
+        __alloc::boxed::Box::new(self.clone())
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<'a, T: 'a> Clone for __alloc::boxed::Box<dyn __CloneDebugIter<'a, Item = T> + 'a> {
+    fn clone(&self) -> Self {
+        (**self).__clone_box()
+    }
+}
+
+/// Buffers the values of the source of a `memo` binding in `iter!` as they are produced, so
+/// that its clones replay them instead of evaluating the source again.
+#[cfg(feature = "alloc")]
+#[doc(hidden)]
+pub struct __Memo<I: Iterator> {
+    shared: __alloc::rc::Rc<core::cell::RefCell<MemoBuffer<I>>>,
+    pos: usize,
+}
+
+#[cfg(feature = "alloc")]
+struct MemoBuffer<I: Iterator> {
+    iter: core::iter::Fuse<I>,
+    values: __alloc::vec::Vec<I::Item>,
+}
+
+#[cfg(feature = "alloc")]
+impl<I: Iterator> Clone for __Memo<I> {
+    fn clone(&self) -> Self {
+        __Memo {
+            shared: self.shared.clone(),
+            pos: self.pos,
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<I: Iterator> Iterator for __Memo<I>
+where
+    I::Item: Clone,
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        let mut buffer = self.shared.borrow_mut();
+        let item = match buffer.values.get(self.pos) {
+            Some(item) => item.clone(),
+            None => {
+                let item = buffer.iter.next()?;
+                buffer.values.push(item.clone());
+                item
+            }
+        };
+        self.pos += 1;
+        Some(item)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let buffer = self.shared.borrow();
+        let buffered = buffer.values.len() - self.pos;
+        let (lower, upper) = buffer.iter.size_hint();
+        let upper = upper.and_then(|upper| upper.checked_add(buffered));
+        (lower.saturating_add(buffered), upper)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<I: Iterator> core::iter::FusedIterator for __Memo<I> where I::Item: Clone {}
+
+/// Wraps the source of a `memo` binding in `iter!`.
+#[cfg(feature = "alloc")]
+#[doc(hidden)]
+pub fn __memo<I: IntoIterator>(iter: I) -> __Memo<I::IntoIter> {
+    let buffer = MemoBuffer {
+        iter: iter.into_iter().fuse(),
+        values: __alloc::vec::Vec::new(),
+    };
+    __Memo {
+        shared: __alloc::rc::Rc::new(core::cell::RefCell::new(buffer)),
+        pos: 0,
+    }
+}
+
+/// Pairs up the values of two sources with equal keys by indexing the inner source in a
+/// `HashMap` and streaming the outer one, for `join`.
+#[cfg(feature = "std")]
+#[doc(hidden)]
+pub fn __hash_join<I, J, K, F, G>(
+    outer: I,
+    inner: J,
+    mut outer_key: F,
+    mut inner_key: G,
+) -> impl core::iter::FusedIterator<Item = (I::Item, J::Item)>
+where
+    I: IntoIterator,
+    I::Item: Clone,
+    J: IntoIterator,
+    J::Item: Clone,
+    K: Eq + core::hash::Hash,
+    F: FnMut(I::Item) -> K,
+    G: FnMut(J::Item) -> K,
+{
+    let mut index = __std::collections::HashMap::<_, __alloc::vec::Vec<_>>::new();
+    for item in inner {
+        index.entry(inner_key(item.clone())).or_default().push(item);
+    }
+    let mut outer = outer.into_iter();
+    let mut current = None;
+    core::iter::from_fn(move || loop {
+        if let Some((item, key, pos)) = &mut current {
+            if let Some(matched) = index.get(&*key).and_then(|items| items.get(*pos)) {
+                *pos += 1;
+                return Some((I::Item::clone(item), J::Item::clone(matched)));
+            }
+        }
+        let item = outer.next()?;
+        let key = outer_key(item.clone());
+        current = Some((item, key, 0));
+    })
+    .fuse()
+}
+
+/// Pairs up the values of two sources sorted by key with equal keys by walking them in
+/// lockstep, replaying the run of equal keys of the inner source from a clone of its iterator,
+/// for `merge_join`.
+#[doc(hidden)]
+pub fn __merge_join<I, J, K, F, G>(
+    outer: I,
+    inner: J,
+    mut outer_key: F,
+    mut inner_key: G,
+) -> impl core::iter::FusedIterator<Item = (I::Item, J::Item)>
+where
+    I: IntoIterator,
+    I::Item: Clone,
+    J: IntoIterator,
+    J::IntoIter: Clone,
+    J::Item: Clone,
+    K: Ord,
+    F: FnMut(I::Item) -> K,
+    G: FnMut(J::Item) -> K,
+{
+    let mut outer = outer.into_iter();
+    let mut start = inner.into_iter();
+    let mut current = None;
+    core::iter::from_fn(move || loop {
+        if let Some((item, key, cursor)) = &mut current {
+            if let Some(matched) = Iterator::next(cursor) {
+                if inner_key(J::Item::clone(&matched)) == *key {
+                    return Some((I::Item::clone(item), matched));
+                }
+            }
+        }
+        let item = outer.next()?;
+        let key = outer_key(item.clone());
+        loop {
+            let mut next = start.clone();
+            match next.next().map(&mut inner_key) {
+                Some(next_key) if next_key < key => start = next,
+                _ => break,
+            }
+        }
+        current = Some((item, key, start.clone()));
+    })
+    .fuse()
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(unused_variables)]
+    #![allow(dead_code)]
+    #![allow(redundant_semicolons)]
+    #![allow(clippy::assign_op_pattern)]
+    #![allow(clippy::let_unit_value)]
+
+    fn ok<T>(t: T) -> Result<T, ()> {
+        Ok(t)
+    }
+
+    #[test]
+    fn test_basic() {
+        let option = option! {
+            let a <- Some(1);
+            let b <- Some(2);
+        };
+        assert_eq!(option, Some(()));
+
+        let option = option! {
+            let a <- Some(1);
+            let b <- Some('a');
+            (a, b)
+        };
+        assert_eq!(option, Some((1, 'a')));
+
+        let option = option! {
+            let a <- Some(1);
+            let b <- None::<()>;
+            (a, b)
+        };
+        assert_eq!(option, None);
+
+        let option = option! {
+            let a <- None::<()>;
+            let b <- Some(2);
+            (a, b)
+        };
+        assert_eq!(option, None);
+
+        let result = result! {
+            let a <- ok(1);
+            let b <- ok(2);
+        };
+        assert_eq!(result, Ok(()));
+
+        let result = result! {
+            let a <- ok(1);
+            let b <- ok('a');
+            (a, b)
+        };
+        assert_eq!(result, Ok((1, 'a')));
+
+        let result = result! {
+            let a <- Err::<(), _>(1);
+            let b <- Ok('a');
+            (a, b)
+        };
+        assert_eq!(result, Err(1));
+
+        let result = result! {
+            let a <- Ok('a');
+            let b <- Err::<(), _>(2);
+            (a, b)
+        };
+        assert_eq!(result, Err(2));
+
+        let iter = iter! {
+            let x <- vec![0, 1, 2, 3];
+            let y <- x..4;
+            (x, y)
+        };
+        let expected = vec![(0, 0), (0, 1), (0, 2), (0, 3), (1, 1), (1, 2), (1, 3), (2, 2),
+                            (2, 3), (3, 3)];
+        assert!(iter.eq(expected.into_iter()));
+    }
+
+    #[test]
+    #[allow(array_into_iter)]
+    fn test_array() {
+        let array = [0, 1, 2, 3];
+        let iter = iter! {
+            let x <- array;
+            let y <- *x..4;
+            (*x, y)
+        };
+        let expected = vec![(0, 0), (0, 1), (0, 2), (0, 3), (1, 1), (1, 2), (1, 3), (2, 2),
+                            (2, 3), (3, 3)];
+        assert!(iter.eq(expected.into_iter()));
+
+        let iter = iter! {
+            let s: String <- [String::from("a"), String::from("b")], by_value;
+            let t <- [s.clone() + "!", s], by_value;
+            t
+        };
+        assert_eq!(iter.collect::<Vec<_>>(), ["a!", "a", "b!", "b"]);
+
+        let iter = iter! {
+            let x <- array, by_value;
+            let mut y: i32 <- [x, x + 1], by_value;
+            y *= 2;
+            y
+        };
+        assert_eq!(iter.collect::<Vec<_>>(), [0, 2, 2, 4, 4, 6, 6, 8]);
+
+        let sum: i32 = iter! {
+            sum;
+            let (x, y) <- [1, 30] zip [10, 20];
+            if *x < y;
+            x * y
+        };
+        assert_eq!(sum, 10);
+
+        #[cfg(feature = "alloc")]
+        {
+            let vec = vec_comp! {
+                let x <- [1, 2], by_value;
+                let y <- [x; 2], by_value;
+                x + y
+            };
+            assert_eq!(vec, [2, 2, 4, 4]);
+
+            let vec = vec_comp! {
+                let x <- array;
+                *x * 2
+            };
+            assert_eq!(vec, [0, 2, 4, 6]);
+        }
+
+        #[cfg(feature = "proc-macro")]
+        {
+            let iter = crate::spanned::iter! {
+                let s <- [String::from("a"), String::from("b")], by_value;
+                let n <- [1, 2], by_value;
+                s.repeat(n)
+            };
+            assert_eq!(iter.collect::<Vec<_>>(), ["a", "aa", "b", "bb"]);
+
+            let iter = crate::spanned::iter! {
+                let x <- array;
+                *x * 2
+            };
+            assert_eq!(iter.collect::<Vec<_>>(), [0, 2, 4, 6]);
+        }
+    }
+
+    #[test]
+    fn test_guard() {
+        let iter = iter! {
+            let x <- 0..4;
+            let y <- x..4;
+            if x * 2 == y;
+            (x, y)
+        };
+        let expected = vec![(0, 0), (1, 2)];
+        assert!(iter.eq(expected.into_iter()));
+    }
+
+    #[test]
+    fn test_pattern() {
+        struct TupleStruct0();
+        struct TupleStruct(usize);
+        struct TupleStruct2(usize, usize);
+        struct Struct {
+            x: usize,
+        };
+        struct Struct2 {
+            x: usize,
+            y: usize,
+        };
+
+        let option = option! {
+            let (x, y, z) <- Some((1, 2, 3));
+            (x, y, z)
+        };
+        assert_eq!(option, Some((1, 2, 3)));
+
+        let option = option! {
+            let (x, (y, z)) <- Some((1, (2, 3)));
+            (x, y, z)
+        };
+        assert_eq!(option, Some((1, 2, 3)));
+
+        let option = option! {
+            let TupleStruct0() <- Some(TupleStruct0());
+        };
+        assert_eq!(option, Some(()));
+
+        let option = option! {
+            let TupleStruct0() <- Some(TupleStruct0());
+        };
+        assert_eq!(option, Some(()));
+
+        let option = option! {
+            let TupleStruct(x) <- Some(TupleStruct(9));
+            x
+        };
+        assert_eq!(option, Some(9));
+
+        let option = option! {
+            let TupleStruct2(x, y) <- Some(TupleStruct2(9, 10));
+            (x, y)
+        };
+        assert_eq!(option, Some((9, 10)));
+
+        let option = option! {
+            let Struct { x } <- Some(Struct { x: 8 });
+            x
+        };
+        assert_eq!(option, Some(8));
+
+        let option = option! {
+            let Struct2 { x, y } <- Some(Struct2 { x: 9, y: 10 });
+            (x, y)
+        };
+        assert_eq!(option, Some((9, 10)));
+    }
+
+    #[test]
+    fn test_ignore() {
+        struct TupleStruct(usize);
+        struct TupleStruct2(usize, usize);
+        struct Struct {
+            x: usize,
+        };
+        struct Struct2 {
+            x: usize,
+            y: usize,
+        };
+
+        let option = option! {
+            let _ <- Some(0);
+        };
+        assert_eq!(option, Some(()));
+
+        let iter = iter! {
+            let _ <- 0..10;
+            1
+        };
+        assert_eq!(iter.sum::<u8>(), 10);
+
+        let option = option! {
+            let (_, y, _) <- Some((1, 2, 3));
+            y
+        };
+        assert_eq!(option, Some(2));
+
+        let option = option! {
+            let (_, (y, _)) <- Some((1, (2, 3)));
+            y
+        };
+        assert_eq!(option, Some(2));
+
+        let option = option! {
+            let TupleStruct(_) <- Some(TupleStruct(9));
+        };
+        assert_eq!(option, Some(()));
+
+        let option = option! {
+            let TupleStruct2(x, _) <- Some(TupleStruct2(9, 10));
+            x
+        };
+        assert_eq!(option, Some(9));
+
+        let option = option! {
+            let Struct { x: _ } <- Some(Struct { x: 8 });
+        };
+        assert_eq!(option, Some(()));
+
+        let option = option! {
+            let Struct2 { x, y: _ } <- Some(Struct2 { x: 9, y: 10 });
+            x
+        };
+        assert_eq!(option, Some(9));
+    }
+
+    #[test]
+    fn test_if_expression() {
+        let iter = iter! {
+            let x <- 0..5;
+            let y <- if x % 2 == 0 { Some(x + 1) } else { None };
+            y
+        };
+        let expected = vec![1, 3, 5];
+        assert!(iter.eq(expected.into_iter()));
+
+        let iter = iter! {
+            let x <- 0..5;
+            if x < 2 { 0 } else { 1 }
+        };
+        let expected = vec![0, 0, 1, 1, 1];
+        assert!(iter.eq(expected.into_iter()));
+    }
+
+    #[test]
+    fn test_statement() {
+        let iter = iter! {
+            let start = 5;
+            let end;
+            end = start * 3;
+
+            // 5, 6, ..., 13, 14
+            let x <- start..end;
+            x
+        };
+        let expected = 5..15;
+        assert!(iter.eq(expected.into_iter()));
+    }
+
+    #[test]
+    fn test_block() {
+        let iter = iter! {
+            let mut a <- 0..5;
+
+            {
+                fn double(x: u8) -> u8 { x * 2}
+                let tmp = double(a);
+                a = tmp;
+            };
+
+            let count = unsafe {
+                static mut CALL_COUNT: u8 = 0;
+                CALL_COUNT += 1;
+
+                CALL_COUNT
+            };
+
+            (a, count)
+        };
+        let expected = vec![(0, 1), (2, 2), (4, 3), (6, 4), (8, 5)];
+        assert!(iter.eq(expected.into_iter()));
+    }
+
+    #[test]
+    fn test_mut() {
+        struct TupleStruct2(usize, usize);
+
+        let option = option! {
+            let mut a <- Some(2);
+            a = a + 10;
+
+            let (mut b,) <- Some((3,));
+            b = b + 10;
+
+            (a, b)
+        };
+        assert_eq!(option, Some((12, 13)));
+
+        let result = result! {
+            let mut a <- ok(2);
+            a = a + 10;
+
+            let TupleStruct2(mut b, _) <- ok(TupleStruct2(3, 4));
+            b = b + 10;
+
+            (a, b)
+        };
+        assert_eq!(result, ok((12, 13)));
+
+        let iter = iter! {
+            let mut a <- 2..3;
+            a = a + 10;
+            a
+        };
+        let expected = vec![12];
+        assert!(iter.eq(expected.into_iter()));
+    }
+
+    #[test]
+    fn test_comments() {
+        option! {
+            // single line comments
+            let a <- Some(1);
+
+            /*
+             * block comments
+             */
+            let b <- Some(2);
+        };
+    }
+
+    #[test]
+    fn test_nested() {
+        let iter = iter! {
+            let a <- 0..2;
+
+            option! {
+                let b <- Some(a);
+                (b,)
+            }
+        };
+        let expected = vec![Some((0,)), Some((1,))];
+        assert!(iter.eq(expected.into_iter()));
+    }
+
+    #[test]
+    fn test_comp() {
+        use super::{Bind, Pure};
+
+        #[derive(Debug, PartialEq)]
+        struct Logged<T>(T, u32);
+
+        impl<T, U> Bind<Logged<U>> for Logged<T> {
+            type Item = T;
+
+            fn bind<F: FnOnce(T) -> Logged<U>>(self, f: F) -> Logged<U> {
+                let Logged(u, n) = f(self.0);
+                Logged(u, self.1 + n)
+            }
+        }
+
+        impl<T> Pure for Logged<T> {
+            type Item = T;
+
+            fn pure(t: T) -> Self {
+                Logged(t, 0)
+            }
+        }
+
+        let option = comp! {
+            let a <- Some(1);
+            let b <- Some('a');
+            (a, b)
+        };
+        assert_eq!(option, Some((1, 'a')));
+
+        let option = comp! {
+            let a <- Some(1);
+            let b <- None::<()>;
+            (a, b)
+        };
+        assert_eq!(option, None);
+
+        let result = comp! {
+            let a <- ok(1);
+            let mut b <- ok(2);
+            b += a;
+            b
+        };
+        assert_eq!(result, Ok(3));
+
+        let result = comp! {
+            let a <- Err::<(), _>(1);
+            let b <- Ok('a');
+            (a, b)
+        };
+        assert_eq!(result, Err(1));
+
+        let logged = comp! {
+            let (a, b) <- Logged((1, 2), 1);
+            let c <- Logged(a + b, 2);
+        };
+        assert_eq!(logged, Logged((), 3));
+    }
+
+    #[test]
+    #[cfg(feature = "proc-macro")]
+    fn test_spanned() {
+        use crate::spanned;
+
+        let option = spanned::option! {
+            let a <- Some(1);
+            let (b, mut c): (u8, u8) <- Some((2, 3));
+            c += b;
+            (a, c)
+        };
+        assert_eq!(option, Some((1, 5)));
+
+        let result = spanned::result! {
+            let a <- ok(1);
+            let b <- Err::<u8, _>(());
+            a + b
+        };
+        assert_eq!(result, Err(()));
+
+        let iter = spanned::iter! {
+            let x <- 0..4;
+            let y <- x..4;
+            if x * 2 == y;
+            (x, y)
+        };
+        let expected = vec![(0, 0), (1, 2)];
+        assert!(iter.eq(expected.into_iter()));
+    }
+
+    #[test]
+    #[cfg(feature = "proc-macro")]
+    fn test_from_fn() {
+        let limit = 2;
+        let iter = crate::spanned::iter! {
+            from_fn;
+            let offset = 10;
+            if limit > 0;
+            let (i, Some(c)) <- vec![(0, Some('a')), (1, None), (2, Some('c'))];
+            let mut s = c.to_string();
+            s.push('!');
+            let k: usize <- 0..limit;
+            while k < i;
+            unless k == 1;
+            format!("{}{}", s, k + offset)
+        };
+        assert_eq!(iter.collect::<Vec<_>>(), ["c!10"]);
+
+        let count = crate::spanned::iter! {
+            count;
+            from_fn;
+            let x <- 0..4;
+            let y <- x..4 or 0..1;
+            let z <- (0..y).rev(), step_by 2;
+            (x, y, z)
+        };
+        assert_eq!(count, 13);
+    }
+
+    #[test]
+    #[cfg(all(feature = "proc-macro", feature = "alloc"))]
+    fn test_iter_struct() {
+        crate::spanned::iter_struct! {
+            /// The distinct pairs below `n`.
+            #[derive(Clone, Debug)]
+            struct Pairs(n: u32) -> (u32, u32) {
+                let x <- 0..n;
+                let y <- x..n;
+                if x != y;
+                (x, y)
+            }
+        }
+
+        crate::spanned::iter_struct! {
+            pub(crate) struct Words<'a>(text: &'a str, (min, max): (usize, usize)) -> &'a str {
+                let word <- text.split(' ');
+                if (min..=max).contains(&word.len());
+                word
+            }
+        }
+
+        struct Holder<'a> {
+            pairs: Pairs,
+            words: Words<'a>,
+        }
+
+        let holder = Holder { pairs: Pairs::new(3), words: Words::new("a bb  ccc", (1, 2)) };
+        let pairs = holder.pairs.clone();
+        assert!(format!("{:?}", pairs).starts_with("Pairs { inner: "));
+        assert_eq!(holder.pairs.collect::<Vec<_>>(), [(0, 1), (0, 2), (1, 2)]);
+        assert_eq!(pairs.count(), 3);
+        assert_eq!(holder.words.collect::<Vec<_>>(), ["a", "bb"]);
+    }
+
+    #[test]
+    #[cfg(feature = "futures")]
+    fn test_future() {
+        use futures::executor::block_on;
+        use futures::future::{err, ok, ready};
+
+        let future = future! {
+            let a <- ready(1);
+            let (b, c) <- ready((2, 'a'));
+            (a + b, c)
+        };
+        assert_eq!(block_on(future), (3, 'a'));
+
+        let future = future! {
+            let a <- ready(1);
+            let b <- ready(2);
+        };
+        assert_eq!(block_on(future), ());
+
+        let future = try_future! {
+            let a <- ok::<_, ()>(1);
+            let mut b <- ok(2);
+            b += a;
+            b
+        };
+        assert_eq!(block_on(future), Ok(3));
+
+        let future = try_future! {
+            let a <- err::<u8, _>(1);
+            let b <- ok(2);
+            a + b
+        };
+        assert_eq!(block_on(future), Err(1));
+    }
+
+    #[test]
+    #[cfg(feature = "futures")]
+    fn test_stream() {
+        use futures::executor::block_on;
+        use futures::stream::{iter, StreamExt};
+
+        let stream = stream! {
+            let x <- iter(vec![0, 1, 2, 3]);
+            let y <- iter(x..4);
+            (x, y)
+        };
+        let expected = vec![(0, 0), (0, 1), (0, 2), (0, 3), (1, 1), (1, 2), (1, 3), (2, 2),
+                            (2, 3), (3, 3)];
+        assert_eq!(block_on(stream.collect::<Vec<_>>()), expected);
+
+        let stream = stream! {
+            let x <- iter(0..4);
+            let y <- iter(x..4);
+            if x * 2 == y;
+            (x, y)
+        };
+        let expected = vec![(0, 0), (1, 2)];
+        assert_eq!(block_on(stream.collect::<Vec<_>>()), expected);
+
+        let stream = stream! {
+            let _ <- iter(0..10);
+            1
+        };
+        assert_eq!(block_on(stream.collect::<Vec<_>>()).iter().sum::<u8>(), 10);
+    }
+
+    #[test]
+    fn test_async() {
+        use futures::executor::block_on;
+        use futures::future::ready;
+
+        async fn fetch(x: u8) -> Option<u8> {
+            Some(x)
+        }
+
+        let future = async_option! {
+            let a <- fetch(1);
+            let (b, mut c) <- ready(Some((2, 3)));
+            c += ready(b).await;
+            (a, c)
+        };
+        assert_eq!(block_on(future), Some((1, 5)));
+
+        let future = async_option! {
+            let a <- fetch(1);
+            let b <- ready(None::<u8>);
+            a + b
+        };
+        assert_eq!(block_on(future), None);
+
+        let future = async_result! {
+            let a <- ready(ok(1));
+            let b: u8 <- ready(ok(2));
+            a + b
+        };
+        assert_eq!(block_on(future), Ok(3));
+
+        let future = async_result! {
+            let a <- ready(Err::<u8, _>(1));
+            let b <- ready(Ok(2));
+            a + b
+        };
+        assert_eq!(block_on(future), Err(1));
+    }
+
+    #[test]
+    #[cfg(feature = "either")]
+    fn test_either() {
+        use either::Either::{self, Left, Right};
+
+        let either = either! {
+            let a <- Right::<(), _>(1);
+            let (b, mut c) <- Right((2, 3));
+            c += b;
+            (a, c)
+        };
+        assert_eq!(either, Right((1, 5)));
+
+        let either: Either<char, ()> = either! {
+            let a <- Right(1);
+            let b <- Left::<_, u8>('a');
+        };
+        assert_eq!(either, Left('a'));
+
+        let either = either! {
+            let a <- Left::<_, u8>('a');
+            let b <- Left::<_, u8>('b');
+            a + b
+        };
+        assert_eq!(either, Left('a'));
+    }
+
+    #[test]
+    fn test_control_flow() {
+        use std::ops::ControlFlow::{self, Break, Continue};
+
+        let flow = control_flow! {
+            let a <- Continue::<(), _>(1);
+            let (b, mut c) <- Continue((2, 3));
+            c += b;
+            (a, c)
+        };
+        assert_eq!(flow, Continue((1, 5)));
+
+        let flow: ControlFlow<char, ()> = control_flow! {
+            let a: u8 <- Continue(1);
+            let b: u8 <- Break('a');
+        };
+        assert_eq!(flow, Break('a'));
+
+        let sum = (1..10).try_fold(0, |acc, x| control_flow! {
+            let x <- if x < 5 { Continue(x) } else { Break(acc) };
+            acc + x
+        });
+        assert_eq!(sum, Break(10));
+    }
+
+    #[test]
+    fn test_poll() {
+        use std::task::Poll::{self, Pending, Ready};
+
+        let poll = poll! {
+            let a <- Ready(1);
+            let (b, mut c) <- Ready((2, 3));
+            c += b;
+            (a, c)
+        };
+        assert_eq!(poll, Ready((1, 5)));
+
+        let poll: Poll<()> = poll! {
+            let a: u8 <- Ready(1);
+            let b: u8 <- Pending;
+        };
+        assert_eq!(poll, Pending);
+
+        let poll = poll_next! {
+            let a <- Ready(Some(1));
+            let b <- Ready(Some(2));
+            a + b
+        };
+        assert_eq!(poll, Ready(Some(3)));
+
+        let poll = poll_next! {
+            let a <- Ready(Some(1));
+            let b <- Ready(None::<u8>);
+            a + b
+        };
+        assert_eq!(poll, Ready(None));
+
+        let poll = poll_next! {
+            let a <- Ready(Some(1));
+            let b <- Pending::<Option<u8>>;
+            a + b
+        };
+        assert_eq!(poll, Pending);
+    }
+
+    #[test]
+    fn test_validated() {
+        use super::Validated::{self, Invalid, Valid};
+
+        fn check(x: u8) -> Result<u8, u8> {
+            if x < 10 { Ok(x) } else { Err(x) }
+        }
+
+        let validated: Validated<_, Vec<u8>> = validated! {
+            let a <- check(1);
+            let (b, mut c) <- Valid((2, 3));
+            let d: u8 <- check(4);
+            c += b;
+            (a, c, d)
+        };
+        assert_eq!(validated, Valid((1, 5, 4)));
+
+        let validated: Validated<_, Vec<u8>> = validated! {
+            let a <- check(10);
+            let b <- check(2);
+            let c <- check(30);
+            a + b + c
+        };
+        assert_eq!(validated, Invalid(vec![10, 30]));
+
+        let validated = validated! {
+            let a <- Validated::<u8, Vec<char>>::fail('a');
+            let b: u8 <- Invalid(vec!['b', 'c']);
+        };
+        assert_eq!(validated, Invalid(vec!['a', 'b', 'c']));
+        assert_eq!(validated.into_result(), Err(vec!['a', 'b', 'c']));
+    }
+
+    #[test]
+    fn test_writer() {
+        fn double(x: u8) -> (u8, Vec<u8>) {
+            (x * 2, vec![x])
+        }
+
+        let (value, log): (_, Vec<u8>) = writer! {
+            let a <- double(1);
+            let (b, mut c) <- ((2, 3), None::<u8>);
+            tell b;
+            c += b;
+            let d: u8 <- double(c);
+            (a, d)
+        };
+        assert_eq!(value, (2, 10));
+        assert_eq!(log, vec![1, 2, 5]);
+
+        let (value, log): (_, String) = writer! {
+            tell 'a';
+            let _ <- (0, "bc".chars());
+            1
+        };
+        assert_eq!(value, 1);
+        assert_eq!(log, "abc");
+    }
+
+    #[test]
+    fn test_state() {
+        fn pop(stack: Vec<u8>) -> (Option<u8>, Vec<u8>) {
+            let mut stack = stack;
+            (stack.pop(), stack)
+        }
+
+        fn push(x: u8) -> impl FnOnce(Vec<u8>) -> ((), Vec<u8>) {
+            move |mut stack| {
+                stack.push(x);
+                ((), stack)
+            }
+        }
+
+        let run = state! {
+            let a <- pop;
+            let b <- pop;
+            let mut c: u8 <- |s| (2, s);
+            c *= 10;
+            let _ <- push(7);
+            let (d, e) <- |s| ((1, 2), s);
+            (a, b, c + d + e)
+        };
+        assert_eq!(run(vec![1, 2, 3]), ((Some(3), Some(2), 23), vec![1, 7]));
+
+        let run = state! {
+            let _ <- push(1);
+            let _ <- push(2);
+        };
+        assert_eq!(run(vec![]), ((), vec![1, 2]));
+    }
+
+    #[test]
+    fn test_reader() {
+        struct Env {
+            x: u8,
+            y: u8,
+        }
+
+        fn x(env: &Env) -> u8 {
+            env.x
+        }
+
+        let run = reader! {
+            let a <- x;
+            let mut b: u8 <- |env: &Env| env.y;
+            b += a;
+            let (c, d) <- |env: &Env| (env.x, env.y);
+            (a, b, c + d)
+        };
+        assert_eq!(run(Env { x: 1, y: 2 }), (1, 3, 3));
+
+        let run = reader! {
+            let _ <- x;
+        };
+        assert_eq!(run(Env { x: 1, y: 2 }), ());
+    }
+
+    #[test]
+    #[cfg(feature = "rayon")]
+    fn test_par_iter() {
+        use rayon::prelude::*;
+
+        let iter = par_iter! {
+            let x <- vec![0, 1, 2, 3];
+            let y <- x..4;
+            (x, y)
+        };
+        let expected = vec![(0, 0), (0, 1), (0, 2), (0, 3), (1, 1), (1, 2), (1, 3), (2, 2),
+                            (2, 3), (3, 3)];
+        assert_eq!(iter.collect::<Vec<_>>(), expected);
+
+        let iter = par_iter! {
+            let x <- 0..4;
+            let y <- x..4;
+            if x * 2 == y;
+            if x < 3;
+            (x, y)
+        };
+        assert_eq!(iter.collect::<Vec<_>>(), vec![(0, 0), (1, 2)]);
+
+        let iter = par_iter! {
+            let x <- 0..4;
+            if x % 2 == 0;
+            let mut y: u8 <- 0..2;
+            y += 10;
+            (x, y)
+        };
+        assert_eq!(iter.collect::<Vec<_>>(), vec![(0, 10), (0, 11), (2, 10), (2, 11)]);
+
+        let iter = par_iter! {
+            let (x, _) <- vec![(0, 'a'), (1, 'b')];
+            let _ <- 0..2;
+        };
+        assert_eq!(iter.count(), 4);
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn test_vec_comp() {
+        let vec = vec_comp! {
+            let x <- vec![0, 1, 2, 3];
+            let y <- x..4;
+            (x, y)
+        };
+        let expected = vec![(0, 0), (0, 1), (0, 2), (0, 3), (1, 1), (1, 2), (1, 3), (2, 2),
+                            (2, 3), (3, 3)];
+        assert_eq!(vec, expected);
+
+        let vec = vec_comp! {
+            let x <- 0..3;
+            let y <- 0..1000;
+            (x, y)
+        };
+        assert_eq!(vec.len(), 3000);
+        assert_eq!(vec.capacity(), 3000);
+
+        let vec = vec_comp! {
+            let x <- 0..6;
+            if x % 2 == 0;
+            x
+        };
+        assert_eq!(vec, vec![0, 2, 4]);
+
+        let vec = vec_comp! {
+            let x <- 0..10_000_000u64;
+            if x == 3;
+            x
+        };
+        assert_eq!(vec, vec![3]);
+        assert!(vec.capacity() < 16);
+
+        let vec = vec_comp! {
+            let mut x <- 0..4;
+            if x % 2 == 0;
+            let start = x;
+            x += 10;
+            let (y, _) <- vec![(start, 'a')];
+            x + y
+        };
+        assert_eq!(vec, vec![10, 14]);
+
+        let vec = vec_comp! {
+            let _ <- 0..2;
+        };
+        assert_eq!(vec, vec![(), ()]);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_map_comp() {
+        use std::collections::HashMap;
+
+        let map = map_comp! {
+            let x <- 0..3;
+            let y <- x..3;
+            if x != y;
+            (x, y) => x * y
+        };
+        let expected: HashMap<_, _> = vec![((0, 1), 0), ((0, 2), 0), ((1, 2), 2)].into_iter().collect();
+        assert_eq!(map, expected);
+
+        let map = map_comp! {
+            let (k, v) <- vec![('a', 1), ('b', 2), ('a', 3)];
+            let v = v * 10;
+            k => v
+        };
+        let expected: HashMap<_, _> = vec![('a', 30), ('b', 20)].into_iter().collect();
+        assert_eq!(map, expected);
+
+        let map = map_comp! {
+            let x <- 0..3;
+            let y <- 0..100;
+            (x, y) => x * y
+        };
+        assert!(map.capacity() >= 300);
+
+        let map = map_comp! {
+            let x <- 0..1_000_000;
+            if x == 3;
+            x => x
+        };
+        assert!(map.capacity() < 16);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_group_comp() {
+        use std::collections::HashMap;
+
+        let groups = group_comp! {
+            let x <- 0..3;
+            let y <- 0..4;
+            if x != y;
+            x + y => (x, y)
+        };
+        let expected: HashMap<_, _> = vec![
+            (1, vec![(0, 1), (1, 0)]),
+            (2, vec![(0, 2), (2, 0)]),
+            (3, vec![(0, 3), (1, 2), (2, 1)]),
+            (4, vec![(1, 3)]),
+            (5, vec![(2, 3)]),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(groups, expected);
+
+        let groups = group_comp! {
+            let (k, v) <- vec![("b", 1), ("a", 2), ("b", 3)];
+            unique;
+            k => v * 10
+        };
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups["a"], vec![20]);
+        assert_eq!(groups["b"], vec![10, 30]);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_set_comp() {
+        use std::collections::HashSet;
+
+        let set = set_comp! {
+            let x <- 0..4;
+            let y <- 0..4;
+            if x <= y;
+            x + y
+        };
+        let expected: HashSet<_> = (0..7).collect();
+        assert_eq!(set, expected);
+
+        let set = set_comp! {
+            let word <- vec!["a", "bb", "cc", "ddd"];
+            let len = word.len();
+            len
+        };
+        let expected: HashSet<_> = vec![1, 2, 3].into_iter().collect();
+        assert_eq!(set, expected);
+
+        let set = set_comp! {
+            let x <- 0..1_000_000;
+            unless x != 3;
+            x
+        };
+        assert!(set.capacity() < 16);
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn test_btree_comp() {
+        let map = btreemap_comp! {
+            let x <- (0..3).rev();
+            let y <- x..3;
+            if x != y;
+            (x, y) => x * y
+        };
+        assert_eq!(
+            map.into_iter().collect::<Vec<_>>(),
+            vec![((0, 1), 0), ((0, 2), 0), ((1, 2), 2)]
+        );
+
+        let set = btreeset_comp! {
+            let x <- vec![5, 3, 5, 1];
+            let y <- 0..2;
+            x + y
+        };
+        assert_eq!(set.into_iter().collect::<Vec<_>>(), vec![1, 2, 3, 4, 5, 6]);
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn test_string() {
+        let s = string! {
+            let x <- vec!["a", "b", "c"];
+            x
+        };
+        assert_eq!(s, "abc");
+
+        let s = string! {
+            sep " AND ";
+            let col <- vec!["id", "name", "age"];
+            if col != "name";
+            format!("{} IS NOT NULL", col)
+        };
+        assert_eq!(s, "id IS NOT NULL AND age IS NOT NULL");
+
+        let s = string! {
+            sep ",";
+            let x <- 0..3;
+            ""
+        };
+        assert_eq!(s, ",,");
+
+        let s = string! {
+            sep ",";
+            let x <- 0..0;
+            x
+        };
+        assert_eq!(s, "");
+    }
+
+    #[test]
+    fn test_array_comp() {
+        const N: usize = 6;
+
+        let table = array_comp! {
+            N;
+            let x <- 0..2;
+            let y <- 0..3;
+            (x, y)
+        };
+        assert_eq!(table, Some([(0, 0), (0, 1), (0, 2), (1, 0), (1, 1), (1, 2)]));
+
+        let evens = array_comp! {
+            3;
+            let x <- 0..6;
+            if x % 2 == 0;
+            x
+        };
+        assert_eq!(evens, Some([0, 2, 4]));
+
+        let too_long = array_comp! {
+            2;
+            let x <- 0..3;
+            x
+        };
+        assert_eq!(too_long, None);
+
+        let empty: Option<[String; 0]> = array_comp! {
+            0;
+            let x <- 0..0;
+            x.to_string()
+        };
+        assert_eq!(empty, Some([]));
+    }
+
+    #[test]
+    fn test_const() {
+        const fn digit(c: u8) -> Option<u8> {
+            if c >= b'0' && c <= b'9' {
+                Some(c - b'0')
+            } else {
+                None
+            }
+        }
+
+        const fn parse2(s: &[u8]) -> Option<u8> {
+            const_option! {
+                let hi <- digit(s[0]);
+                let lo <- digit(s[1]);
+                hi * 10 + lo
+            }
+        }
+
+        const fn checked(a: u8, b: u8) -> Result<u8, &'static str> {
+            const_result! {
+                let sum <- match a.checked_add(b) {
+                    Some(sum) => Ok(sum),
+                    None => Err("overflow"),
+                };
+                let mut double <- match sum.checked_mul(2) {
+                    Some(double) => Ok(double),
+                    None => Err("overflow"),
+                };
+                double -= 1;
+                double
+            }
+        }
+
+        const PARSED: Option<u8> = parse2(b"42");
+        const INVALID: Option<u8> = parse2(b"4x");
+        const CHECKED: Result<u8, &str> = checked(1, 2);
+        const OVERFLOW: Result<u8, &str> = checked(100, 100);
+
+        assert_eq!(PARSED, Some(42));
+        assert_eq!(INVALID, None);
+        assert_eq!(CHECKED, Ok(5));
+        assert_eq!(OVERFLOW, Err("overflow"));
+        assert_eq!(const_option! {}, Some(()));
+        assert_eq!(const_result! {}, Ok::<(), ()>(()));
+    }
+
+    #[test]
+    fn test_const_borrow() {
+        let mut count = 0;
+        let mut buf = String::new();
+        let words = ["foo", "bar"];
+
+        let r: Result<&str, ()> = const_result! {
+            let w <- words.first().ok_or(());
+            count += 1;
+            buf.push_str(w);
+            let tail <- buf.get(1..).ok_or(());
+            count += tail.len();
+            *w
+        };
+        assert_eq!(r, Ok("foo"));
+        assert_eq!(count, 3);
+        assert_eq!(buf, "foo");
+
+        let mut seen = Vec::new();
+        let o = const_option! {
+            let x <- Some(&mut seen);
+            x.push(1);
+            let last <- x.last().copied();
+            last
+        };
+        assert_eq!(o, Some(1));
+        assert_eq!(seen, vec![1]);
+    }
+
+    #[test]
+    fn test_refutable() {
+        let iter = iter! {
+            let Some(x) <- vec![Some(1), None, Some(3)];
+            let Ok(y) <- vec![Ok(x), Err("skipped"), Ok(x * 10)];
+            y
+        };
+        assert_eq!(iter.collect::<Vec<_>>(), vec![1, 10, 3, 30]);
+
+        let iter = iter! {
+            let (k, Some(v)) <- vec![('a', Some(1)), ('b', None), ('c', Some(3))];
+            (k, v)
+        };
+        assert_eq!(iter.collect::<Vec<_>>(), vec![('a', 1), ('c', 3)]);
+
+        let iter = iter! {
+            let Some(mut x) <- vec![None, Some(2)];
+            x += 1;
+            x
+        };
+        assert_eq!(iter.collect::<Vec<_>>(), vec![3]);
+
+        #[cfg(feature = "proc-macro")]
+        {
+            let iter = crate::spanned::iter! {
+                let Some(x) <- vec![Some(1), None, Some(3)];
+                let y: i32 <- 0..x;
+                y
+            };
+            assert_eq!(iter.collect::<Vec<_>>(), vec![0, 0, 1, 2]);
+        }
+    }
+
+    #[test]
+    fn test_refutable_option_result() {
+        let option = option! {
+            let Ok(x) <- Some(ok(1));
+            let (y, Some(z)) <- Some((x, Some(2)));
+            x + y + z
+        };
+        assert_eq!(option, Some(4));
+
+        let option = option! {
+            let Ok(x) <- Some(Err::<u8, _>("error"));
+            x
+        };
+        assert_eq!(option, None);
+
+        let result = result! {
+            let Some(x) <- ok(Some(1)) => ();
+            let Some(mut y) <- ok(Some(x)) => ();
+            y += 1;
+            (x, y)
+        };
+        assert_eq!(result, Ok((1, 2)));
+
+        let result = result! {
+            let x <- Ok::<_, &str>(None::<u8>);
+            let Some(y) <- Ok(x) => "missing";
+            y + 1
+        };
+        assert_eq!(result, Err("missing"));
+
+        #[cfg(feature = "proc-macro")]
+        {
+            let option = crate::spanned::option! {
+                let Ok(x) <- Some(Err::<u8, _>(()));
+                x
+            };
+            assert_eq!(option, None);
+
+            let result = crate::spanned::result! {
+                let Some(x) <- ok(Some(1)) => ();
+                let Some(y) <- ok(None::<u8>) => ();
+                x + y
+            };
+            assert_eq!(result, Err(()));
+        }
+    }
+
+    #[test]
+    fn test_option_guard() {
+        let option = option! {
+            let x <- Some(4);
+            if x % 2 == 0;
+            let y <- Some(x / 2);
+            if y > 1;
+            y
+        };
+        assert_eq!(option, Some(2));
+
+        let reached = &std::cell::Cell::new(false);
+        let option = option! {
+            let x <- Some(1);
+            if x > 1;
+            reached.set(true);
+            x
+        };
+        assert_eq!(option, None);
+        assert!(!reached.get());
+
+        let option = option! {
+            let x <- Some(1);
+            if x > 1 { 0 } else { 1 }
+        };
+        assert_eq!(option, Some(1));
+
+        const fn pred(x: u8) -> Option<u8> {
+            const_option! {
+                if x > 0;
+                x - 1
+            }
+        }
+        assert_eq!(pred(4), Some(3));
+        assert_eq!(pred(0), None);
+
+        #[cfg(feature = "proc-macro")]
+        {
+            let option = crate::spanned::option! {
+                let x <- Some(1);
+                if x > 1;
+                x
+            };
+            assert_eq!(option, None);
+        }
+    }
+
+    #[test]
+    fn test_result_guard() {
+        fn parse_age(s: &str) -> Result<u8, String> {
+            result! {
+                let age <- s.parse::<u8>().map_err(|e| e.to_string());
+                if age > 0 else "zero".to_string();
+                if age < 150 => format!("{} is too old", age);
+                age
+            }
+        }
+        assert_eq!(parse_age("42"), Ok(42));
+        assert_eq!(parse_age("0"), Err("zero".to_string()));
+        assert_eq!(parse_age("200"), Err("200 is too old".to_string()));
+        assert!(parse_age("x").is_err());
+
+        const fn pred(x: u8) -> Result<u8, &'static str> {
+            const_result! {
+                if x > 0 else "underflow";
+                x - 1
+            }
+        }
+        assert_eq!(pred(4), Ok(3));
+        assert_eq!(pred(0), Err("underflow"));
+
+        const fn succ(x: u8) -> Result<u8, &'static str> {
+            const_result! {
+                if x < u8::MAX => "overflow";
+                x + 1
+            }
+        }
+        assert_eq!(succ(4), Ok(5));
+        assert_eq!(succ(u8::MAX), Err("overflow"));
+
+        #[cfg(feature = "proc-macro")]
+        {
+            fn parse_len(s: &str) -> Result<usize, String> {
+                crate::spanned::result! {
+                    let n <- s.parse::<usize>().map_err(|e| e.to_string());
+                    if n > 0 else "zero".to_string();
+                    if n < 10 => format!("{} is too long", n);
+                    n
+                }
+            }
+            assert_eq!(parse_len("3"), Ok(3));
+            assert_eq!(parse_len("0"), Err("zero".to_string()));
+            assert_eq!(parse_len("12"), Err("12 is too long".to_string()));
+        }
+
+        #[cfg(feature = "proc-macro")]
+        {
+            let result = crate::spanned::result! {
+                let x <- ok(1);
+                if x > 1 => ();
+                x
+            };
+            assert_eq!(result, Err(()));
+        }
+    }
+
+    #[test]
+    fn test_guard_keywords() {
+        let iter = iter! {
+            let x <- 0..10;
+            guard x % 3 == 0;
+            unless x > 6;
+            x
+        };
+        assert_eq!(iter.collect::<Vec<_>>(), vec![0, 3, 6]);
+
+        let option = option! {
+            let x <- Some(1);
+            unless x == 1;
+            x
+        };
+        assert_eq!(option, None);
+
+        let result = result! {
+            let x <- ok(1);
+            guard x > 0 => ();
+            unless x > 0 => ();
+            x
+        };
+        assert_eq!(result, Err(()));
+
+        let option = option! {
+            let y <- Some(5);
+            unless (y > 3);
+            y
+        };
+        assert_eq!(option, None);
+
+        let result = result! {
+            let y <- ok(5);
+            let z = &y;
+            guard *z > 3 => ();
+            guard -y < 0 => ();
+            unless -y > 0 else ();
+            guard (y - 3) < 0 else ();
+            y
+        };
+        assert_eq!(result, Err(()));
+
+        let mut guard = vec![];
+        let option = option! {
+            let x <- Some(1);
+            guard.push(x);
+            guard = vec![x, x];
+            guard.len()
+        };
+        assert_eq!(option, Some(2));
+
+        #[cfg(feature = "alloc")]
+        {
+            let vec = vec_comp! {
+                let x <- 0..5;
+                unless x % 2 == 0;
+                x
+            };
+            assert_eq!(vec, vec![1, 3]);
+        }
+
+        #[cfg(feature = "rayon")]
+        {
+            use rayon::iter::ParallelIterator;
+
+            let vec: Vec<_> = par_iter! {
+                let x <- 0..5;
+                guard x > 1;
+                unless x == 3;
+                x
+            }.collect();
+            assert_eq!(vec, vec![2, 4]);
+        }
+
+        #[cfg(feature = "proc-macro")]
+        {
+            let mut guard = vec![];
+            let iter = crate::spanned::iter! {
+                let x <- 0..6;
+                guard x % 2 == 0;
+                unless x == 2;
+                guard.push(x);
+                x
+            };
+            assert_eq!(iter.collect::<Vec<_>>(), vec![0, 4]);
+
+            let option = crate::spanned::option! {
+                let y <- Some(5);
+                unless (y > 3);
+                y
+            };
+            assert_eq!(option, None);
+
+            let result = crate::spanned::result! {
+                let y <- ok(5);
+                let z = &y;
+                guard *z > 3 => ();
+                guard -y < 0 => ();
+                unless -y > 0 else ();
+                guard (y - 3) < 0 else ();
+                y
+            };
+            assert_eq!(result, Err(()));
+        }
+    }
+
+    #[test]
+    fn test_for_binding() {
+        let iter = iter! {
+            for x in 0..3;
+            for y in x..3;
+            if x != y;
+            (x, y)
+        };
+        assert_eq!(iter.collect::<Vec<_>>(), vec![(0, 1), (0, 2), (1, 2)]);
+
+        let option = option! {
+            for (a, b) in Some((1, 2));
+            let c <- Some(3);
+            a + b + c
+        };
+        assert_eq!(option, Some(6));
+
+        let result = result! {
+            for a in ok(1);
+            for b in Err::<u8, _>(());
+            a + b
+        };
+        assert_eq!(result, Err(()));
+
+        let option = option! {
+            let v <- Some(vec![1, 2, 3]);
+            let mut sum = 0;
+            for x in v { sum += x; };
+            for x in 0..2 { sum += x }
+            sum
+        };
+        assert_eq!(option, Some(7));
+
+        #[cfg(feature = "alloc")]
+        {
+            let vec = vec_comp! {
+                for x in 0..3;
+                x * 2
+            };
+            assert_eq!(vec, vec![0, 2, 4]);
+        }
+
+        #[cfg(feature = "proc-macro")]
+        {
+            let iter = crate::spanned::iter! {
+                for x in 0..3;
+                let mut sum = 0;
+                for y in 0..x { sum += y; }
+                sum
+            };
+            assert_eq!(iter.collect::<Vec<_>>(), vec![0, 0, 1]);
+        }
+    }
+
+    #[test]
+    fn test_inline() {
+        let iter = iter![x * y; for x in 0..3, for y in 0..3, if x != y];
+        assert_eq!(iter.collect::<Vec<_>>(), vec![0, 0, 0, 2, 0, 2]);
+
+        let iter = iter![(x, c); for x in 0..2, for c in vec!['a', 'b']];
+        assert_eq!(iter.collect::<Vec<_>>(), vec![(0, 'a'), (0, 'b'), (1, 'a'), (1, 'b')]);
+
+        let iter = iter![x; for Some(x) in vec![Some(1), None, Some(3)], if x > 1,];
+        assert_eq!(iter.collect::<Vec<_>>(), vec![3]);
+
+        let mut count = 0;
+        let iter = iter! {
+            count += 1;
+            for x in 0..2;
+            x
+        };
+        assert_eq!(iter.collect::<Vec<_>>(), vec![0, 1]);
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn test_hs_iter() {
+        let iter = hs_iter![(x, y) | x <- 0..3, y <- 0..3, x < y];
+        assert_eq!(iter.collect::<Vec<_>>(), vec![(0, 1), (0, 2), (1, 2)]);
+
+        let iter = hs_iter![
+            s | (i, Some(c)) <- vec![Some('a'), None, Some('c')].into_iter().enumerate(),
+                let s = format!("{}{}", c, i),
+                i > 0,
+        ];
+        assert_eq!(iter.collect::<Vec<_>>(), vec!["c2".to_string()]);
+
+        let iter = hs_iter![(x | 1) | x <- vec![2, 4], mut y <- Some(x), { y += 1; y } > 3];
+        assert_eq!(iter.collect::<Vec<_>>(), vec![5]);
+    }
+
+    #[test]
+    fn test_for_comp() {
+        struct Wrapper(u32);
+
+        let iter = for_comp! {
+            {
+                x <- 0..3;
+                y <- 0..3;
+                if x < y;
+            } yield (x, y)
+        };
+        assert_eq!(iter.collect::<Vec<_>>(), vec![(0, 1), (0, 2), (1, 2)]);
+
+        let iter = for_comp!({
+            (i, c) <- vec!['a', 'b'].into_iter().enumerate();
+            Some(d) <- vec![c.to_digit(16), None];
+            Wrapper(w) = Wrapper(d);
+            mut n <- Some(w);
+            n += i as u32
+        } yield n);
+        assert_eq!(iter.collect::<Vec<_>>(), vec![10, 12]);
+    }
+
+    #[test]
+    fn test_yield() {
+        let iter = iter! {
+            let x <- 0..3;
+            yield x;
+            yield x * 10;
+        };
+        assert_eq!(iter.collect::<Vec<_>>(), vec![0, 0, 1, 10, 2, 20]);
+
+        let iter = iter! {
+            yield 0;
+            let x <- 1..3;
+            x
+        };
+        assert_eq!(iter.collect::<Vec<_>>(), vec![0, 1, 2]);
+
+        let iter = iter! {
+            let x <- 0..6;
+            if x % 2 == 0 {
+                yield x;
+            } else if x % 3 == 0 {
+                yield x;
+                yield -x;
+            } else {
+                let y <- 0..2;
+                yield x * 10 + y;
+            }
+            if x == 5 {
+                yield 100;
+            }
+        };
+        assert_eq!(
+            iter.collect::<Vec<_>>(),
+            vec![0, 10, 11, 2, 3, -3, 4, 50, 51, 100]
+        );
+
+        #[cfg(feature = "proc-macro")]
+        {
+            let iter = crate::spanned::iter! {
+                strict;
+                let x <- 0..3;
+                yield x;
+                let y = x * 10;
+                yield y;
+            };
+            assert_eq!(iter.collect::<Vec<_>>(), vec![0, 0, 1, 10, 2, 20]);
+
+            let iter = crate::spanned::iter! {
+                yield 0;
+                let x <- 1..3;
+                x
+            };
+            assert_eq!(iter.collect::<Vec<_>>(), vec![0, 1, 2]);
+        }
+    }
+
+    #[test]
+    fn test_yield_from() {
+        fn flatten(depth: u32) -> Box<dyn Iterator<Item = u32>> {
+            Box::new(iter! {
+                yield depth;
+                if depth > 0 {
+                    yield from flatten(depth - 1);
+                }
+            })
+        }
+        assert_eq!(flatten(3).collect::<Vec<_>>(), vec![3, 2, 1, 0]);
+
+        let iter = iter! {
+            let x <- 0..3;
+            yield from 0..x;
+            x * 10
+        };
+        assert_eq!(iter.collect::<Vec<_>>(), vec![0, 0, 10, 0, 1, 20]);
+
+        let iter = iter! {
+            let xs <- vec![vec![1, 2], vec![], vec![3]];
+            yield from xs;
+        };
+        assert_eq!(iter.collect::<Vec<_>>(), vec![1, 2, 3]);
+
+        #[cfg(feature = "proc-macro")]
+        {
+            let iter = crate::spanned::iter! {
+                let x <- 0..3;
+                yield from 0..x;
+                x * 10
+            };
+            assert_eq!(iter.collect::<Vec<_>>(), vec![0, 0, 10, 0, 1, 20]);
+
+            let iter = crate::spanned::iter! {
+                let xs <- vec![vec![1, 2], vec![], vec![3]];
+                yield from xs;
+            };
+            assert_eq!(iter.collect::<Vec<_>>(), vec![1, 2, 3]);
+
+            let from = 4;
+            let iter = crate::spanned::iter! { yield from; yield from from..6; };
+            assert_eq!(iter.collect::<Vec<_>>(), vec![4, 4, 5]);
+        }
+    }
+
+    #[test]
+    fn test_return() {
+        use std::collections::HashMap;
+
+        let mut cache = HashMap::new();
+        cache.insert("a", 1);
+        let cache = &cache;
+        let lookup = |key: &str| {
+            option! {
+                let first <- key.chars().next();
+                if let Some(value) = cache.get(key) {
+                    return *value;
+                }
+                let digit <- first.to_digit(10);
+                digit as i32
+            }
+        };
+        assert_eq!(lookup("a"), Some(1));
+        assert_eq!(lookup("7"), Some(7));
+        assert_eq!(lookup("b"), None);
+
+        let cached = true;
+        let option = option! {
+            let a <- Some(1);
+            if cached { return 0; }
+            a
+        };
+        assert_eq!(option, Some(0));
+
+        let result = result! {
+            let a <- ok(1);
+            return a + 1;
+            let b <- Err(());
+            b
+        };
+        assert_eq!(result, Ok(2));
+
+        let result = result! {
+            let a <- ok(1);
+            if a > 0 { return a * 10 }
+            let b <- Err::<i32, _>(());
+            a + b
+        };
+        assert_eq!(result, Ok(10));
+
+        let mut count = 0;
+        let option = option! {
+            if count == 0 { count += 1; }
+            let a <- Some(1);
+            a
+        };
+        assert_eq!(option, Some(1));
+        assert_eq!(count, 1);
+
+        const fn clamp(x: u8) -> Option<u8> {
+            const_option! {
+                if x > 10 { return 10; }
+                if x > 0;
+                x
+            }
+        }
+        assert_eq!(clamp(20), Some(10));
+        assert_eq!(clamp(5), Some(5));
+        assert_eq!(clamp(0), None);
+
+        let double = |a: i32| option! {
+            if a > 1 {
+                let b = a * 2;
+                return b;
+            } else if a < 0 {
+                let b = -a;
+                let b = if b > 10 { 10 } else { b };
+                return b
+            } else {
+                let _b = a;
+            }
+            let c <- a.checked_add(100);
+            c
+        };
+        assert_eq!(double(2), Some(4));
+        assert_eq!(double(-3), Some(3));
+        assert_eq!(double(1), Some(101));
+
+        let pick = |a: i32| result! {
+            let b <- ok(a);
+            if b > 0 { return b } else { b - 1 }
+        };
+        assert_eq!(pick(1), Ok(1));
+        assert_eq!(pick(0), Ok(-1));
+
+        const fn shrink(x: u8) -> Option<u8> {
+            const_option! {
+                if x > 200 { let y = x / 2; return y; } else if x > 100 { return 50; }
+                if x > 0;
+                x
+            }
+        }
+        assert_eq!(shrink(250), Some(125));
+        assert_eq!(shrink(101), Some(50));
+        assert_eq!(shrink(3), Some(3));
+
+        #[cfg(feature = "proc-macro")]
+        {
+            let lookup = |key: &str| {
+                crate::spanned::option! {
+                    let first <- key.chars().next();
+                    if let Some(value) = cache.get(key) {
+                        return *value;
+                    }
+                    let digit <- first.to_digit(10);
+                    digit as i32
+                }
+            };
+            assert_eq!(lookup("a"), Some(1));
+            assert_eq!(lookup("7"), Some(7));
+            assert_eq!(lookup("b"), None);
+
+            let result = crate::spanned::result! {
+                let a <- ok(1);
+                return a + 1;
+                let b <- Err(());
+                b
+            };
+            assert_eq!(result, Ok(2));
+
+            let double = |a: i32| crate::spanned::option! {
+                if a > 1 {
+                    let b = a * 2;
+                    return b;
+                } else if a < 0 {
+                    let b = -a;
+                    let b = if b > 10 { 10 } else { b };
+                    return b
+                } else {
+                    let _b = a;
+                }
+                let c <- a.checked_add(100);
+                c
+            };
+            assert_eq!(double(2), Some(4));
+            assert_eq!(double(-3), Some(3));
+            assert_eq!(double(1), Some(101));
+
+            let pick = |a: i32| crate::spanned::result! {
+                let b <- ok(a);
+                if b > 0 { return b } else { b - 1 }
+            };
+            assert_eq!(pick(1), Ok(1));
+            assert_eq!(pick(0), Ok(-1));
+        }
+    }
+
+    #[test]
+    fn test_fail() {
+        let option = option! {
+            let a <- Some(1);
+            fail;
+            a
+        };
+        assert_eq!(option, None::<i32>);
+
+        let option = option! {
+            let a <- Some(1);
+            if a > 1 { fail; }
+            a
+        };
+        assert_eq!(option, Some(1));
+
+        let result = result! {
+            let a <- Ok::<_, String>(1);
+            if a == 1 {
+                fail format!("{} is not allowed", a);
+            }
+            a
+        };
+        assert_eq!(result, Err("1 is not allowed".to_string()));
+
+        let forbidden = true;
+        let result = result! {
+            let a <- Ok(1);
+            if forbidden { fail 7; }
+            a
+        };
+        assert_eq!(result, Err(7));
+
+        let result: Result<i32, &str> = result! {
+            fail "always";
+        };
+        assert_eq!(result, Err("always"));
+
+        let mut log = Vec::new();
+        if log.is_empty() {
+            log.push(0);
+        }
+        let option = option! {
+            if log.len() > 1 {
+                log.push(1);
+            } else if log.is_empty() {
+                log.push(2);
+            } else {
+                log.push(3);
+            };
+            if log.len() > 2 { log.push(4); }
+            let a <- Some(1);
+            if a > 0 { a } else { 0 }
+        };
+        assert_eq!(option, Some(1));
+        assert_eq!(log, vec![0, 3]);
+
+        const fn checked(x: u8) -> Result<u8, u8> {
+            const_result! {
+                if x == 0 { fail x }
+                x
+            }
+        }
+        assert_eq!(checked(1), Ok(1));
+        assert_eq!(checked(0), Err(0));
+
+        let check = |n: i32| result! {
+            let a <- Ok::<_, String>(1);
+            if n > 1 {
+                let z = a + n;
+                fail format!("{}", z);
+            } else if a > 1 {
+                fail "unreachable".to_string()
+            }
+            a
+        };
+        assert_eq!(check(2), Err("3".to_string()));
+        assert_eq!(check(0), Ok(1));
+
+        let positive = |a: i32| option! {
+            if a > 0 { a } else { let _b = a; fail; }
+        };
+        assert_eq!(positive(1), Some(1));
+        assert_eq!(positive(0), None);
+
+        const fn nonzero(x: u8) -> Result<u8, u8> {
+            const_result! {
+                if x > 0 { let y = x; y } else { fail x; }
+            }
+        }
+        assert_eq!(nonzero(1), Ok(1));
+        assert_eq!(nonzero(0), Err(0));
+
+        #[cfg(feature = "proc-macro")]
+        {
+            let option = crate::spanned::option! {
+                let a <- Some(1);
+                fail;
+                a
+            };
+            assert_eq!(option, None::<i32>);
+
+            let result: Result<i32, &str> = crate::spanned::result! {
+                fail "always";
+            };
+            assert_eq!(result, Err("always"));
+
+            let check = |n: i32| crate::spanned::result! {
+                let a <- Ok::<_, String>(1);
+                if n > 1 {
+                    let z = a + n;
+                    fail format!("{}", z);
+                } else if a > 1 {
+                    fail "unreachable".to_string()
+                }
+                a
+            };
+            assert_eq!(check(2), Err("3".to_string()));
+            assert_eq!(check(0), Ok(1));
+
+            let positive = |a: i32| crate::spanned::option! {
+                if a > 0 { a } else { let _b = a; fail; }
+            };
+            assert_eq!(positive(1), Some(1));
+            assert_eq!(positive(0), None);
+        }
+    }
+
+    #[test]
+    fn test_continue() {
+        let iter = iter! {
+            let x <- 0..4;
+            if x == 1 { continue; }
+            let y <- 0..2;
+            if x == 2 && y == 0 {
+                skip;
+            }
+            (x, y)
+        };
+        assert_eq!(iter.collect::<Vec<_>>(), vec![(0, 0), (0, 1), (2, 1), (3, 0), (3, 1)]);
+
+        let iter = iter! {
+            let x <- 0..3;
+            yield x;
+            if x > 0 { continue }
+            yield x + 10;
+        };
+        assert_eq!(iter.collect::<Vec<_>>(), vec![0, 10, 1, 2]);
+
+        let iter = iter! {
+            let x <- 0..4;
+            let odd = x % 2 == 1;
+            if odd { continue; }
+            x
+        };
+        assert_eq!(iter.collect::<Vec<_>>(), vec![0, 2]);
+
+        let mut dropped = Vec::new();
+        let log = &mut dropped;
+        let odd = iter! {
+            let x <- 0..5;
+            if x % 2 == 0 {
+                let y = x * 10;
+                log.push(y);
+                continue;
+            }
+            x
+        }
+        .collect::<Vec<_>>();
+        assert_eq!(odd, vec![1, 3]);
+        assert_eq!(dropped, vec![0, 20, 40]);
+
+        #[cfg(feature = "proc-macro")]
+        {
+            let iter = crate::spanned::iter! {
+                let x <- 0..4;
+                if x == 1 { continue; }
+                let y <- 0..2;
+                if x == 2 && y == 0 {
+                    skip;
+                }
+                (x, y)
+            };
+            assert_eq!(iter.collect::<Vec<_>>(), vec![(0, 0), (0, 1), (2, 1), (3, 0), (3, 1)]);
+
+            let mut dropped = Vec::new();
+            let log = &mut dropped;
+        let log = &mut dropped;
+            let odd = crate::spanned::iter! {
+                let x <- 0..5;
+                if x % 2 == 0 {
+                    let y = x * 10;
+                    log.push(y);
+                    continue;
+                } else if x == 3 {
+                    continue
+                }
+                x
+            }
+            .collect::<Vec<_>>();
+            assert_eq!(odd, vec![1]);
+            assert_eq!(dropped, vec![0, 20, 40]);
+        }
+    }
+
+    #[test]
+    fn test_let_else() {
+        let option = option! {
+            let a <- Some(1);
+            let mut b <- None::<i32> else a + 1;
+            b *= 2;
+            let c <- Some(3) else 0;
+            (a, b, c)
+        };
+        assert_eq!(option, Some((1, 4, 3)));
+
+        let option = option! {
+            let a <- None else 1;
+            let b <- None::<i32>;
+            a + b
+        };
+        assert_eq!(option, None);
+
+        let result: Result<_, ()> = result! {
+            let a <- "x".parse::<i32>() else -1;
+            let b <- "2".parse::<i32>() else -1;
+            a + b
+        };
+        assert_eq!(result, Ok(1));
+
+        const fn first_or(xs: &[u8], d: u8) -> Option<u8> {
+            const_option! {
+                let (x, y) <- match xs { [x, ..] => Some((*x, 0)), [] => None } else (d, 1);
+                x + y
+            }
+        }
+        assert_eq!(first_or(&[5], 0), Some(5));
+        assert_eq!(first_or(&[], 7), Some(8));
+
+        const fn parse_or(r: Result<u8, u8>) -> Result<u8, u8> {
+            const_result! {
+                let x <- r else 0;
+                x
+            }
+        }
+        assert_eq!(parse_or(Ok(3)), Ok(3));
+        assert_eq!(parse_or(Err(3)), Ok(0));
+
+        #[cfg(feature = "proc-macro")]
+        {
+            let option = crate::spanned::option! {
+                let a <- None::<u8> else 1;
+                let b: u8 <- Some(2);
+                a + b
+            };
+            assert_eq!(option, Some(3));
+
+            let result: Result<_, ()> = crate::spanned::result! {
+                let a <- Err::<u8, ()>(()) else 1;
+                a
+            };
+            assert_eq!(result, Ok(1));
+        }
+    }
+
+    #[test]
+    fn test_let_or() {
+        let option = option! {
+            let a <- None or Some(1) or Some(2);
+            let mut b <- None::<i32> or None;
+            b += a;
+            b
+        };
+        assert_eq!(option, None);
+
+        let option = option! {
+            let a <- None or Some(1) or Some(2);
+            let mut b <- None or None else a;
+            b += a;
+            b
+        };
+        assert_eq!(option, Some(2));
+
+        let result = result! {
+            let a <- "x".parse::<i32>() or "1".parse::<i32>();
+            let b <- "y".parse::<i32>() or "z".parse::<i32>() or "2".parse::<i32>();
+            a + b
+        };
+        assert_eq!(result, Ok(3));
+
+        let result: Result<i32, &str> = result! {
+            let a <- Err("a") or Err("b");
+            a
+        };
+        assert_eq!(result, Err("b"));
+
+        let iter = iter! {
+            let x <- 0..2 or 5..6 or vec![7];
+            let mut y <- None or Some(x);
+            y *= 2;
+            y
+        };
+        assert_eq!(iter.collect::<Vec<_>>(), vec![0, 2, 10, 14]);
+
+        const fn pick(a: Option<u8>, b: Option<u8>, c: Result<u8, ()>) -> Option<u8> {
+            const_option! {
+                let x <- a or b or Some(0);
+                let y <- match c { Ok(y) => Some(y), Err(_) => None } or Some(1);
+                x + y
+            }
+        }
+        assert_eq!(pick(None, Some(2), Err(())), Some(3));
+
+        const fn pick_result(a: Result<u8, u8>, b: Result<u8, u8>) -> Result<u8, u8> {
+            const_result! {
+                let x <- a or b;
+                x
+            }
+        }
+        assert_eq!(pick_result(Err(0), Ok(2)), Ok(2));
+        assert_eq!(pick_result(Err(0), Err(1)), Err(1));
+
+        #[cfg(feature = "proc-macro")]
+        {
+            let option = crate::spanned::option! {
+                let a <- None or Some(1);
+                let b: u8 <- None or None or Some(2);
+                a + b
+            };
+            assert_eq!(option, Some(3));
+
+            let iter = crate::spanned::iter! {
+                let x <- 0..2 or vec![5];
+                x
+            };
+            assert_eq!(iter.collect::<Vec<_>>(), vec![0, 1, 5]);
+        }
+    }
+
+    #[test]
+    fn test_ensure() {
+        let check = |a: i32, b: i32| result! {
+            ensure a >= 0, "negative a";
+            let sum <- a.checked_add(b).ok_or("overflow");
+            ensure sum < 10, "too large";
+            sum
+        };
+        assert_eq!(check(1, 2), Ok(3));
+        assert_eq!(check(-1, 2), Err("negative a"));
+        assert_eq!(check(5, 5), Err("too large"));
+        assert_eq!(check(1, i32::MAX), Err("overflow"));
+
+        const fn double(x: u8) -> Result<u8, u8> {
+            const_result! {
+                ensure x < 128, x;
+                x * 2
+            }
+        }
+        assert_eq!(double(4), Ok(8));
+        assert_eq!(double(200), Err(200));
+
+        #[cfg(feature = "proc-macro")]
+        {
+            let result = crate::spanned::result! {
+                let a <- ok(1);
+                ensure a > 0, ();
+                ensure a > 1, ();
+                a
+            };
+            assert_eq!(result, Err(()));
+        }
+    }
+
+    #[cfg(feature = "anyhow")]
+    #[test]
+    fn test_context() {
+        fn parse(a: &str, b: Option<&str>) -> anyhow::Result<i32> {
+            result! {
+                let a <- a.parse::<i32>(), context("parsing a");
+                let mut b <- b, context("missing b");
+                b = b.trim();
+                let b <- b.parse::<i32>(), context(format!("parsing b {}", b));
+                a + b
+            }
+        }
+        assert_eq!(parse("1", Some(" 2")).unwrap(), 3);
+        assert_eq!(parse("x", Some("2")).unwrap_err().to_string(), "parsing a");
+        assert_eq!(parse("1", None).unwrap_err().to_string(), "missing b");
+        assert_eq!(parse("1", Some("y")).unwrap_err().to_string(), "parsing b y");
+    }
+
+    #[test]
+    fn test_error_type() {
+        let parse = |a: &str, b: &[u8]| result! {
+            type Error = String;
+            let a <- a.parse::<u8>().map_err(|e| e.to_string());
+            let b <- core::str::from_utf8(b).map_err(|_| "invalid utf-8");
+            let digit = b.chars().all(|c| c.is_ascii_digit() || c == ' ');
+            let _ <- (if digit { Ok(()) } else { Err("not a digit") });
+            let mut b <- (if b.is_empty() { Err("empty") } else { Ok(b) });
+            b = b.trim();
+            let c <- b.parse::<u8>().map_err(|_| "") else 0;
+            let Some(d) <- Ok::<_, &str>(a.checked_add(c)) => "overflow".to_string();
+            d
+        };
+        assert_eq!(parse("1", b"2"), Ok(3));
+        assert_eq!(parse("x", b"2"), Err("invalid digit found in string".to_string()));
+        assert_eq!(parse("1", b"\xff"), Err("invalid utf-8".to_string()));
+        assert_eq!(parse("1", b""), Err("empty".to_string()));
+        assert_eq!(parse("1", b"a"), Err("not a digit".to_string()));
+        assert_eq!(parse("1", b"9 9"), Ok(1));
+        assert_eq!(parse("255", b"1"), Err("overflow".to_string()));
+
+        let result = result! {
+            type Error = u64;
+            let a <- Err::<u8, _>(1u8) or Err::<u8, _>(2u16) or Ok::<_, u32>(3);
+            a
+        };
+        assert_eq!(result, Ok(3));
+
+        #[cfg(feature = "proc-macro")]
+        {
+            let result = crate::spanned::result! {
+                type Error = i64;
+                let a <- Err::<u8, _>(1u8) or Ok::<_, u8>(2);
+                let b <- Err::<u8, _>(3i32);
+                a + b
+            };
+            assert_eq!(result, Err(3));
+        }
+    }
+
+    #[test]
+    fn test_error_conversion() {
+        #[derive(Debug, PartialEq)]
+        enum Error {
+            Parse(core::num::ParseIntError),
+            Fmt(core::fmt::Error),
+            Custom(&'static str),
+        }
+
+        impl From<core::num::ParseIntError> for Error {
+            fn from(err: core::num::ParseIntError) -> Self {
+                Error::Parse(err)
+            }
+        }
+
+        impl From<core::fmt::Error> for Error {
+            fn from(err: core::fmt::Error) -> Self {
+                Error::Fmt(err)
+            }
+        }
+
+        fn run(a: &str, fmt: Result<(), core::fmt::Error>) -> Result<u8, Error> {
+            result! {
+                type Error = _;
+                let a <- a.parse::<u8>();
+                let _ <- fmt;
+                let b <- a.checked_mul(2).ok_or(Error::Custom("overflow"));
+                b
+            }
+        }
+        assert_eq!(run("2", Ok(())), Ok(4));
+        assert!(matches!(run("x", Ok(())), Err(Error::Parse(_))));
+        assert_eq!(run("2", Err(core::fmt::Error)), Err(Error::Fmt(core::fmt::Error)));
+        assert_eq!(run("200", Ok(())), Err(Error::Custom("overflow")));
+
+        #[cfg(feature = "proc-macro")]
+        {
+            let result: Result<u8, Error> = crate::spanned::result! {
+                type Error = _;
+                let a <- "x".parse::<u8>();
+                a
+            };
+            assert!(matches!(result, Err(Error::Parse(_))));
+        }
+    }
+
+    #[test]
+    fn test_else_lift() {
+        #[derive(Debug, PartialEq)]
+        enum Error {
+            Missing(&'static str),
+            Parse,
+        }
+
+        let get = |a: Option<&'static str>, b: Option<u8>| result! {
+            let a <- a else Error::Missing("a");
+            let a <- a.parse::<u8>().map_err(|_| Error::Parse);
+            let mut b <- b else Error::Missing("b");
+            b += 1;
+            let c <- "x".parse::<u8>() else 0;
+            a + b + c
+        };
+        assert_eq!(get(Some("1"), Some(2)), Ok(4));
+        assert_eq!(get(None, Some(2)), Err(Error::Missing("a")));
+        assert_eq!(get(Some("x"), None), Err(Error::Parse));
+        assert_eq!(get(Some("1"), None), Err(Error::Missing("b")));
+
+        let result = result! {
+            type Error = u32;
+            let a <- Some(1u8) else 2u32;
+            let b <- "2".parse::<u8>() else 0;
+            let c <- Err::<u8, u16>(3);
+            a + b + c
+        };
+        assert_eq!(result, Err(3));
+
+        #[cfg(feature = "proc-macro")]
+        {
+            let result = crate::spanned::result! {
+                type Error = i64;
+                let a <- Some(1) else 2i64;
+                let b <- Err::<u8, _>(3i8) or Err(4u8) else 5;
+                a + b
+            };
+            assert_eq!(result, Ok(6));
+        }
+    }
+
+    #[test]
+    fn test_question_mark() {
+        fn run(a: &str, b: &str, c: &str) -> Option<Result<i32, core::num::ParseIntError>> {
+            let result = result! {
+                let a = a.parse::<i32>()?;
+                let b <- b.parse::<i32>();
+                let c = c.parse::<i32>()?;
+                a + b + c.checked_add(0).unwrap() + "0".parse::<i32>()?
+            };
+            Some(result)
+        }
+        assert_eq!(run("1", "2", "3"), Some(Ok(6)));
+        assert!(matches!(run("x", "2", "3"), Some(Err(_))));
+        assert!(matches!(run("1", "x", "3"), Some(Err(_))));
+        assert!(matches!(run("1", "2", "x"), Some(Err(_))));
+
+        let result: Result<u8, u16> = result! {
+            type Error = u16;
+            let a = Err::<u8, u8>(1)?;
+            a
+        };
+        assert_eq!(result, Err(1));
+
+        // without `?` before the first binding, the body isn't wrapped in a closure
+        async fn get() -> i32 {
+            1
+        }
+        async fn awaited() -> Result<i32, ()> {
+            result! {
+                let a = get().await;
+                let b <- Ok(a);
+                b
+            }
+        }
+        drop(awaited());
+        let mut count = 0;
+        loop {
+            let _: Result<(), ()> = result! {
+                count += 1;
+                if count == 3 { break; }
+                let _ <- Ok(());
+            };
+        }
+        assert_eq!(count, 3);
+
+        #[cfg(feature = "proc-macro")]
+        {
+            let result: Result<i32, core::num::ParseIntError> = crate::spanned::result! {
+                let a = "1".parse::<i32>()?;
+                let b <- ok(2).map_err(|_| "x".parse::<i32>().unwrap_err());
+                a + "y".parse::<i32>()? + b
+            };
+            assert!(result.is_err());
+
+            async fn spanned() -> Result<i32, ()> {
+                crate::spanned::result! {
+                    let a = get().await;
+                    let b <- Ok(a);
+                    b
+                }
+            }
+            drop(spanned());
+            let mut count = 0;
+            loop {
+                let _: Result<(), ()> = crate::spanned::result! {
+                    count += 1;
+                    if count == 3 { break; }
+                    let _ <- Ok(());
+                };
+            }
+            assert_eq!(count, 3);
+        }
+    }
+
+    #[test]
+    fn test_catch() {
+        let run = |a: Result<u8, u8>, b: Result<(u8, u8), u8>| result! {
+            let mut a <- a;
+            catch |e| if e == 0 { Ok(10) } else { Err(e) };
+            a += 1;
+            let (b, c) <- b;
+            catch |_| Ok::<_, u8>((0, 0));
+            let Some(d) <- Ok(Some(1)) => 9;
+            let e: u8 <- Err(5);
+            catch move |e| Ok::<_, u8>(e + a);
+            a + b + c + d + e
+        };
+        assert_eq!(run(Ok(1), Ok((2, 3))), Ok(15));
+        assert_eq!(run(Err(0), Ok((2, 3))), Ok(33));
+        assert_eq!(run(Err(1), Ok((2, 3))), Err(1));
+        assert_eq!(run(Ok(1), Err(2)), Ok(10));
+
+        let result = result! {
+            type Error = u16;
+            let a <- Err::<u8, u8>(1);
+            catch |e: u16| Err(e + 1);
+            a
+        };
+        assert_eq!(result, Err(2));
+
+        #[cfg(feature = "proc-macro")]
+        {
+            let result = crate::spanned::result! {
+                let a <- Err::<u8, u8>(1);
+                catch |e| Ok::<_, u8>(e + 1);
+                let b: u8 <- Err(3);
+                catch |e| if e > 2 { Err(e) } else { Ok(0) };
+                a + b
+            };
+            assert_eq!(result, Err(3));
+        }
+    }
+
+    #[test]
+    fn test_retry() {
+        use core::cell::Cell;
+
+        let count = Cell::new(0);
+        let attempt = |succeed: u32| {
+            count.set(count.get() + 1);
+            if count.get() >= succeed { Ok(count.get()) } else { Err(count.get()) }
+        };
+
+        let result = result! {
+            let a <- attempt(3), retry(3);
+            a
+        };
+        assert_eq!(result, Ok(3));
+
+        count.set(0);
+        let result = result! {
+            let a <- attempt(10), retry(3);
+            a
+        };
+        assert_eq!(result, Err(4));
+
+        count.set(0);
+        let backoffs = Cell::new(0);
+        let result = result! {
+            let mut a <- attempt(2), retry(1u8, |n| backoffs.set(backoffs.get() + n));
+            a += 1;
+            a
+        };
+        assert_eq!((result, backoffs.get()), (Ok(3), 1));
+
+        #[cfg(feature = "proc-macro")]
+        {
+            let count = core::cell::Cell::new(0);
+            let result = crate::spanned::result! {
+                let a <- Err::<u8, _>(count.replace(count.get() + 1)), retry(2);
+                a
+            };
+            assert_eq!((result, count.get()), (Err(2), 3));
+        }
+    }
+
+    #[test]
+    fn test_take_while() {
+        let iter = iter! {
+            let x <- 0..;
+            while x < 3;
+            let mut y <- x..;
+            while y < 4;
+            y *= 10;
+            (x, y)
+        };
+        assert_eq!(
+            iter.collect::<Vec<_>>(),
+            vec![(0, 0), (0, 10), (0, 20), (0, 30), (1, 10), (1, 20), (1, 30), (2, 20), (2, 30)]
+        );
+
+        let iter = iter! {
+            let x: u8 <- vec![1, 2, 5, 1];
+            while x < 5;
+            let mut n = x;
+            while n > 0 {
+                n -= 1;
+            }
+            let (a, b) <- vec![(x, n)];
+            while a > 1 {
+                break;
+            }
+            a + b
+        };
+        assert_eq!(iter.collect::<Vec<_>>(), vec![1, 2]);
+
+        #[cfg(feature = "alloc")]
+        {
+            let vec = vec_comp! {
+                let x <- 0..;
+                let y = x * x;
+                while y < 10;
+                let z <- 0..x;
+                while z < 2;
+                (x, z)
+            };
+            assert_eq!(vec, vec![(1, 0), (2, 0), (2, 1), (3, 0), (3, 1)]);
+        }
+
+        #[cfg(feature = "proc-macro")]
+        {
+            let iter = crate::spanned::iter! {
+                let x <- 0..;
+                while x < 3;
+                let y: i32 <- 0..;
+                while y < x;
+                (x, y)
+            };
+            assert_eq!(iter.collect::<Vec<_>>(), vec![(1, 0), (2, 0), (2, 1)]);
+        }
+    }
+
+    #[test]
+    fn test_skip_while() {
+        let iter = iter! {
+            let x <- 0..3;
+            let mut y <- vec![1, 2, 1, 3];
+            skip_while y <= x;
+            y *= 10;
+            (x, y)
+        };
+        assert_eq!(
+            iter.collect::<Vec<_>>(),
+            vec![
+                (0, 10), (0, 20), (0, 10), (0, 30),
+                (1, 20), (1, 10), (1, 30),
+                (2, 30)
+            ]
+        );
+
+        let iter = iter! {
+            let c: char <- "  a b".chars();
+            skip_while c.is_whitespace();
+            c
+        };
+        assert_eq!(iter.collect::<String>(), "a b");
+
+        #[cfg(feature = "alloc")]
+        {
+            let vec = vec_comp! {
+                let x <- 0..3;
+                let y <- 0..4;
+                skip_while y < x;
+                (x, y)
+            };
+            assert_eq!(
+                vec,
+                vec![(0, 0), (0, 1), (0, 2), (0, 3), (1, 1), (1, 2), (1, 3), (2, 2), (2, 3)]
+            );
+        }
+
+        #[cfg(feature = "proc-macro")]
+        {
+            let iter = crate::spanned::iter! {
+                let x <- 0..2;
+                let y: i32 <- vec![0, 0, 1, 0];
+                skip_while y == x;
+                (x, y)
+            };
+            assert_eq!(iter.collect::<Vec<_>>(), vec![(0, 1), (0, 0), (1, 0), (1, 0), (1, 1), (1, 0)]);
+        }
+    }
+
+    #[test]
+    fn test_take_skip() {
+        let iter = iter! {
+            let x <- 0..;
+            take 3;
+            let mut y: u32 <- 0..;
+            skip x;
+            take 2;
+            y *= 10;
+            (x, y)
+        };
+        assert_eq!(
+            iter.collect::<Vec<_>>(),
+            vec![(0, 0), (0, 10), (1, 10), (1, 20), (2, 20), (2, 30)]
+        );
+
+        let iter = iter! {
+            let (a, b) <- vec![(1, 'a'), (2, 'b'), (3, 'c')];
+            skip 1;
+            let take = a * 2;
+            take + b as i32
+        };
+        assert_eq!(iter.collect::<Vec<_>>(), vec![102, 105]);
+
+        // a call to a function named `take` or `skip` is a statement, not the clause
+        let calls = core::cell::Cell::new(0);
+        let take = |x: i32| calls.set(calls.get() + x);
+        let skip = |x: i32| calls.set(calls.get() + x * 10);
+        let iter = iter! {
+            let x <- 0..3;
+            take(x);
+            let y <- 0..2;
+            skip(y);
+            (x, y)
+        };
+        assert_eq!(iter.count(), 6);
+        assert_eq!(calls.get(), 3 + 30);
+
+        #[cfg(feature = "alloc")]
+        {
+            calls.set(0);
+            let vec = vec_comp! {
+                let x <- 0..3;
+                take(x);
+                skip(x);
+                let y <- 0..2;
+                skip(y);
+                (x, y)
+            };
+            assert_eq!(vec.len(), 6);
+            assert_eq!(calls.get(), 3 + 30 + 30);
+        }
+
+        #[cfg(feature = "alloc")]
+        {
+            let vec = vec_comp! {
+                let x <- 1..;
+                take 3;
+                let y <- 0..x;
+                skip 1;
+                (x, y)
+            };
+            assert_eq!(vec, vec![(2, 1), (3, 1), (3, 2)]);
+        }
+
+        #[cfg(feature = "proc-macro")]
+        {
+            let iter = crate::spanned::iter! {
+                let x <- 0..;
+                skip 2;
+                take 2;
+                let y: i32 <- 0..;
+                take x;
+                (x, y)
+            };
+            assert_eq!(iter.collect::<Vec<_>>(), vec![(2, 0), (2, 1), (3, 0), (3, 1), (3, 2)]);
+
+            calls.set(0);
+            let iter = crate::spanned::iter! {
+                let x <- 0..3;
+                take(x);
+                let y <- 0..2;
+                skip(y);
+                (x, y)
+            };
+            assert_eq!(iter.count(), 6);
+            assert_eq!(calls.get(), 3 + 30);
+        }
+    }
+
+    #[test]
+    fn test_step_by() {
+        let iter = iter! {
+            let x <- 0..6, step_by 2;
+            let mut y: u32 <- 0..5, step_by 3;
+            y += 1;
+            (x, y)
+        };
+        assert_eq!(iter.collect::<Vec<_>>(), vec![(0, 1), (0, 4), (2, 1), (2, 4), (4, 1), (4, 4)]);
+
+        let iter = iter! {
+            let (i, c) <- "abcdef".chars().enumerate(), step_by 2;
+            skip 1;
+            (i, c)
+        };
+        assert_eq!(iter.collect::<Vec<_>>(), vec![(2, 'c'), (4, 'e')]);
+
+        #[cfg(feature = "alloc")]
+        {
+            let vec = vec_comp! {
+                let x <- 1..4;
+                let y <- 0.., step_by x;
+                take 2;
+                (x, y)
+            };
+            assert_eq!(vec, vec![(1, 0), (1, 1), (2, 0), (2, 2), (3, 0), (3, 3)]);
+        }
+
+        #[cfg(feature = "proc-macro")]
+        {
+            let iter = crate::spanned::iter! {
+                let x <- 0..9, step_by 3;
+                let y: usize <- 0.., step_by x + 1;
+                take 2;
+                (x, y)
+            };
+            assert_eq!(iter.collect::<Vec<_>>(), vec![(0, 0), (0, 1), (3, 0), (3, 4), (6, 0), (6, 7)]);
+        }
+    }
+
+    #[test]
+    fn test_enumerate() {
+        let iter = iter! {
+            let (i, x) <- enumerate vec!['a', 'b', 'c'];
+            let mut j <- enumerate x..'e', step_by 2;
+            skip 1;
+            j.0 += i;
+            j
+        };
+        assert_eq!(iter.collect::<Vec<_>>(), vec![(2, 'c'), (3, 'd')]);
+
+        #[cfg(feature = "alloc")]
+        {
+            let vec = vec_comp! {
+                let (i, word) <- enumerate "to be or not".split(' ');
+                if i % 2 == 1;
+                let c: (usize, char) <- enumerate word.chars();
+                c
+            };
+            assert_eq!(vec, vec![(0, 'b'), (1, 'e'), (0, 'n'), (1, 'o'), (2, 't')]);
+        }
+
+        #[cfg(feature = "proc-macro")]
+        {
+            let iter = crate::spanned::iter! {
+                let (i, c) <- enumerate "abcde".chars(), step_by 2;
+                let j <- enumerate 0..i;
+                (c, j)
+            };
+            assert_eq!(
+                iter.collect::<Vec<_>>(),
+                vec![('c', (0, 0)), ('c', (1, 1)), ('e', (0, 0)), ('e', (1, 1)), ('e', (2, 2)), ('e', (3, 3))]
+            );
+        }
+    }
+
+    #[test]
+    fn test_zip() {
+        let xs = &[1, 2, 3, 4];
+        let iter = iter! {
+            let (x, mut y) <- xs.iter() zip 1..;
+            if x % 2 == 0;
+            y -= 1;
+            let ((a, b), c) <- xs.iter().rev() zip 0..2 zip vec!['a', 'b', 'c'];
+            (x * y, a + b, c)
+        };
+        assert_eq!(
+            iter.collect::<Vec<_>>(),
+            vec![(2, 4, 'a'), (2, 4, 'b'), (12, 4, 'a'), (12, 4, 'b')]
+        );
+
+        #[cfg(feature = "proc-macro")]
+        {
+            let iter = crate::spanned::iter! {
+                let ((x, y), c) <- 0..3 zip vec![10, 20] zip "abc".chars();
+                (x + y, c)
+            };
+            assert_eq!(iter.collect::<Vec<_>>(), vec![(10, 'a'), (21, 'b')]);
+        }
+    }
+
+    #[test]
+    fn test_rev() {
+        let iter = iter! {
+            let x <- vec![1, 2, 3], rev;
+            take 2;
+            let mut y: i32 <- 0..x, rev;
+            y *= 10;
+            (x, y)
+        };
+        assert_eq!(iter.collect::<Vec<_>>(), vec![(3, 20), (3, 10), (3, 0), (2, 10), (2, 0)]);
+
+        #[cfg(feature = "alloc")]
+        {
+            let vec = vec_comp! {
+                let (i, c) <- enumerate vec!['a', 'b', 'c'], rev;
+                (i, c)
+            };
+            assert_eq!(vec, vec![(2, 'c'), (1, 'b'), (0, 'a')]);
+        }
+
+        #[cfg(feature = "proc-macro")]
+        {
+            let iter = crate::spanned::iter! {
+                let x <- 0..3, rev;
+                let y: char <- "ab".chars(), rev;
+                (x, y)
+            };
+            assert_eq!(
+                iter.collect::<Vec<_>>(),
+                vec![(2, 'b'), (2, 'a'), (1, 'b'), (1, 'a'), (0, 'b'), (0, 'a')]
+            );
+        }
+    }
+
+    #[test]
+    fn test_copied_cloned() {
+        let weights = [1, 2, 3];
+        let names = vec!["alice".to_string(), "bob".to_string()];
+        let iter = iter! {
+            ref names;
+            let w <- &weights, copied;
+            let mut name: String <- names, cloned;
+            name.push('!');
+            (w * 10, name)
+        };
+        let pairs = iter.collect::<Vec<_>>();
+        assert_eq!(pairs.len(), 6);
+        assert_eq!(pairs[1], (10, "bob!".to_string()));
+
+        let sum: i32 = iter_ref! {
+            sum;
+            let w <- weights, copied;
+            if w > 1;
+            w
+        };
+        assert_eq!(sum, 5);
+
+        #[cfg(feature = "alloc")]
+        {
+            let vec = vec_comp! {
+                let w <- &weights, copied;
+                let name <- &names, cloned;
+                name.len() * w as usize
+            };
+            assert_eq!(vec, [5, 3, 10, 6, 15, 9]);
+        }
+
+        #[cfg(feature = "proc-macro")]
+        {
+            let iter = crate::spanned::iter! {
+                let w <- &weights, copied;
+                let name <- &names, cloned;
+                name + &w.to_string()
+            };
+            assert_eq!(iter.last(), Some("bob3".to_string()));
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_sorted_by_key() {
+        let words = vec!["pear", "fig", "banana"];
+        let iter = iter! {
+            let w <- words.clone(), sorted_by_key(|w| w.len());
+            let mut c: char <- w.chars(), sorted_by_key(|c| *c);
+            take 2;
+            c.make_ascii_uppercase();
+            c
+        };
+        assert_eq!(iter.collect::<String>(), "FGAEAA");
+
+        let vec = vec_comp! {
+            let (i, w) <- enumerate words, sorted_by_key(|(_, w)| w.to_string());
+            (w, i)
+        };
+        assert_eq!(vec, vec![("banana", 2), ("fig", 1), ("pear", 0)]);
+
+        #[cfg(feature = "proc-macro")]
+        {
+            let iter = crate::spanned::iter! {
+                let x <- vec![3, 1, 2], sorted_by_key(|x| *x);
+                let s: &str <- vec!["bb", "a"], sorted_by_key(|s| s.len());
+                (x, s)
+            };
+            assert_eq!(
+                iter.collect::<Vec<_>>(),
+                vec![(1, "a"), (1, "bb"), (2, "a"), (2, "bb"), (3, "a"), (3, "bb")]
+            );
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_unique() {
+        let iter = iter! {
+            let x <- 0..;
+            take 4;
+            let mut y: i32 <- vec![x / 2, 2, x / 2];
+            unique;
+            y *= 10;
+            (x, y)
+        };
+        assert_eq!(
+            iter.collect::<Vec<_>>(),
+            vec![(0, 0), (0, 20), (1, 0), (1, 20), (2, 10), (2, 20), (3, 10), (3, 20)]
+        );
+
+        let set = set_comp! {
+            let (a, b) <- vec![(1, 'a'), (1, 'a'), (2, 'b')];
+            unique;
+            let c <- vec![a, a];
+            unique;
+            (c, b)
+        };
+        assert_eq!(set, vec![(1, 'a'), (2, 'b')].into_iter().collect());
+
+        #[cfg(feature = "proc-macro")]
+        {
+            let iter = crate::spanned::iter! {
+                let x <- vec![1, 2, 1, 3, 2];
+                unique;
+                let y: i32 <- vec![x, 0, x];
+                unique;
+                (x, y)
+            };
+            assert_eq!(
+                iter.collect::<Vec<_>>(),
+                vec![(1, 1), (1, 0), (2, 2), (2, 0), (3, 3), (3, 0)]
+            );
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn test_chunks() {
+        let iter = iter! {
+            let x <- 1..3;
+            let mut batch <- 0..x * 3, chunks 2;
+            batch.push(x);
+            batch
+        };
+        assert_eq!(
+            iter.collect::<Vec<_>>(),
+            vec![
+                vec![0, 1, 1], vec![2, 1],
+                vec![0, 1, 2], vec![2, 3, 2], vec![4, 5, 2]
+            ]
+        );
+
+        let vec = vec_comp! {
+            let batch <- enumerate "a b c".split(' '), chunks 2;
+            let (i, s) <- batch;
+            format!("{}{}", s, i)
+        };
+        assert_eq!(vec, vec!["a0", "b1", "c2"]);
+
+        #[cfg(feature = "proc-macro")]
+        {
+            let iter = crate::spanned::iter! {
+                let batch: Vec<char> <- "abcde".chars(), chunks 2;
+                batch.into_iter().collect::<String>()
+            };
+            assert_eq!(iter.collect::<Vec<_>>(), vec!["ab", "cd", "e"]);
+        }
+    }
+
+    #[test]
+    fn test_windows() {
+        let iter = iter! {
+            let (prev, mut cur) <- 0..4, windows 2;
+            cur *= 10;
+            let (a, b, c) <- vec![prev, cur, prev, cur], windows 3;
+            a + b + c
+        };
+        assert_eq!(iter.collect::<Vec<_>>(), vec![10, 20, 22, 41, 34, 62]);
+
+        let iter = iter! {
+            let pair <- 0..1, windows 2;
+            pair
+        };
+        assert_eq!(iter.count(), 0);
+
+        #[cfg(feature = "alloc")]
+        {
+            let size = 4;
+            let vec = vec_comp! {
+                let w <- 0..6, windows size;
+                let (x, y) <- w, windows 2;
+                if x % 2 == 1;
+                y
+            };
+            assert_eq!(vec, vec![2, 2, 4, 4]);
+        }
+
+        #[cfg(feature = "proc-macro")]
+        {
+            let iter = crate::spanned::iter! {
+                let (a, b) <- vec![1, 2, 4], windows 2;
+                let (x, y, z): (i32, i32, i32) <- a..b + 3, windows 3;
+                x * y * z
+            };
+            assert_eq!(iter.collect::<Vec<_>>(), vec![6, 24, 24, 60, 120]);
+
+            #[cfg(feature = "alloc")]
+            {
+                let iter = crate::spanned::iter! {
+                    let w <- "abcd".chars(), windows 2 + 1;
+                    w.into_iter().collect::<String>()
+                };
+                assert_eq!(iter.collect::<Vec<_>>(), vec!["abc", "bcd"]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_scan() {
+        let iter = iter! {
+            let x <- 1..4;
+            let mut product <- scan(1, |acc, y| {
+                *acc *= y;
+                if *acc < 20 { Some(*acc) } else { None }
+            }) over x..;
+            product += 1;
+            (x, product)
+        };
+        assert_eq!(
+            iter.collect::<Vec<_>>(),
+            vec![(1, 2), (1, 3), (1, 7), (2, 3), (2, 7), (3, 4), (3, 13)]
+        );
+
+        #[cfg(feature = "alloc")]
+        {
+            let vec = vec_comp! {
+                let (c, depth) <- scan(0, |depth, c| {
+                    match c {
+                        '(' => *depth += 1,
+                        ')' => *depth -= 1,
+                        _ => {}
+                    }
+                    Some((c, *depth))
+                }) over "(a(b))".chars();
+                if c.is_alphabetic();
+                (c, depth)
+            };
+            assert_eq!(vec, vec![('a', 1), ('b', 2)]);
+        }
+
+        #[cfg(feature = "proc-macro")]
+        {
+            let iter = crate::spanned::iter! {
+                let n <- 1..3;
+                let (x, total): (i32, i32) <- scan(0, |acc, x| {
+                    *acc += x;
+                    Some((x, *acc))
+                }) over 0..4, step_by n;
+                (x, total)
+            };
+            assert_eq!(
+                iter.collect::<Vec<_>>(),
+                vec![(0, 0), (1, 1), (2, 3), (3, 6), (0, 0), (2, 3)]
+            );
+        }
+    }
+
+    #[test]
+    fn test_fold() {
+        let n = iter! {
+            fold(0, |acc, (x, y)| acc + x * y);
+            let x <- 1..4;
+            let y <- x..4;
+            (x, y)
+        };
+        assert_eq!(n, 1 + 2 + 3 + 4 + 6 + 9);
+
+        let max_len = iter! {
+            fold(None, |acc: Option<usize>, len| acc.max(Some(len)));
+            let word <- "the quick brown fox".split(' ');
+            word.len()
+        };
+        assert_eq!(max_len, Some(5));
+
+        let empty = iter! {
+            fold(1, |acc, x| acc * x);
+            let x <- 0..0;
+            x
+        };
+        assert_eq!(empty, 1);
+
+        #[cfg(feature = "proc-macro")]
+        {
+            let folded = crate::spanned::iter! {
+                fold(String::new(), |s, c| s + c);
+                let x <- vec!["a", "b", "c"];
+                if x != "b";
+                x
+            };
+            assert_eq!(folded, "ac");
+        }
+    }
+
+    #[test]
+    fn test_reduce() {
+        let sum: u32 = iter! {
+            sum;
+            let x <- 1..4;
+            let y <- 0..x;
+            x * y
+        };
+        assert_eq!(sum, 2 + 3 + 6);
+
+        let product: u64 = iter! {
+            product;
+            let x <- 1..6;
+            x
+        };
+        assert_eq!(product, 120);
+
+        let count = iter! {
+            count;
+            let c <- "hello world".chars();
+            if "aeiou".contains(c);
+        };
+        assert_eq!(count, 3);
+
+        let min = iter! {
+            min;
+            let (name, age) <- vec![("alice", 30), ("bob", 25)];
+            (age, name)
+        };
+        assert_eq!(min, Some((25, "bob")));
+
+        let max = iter! {
+            max;
+            let x <- 0..0;
+            x
+        };
+        assert_eq!(max, None);
+
+        #[cfg(feature = "proc-macro")]
+        {
+            let count = crate::spanned::iter! {
+                count;
+                let x <- 0..4;
+                let y <- x..4;
+                (x, y)
+            };
+            assert_eq!(count, 10);
+
+            let min = crate::spanned::iter! {
+                min;
+                let x: i32 <- vec![3, -1, 2];
+                x * x
+            };
+            assert_eq!(min, Some(1));
+        }
+    }
+
+    #[test]
+    fn test_first() {
+        let visited = &core::cell::Cell::new(0);
+        let first = iter! {
+            first;
+            let x <- 0..10;
+            visited.set(visited.get() + 1);
+            if x > 3;
+            x
+        };
+        assert_eq!(first, Some(4));
+        assert_eq!(visited.get(), 5);
 
-/// syntax extension specific for Option
-///
-/// See the module-level documentation for more details.
-#[macro_export]
-macro_rules! option {
-    (@as_pat $p: pat) => ($p);
+        let first = iter! {
+            first;
+            let x <- 0..0;
+            x
+        };
+        assert_eq!(first, None);
 
-    () => {
-        Some(())
-    };
+        let found = iter! {
+            find(|&(x, y)| x + y == 5);
+            let x <- 0..5;
+            let y <- vec![4, 3, 2];
+            (x, y)
+        };
+        assert_eq!(found, Some((1, 4)));
 
-    (
-        let mut $p: tt <- $e: expr ; $( $t: tt )*
-    ) => (
-        $e.and_then(move | option! (@as_pat mut $p) | { option! { $( $t )* } } )
-    );
+        let found = iter! {
+            find(|c: &char| c.is_uppercase());
+            let c <- "no capitals".chars();
+            c
+        };
+        assert_eq!(found, None);
 
-    (
-        let mut $p: ident : $ty: tt <- $e: expr ; $( $t: tt )*
-    ) => (
-        $e.and_then(move | mut $p : $ty | { option! { $( $t )* } } )
-    );
+        #[cfg(feature = "proc-macro")]
+        {
+            let first = crate::spanned::iter! {
+                first;
+                let x <- 1..;
+                if x * x > 50;
+                x
+            };
+            assert_eq!(first, Some(8));
 
-    (
-        let $p: tt <- $e: expr ; $( $t: tt )*
-    ) => (
-        $e.and_then(move | option! (@as_pat $p) | { option! { $( $t )* } } )
-    );
+            let found = crate::spanned::iter! {
+                find(|s: &String| s.len() > 1);
+                let x <- vec![1, 5, 12, 7, 30];
+                x.to_string()
+            };
+            assert_eq!(found.as_deref(), Some("12"));
+        }
+    }
 
-    (
-        let $p: tt ( $( $para: tt )* ) <- $e: expr ; $( $t: tt )*
-    ) => (
-        $e.and_then(move | option! (@as_pat $p ( $( $para )* ) ) | { option! { $( $t )* } } )
-    );
+    #[test]
+    fn test_any_all() {
+        let visited = &core::cell::Cell::new(0);
+        let any = iter! {
+            any;
+            let x <- 0..;
+            visited.set(visited.get() + 1);
+            x * x > 10
+        };
+        assert!(any);
+        assert_eq!(visited.get(), 5);
 
-    (
-        let $p: tt { $( $para: tt )* } <- $e: expr ; $( $t: tt )*
-    ) => (
-        $e.and_then(move | option! (@as_pat $p { $( $para )* } ) | { option! { $( $t )* } } )
-    );
+        let any = iter! {
+            any;
+            let x <- 0..0;
+            x > 0
+        };
+        assert!(!any);
+
+        let all = iter! {
+            all;
+            let x <- 1..5;
+            let y <- x..5;
+            y >= x
+        };
+        assert!(all);
+
+        let all = iter! {
+            all;
+            let word <- vec!["apple", "avocado", "banana"];
+            word.starts_with('a')
+        };
+        assert!(!all);
+
+        #[cfg(feature = "proc-macro")]
+        {
+            let any = crate::spanned::iter! {
+                any;
+                let x <- 0..3;
+                let y <- 0..3;
+                x * y == 4
+            };
+            let all = crate::spanned::iter! {
+                all;
+                let x: u8 <- 1..;
+                take 3;
+                x < 3
+            };
+            assert!(any);
+            assert!(!all);
+        }
+    }
+
+    #[test]
+    fn test_partition() {
+        let (even, odd): (Vec<_>, Vec<_>) = iter! {
+            partition(|n| n % 2 == 0);
+            let x <- 0..6;
+            if x != 3;
+            x
+        };
+        assert_eq!(even, [0, 2, 4]);
+        assert_eq!(odd, [1, 5]);
+
+        let (short, long): (Vec<_>, Vec<_>) = iter! {
+            partition(|word: &&str| word.len() < 6);
+            let word <- vec!["apple", "avocado", "banana", "fig"];
+            word
+        };
+        assert_eq!(short, ["apple", "fig"]);
+        assert_eq!(long, ["avocado", "banana"]);
+
+        #[cfg(feature = "proc-macro")]
+        {
+            let (small, large): (Vec<_>, Vec<_>) = crate::spanned::iter! {
+                partition(|&n| n < 4);
+                let x <- 1..3;
+                let y <- 1..3;
+                x * y
+            };
+            assert_eq!(small, [1, 2, 2]);
+            assert_eq!(large, [4]);
+        }
+    }
+
+    #[test]
+    fn test_unzip() {
+        let (xs, ys): (Vec<_>, Vec<_>) = iter! {
+            unzip;
+            let x <- 0..3;
+            let y <- x..3;
+            (x, y)
+        };
+        assert_eq!(xs, [0, 0, 0, 1, 1, 2]);
+        assert_eq!(ys, [0, 1, 2, 1, 2, 2]);
+
+        let (keys, values): (Vec<_>, std::collections::BTreeSet<_>) = iter! {
+            unzip;
+            let word <- vec!["apple", "avocado", "banana"];
+            (word, word.chars().next().unwrap())
+        };
+        assert_eq!(keys, ["apple", "avocado", "banana"]);
+        assert_eq!(values.into_iter().collect::<Vec<_>>(), ['a', 'b']);
+
+        #[cfg(feature = "proc-macro")]
+        {
+            let (xs, squares): (Vec<_>, Vec<_>) = crate::spanned::iter! {
+                unzip;
+                let x <- 0..3;
+                (x, x * x)
+            };
+            assert_eq!(xs, [0, 1, 2]);
+            assert_eq!(squares, [0, 1, 4]);
+        }
+    }
+
+    #[test]
+    fn test_collect_into() {
+        let pairs = iter! {
+            collect into Vec<_>;
+            let x <- 0..3;
+            let y <- x..3;
+            (x, y)
+        };
+        assert_eq!(pairs, [(0, 0), (0, 1), (0, 2), (1, 1), (1, 2), (2, 2)]);
+
+        let initials = iter! {
+            collect into std::collections::BTreeSet<char>;
+            let word <- vec!["banana", "apple", "avocado"];
+            word.chars().next().unwrap()
+        };
+        assert_eq!(initials.into_iter().collect::<String>(), "ab");
+
+        let sentence = iter! {
+            collect into String;
+            let word <- vec!["comp", "rs"];
+            word
+        };
+        assert_eq!(sentence, "comprs");
+
+        #[cfg(feature = "proc-macro")]
+        {
+            let squares = crate::spanned::iter! {
+                collect into Vec<u32>;
+                let x <- 0..4;
+                if x % 2 == 1;
+                x * x
+            };
+            assert_eq!(squares, [1, 9]);
+        }
+    }
+
+    #[test]
+    fn test_extend() {
+        let mut pairs = vec![(9, 9)];
+        iter! {
+            extend pairs;
+            let x <- 0..2;
+            let y <- x..2;
+            (x, y)
+        };
+        assert_eq!(pairs, [(9, 9), (0, 0), (0, 1), (1, 1)]);
+
+        fn fill(buffer: &mut Vec<usize>, words: &[&str]) {
+            iter! {
+                extend *buffer;
+                let word <- words.iter();
+                if !word.is_empty();
+                word.len()
+            };
+        }
+        let mut lengths = Vec::new();
+        fill(&mut lengths, &["comp", "", "rs"]);
+        fill(&mut lengths, &["iter"]);
+        assert_eq!(lengths, [4, 2, 4]);
+
+        #[cfg(feature = "proc-macro")]
+        {
+            let mut buffer = vec![0];
+            crate::spanned::iter! {
+                extend buffer;
+                let x <- 1..3;
+                x
+            };
+            assert_eq!(buffer, [0, 1, 2]);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_join() {
+        let iter = iter! {
+            let x <- 0..6;
+            join y in vec![3, 4, 7, 10] on x % 3 == y % 3;
+            (x, y)
+        };
+        assert_eq!(
+            iter.collect::<Vec<_>>(),
+            [(0, 3), (1, 4), (1, 7), (1, 10), (3, 3), (4, 4), (4, 7), (4, 10)]
+        );
+
+        let authors = [("ann", 1), ("ben", 2), ("cat", 3)];
+        let books = [(2, "dune"), (1, "emma"), (4, "ulysses"), (1, "persuasion")];
+        let iter = iter! {
+            let (name, id) <- authors.iter();
+            join (author, title) in books.iter() on id == author;
+            if title.len() > 4;
+            (*name, *title)
+        };
+        assert_eq!(iter.collect::<Vec<_>>(), [("ann", "persuasion")]);
+
+        let iter = iter! {
+            let x <- 1..3;
+            let y <- 0..4;
+            join z in 0..x on y == z;
+            (x, y, z)
+        };
+        assert_eq!(iter.collect::<Vec<_>>(), [(1, 0, 0), (2, 0, 0), (2, 1, 1)]);
+
+        #[cfg(feature = "proc-macro")]
+        {
+            let iter = crate::spanned::iter! {
+                let x: u32 <- 0..4;
+                join (y, name) in vec![(1, "one"), (3, "three")] on x == y;
+                if x > 1;
+                (x, name)
+            };
+            assert_eq!(iter.collect::<Vec<_>>(), [(3, "three")]);
+        }
+    }
+
+    #[test]
+    fn test_merge_join() {
+        let iter = iter! {
+            let x <- vec![1, 2, 2, 4, 6];
+            merge_join y in [0, 2, 2, 3, 4, 7].iter() on x == *y;
+            (x, *y)
+        };
+        assert_eq!(
+            iter.collect::<Vec<_>>(),
+            [(2, 2), (2, 2), (2, 2), (2, 2), (4, 4)]
+        );
+
+        let iter = iter! {
+            let x <- 1..3;
+            let y <- 0..10;
+            merge_join z in (0..x).map(|z| z * 3) on y == z;
+            if y > 0;
+            (x, y, z)
+        };
+        assert_eq!(iter.collect::<Vec<_>>(), [(2, 3, 3)]);
+
+        let iter = iter! {
+            let x <- 0..3;
+            merge_join y in 5..5 on x == y;
+            (x, y)
+        };
+        assert_eq!(iter.count(), 0);
+
+        #[cfg(feature = "proc-macro")]
+        {
+            let iter = crate::spanned::iter! {
+                let x: u32 <- 0..4;
+                merge_join (y, name) in [(1, "one"), (3, "three")].iter() on x == *y;
+                (x, *name)
+            };
+            assert_eq!(iter.collect::<Vec<_>>(), [(1, "one"), (3, "three")]);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "proc-macro")]
+    fn test_hoist() {
+        // neither `Clone` nor evaluated only once, as it isn't made of literals and paths
+        struct Once(Option<char>);
+        impl Iterator for Once {
+            type Item = char;
+            fn next(&mut self) -> Option<char> {
+                self.0.take()
+            }
+        }
+        let built = &core::cell::Cell::new(0);
+        let ys = vec![10, 20];
+        let iter = crate::spanned::iter! {
+            let x <- 0..2;
+            let c <- { built.set(built.get() + 1); Once(Some('a')) };
+            let y <- &ys;
+            let z <- x..2;
+            (x, c, y + z)
+        };
+        assert_eq!(
+            iter.collect::<Vec<_>>(),
+            [(0, 'a', 10), (0, 'a', 11), (0, 'a', 20), (0, 'a', 21), (1, 'a', 11), (1, 'a', 21)]
+        );
+        assert_eq!(built.get(), 2);
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn test_memo() {
+        let evaluated = &core::cell::Cell::new(0);
+        let pulled = &core::cell::Cell::new(0);
+        let source = || {
+            evaluated.set(evaluated.get() + 1);
+            (0..).map(move |y| {
+                pulled.set(pulled.get() + 1);
+                y * 10
+            })
+        };
+        let iter = iter! {
+            let x <- 0..3;
+            let y <- memo source();
+            take 2;
+            x + y
+        };
+        assert_eq!(iter.collect::<Vec<_>>(), [0, 10, 1, 11, 2, 12]);
+        assert_eq!(evaluated.get(), 1);
+        assert_eq!(pulled.get(), 2);
+
+        evaluated.set(0);
+        let iter = iter! {
+            let a <- 0..2;
+            let b <- 0..2;
+            let c: i32 <- memo { evaluated.set(evaluated.get() + 1); vec![a * 10] };
+            a + b + c
+        };
+        assert_eq!(iter.collect::<Vec<_>>(), [0, 1, 11, 12]);
+        assert_eq!(evaluated.get(), 2);
+
+        let mut memo = crate::__memo(0..3);
+        let replay = memo.clone();
+        assert_eq!(memo.next(), Some(0));
+        assert_eq!(memo.size_hint(), (2, Some(2)));
+        assert_eq!(replay.size_hint(), (3, Some(3)));
+
+        #[cfg(feature = "proc-macro")]
+        {
+            let built = &core::cell::Cell::new(0);
+            let iter = crate::spanned::iter! {
+                let x <- 0..2;
+                let c <- memo { built.set(built.get() + 1); vec!['a', 'b'] };
+                (x, c)
+            };
+            assert_eq!(iter.collect::<Vec<_>>(), [(0, 'a'), (0, 'b'), (1, 'a'), (1, 'b')]);
+            assert_eq!(built.get(), 1);
+
+            let evaluated = &core::cell::Cell::new(0);
+            let iter = crate::spanned::iter! {
+                let a <- 0..2;
+                let b: i32 <- 0..2;
+                let c <- memo { evaluated.set(evaluated.get() + 1); vec![a * 10] };
+                a + b + c
+            };
+            assert_eq!(iter.collect::<Vec<_>>(), [0, 1, 11, 12]);
+            assert_eq!(evaluated.get(), 2);
+        }
+    }
+
+    #[test]
+    fn test_lazy_guard() {
+        let iter = iter! {
+            let word <- vec!["comp", "", "rs"];
+            if !word.is_empty();
+            word.chars().next().unwrap()
+        };
+        assert_eq!(iter.collect::<String>(), "cr");
+
+        let built = &core::cell::Cell::new(0);
+        let iter = iter! {
+            let x <- 0..6;
+            if x % 3 == 0;
+            built.set(built.get() + 1);
+            let y <- 0..2;
+            x + y
+        };
+        assert_eq!(iter.collect::<Vec<_>>(), [0, 1, 3, 4]);
+        assert_eq!(built.get(), 2);
+
+        #[cfg(feature = "proc-macro")]
+        {
+            let iter = crate::spanned::iter! {
+                let word <- vec!["comp", "", "rs"];
+                if !word.is_empty();
+                word.chars().next().unwrap()
+            };
+            assert_eq!(iter.collect::<String>(), "cr");
+        }
+    }
+
+    #[test]
+    fn test_last_binding() {
+        use core::iter::{FilterMap, FlatMap, Flatten, Map};
+
+        let iter: Map<_, _> = iter! {
+            let x: i32 <- 0..3;
+            x * 2
+        };
+        assert_eq!(iter.collect::<Vec<_>>(), [0, 2, 4]);
+
+        let iter: FilterMap<_, _> = iter! {
+            let x: i32 <- 0..6;
+            if x % 2 == 0;
+            unless x == 2;
+            x
+        };
+        assert_eq!(iter.collect::<Vec<_>>(), [0, 4]);
+
+        let iter: FilterMap<_, _> = iter! {
+            let (x, Some(y)) <- vec![(1, Some('a')), (2, None), (3, Some('c'))];
+            (x, y)
+        };
+        assert_eq!(iter.collect::<Vec<_>>(), [(1, 'a'), (3, 'c')]);
+
+        let iter: Flatten<FilterMap<_, _>> = iter! {
+            let (x, true) <- vec![(0, true), (1, false), (2, true)];
+            let y: i32 <- 0..x;
+            if y > 0;
+            (x, y)
+        };
+        assert_eq!(iter.collect::<Vec<_>>(), [(2, 1)]);
+
+        let iter: FlatMap<_, crate::__Bind<_, _, true>, _> = iter! {
+            let x: i32 <- 0..3;
+            let y <- x..3;
+            (x, y * 2)
+        };
+        assert_eq!(iter.collect::<Vec<_>>(), [(0, 0), (0, 2), (0, 4), (1, 2), (1, 4), (2, 4)]);
+
+        const ZERO: i32 = 0;
+        let iter = iter! {
+            let ZERO <- vec![0, 1, 0];
+            'z'
+        };
+        assert_eq!(iter.size_hint(), (0, Some(3)));
+        assert_eq!(iter.collect::<String>(), "zz");
+
+        let iter = iter! {
+            let None <- vec![Some(1), None];
+            let x <- 0..2;
+            x
+        };
+        assert_eq!(iter.collect::<Vec<_>>(), [0, 1]);
+
+        #[cfg(feature = "proc-macro")]
+        {
+            let iter = crate::spanned::iter! {
+                let ZERO: i32 <- vec![0, 1, 0];
+                'z'
+            };
+            assert_eq!(iter.size_hint(), (0, Some(3)));
+            assert_eq!(iter.collect::<String>(), "zz");
+
+            let iter = crate::spanned::iter! {
+                let None <- vec![Some(1), None];
+                let x <- 0..2;
+                x
+            };
+            assert_eq!(iter.collect::<Vec<_>>(), [0, 1]);
+
+            let option = crate::spanned::option! {
+                let None: Option<i32> <- Some(Some(1));
+                7
+            };
+            assert_eq!(option, None);
+        }
+
+        #[cfg(feature = "proc-macro")]
+        {
+            let iter: core::iter::Map<_, _> = crate::spanned::iter! {
+                let x: i32 <- 0..3;
+                x * 2
+            };
+            assert_eq!(iter.collect::<Vec<_>>(), [0, 2, 4]);
+        }
+    }
+
+    #[test]
+    fn test_iter_base() {
+        let iter: core::iter::Once<()> = iter! {};
+        assert_eq!(iter.count(), 1);
+
+        let iter = iter! { 1 + 2 };
+        assert_eq!(iter.map(|x| x * 2).collect::<Vec<_>>(), [6]);
+
+        let iter: core::iter::Once<_> = iter! {
+            let x = 'a';
+            x
+        };
+        assert_eq!(iter.collect::<String>(), "a");
+
+        #[cfg(feature = "proc-macro")]
+        {
+            let iter: core::iter::Once<()> = crate::spanned::iter! {};
+            assert_eq!(iter.count(), 1);
+        }
+    }
+
+    #[test]
+    fn test_hygiene() {
+        enum Shadow {
+            Some,
+            None,
+            Ok,
+            Err,
+        }
+        #[allow(unused_imports)]
+        use Shadow::*;
+
+        let option = option! {
+            let a <- Option::Some(1);
+            let (b, c) <- Option::Some((2, 3));
+            a + b + c
+        };
+        assert_eq!(option, Option::Some(6));
+
+        let result = result! {
+            let a <- ok(1);
+            let b <- ok(2);
+            a + b
+        };
+        assert_eq!(result, Result::Ok(3));
+
+        let iter = iter! {
+            let (x, true) <- [(1, true), (2, false), (3, true)], by_value;
+            let y <- 0..x;
+            if y % 2 == 0;
+            y
+        };
+        assert_eq!(iter.collect::<Vec<_>>(), [0, 0, 2]);
+
+        assert_eq!(no_prelude::sum(), 6);
+        assert_eq!(no_prelude::checked(), Option::Some(3));
+
+        #[cfg(feature = "proc-macro")]
+        {
+            let option = crate::spanned::option! {
+                let a <- Option::Some(1);
+                let b <- Option::Some(2);
+                a + b
+            };
+            assert_eq!(option, Option::Some(3));
+
+            let result = crate::spanned::result! {
+                let a <- ok(1);
+                if a > 0 => ();
+                a
+            };
+            assert_eq!(result, Result::Ok(1));
+
+            let iter = crate::spanned::iter! {
+                let Option::Some(x) <- [Option::Some(1), Option::None, Option::Some(3)], by_value;
+                let y <- 0..x;
+                if y % 2 == 0;
+                y
+            };
+            assert_eq!(iter.collect::<Vec<_>>(), [0, 0, 2]);
+        }
+    }
+
+    #[no_implicit_prelude]
+    mod no_prelude {
+        pub fn sum() -> i32 {
+            iter! {
+                sum;
+                let x <- 0..4;
+                unless x == 0;
+                x
+            }
+        }
+
+        pub const fn checked() -> ::core::option::Option<u8> {
+            const_option! {
+                let a <- ::core::option::Option::Some(1u8);
+                let b <- a.checked_add(1);
+                a + b
+            }
+        }
+    }
+
+    #[test]
+    fn test_double_ended() {
+        let iter = iter! {
+            let x <- 0..3;
+            x * 2
+        };
+        assert_eq!(iter.rev().collect::<Vec<_>>(), [4, 2, 0]);
+
+        let iter = iter! {
+            let (x, true) <- vec![(1, true), (2, false), (3, true)];
+            unless x == 3;
+            x
+        };
+        assert_eq!(iter.rev().collect::<Vec<_>>(), [1]);
+
+        let iter = iter! {
+            let x <- 0..3;
+            let y = x + 1;
+            let z <- 0..y;
+            if z % 2 == 0;
+            (x, z)
+        };
+        let mut iter = iter.rev();
+        assert_eq!(iter.next(), Some((2, 2)));
+        assert_eq!(iter.next(), Some((2, 0)));
+        assert_eq!(iter.collect::<Vec<_>>(), [(1, 0), (0, 0)]);
+
+        let iter = iter! {
+            let (i, c) <- enumerate vec!['a', 'b'];
+            if i > 0 { c } else { '_' }
+        };
+        assert_eq!(iter.rev().collect::<String>(), "b_");
+
+        #[cfg(feature = "proc-macro")]
+        {
+            let iter = crate::spanned::iter! {
+                let x <- 0..3;
+                let y <- 0..2;
+                if x != y;
+                (x, y)
+            };
+            assert_eq!(iter.rev().collect::<Vec<_>>(), [(2, 1), (2, 0), (1, 0), (0, 1)]);
+        }
+    }
+
+    #[test]
+    fn test_size_hint() {
+        let iter = iter! {
+            let x <- vec![1, 2, 3];
+            x * 2
+        };
+        assert_eq!(iter.len(), 3);
+
+        let iter = iter! {
+            let x: usize <- 0..4;
+            if x > 0;
+            x
+        };
+        assert_eq!(iter.size_hint(), (0, Some(4)));
+
+        let enabled = true;
+        let iter = iter! {
+            if enabled;
+            let pair <- 0..2 zip vec!['a', 'b', 'c'];
+            pair
+        };
+        assert_eq!(iter.len(), 2);
+
+        let iter = iter! {
+            unless enabled;
+            let x <- 0..4;
+            x
+        };
+        assert_eq!(iter.size_hint(), (0, Some(0)));
+
+        let mut iter = iter! {
+            let x <- 0..2;
+            let y <- vec![x; 3];
+            y
+        };
+        iter.next();
+        assert_eq!(iter.size_hint(), (2, None));
+
+        #[cfg(feature = "proc-macro")]
+        {
+            let enabled = true;
+            let iter = crate::spanned::iter! {
+                if enabled;
+                let x <- vec![1, 2, 3];
+                x * 2
+            };
+            assert_eq!(iter.len(), 3);
+        }
+    }
+
+    #[test]
+    fn test_fused() {
+        fn fused<I: core::iter::FusedIterator>(iter: I) -> I {
+            iter
+        }
+
+        let enabled = true;
+        let iter = fused(iter! {
+            if enabled;
+            let x <- 0..3;
+            let y <- x..3;
+            if x != y;
+            (x, y)
+        });
+        assert_eq!(iter.count(), 3);
 
-    (
-        let $p: ident : $ty: tt <- $e: expr ; $( $t: tt )*
-    ) => (
-        $e.and_then(move | $p : $ty | { option! { $( $t )* } } )
-    );
+        let iter = fused(iter! {
+            let (a, b) <- vec![1, 2, 4], windows 2;
+            b - a
+        });
+        assert_eq!(iter.collect::<Vec<_>>(), [1, 2]);
 
-    (
-        $stmt: stmt ; $( $t: tt )*
-    ) => (
-        { $stmt ; option! { $( $t )* } }
-    );
+        #[cfg(feature = "alloc")]
+        {
+            let iter = fused(iter! {
+                let x <- 0..2;
+                let y <- memo vec!['a', 'b'];
+                (x, y)
+            });
+            assert_eq!(iter.count(), 4);
+        }
+    }
 
-    (
-        $e: expr ; $( $t: tt )*
-    ) => (
-        { $e ; option! { $( $t )* } }
-    );
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn test_boxed() {
+        fn pairs(xs: &[u32]) -> Box<dyn Iterator<Item = (u32, u32)> + '_> {
+            iter! {
+                boxed;
+                let (i, x) <- enumerate xs;
+                boxed;
+                let y <- &xs[i + 1..];
+                if x != y;
+                (*x, *y)
+            }
+        }
+        assert_eq!(pairs(&[1, 2, 2]).collect::<Vec<_>>(), [(1, 2), (1, 2)]);
 
-    (
-        $e: expr
-    ) => (
-        Some($e)
-    );
+        let iter: Box<dyn Iterator<Item = i32>> = iter! {
+            boxed;
+            let x <- 0..3;
+            boxed;
+            let y <- 0..x;
+            boxed;
+            x * y
+        };
+        assert_eq!(iter.collect::<Vec<_>>(), [0, 0, 2]);
 
-    (
-        $b: block ; $( $t: tt )*
-    ) => (
-        $b ; option! { $( $t )* }
-    );
-}
+        let sum: i32 = iter! {
+            sum;
+            boxed;
+            let x <- 0..4;
+            x
+        };
+        assert_eq!(sum, 6);
 
-/// syntax extension specific for Result
-///
-/// See the module-level documentation for more details.
-#[macro_export]
-macro_rules! result {
-    (@as_pat $p: pat) => ($p);
+        #[cfg(feature = "proc-macro")]
+        {
+            let iter: Box<dyn Iterator<Item = (i32, i32)>> = crate::spanned::iter! {
+                boxed;
+                let x <- 0..3;
+                boxed;
+                let y <- x..3;
+                (x, y)
+            };
+            assert_eq!(iter.count(), 6);
+        }
+    }
 
-    () => {
-        Ok(())
-    };
+    #[test]
+    fn test_send() {
+        use futures::executor::block_on;
+        use futures::future::ready;
 
-    (
-        let mut $p: tt <- $e: expr ; $( $t: tt )*
-    ) => (
-        $e.and_then(move | result! (@as_pat mut $p) | { result! { $( $t )* } } )
-    );
+        let iter = iter! {
+            send;
+            let x <- 0..3;
+            let y <- vec![x; 2];
+            x + y
+        };
+        assert_eq!(std::thread::spawn(move || iter.sum::<i32>()).join().unwrap(), 12);
 
-    (
-        let mut $p: ident : $ty: tt <- $e: expr ; $( $t: tt )*
-    ) => (
-        $e.and_then(move | mut $p : $ty | { result! { $( $t )* } } )
-    );
+        let sum: i32 = iter! {
+            sum;
+            send;
+            let x <- 0..4;
+            x
+        };
+        assert_eq!(sum, 6);
 
-    (
-        let $p: tt <- $e: expr ; $( $t: tt )*
-    ) => (
-        $e.and_then(move | result! (@as_pat $p) | { result! { $( $t )* } } )
-    );
+        let future = async_option! {
+            send;
+            let a <- ready(Some(1));
+            let b <- ready(Some(2));
+            a + b
+        };
+        assert_eq!(std::thread::spawn(move || block_on(future)).join().unwrap(), Some(3));
 
-    (
-        let $p: tt ( $( $para: tt )* ) <- $e: expr ; $( $t: tt )*
-    ) => (
-        $e.and_then(move | result! (@as_pat $p ( $( $para )* ) )  | { result! { $( $t )* } } )
-    );
+        let future = async_result! {
+            send;
+            let a <- ready(ok(1));
+            a
+        };
+        assert_eq!(block_on(future), Ok(1));
 
-    (
-        let $p: tt { $( $para: tt )* } <- $e: expr ; $( $t: tt )*
-    ) => (
-        $e.and_then(move | result! (@as_pat $p { $( $para )* } ) | { result! { $( $t )* } } )
-    );
+        #[cfg(feature = "futures")]
+        {
+            use futures::future::ok;
+            use futures::stream::{iter, StreamExt};
 
-    (
-        let $p: ident : $ty: tt <- $e: expr ; $( $t: tt )*
-    ) => (
-        $e.and_then(move | $p : $ty | { result! { $( $t )* } } )
-    );
+            let future = future! {
+                send;
+                let a <- ready(1);
+                a + 1
+            };
+            assert_eq!(std::thread::spawn(move || block_on(future)).join().unwrap(), 2);
 
-    (
-        $stmt: stmt ; $( $t: tt )*
-    ) => (
-        { $stmt ; result! { $( $t )* } }
-    );
+            let future = try_future! {
+                send;
+                let a <- ok::<_, ()>(1);
+                a + 1
+            };
+            assert_eq!(block_on(future), Ok(2));
 
-    (
-        $e: expr ; $( $t: tt )*
-    ) => (
-        { $e ; result! { $( $t )* } }
-    );
+            let stream = stream! {
+                send;
+                let x <- iter(0..3);
+                x * 2
+            };
+            assert_eq!(block_on(stream.collect::<Vec<_>>()), [0, 2, 4]);
+        }
 
-    (
-        $e: expr
-    ) => (
-        Ok($e)
-    );
+        #[cfg(feature = "proc-macro")]
+        {
+            let iter = crate::spanned::iter! {
+                send;
+                from_fn;
+                let x <- vec![1, 2];
+                let y <- 0..x;
+                (x, y)
+            };
+            let pairs = std::thread::spawn(move || iter.collect::<Vec<_>>()).join().unwrap();
+            assert_eq!(pairs, [(1, 0), (2, 0), (2, 1)]);
+        }
+    }
 
-    (
-        $b: block ; $( $t: tt )*
-    ) => (
-        $b ; result! { $( $t )* }
-    );
-}
+    #[test]
+    fn test_ref() {
+        use futures::executor::block_on;
+        use futures::future::ready;
 
-/// syntax extension specific for Iterator
-///
-/// See the module-level documentation for more details.
-#[macro_export]
-macro_rules! iter {
-    (@as_pat $p: pat) => ($p);
+        let names = vec!["alice".to_string(), "bob".to_string()];
+        let offset = String::from("!");
+        let iter = iter! {
+            ref names, offset;
+            let i <- 0..names.len();
+            let c <- names[i].chars().take(1);
+            format!("{}{}", c, offset)
+        };
+        assert_eq!(iter.collect::<Vec<_>>(), ["a!", "b!"]);
 
-    () => {
-        Some(())
-    };
+        let option = option! {
+            ref names;
+            let first <- names.first();
+            let last <- names.last();
+            first.len() + last.len()
+        };
+        assert_eq!(option, Some(8));
 
-    (
-        let mut $p: tt <- $e: expr ; $( $t: tt )*
-    ) => (
-        $e.into_iter().flat_map(move | iter! (@as_pat mut $p) | { iter! { $( $t )* } } )
-    );
+        let result = result! {
+            ref names;
+            let n <- names[0].len().checked_sub(1).ok_or(());
+            n
+        };
+        assert_eq!(result, Ok(4));
 
-    (
-        let mut $p: ident : $ty: tt <- $e: expr ; $( $t: tt )*
-    ) => (
-        $e.into_iter().flat_map(move | mut $p : $ty | { iter! { $( $t )* } } )
-    );
+        let future = async_option! {
+            ref names;
+            let name <- ready(names.get(1));
+            name.clone()
+        };
+        assert_eq!(block_on(future), Some("bob".to_string()));
 
-    (
-        let $p: tt <- $e: expr ; $( $t: tt )*
-    ) => (
-        $e.into_iter().flat_map(move | iter! (@as_pat $p) | { iter! { $( $t )* } } )
-    );
+        assert_eq!(names.len(), 2);
+        assert_eq!(offset, "!");
 
-    (
-        let $p: tt ( $( $para: tt )* ) <- $e: expr ; $( $t: tt )*
-    ) => (
-        $e.into_iter().flat_map(move | iter! (@as_pat $p ( $( $para )* ) ) | { iter! { $( $t )* } } )
-    );
+        #[cfg(feature = "proc-macro")]
+        {
+            let words = vec!["a", "bb"];
+            let iter = crate::spanned::iter! {
+                ref words;
+                let w <- words.iter();
+                let n <- 0..words.len();
+                (*w, n)
+            };
+            assert_eq!(iter.count(), 4);
+            assert_eq!(words.len(), 2);
+        }
+    }
 
-    (
-        let $p: tt { $( $para: tt )* } <- $e: expr; $( $t: tt )*
-    ) => (
-        $e.into_iter().flat_map(move | iter! (@as_pat $p { $( $para )* } ) | { iter! { $( $t )* } } )
-    );
+    #[test]
+    fn test_clone() {
+        use std::rc::Rc;
 
-    (
-        let $p: ident : $ty: tt <- $e: expr ; $( $t: tt )*
-    ) => (
-        $e.into_iter().flat_map(move | $p : $ty | { iter! { $( $t )* } } )
-    );
+        let config = Rc::new(vec![10, 20]);
+        let iter = iter! {
+            clone config;
+            let x <- 0..3;
+            while x < 2;
+            let y <- 0..config.len();
+            if y > 0 || x > 0;
+            (x + config[y], config)
+        };
+        let values = iter.map(|(value, config)| (value, config.len())).collect::<Vec<_>>();
+        assert_eq!(values, [(20, 2), (11, 2), (21, 2)]);
+        assert_eq!(Rc::strong_count(&config), 1);
 
-    (
-        if $e: expr ; $( $t: tt )*
-    ) => (
-        ( iter! { $( $t )* } ).into_iter().filter(move |_| $e)
-    );
+        let prefix = Rc::new(String::from("#"));
+        let iter = iter! {
+            let x <- 0..2;
+            clone prefix, config;
+            let y <- 0..2;
+            format!("{}{}{}", prefix, x + y, config.len())
+        };
+        assert_eq!(iter.collect::<Vec<_>>(), ["#02", "#12", "#12", "#22"]);
 
-    (
-        $stmt: stmt ; $( $t: tt )*
-    ) => (
-        { $stmt ; iter! { $( $t )* } }
-    );
+        #[cfg(feature = "alloc")]
+        {
+            let offset = Rc::new(1);
+            let iter = iter! {
+                clone offset;
+                let x <- 0..2;
+                let y <- memo 0..2;
+                (x + y, offset)
+            };
+            assert_eq!(iter.map(|(n, offset)| n + *offset).collect::<Vec<_>>(), [1, 2, 2, 3]);
+            assert_eq!(*offset, 1);
+        }
 
-    (
-        $e: expr ; $( $t: tt )*
-    ) => (
-        { $e ; iter! { $( $t )* } }
-    );
+        #[cfg(feature = "proc-macro")]
+        {
+            let prefix = std::rc::Rc::new(String::from("#"));
+            let iter = crate::spanned::iter! {
+                clone prefix;
+                let x <- 0..2;
+                let y <- 0..x + 1;
+                (x + y, prefix)
+            };
+            assert_eq!(iter.map(|(n, prefix)| format!("{}{}", prefix, n)).collect::<Vec<_>>(),
+                       ["#0", "#1", "#2"]);
+            assert_eq!(prefix.len(), 1);
+        }
+    }
 
-    (
-        $e: expr
-    ) => (
-        Some($e)
-    );
+    #[test]
+    fn test_iter_ref() {
+        let names = ["alice".to_string(), "bob".to_string()];
+        let scores = [[1, 2], [3, 4]];
 
-    (
-        $b: block ; $( $t: tt )*
-    ) => (
-        $b ; iter! { $( $t )* }
-    );    
-}
+        let iter = iter_ref! {
+            ref scores;
+            let (i, name) <- enumerate names;
+            let score <- scores[i], rev;
+            if *score % 2 == 0;
+            let c = name.len();
+            (name.as_str(), *score + c)
+        };
+        assert_eq!(iter.collect::<Vec<_>>(), [("alice", 7), ("bob", 7)]);
 
-#[cfg(test)]
-mod tests {
-    #![allow(unused_variables)]
-    #![allow(dead_code)]
+        let pairs = iter_ref! {
+            collect into Vec<_>;
+            ref scores;
+            for name in names;
+            let row <- scores or scores;
+            let mut total = 0;
+            for x in row.iter() {
+                total += x;
+            }
+            let (x, y) <- row zip row;
+            (name.len(), total, x * y)
+        };
+        assert_eq!(pairs.len(), 16);
+        assert_eq!(pairs[..4], [(5, 3, 1), (5, 3, 4), (5, 7, 9), (5, 7, 16)]);
 
-    fn ok<T>(t: T) -> Result<T, ()> {
-        Ok(t)
+        assert_eq!(names.len(), 2);
+        assert_eq!(scores.len(), 2);
     }
 
     #[test]
-    fn test_basic() {
-        let option = option! {
-            let a <- Some(1);
-            let b <- Some(2);
+    fn test_iter_mut() {
+        let mut scores = vec![3, -1, 4, -5];
+        iter_mut! {
+            let x <- &mut scores;
+            if *x < 0;
+            *x = 0
         };
-        assert_eq!(option, Some(()));
+        assert_eq!(scores, [3, 0, 4, 0]);
 
-        let option = option! {
-            let a <- Some(1);
-            let b <- Some('a');
-            (a, b)
+        let mut grid = [[1, 2], [3, 4]];
+        let mut total = 0;
+        iter_mut! {
+            let (i, row) <- enumerate grid;
+            let x <- row;
+            total += *x;
+            *x *= i + 1
         };
-        assert_eq!(option, Some((1, 'a')));
+        assert_eq!(grid, [[1, 2], [6, 8]]);
+        assert_eq!(total, 10);
+    }
+
 
+    #[test]
+    fn test_at_binding() {
+        #[derive(Clone, Copy, Debug, PartialEq)]
+        struct Point {
+            x: i32,
+            y: i32,
+        }
+
+        let p = Point { x: 1, y: 2 };
         let option = option! {
-            let a <- Some(1);
-            let b <- None::<()>;
-            (a, b)
+            let whole @ Point { x, .. } <- Some(p);
+            let small @ 0..=9 <- Some(whole.y * 2);
+            (whole, x + small)
         };
-        assert_eq!(option, None);
+        assert_eq!(option, Some((p, 5)));
 
         let option = option! {
-            let a <- None::<()>;
-            let b <- Some(2);
-            (a, b)
+            let n @ 0..=9 <- Some(10);
+            n
         };
         assert_eq!(option, None);
 
         let result = result! {
-            let a <- ok(1);
-            let b <- ok(2);
+            let mut whole @ Point { x, .. } <- Ok::<_, ()>(p);
+            whole.y += x;
+            whole
         };
-        assert_eq!(result, Ok(()));
+        assert_eq!(result, Ok(Point { x: 1, y: 3 }));
 
-        let result = result! {
-            let a <- ok(1);
-            let b <- ok('a');
-            (a, b)
+        let iter = iter! {
+            let n @ 1..=3 <- 0..6;
+            let pair @ (_, m) <- vec![(n, n * 10)];
+            (pair, m)
         };
-        assert_eq!(result, Ok((1, 'a')));
+        assert_eq!(iter.collect::<Vec<_>>(), vec![((1, 10), 10), ((2, 20), 20), ((3, 30), 30)]);
 
-        let result = result! {
-            let a <- Err::<(), _>(1);
-            let b <- Ok('a');
-            (a, b)
+        #[cfg(feature = "alloc")]
+        {
+            let vec = vec_comp! {
+                let whole @ (a, _) <- vec![(1, 2), (3, 4)];
+                whole.1 * a
+            };
+            assert_eq!(vec, [2, 12]);
+        }
+
+        #[cfg(feature = "proc-macro")]
+        {
+            let iter = crate::spanned::iter! {
+                let whole @ Point { x, .. } <- vec![p, Point { x: 3, y: 4 }];
+                whole.y - x
+            };
+            assert_eq!(iter.collect::<Vec<_>>(), vec![1, 1]);
+        }
+    }
+
+
+    #[test]
+    fn test_slice_pattern() {
+        let arr = [1, 2, 3, 4];
+        let option = option! {
+            let [first, .., last] <- Some(arr);
+            first + last
         };
-        assert_eq!(result, Err(1));
+        assert_eq!(option, Some(5));
+
+        let option = option! {
+            let &[first, second] <- Some(&arr[..]);
+            first + second
+        };
+        assert_eq!(option, None);
 
         let result = result! {
-            let a <- Ok('a');
-            let b <- Err::<(), _>(2);
-            (a, b)
+            let [head, tail @ ..] <- Ok::<_, ()>(arr);
+            head + tail.len()
         };
-        assert_eq!(result, Err(2));
+        assert_eq!(result, Ok(4));
 
         let iter = iter! {
-            let x <- vec![0, 1, 2, 3];
-            let y <- x..4;
-            (x, y)
+            ref arr;
+            let [x, y] <- arr.chunks(2);
+            let &[.., z] <- vec![&arr[..*x], &[]];
+            (x + y, z)
         };
-        let expected = vec![(0, 0), (0, 1), (0, 2), (0, 3), (1, 1), (1, 2), (1, 3), (2, 2),
-                            (2, 3), (3, 3)];
-        assert!(iter.eq(expected.into_iter()));
-    }
+        assert_eq!(iter.collect::<Vec<_>>(), vec![(3, 1), (7, 3)]);
 
-    #[test]
-    fn test_array() {
-        let array = [0, 1, 2, 3];
+        let words = ["a", "bc", "def"];
         let iter = iter! {
-            let x <- array;
-            let y <- *x..4;
-            (*x, y)
+            let [first, _, ..] <- words.iter().map(|w| w.as_bytes());
+            *first as char
         };
-        let expected = vec![(0, 0), (0, 1), (0, 2), (0, 3), (1, 1), (1, 2), (1, 3), (2, 2),
-                            (2, 3), (3, 3)];
-        assert!(iter.eq(expected.into_iter()));
+        assert_eq!(iter.collect::<Vec<_>>(), vec!['b', 'd']);
+
+        #[cfg(feature = "proc-macro")]
+        {
+            let iter = crate::spanned::iter! {
+                let &[first, .., last] <- vec![&arr[..], &arr[..1], &[]];
+                first * last
+            };
+            assert_eq!(iter.collect::<Vec<_>>(), vec![4]);
+        }
     }
 
+
     #[test]
-    fn test_guard() {
+    fn test_rest_pattern() {
+        mod config {
+            #[derive(Clone, Copy)]
+            pub struct Config {
+                pub host: [u8; 4],
+                pub port: u16,
+                pub retries: u8,
+            }
+
+            pub struct Version(pub u8, pub u8, pub u8);
+        }
+
+        use config::{Config, Version};
+
+        let load = || Config { host: [127, 0, 0, 1], port: 8080, retries: 3 };
+        let option = option! {
+            let Config { port, .. } <- Some(load());
+            let (major, ..) <- Some((1, 2, 3));
+            let Version(_, minor, ..) <- Some(Version(major, 4, 5));
+            (port, minor)
+        };
+        assert_eq!(option, Some((8080, 4)));
+
+        let result = result! {
+            let config::Config { host: [first, ..], .. } <- Ok::<_, ()>(load());
+            first
+        };
+        assert_eq!(result, Ok(127));
+
         let iter = iter! {
-            let x <- 0..4;
-            let y <- x..4;
-            if x * 2 == y;
-            (x, y)
+            let config::Config { port, retries, .. } <- vec![load(), load()];
+            let ::core::option::Option::Some(retry) <- vec![None, Some(retries)];
+            port + u16::from(retry)
         };
-        let expected = vec![(0, 0), (1, 2)];
-        assert!(iter.eq(expected.into_iter()));
+        assert_eq!(iter.collect::<Vec<_>>(), vec![8083, 8083]);
+
+        #[cfg(feature = "alloc")]
+        {
+            let vec = vec_comp! {
+                let config::Config { port, .. } <- vec![load()];
+                let (.., last) <- vec![(0, port)];
+                last
+            };
+            assert_eq!(vec, [8080]);
+        }
+
+        #[cfg(feature = "proc-macro")]
+        {
+            let option = crate::spanned::option! {
+                let config::Config { retries, .. } <- Some(load());
+                retries
+            };
+            assert_eq!(option, Some(3));
+        }
     }
 
+
     #[test]
-    fn test_pattern() {
-        struct TupleStruct0();
-        struct TupleStruct(usize);
-        struct TupleStruct2(usize, usize);
-        struct Struct {
-            x: usize,
-        };
-        struct Struct2 {
-            x: usize,
-            y: usize,
+    #[allow(clippy::toplevel_ref_arg)]
+    fn test_ref_pattern() {
+        let name = String::from("comp");
+        let option = option! {
+            let ref s <- Some(name.clone());
+            let len <- Some(s.len());
+            (s.clone(), len)
         };
+        assert_eq!(option, Some((name.clone(), 4)));
 
         let option = option! {
-            let (x, y, z) <- Some((1, 2, 3));
-            (x, y, z)
+            let ref mut s <- Some(name.clone());
+            s.push_str("-rs");
+            s.len()
         };
-        assert_eq!(option, Some((1, 2, 3)));
+        assert_eq!(option, Some(7));
 
-        let option = option! {
-            let (x, (y, z)) <- Some((1, (2, 3)));
-            (x, y, z)
+        let result = result! {
+            let (ref mut s, n) <- Ok::<_, ()>((name.clone(), 2));
+            s.truncate(n);
+            s.clone()
         };
-        assert_eq!(option, Some((1, 2, 3)));
+        assert_eq!(result, Ok("co".to_string()));
 
-        let option = option! {
-            let TupleStruct0() <- Some(TupleStruct0());
+        let iter = iter! {
+            let s <- vec![name.clone(), "rs".to_string()];
+            let (ref mut t, Some(ref c)) <- vec![(s.clone(), s.chars().last())];
+            t.pop();
+            (t.clone(), *c)
         };
-        assert_eq!(option, Some(()));
+        assert_eq!(iter.collect::<Vec<_>>(), vec![("com".to_string(), 'p'), ("r".to_string(), 's')]);
 
-        let option = option! {
-            let TupleStruct0() <- Some(TupleStruct0());
+        let const_option = const_option! {
+            let (ref a, ref mut b) <- Some((1, 2));
+            *b += *a;
+            *b
         };
-        assert_eq!(option, Some(()));
+        assert_eq!(const_option, Some(3));
+
+        #[cfg(feature = "alloc")]
+        {
+            let vec = vec_comp! {
+                let ref mut s <- vec![name.clone()];
+                s.make_ascii_uppercase();
+                s.clone()
+            };
+            assert_eq!(vec, ["COMP"]);
+        }
+
+        #[cfg(feature = "proc-macro")]
+        {
+            let option = crate::spanned::option! {
+                let ref mut s <- Some(name.clone());
+                s.push('!');
+                s.len()
+            };
+            assert_eq!(option, Some(5));
+        }
+    }
+
+
+    #[test]
+    fn test_nested_mut() {
+        #[derive(Clone, Debug, PartialEq)]
+        struct Counter {
+            count: u32,
+        }
 
         let option = option! {
-            let TupleStruct(x) <- Some(TupleStruct(9));
-            x
+            let (a, mut b, Counter { mut count }) <- Some((1, 2, Counter { count: 3 }));
+            b += a;
+            count += b;
+            count
         };
-        assert_eq!(option, Some(9));
+        assert_eq!(option, Some(6));
 
-        let option = option! {
-            let TupleStruct2(x, y) <- Some(TupleStruct2(9, 10));
-            (x, y)
+        let result = result! {
+            let Some((mut a, Counter { count: mut c })) <- Ok::<_, ()>(Some((1, Counter { count: 2 }))) => ();
+            a += c;
+            c *= a;
+            (a, c)
         };
-        assert_eq!(option, Some((9, 10)));
+        assert_eq!(result, Ok((3, 6)));
 
-        let option = option! {
-            let Struct { x } <- Some(Struct { x: 8 });
-            x
+        let iter = iter! {
+            let (a, mut b) <- vec![(1, 2), (3, 4)];
+            let Some(Counter { mut count }) <- vec![Some(Counter { count: 10 }), None];
+            b += a;
+            count += b;
+            count
         };
-        assert_eq!(option, Some(8));
+        assert_eq!(iter.collect::<Vec<_>>(), vec![13, 17]);
 
-        let option = option! {
-            let Struct2 { x, y } <- Some(Struct2 { x: 9, y: 10 });
-            (x, y)
+        let const_option = const_option! {
+            let (mut a, [b, mut c]) <- Some((1, [2, 3]));
+            a += b;
+            c += a;
+            c
         };
-        assert_eq!(option, Some((9, 10)));
+        assert_eq!(const_option, Some(6));
+
+        #[cfg(feature = "alloc")]
+        {
+            let vec = vec_comp! {
+                let (i, (mut a, b)) <- enumerate vec![(1, 2), (3, 4)];
+                a += b * i;
+                a
+            };
+            assert_eq!(vec, [1, 7]);
+        }
+
+        #[cfg(feature = "proc-macro")]
+        {
+            let iter = crate::spanned::iter! {
+                let (a, mut b) <- vec![(1, 2)];
+                let Some(Counter { mut count }) <- vec![Some(Counter { count: 10 })];
+                b += a;
+                count += b;
+                count
+            };
+            assert_eq!(iter.collect::<Vec<_>>(), vec![13]);
+        }
     }
 
+
     #[test]
-    fn test_ignore() {
-        struct TupleStruct(usize);
-        struct TupleStruct2(usize, usize);
-        struct Struct {
-            x: usize,
-        };
-        struct Struct2 {
-            x: usize,
-            y: usize,
-        };
+    fn test_generic_type() {
+        use std::collections::BTreeMap;
+
+        fn parse(s: &str) -> Option<Vec<u8>> {
+            s.split(',').map(|x| x.parse().ok()).collect()
+        }
 
         let option = option! {
-            let _ <- Some(0);
+            let bytes: Vec<u8> <- parse("1,2,3");
+            let first: Option<u8> = bytes.first().copied();
+            let pair: (u8, ::core::primitive::u8) <- first.map(|x| (x, bytes[2]));
+            pair
         };
-        assert_eq!(option, Some(()));
+        assert_eq!(option, Some((1, 3)));
+
+        let result = result! {
+            let mut map: BTreeMap<u8, Vec<u8>> <- Ok::<_, ()>(BTreeMap::new());
+            map.entry(1).or_default().push(2);
+            let len: ::core::primitive::usize <- Ok(map.len());
+            (map, len)
+        };
+        assert_eq!(result.map(|(map, len)| (map[&1].clone(), len)), Ok((vec![2], 1)));
 
         let iter = iter! {
-            let _ <- 0..10;
-            1
+            let row: Vec<u8> <- vec![vec![1, 2], vec![3]];
+            let x: u8 <- row, rev;
+            let sum: u8 = x * 10;
+            sum
         };
-        assert_eq!(iter.sum::<u8>(), 10);
+        assert_eq!(iter.collect::<Vec<_>>(), vec![20, 10, 30]);
 
-        let option = option! {
-            let (_, y, _) <- Some((1, 2, 3));
-            y
+        #[cfg(feature = "alloc")]
+        {
+            let vec = vec_comp! {
+                let (a, b): (u8, u8) = (1, 2);
+                let boxed: Box<dyn Iterator<Item = u8>> = Box::new(a..=b);
+                let items: Box<dyn Iterator<Item = u8>> <- vec![boxed];
+                let x: u8 <- items;
+                x
+            };
+            assert_eq!(vec, [1, 2]);
+        }
+    }
+
+
+    #[test]
+    fn test_reference_type() {
+        use std::collections::HashMap;
+
+        struct Foo(u8);
+
+        fn find<'a>(map: &'a HashMap<&str, Foo>, key: &str) -> Option<&'a Foo> {
+            map.get(key)
+        }
+
+        fn lookup<'a>(map: &'a HashMap<&str, Foo>, key: &'a str) -> Option<(&'a str, u8)> {
+            option! {
+                let foo: &'a Foo <- find(map, key);
+                let name: &str <- Some(key);
+                (name, foo.0)
+            }
+        }
+
+        let map = vec![("a", Foo(1))].into_iter().collect::<HashMap<_, _>>();
+        assert_eq!(lookup(&map, "a"), Some(("a", 1)));
+        assert_eq!(lookup(&map, "b"), None);
+
+        let mut count = 0;
+        let result = result! {
+            let counter: &mut u32 <- Ok::<_, ()>(&mut count);
+            *counter += 1;
+            let word: &'static str <- Ok("done");
+            word
         };
-        assert_eq!(option, Some(2));
+        assert_eq!(result, Ok("done"));
+        assert_eq!(count, 1);
 
-        let option = option! {
-            let (_, (y, _)) <- Some((1, (2, 3)));
-            y
+        let iter = iter! {
+            let s: &str <- vec!["ab", "c"];
+            let b: &u8 <- s.as_bytes();
+            *b as char
         };
-        assert_eq!(option, Some(2));
+        assert_eq!(iter.collect::<String>(), "abc");
+
+        #[cfg(feature = "proc-macro")]
+        {
+            let iter = crate::spanned::iter! {
+                let s: &'static str <- vec!["ab", "c"];
+                s.len()
+            };
+            assert_eq!(iter.collect::<Vec<_>>(), vec![2, 1]);
+        }
+    }
 
+    #[test]
+    fn test_strict() {
         let option = option! {
-            let TupleStruct(_) <- Some(TupleStruct(9));
+            strict;
+            let x <- Some(1);
+            let y <- Some(2);
+            x + y
         };
-        assert_eq!(option, Some(()));
+        assert_eq!(option, Some(3));
 
-        let option = option! {
-            let TupleStruct2(x, _) <- Some(TupleStruct2(9, 10));
-            x
+        let result = result! {
+            strict;
+            let _ <- Ok::<_, ()>(1);
+            ()
         };
-        assert_eq!(option, Some(9));
+        assert_eq!(result, Ok(()));
 
-        let option = option! {
-            let Struct { x: _ } <- Some(Struct { x: 8 });
+        let iter = iter! {
+            strict;
+            ref option;
+            let x <- 0..4;
+            if x % 2 == 0;
+            let y <- *option;
+            let z <- 0..1;
+            let w <- 0..1;
+            let v <- 0..1;
+            x + y + z + w + v
         };
-        assert_eq!(option, Some(()));
+        assert_eq!(iter.collect::<Vec<_>>(), vec![3, 5]);
 
-        let option = option! {
-            let Struct2 { x, y: _ } <- Some(Struct2 { x: 9, y: 10 });
+        let sum: i32 = iter! {
+            sum;
+            strict;
+            let x <- 0..4;
             x
         };
-        assert_eq!(option, Some(9));
-    }
+        assert_eq!(sum, 6);
+
+        #[cfg(feature = "proc-macro")]
+        {
+            let iter = crate::spanned::iter! {
+                send;
+                strict;
+                let x <- 0..3;
+                ()
+            };
+            assert_eq!(iter.count(), 3);
+        }
 
-    #[test]
-    fn test_if_expression() {
         let iter = iter! {
-            let x <- 0..5;
-            let y <- if x % 2 == 0 { Some(x + 1) } else { None };
-            y
+            strict;
+            let x <- 0..3;
+            yield x;
+            yield from 0..x;
         };
-        let expected = vec![1, 3, 5];
-        assert!(iter.eq(expected.into_iter()));
+        assert_eq!(iter.collect::<Vec<_>>(), vec![0, 1, 0, 2, 0, 1]);
 
-        let iter = iter! {
-            let x <- 0..5;
-            if x < 2 { 0 } else { 1 }
+        let option = option! {
+            strict;
+            let x <- Some(1);
+            return x + 1;
         };
-        let expected = vec![0, 0, 1, 1, 1];
-        assert!(iter.eq(expected.into_iter()));
+        assert_eq!(option, Some(2));
+
+        #[cfg(feature = "proc-macro")]
+        {
+            let option = crate::spanned::option! {
+                strict;
+                let x <- Some(1);
+                return x + 1;
+            };
+            assert_eq!(option, Some(2));
+        }
     }
 
     #[test]
-    fn test_statement() {
-        let iter = iter! {
-            let start = 5;
-            let end;
-            end = start * 3;
+    fn test_comp_expand() {
+        #[cfg(feature = "proc-macro")]
+        {
+            let expanded = crate::spanned::comp_expand!(option! {
+                let a <- Some(1);
+                let b <- Some(2);
+                a + b
+            });
+            assert!(expanded.contains("and_then"));
+            assert!(expanded.contains("a + b"));
 
-            // 5, 6, ..., 13, 14
-            let x <- start..end;
-            x
-        };
-        let expected = 5..15;
-        assert!(iter.eq(expected.into_iter()));
+            let expanded = crate::spanned::comp_expand!(iter! {
+                let x <- 0..3;
+                x * 2
+            });
+            assert!(expanded.contains("x * 2"));
+        }
     }
 
     #[test]
-    fn test_block() {
-        let iter = iter! {
-            let mut a <- 0..5;
+    fn test_trace() {
+        #[cfg(feature = "trace")]
+        {
+            use crate::{Outcome, Trace};
+            use std::sync::Mutex;
 
-            {
-                fn double(x: u8) -> u8 { x * 2}
-                let tmp = double(a);
-                a = tmp;
-            };
+            static TRACES: Mutex<Vec<Trace>> = Mutex::new(Vec::new());
+            crate::set_trace_hook(|trace| TRACES.lock().unwrap().push(*trace));
 
-            let count = unsafe {
-                static mut CALL_COUNT: u8 = 0;
-                CALL_COUNT += 1;
+            let traced = |n: i32| if n > 0 { Some(n) } else { None };
+            let traced_err = |n: i32| if n > 0 { Ok(n) } else { Err(n) };
 
-                CALL_COUNT
+            let option = option! {
+                let a <- traced(1);
+                let b <- traced(0);
+                a + b
             };
+            assert_eq!(option, None);
 
-            (a, count)
-        };
-        let expected = vec![(0, 1), (2, 2), (4, 3), (6, 4), (8, 5)];
-        assert!(iter.eq(expected.into_iter()));
+            let result = result! {
+                let a <- traced_err(1);
+                let b <- traced_err(-1);
+                a + b
+            };
+            assert_eq!(result, Err(-1));
+
+            #[cfg(feature = "proc-macro")]
+            {
+                let option = crate::spanned::option! {
+                    let a <- traced(2);
+                    a
+                };
+                assert_eq!(option, Some(2));
+            }
+
+            let traces = TRACES.lock().unwrap();
+            let traces = traces
+                .iter()
+                .filter(|trace| trace.source.starts_with("traced"))
+                .map(|trace| (trace.source, trace.outcome))
+                .collect::<Vec<_>>();
+            let mut expected = vec![
+                ("traced(1)", Outcome::Value),
+                ("traced(0)", Outcome::None),
+                ("traced_err(1)", Outcome::Value),
+                ("traced_err(-1)", Outcome::Err),
+            ];
+            #[cfg(feature = "proc-macro")]
+            expected.push(("traced(2)", Outcome::Value));
+            assert_eq!(traces, expected);
+        }
     }
 
     #[test]
-    fn test_mut() {
-        struct TupleStruct2(usize, usize);
+    fn test_inspect() {
+        use core::cell::RefCell;
+
+        let seen = RefCell::new(Vec::new());
+        let seen = &seen;
 
         let option = option! {
-            let mut a <- Some(2);
-            a = a + 10;
+            let a <- Some(1);
+            inspect |a| seen.borrow_mut().push(*a);
+            let b <- None::<i32>;
+            inspect |b| seen.borrow_mut().push(*b);
+            a + b
+        };
+        assert_eq!(option, None);
+        assert_eq!(seen.take(), vec![1]);
 
-            let (mut b,) <- Some((3,));
-            b = b + 10;
+        let result = result! {
+            let a <- Ok::<_, ()>(2);
+            inspect move |a: &i32| seen.borrow_mut().push(*a);
+            let (b, c) <- Ok((3, 4));
+            inspect |&(b, c)| seen.borrow_mut().push(b + c);
+            a + b + c
+        };
+        assert_eq!(result, Ok(9));
+        assert_eq!(seen.take(), vec![2, 7]);
 
-            (a, b)
+        let iter = iter! {
+            let x <- 0..3;
+            inspect |x| seen.borrow_mut().push(*x);
+            if x % 2 == 0;
+            let mut y <- x..3;
+            inspect move |y| seen.borrow_mut().push(*y * 10);
+            y += 1;
+            (x, y)
         };
-        assert_eq!(option, Some((12, 13)));
+        assert_eq!(iter.collect::<Vec<_>>(), vec![(0, 1), (0, 2), (0, 3), (2, 3)]);
+        assert_eq!(seen.take(), vec![0, 0, 10, 20, 1, 2, 20]);
 
-        let result = result! {
-            let mut a <- ok(2);
-            a = a + 10;
+        #[cfg(feature = "alloc")]
+        {
+            let vec = vec_comp! {
+                let x <- 0..3;
+                inspect |x| seen.borrow_mut().push(*x);
+                x * 2
+            };
+            assert_eq!(vec, vec![0, 2, 4]);
+            assert_eq!(seen.take(), vec![0, 1, 2]);
+        }
 
-            let TupleStruct2(mut b, _) <- ok(TupleStruct2(3, 4));
-            b = b + 10;
+        #[cfg(feature = "proc-macro")]
+        {
+            let option = crate::spanned::option! {
+                let a <- Some(1);
+                inspect |a| seen.borrow_mut().push(*a);
+                a
+            };
+            assert_eq!(option, Some(1));
 
-            (a, b)
-        };
-        assert_eq!(result, ok((12, 13)));
+            let iter = crate::spanned::iter! {
+                let x <- 0..2;
+                inspect |x| seen.borrow_mut().push(*x);
+                x
+            };
+            assert_eq!(iter.count(), 2);
+            assert_eq!(seen.take(), vec![1, 0, 1]);
+        }
 
-        let iter = iter! {
-            let mut a <- 2..3;
-            a = a + 10;
+        // a call to a function named `inspect` is a statement, not the clause
+        let inspect = |x: i32| seen.borrow_mut().push(x);
+        let option = option! {
+            let a <- Some(1);
+            inspect(a);
             a
         };
-        let expected = vec![12];
-        assert!(iter.eq(expected.into_iter()));
-    }
+        assert_eq!(option, Some(1));
+        let result = result! {
+            let a <- Ok::<_, ()>(2);
+            inspect(a);
+            a
+        };
+        assert_eq!(result, Ok(2));
+        let iter = iter! {
+            let x <- 3..5;
+            inspect(x);
+            x
+        };
+        assert_eq!(iter.count(), 2);
+        assert_eq!(seen.take(), vec![1, 2, 3, 4]);
 
-    #[test]
-    fn test_comments() {
-        option! {
-            // single line comments
-            let a <- Some(1);
+        #[cfg(feature = "alloc")]
+        {
+            let vec = vec_comp! {
+                let x <- 0..2;
+                inspect(x);
+                x
+            };
+            assert_eq!(vec, vec![0, 1]);
+            assert_eq!(seen.take(), vec![0, 1]);
+        }
 
-            /*
-             * block comments
-             */
-            let b <- Some(2);
-        };
+        #[cfg(feature = "proc-macro")]
+        {
+            let option = crate::spanned::option! {
+                let a <- Some(1);
+                inspect(a);
+                a
+            };
+            assert_eq!(option, Some(1));
+            let iter = crate::spanned::iter! {
+                let x <- 0..2;
+                inspect(x);
+                x
+            };
+            assert_eq!(iter.count(), 2);
+            assert_eq!(seen.take(), vec![1, 0, 1]);
+        }
     }
 
     #[test]
-    fn test_nested() {
-        let iter = iter! {
-            let a <- 0..2;
+    fn test_timed() {
+        #[cfg(feature = "std")]
+        {
+            use core::cell::Cell;
+            use std::time::Duration;
 
-            option! {
-                let b <- Some(a);
-                (b,)
+            let count = &Cell::new(0);
+            let total = &Cell::new(Duration::ZERO);
+            let record = move |elapsed: Duration| {
+                count.set(count.get() + 1);
+                total.set(total.get() + elapsed);
+            };
+
+            let option = option! {
+                let a <- Some(1);
+                timed record;
+                let b <- {
+                    std::thread::sleep(Duration::from_millis(10));
+                    Some(2)
+                };
+                timed record;
+                a + b
+            };
+            assert_eq!(option, Some(3));
+            assert_eq!(count.replace(0), 2);
+            assert!(total.take() >= Duration::from_millis(10));
+
+            let result = result! {
+                let a <- Err::<i32, _>(());
+                timed record;
+                a
+            };
+            assert_eq!(result, Err(()));
+            assert_eq!(count.replace(0), 1);
+
+            let iter = iter! {
+                let x <- 0..2;
+                timed record;
+                let y <- x..2;
+                timed record;
+                (x, y)
+            };
+            assert_eq!(iter.collect::<Vec<_>>(), vec![(0, 0), (0, 1), (1, 1)]);
+            assert_eq!(count.replace(0), 8);
+
+            let vec = vec_comp! {
+                let x <- 0..3;
+                timed record;
+                x
+            };
+            assert_eq!(vec, vec![0, 1, 2]);
+            assert_eq!(count.replace(0), 4);
+
+            #[cfg(feature = "proc-macro")]
+            {
+                let option = crate::spanned::option! {
+                    let a <- Some(1);
+                    timed record;
+                    a
+                };
+                assert_eq!(option, Some(1));
+
+                let iter = crate::spanned::iter! {
+                    let x <- 0..2;
+                    timed record;
+                    x
+                };
+                assert_eq!(iter.count(), 2);
+                assert_eq!(count.replace(0), 4);
             }
-        };
-        let expected = vec![Some((0,)), Some((1,))];
-        assert!(iter.eq(expected.into_iter()));
+        }
     }
 }
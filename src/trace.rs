@@ -0,0 +1,86 @@
+use crate::__std::sync::RwLock;
+use core::fmt;
+
+/// A bind in `option!` or `result!` traced by the `trace` feature.
+///
+/// See Tracing in the module-level documentation for more details.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Trace {
+    /// The source text of the bound expression.
+    pub source: &'static str,
+    /// The file of the comprehension.
+    pub file: &'static str,
+    /// The line of the comprehension.
+    pub line: u32,
+    /// What the bound expression evaluated to.
+    pub outcome: Outcome,
+}
+
+/// What a traced bind evaluated to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Outcome {
+    /// A value was bound and the comprehension goes on.
+    Value,
+    /// The bind short-circuited on `None`.
+    None,
+    /// The bind short-circuited on `Err`.
+    Err,
+}
+
+impl fmt::Display for Trace {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let outcome = match self.outcome {
+            Outcome::Value => "produced a value",
+            Outcome::None => "returned `None`",
+            Outcome::Err => "returned `Err`",
+        };
+        write!(f, "{}:{}: `{}` {}", self.file, self.line, self.source, outcome)
+    }
+}
+
+static HOOK: RwLock<Option<fn(&Trace)>> = RwLock::new(None);
+
+/// Sets the function called with every traced bind, instead of printing it to stderr.
+pub fn set_trace_hook(hook: fn(&Trace)) {
+    *HOOK.write().unwrap_or_else(|err| err.into_inner()) = Some(hook);
+}
+
+/// The value of a bind which can be traced.
+#[doc(hidden)]
+pub trait __Traced {
+    fn outcome(&self) -> Outcome;
+}
+
+impl<T> __Traced for Option<T> {
+    fn outcome(&self) -> Outcome {
+        match self {
+            Some(_) => Outcome::Value,
+            None => Outcome::None,
+        }
+    }
+}
+
+impl<T, E> __Traced for Result<T, E> {
+    fn outcome(&self) -> Outcome {
+        match self {
+            Ok(_) => Outcome::Value,
+            Err(_) => Outcome::Err,
+        }
+    }
+}
+
+/// Reports a bind to the hook, for the `trace` feature.
+#[doc(hidden)]
+pub fn __trace<T: __Traced>(source: &'static str, file: &'static str, line: u32, value: T) -> T {
+    let trace = Trace {
+        source,
+        file,
+        line,
+        outcome: value.outcome(),
+    };
+    match *HOOK.read().unwrap_or_else(|err| err.into_inner()) {
+        Some(hook) => hook(&trace),
+        None => crate::__std::eprintln!("{}", trace),
+    }
+    value
+}
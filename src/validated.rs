@@ -0,0 +1,80 @@
+/// The result of validations which accumulates all errors instead of stopping at the first.
+///
+/// `E` is the collection of errors, e.g. `Vec<MyError>`.
+///
+/// See `validated!` in the module-level documentation for more details.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Validated<T, E> {
+    /// All validations passed.
+    Valid(T),
+    /// At least one validation failed, with all the errors collected.
+    Invalid(E),
+}
+
+impl<T, E> Validated<T, E> {
+    /// Creates an `Invalid` with a single error.
+    pub fn fail<R>(err: R) -> Self
+    where
+        E: core::iter::FromIterator<R>,
+    {
+        Validated::Invalid(core::iter::once(err).collect())
+    }
+
+    /// Returns `true` if the validated is `Valid`.
+    pub fn is_valid(&self) -> bool {
+        match *self {
+            Validated::Valid(_) => true,
+            Validated::Invalid(_) => false,
+        }
+    }
+
+    /// Maps the valid value, leaving the errors untouched.
+    pub fn map<U, F: FnOnce(T) -> U>(self, f: F) -> Validated<U, E> {
+        match self {
+            Validated::Valid(t) => Validated::Valid(f(t)),
+            Validated::Invalid(e) => Validated::Invalid(e),
+        }
+    }
+
+    /// Combines two validateds into a pair, collecting the errors from both.
+    pub fn zip<U>(self, other: Validated<U, E>) -> Validated<(T, U), E>
+    where
+        E: Extend<<E as IntoIterator>::Item> + IntoIterator,
+    {
+        match (self, other) {
+            (Validated::Valid(t), Validated::Valid(u)) => Validated::Valid((t, u)),
+            (Validated::Valid(_), Validated::Invalid(e)) => Validated::Invalid(e),
+            (Validated::Invalid(e), Validated::Valid(_)) => Validated::Invalid(e),
+            (Validated::Invalid(mut e), Validated::Invalid(other)) => {
+                e.extend(other);
+                Validated::Invalid(e)
+            }
+        }
+    }
+
+    /// Converts into a `Result` with all errors as the `Err` value.
+    pub fn into_result(self) -> Result<T, E> {
+        match self {
+            Validated::Valid(t) => Ok(t),
+            Validated::Invalid(e) => Err(e),
+        }
+    }
+}
+
+impl<T, E, R> From<Result<T, R>> for Validated<T, E>
+where
+    E: core::iter::FromIterator<R>,
+{
+    fn from(result: Result<T, R>) -> Self {
+        match result {
+            Ok(t) => Validated::Valid(t),
+            Err(err) => Validated::fail(err),
+        }
+    }
+}
+
+impl<T, E> From<Validated<T, E>> for Result<T, E> {
+    fn from(validated: Validated<T, E>) -> Self {
+        validated.into_result()
+    }
+}